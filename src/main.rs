@@ -1,6 +1,5 @@
-mod parser;
 use std::env;
-use crate::parser::symbol::Symbol;
+use elf_parser::parser;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -9,6 +8,107 @@ fn main() {
         println!("[!]please input parser elf file path");
         return;
     }
+    //子命令模式
+    if args_len>2{
+        match args[1].as_str() {
+            //manylinux/musllinux兼容性检查
+            "manylinux" => {
+                print_policy_report(&args[2]);
+                return;
+            }
+            "notes" => {
+                print_notes(&args[2]);
+                return;
+            }
+            //加固选项检查
+            "checksec" => {
+                print_hardening_report(&args[2]);
+                return;
+            }
+            //结构异常检查
+            "lint" => {
+                print_lint(&args[2]);
+                return;
+            }
+            //从段信息重建节头表,带装载基址时按内存dump处理
+            "rebuild" if args_len>3 => {
+                rebuild_sections(&args[2],&args[3],args.get(4).map(|s| s.as_str()));
+                return;
+            }
+            //patchelf风格修改解释器、RPATH/RUNPATH、SONAME与DT_NEEDED,不带选项时只打印
+            "edit" => {
+                edit_elf(&args[2],args.get(3).map(|s| s.as_str()),args.get(4..).unwrap_or(&[]));
+                return;
+            }
+            //增加、替换、删除、重命名节
+            "section" if args_len>3 => {
+                edit_sections(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            //strip/objcopy风格去除调试信息与符号
+            "strip" if args_len>3 => {
+                strip_elf(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            //objcopy风格的符号重命名、局部化与弱化
+            "symbol" if args_len>3 => {
+                edit_symbols(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            //导出为烧录用的bin/Intel HEX/S-record,或反向导入为ELF
+            "export" if args_len>3 => {
+                export_image(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            "import" if args_len>3 => {
+                import_image(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            //按虚拟地址打补丁
+            "patch" if args_len>3 => {
+                patch_elf(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            //列出静态库成员,查询符号由哪个成员定义
+            "archive" if args_len>2 => {
+                list_archive(&args[2],&args[3..]);
+                return;
+            }
+            //在zip/APK、tar、cpio、deb等容器里找ELF
+            "scan" if args_len>2 => {
+                for file_path in &args[2..] {
+                    scan_container(file_path);
+                }
+                return;
+            }
+            //从固件或内存镜像里切出嵌入的ELF
+            "carve" if args_len>2 => {
+                carve_elves(&args[2],&args[3..]);
+                return;
+            }
+            //解析core dump:线程寄存器、信号、映射文件、auxv,按虚拟地址读内存
+            "core" if args_len>2 => {
+                show_core(&args[2],&args[3..]);
+                return;
+            }
+            //解析.eh_frame,与.eh_frame_hdr对照,给出地址处的展开规则
+            "ehframe" if args_len>2 => {
+                show_eh_frame(&args[2],&args[3..]);
+                return;
+            }
+            //按.eh_frame展开core里各线程的调用栈
+            "backtrace" if args_len>2 => {
+                show_backtrace(&args[2],&args[3..]);
+                return;
+            }
+            //生成只保留导出ABI的桩共享库
+            "stub" if args_len>3 => {
+                write_stub(&args[2],&args[3],&args[4..]);
+                return;
+            }
+            _ => {}
+        }
+    }
     let file_path=&args[1];
 
     //parser::elf_header::elf_header::read_file_range(file_path);
@@ -55,8 +155,18 @@ fn main() {
     }
     //修复符号表内容
     let symbol_headers=parser::symbol::Symbol::fix_symbol_name(sym_str.unwrap(),symbol.unwrap());
+    //附加符号版本信息
+    let symbol_headers=match parser::version::VersionTables::read_versions(file_path) {
+        Some(versions) => versions.fix_symbol_version(symbol_headers),
+        None => symbol_headers,
+    };
     println!("[*]解析elf symbol string成功:");
     println!("{:?}",symbol_headers);
+    println!("[*]符号版本:");
+    for symbol_header in symbol_headers.iter().filter(|sym| sym.version.is_some()){
+        let library=symbol_header.version.as_ref().and_then(|v| v.library.clone()).unwrap_or_default();
+        println!("{} {}",symbol_header.versioned_name(),library);
+    }
     //gun hash
     let gun_hash=parser::hash::hash::read_hash(file_path,section_header.clone(),elf_header);
     if gun_hash==None{
@@ -64,7 +174,7 @@ fn main() {
         return;
     }
     //测试hash表寻找符号
-    let count=parser::hash::hash::all_sym_find(symbol_headers,gun_hash.unwrap(),elf_header);
+    let count=parser::hash::hash::all_sym_find(symbol_headers.clone(),gun_hash.unwrap(),elf_header);
     println!("[!]通过gun hash发现符号:");
     println!("{count}");
     //读取重定位表
@@ -81,8 +191,812 @@ fn main() {
     }
     let rela=parser::relocation::Rela::read_rela(file_path,".rela.plt".to_string(),
                                                  section_header.clone(),elf_header);
+    //-z now等没有.rela.plt的文件仍然显示.got表项
     if rela ==None{
         println!("[!]解析elf .rela.plt 出错");
+    }
+    //GOT表项与导入符号、PLT桩的对应关系
+    let got_map=parser::got::GotMap::read_got(file_path,section_header.clone(),&symbol_headers,elf_header);
+    if got_map.is_none(){
+        println!("[!]解析elf GOT/PLT 出错");
+        return;
+    }
+    println!("[*]GOT/PLT 映射:");
+    for entry in got_map.unwrap().entries{
+        match entry.plt_addr {
+            Some(plt_addr) => println!("{:#x} {} plt:{:#x} {}",entry.got_addr,entry.section,plt_addr,entry.symbol),
+            None => println!("{:#x} {} {}",entry.got_addr,entry.section,entry.symbol),
+        }
+    }
+}
+
+fn print_policy_report(file_path:&str){
+    let Some(report)=parser::policy::PolicyReport::read_report(file_path) else {
+        println!("[!]解析elf出错");
+        return;
+    };
+    println!("[*]依赖库:");
+    for library in &report.needed{
+        println!("  {}",library);
+    }
+    println!("[*]最高符号版本需求:");
+    for requirement in &report.requirements{
+        println!("  {:<20} {}",requirement.library,requirement.name);
+    }
+    println!("[*]平台策略:");
+    for result in &report.results{
+        if result.compatible{
+            println!("  {:<16} 兼容",result.name);
+            continue;
+        }
+        println!("  {:<16} 不兼容",result.name);
+        for violation in &result.violations{
+            println!("      {}",violation);
+        }
+    }
+    match report.best_policy() {
+        Some(best) => println!("[*]推荐平台标签: {}",best.name),
+        None => println!("[!]没有兼容的平台标签"),
+    }
+}
+
+fn print_notes(file_path:&str){
+    let Some(notes)=parser::note::Note::read_notes(file_path) else {
+        println!("[!]解析elf出错");
+        return;
+    };
+    println!("[*]解析elf note成功:");
+    for note in notes{
+        match note {
+            parser::note::Note::GnuAbiTag{os,major,minor,subminor} =>
+                println!("GNU ABI tag: {} {}.{}.{}",parser::note::Note::abi_os_name(os),major,minor,subminor),
+            parser::note::Note::GnuBuildId(id) =>
+                println!("GNU build id: {}",id.iter().map(|b| format!("{:02x}",b)).collect::<String>()),
+            parser::note::Note::GnuGoldVersion(version) => println!("GNU gold version: {}",version),
+            parser::note::Note::FdoPackage(json) => println!("FDO package: {}",json),
+            parser::note::Note::GoBuildId(id) => println!("Go build id: {}",id),
+            parser::note::Note::AndroidIdent{api_level,ndk_version,ndk_build} =>
+                println!("Android ident: api {} ndk {} {}",api_level,ndk_version.unwrap_or_default(),ndk_build.unwrap_or_default()),
+            parser::note::Note::GnuProperty(properties) => {
+                println!("GNU properties:");
+                for property in properties{
+                    println!("    {:?}",property);
+                }
+            }
+            parser::note::Note::Unknown(entry) =>
+                println!("{} type {:#x} desc {} bytes",entry.name,entry.n_type,entry.desc.len()),
+        }
+    }
+}
+
+fn print_hardening_report(file_path:&str){
+    let Some(report)=parser::hardening::HardeningReport::read_report(file_path) else {
+        println!("[!]解析elf出错");
+        return;
+    };
+    println!("[*]加固选项:");
+    for (name,check) in report.checks(){
+        let status=match check.status {
+            parser::hardening::CheckStatus::Pass => "pass",
+            parser::hardening::CheckStatus::Partial => "partial",
+            parser::hardening::CheckStatus::Fail => "fail",
+        };
+        println!("  {:<14} {:<8} {}",name,status,check.detail);
+    }
+}
+
+fn print_lint(file_path:&str){
+    let Some(diagnostics)=parser::lint::Linter::read_lint(file_path) else {
+        println!("[!]解析elf出错");
+        return;
+    };
+    println!("[*]发现{}个问题:",diagnostics.len());
+    for diagnostic in diagnostics{
+        let severity=match diagnostic.severity {
+            parser::lint::Severity::Error => "error",
+            parser::lint::Severity::Warning => "warning",
+            parser::lint::Severity::Info => "info",
+        };
+        println!("  {:<8} {:#010x} {}",severity,diagnostic.offset,diagnostic.message);
+    }
+}
+
+fn rebuild_sections(input:&str,output:&str,load_bias:Option<&str>){
+    let options=match load_bias {
+        Some(bias) => {
+            let Ok(load_bias)=u64::from_str_radix(bias.trim_start_matches("0x"),16) else {
+                println!("[!]装载基址格式错误: {}",bias);
+                return;
+            };
+            parser::rebuild::RebuildOptions{dumped:true,load_bias}
+        }
+        None => parser::rebuild::RebuildOptions::default(),
+    };
+    let rebuilt=match parser::rebuild::RebuiltFile::read_rebuild(input,options) {
+        Ok(rebuilt) => rebuilt,
+        Err(e) => {
+            println!("[!]重建节头表出错: {:?}",e);
+            return;
+        }
+    };
+    println!("[*]重建节头表:");
+    for sh in &rebuilt.section_headers{
+        println!("  [{:>2}] {:<14} {:#010x} {:#08x} {:#x}",sh.index,sh.string_name,sh.sh_addr,sh.sh_offset,sh.sh_size);
+    }
+    if let Err(e)=rebuilt.write(output){
+        println!("[!]写入文件出错: {:?}",e);
+    }
+}
+
+fn edit_elf(input:&str,output:Option<&str>,options:&[String]){
+    let mut editor=match parser::edit::ElfEditor::read_editor(input) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!("[!]解析elf出错: {:?}",e);
+            return;
+        }
+    };
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        let mut value=|| options.next().unwrap_or_default();
+        let result=match option {
+            "--set-interpreter" => editor.set_interpreter(value()),
+            "--remove-interpreter" => editor.remove_interpreter(),
+            "--set-rpath" => {
+                editor.set_rpath(value());
+                Ok(())
+            }
+            "--add-rpath" => {
+                editor.append_rpath(value());
+                Ok(())
+            }
+            "--remove-rpath" => {
+                editor.remove_rpath();
+                Ok(())
+            }
+            "--set-runpath" => {
+                editor.set_runpath(value());
+                Ok(())
+            }
+            "--add-runpath" => {
+                editor.append_runpath(value());
+                Ok(())
+            }
+            "--remove-runpath" => {
+                editor.remove_runpath();
+                Ok(())
+            }
+            "--set-soname" => {
+                editor.set_soname(value());
+                Ok(())
+            }
+            "--add-needed" => {
+                editor.add_needed(value());
+                Ok(())
+            }
+            "--remove-needed" => editor.remove_needed(value()),
+            "--replace-needed" => {
+                let library=value();
+                editor.replace_needed(library,value())
+            }
+            _ => {
+                println!("[!]未知选项: {}",option);
+                return;
+            }
+        };
+        if let Err(e)=result {
+            println!("[!]{} 出错: {:?}",option,e);
+            return;
+        }
+    }
+    println!("[*]interpreter: {}",editor.interpreter().unwrap_or_default());
+    println!("[*]soname: {}",editor.soname().unwrap_or_default());
+    println!("[*]rpath: {}",editor.rpath().unwrap_or_default());
+    println!("[*]runpath: {}",editor.runpath().unwrap_or_default());
+    println!("[*]needed:");
+    for library in editor.needed(){
+        println!("  {}",library);
+    }
+    if let Some(output)=output {
+        if let Err(e)=editor.write(output){
+            println!("[!]写入文件出错: {:?}",e);
+        }
+    }
+}
+
+fn edit_sections(input:&str,output:&str,options:&[String]){
+    let mut editor=match parser::section_edit::SectionEditor::read_editor(input) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!("[!]解析elf出错: {:?}",e);
+            return;
+        }
+    };
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        let mut value=|| options.next().unwrap_or_default();
+        let result=match option {
+            "--add" | "--add-load" | "--replace" => {
+                let name=value();
+                let file=value();
+                let data=match std::fs::read(file) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        println!("[!]读取{}出错: {}",file,e);
+                        return;
+                    }
+                };
+                let header=parser::section::SectionHeader::new(name,parser::abi::SHT_PROGBITS,0);
+                match option {
+                    "--add" => {
+                        editor.add_section(header,data);
+                        Ok(())
+                    }
+                    "--add-load" => editor.add_loaded_section(header,data).map(|_| ()),
+                    _ => editor.replace_section(name,data),
+                }
+            }
+            "--remove" => editor.remove_section(value()),
+            "--rename" => {
+                let name=value();
+                editor.rename_section(name,value())
+            }
+            _ => {
+                println!("[!]未知选项: {}",option);
+                return;
+            }
+        };
+        if let Err(e)=result {
+            println!("[!]{} 出错: {:?}",option,e);
+            return;
+        }
+    }
+    if let Err(e)=editor.write(output){
+        println!("[!]写入文件出错: {:?}",e);
         return;
     }
+    println!("[*]节头表:");
+    for sh in editor.section_headers(){
+        println!("  [{:>2}] {}",sh.index,sh.string_name);
+    }
+}
+
+fn strip_elf(input:&str,output:&str,options:&[String]){
+    let mut editor=match parser::section_edit::SectionEditor::read_editor(input) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!("[!]解析elf出错: {:?}",e);
+            return;
+        }
+    };
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        let result=match option {
+            "--strip-all" => editor.strip(parser::strip::StripMode::All),
+            "--strip-debug" => editor.strip(parser::strip::StripMode::Debug),
+            "--strip-unneeded" => editor.strip(parser::strip::StripMode::Unneeded),
+            //只保留调试信息,输出单独的调试文件
+            "--only-keep-debug" => {
+                if let Err(e)=std::fs::write(output,editor.only_keep_debug()){
+                    println!("[!]写入文件出错: {}",e);
+                }
+                return;
+            }
+            "--add-gnu-debuglink" => {
+                let debug_file=options.next().unwrap_or_default();
+                let data=match std::fs::read(debug_file) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        println!("[!]读取{}出错: {}",debug_file,e);
+                        return;
+                    }
+                };
+                let filename=std::path::Path::new(debug_file).file_name().and_then(|name| name.to_str()).unwrap_or(debug_file);
+                let link=parser::strip::DebugLink::new(filename,&data);
+                println!("[*]gnu_debuglink: {} crc {:#010x}",link.filename,link.crc);
+                editor.add_gnu_debuglink(&link);
+                Ok(())
+            }
+            "-R" | "--remove-section" => editor.remove_section(options.next().unwrap_or_default()),
+            _ => {
+                println!("[!]未知选项: {}",option);
+                return;
+            }
+        };
+        if let Err(e)=result {
+            println!("[!]{} 出错: {:?}",option,e);
+            return;
+        }
+    }
+    if let Err(e)=editor.write(output){
+        println!("[!]写入文件出错: {:?}",e);
+    }
+}
+
+fn edit_symbols(input:&str,output:&str,options:&[String]){
+    let mut editor=match parser::section_edit::SectionEditor::read_editor(input) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!("[!]解析elf出错: {:?}",e);
+            return;
+        }
+    };
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        let value=options.next().unwrap_or_default();
+        let result=match option {
+            "--redefine-sym" => match value.split_once('=') {
+                Some((old,new)) => editor.redefine_symbol(old,new),
+                None => {
+                    println!("[!]--redefine-sym 需要 OLD=NEW");
+                    return;
+                }
+            },
+            "--localize-symbol" => editor.localize_symbol(value),
+            "--weaken-symbol" => editor.weaken_symbol(value),
+            "--prefix-symbols" => editor.prefix_symbols(value),
+            _ => {
+                println!("[!]未知选项: {}",option);
+                return;
+            }
+        };
+        match result {
+            Ok(count) => println!("[*]{} {}: 修改了{}个符号",option,value,count),
+            Err(e) => {
+                println!("[!]{} 出错: {:?}",option,e);
+                return;
+            }
+        }
+    }
+    if let Err(e)=editor.write(output){
+        println!("[!]写入文件出错: {:?}",e);
+    }
+}
+
+fn export_image(input:&str,output:&str,options:&[String]){
+    let Some(image)=parser::firmware::FirmwareImage::read_image(input) else {
+        println!("[!]解析elf出错");
+        return;
+    };
+    //默认按输出文件扩展名选择格式
+    let mut format=std::path::Path::new(output).extension().and_then(|ext| ext.to_str()).unwrap_or("bin").to_lowercase();
+    let mut gap_fill=None;
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        let value=options.next().unwrap_or_default();
+        match option {
+            "--format" => format=value.to_lowercase(),
+            "--gap-fill" => match u8::from_str_radix(value.trim_start_matches("0x"),16) {
+                Ok(pad) => gap_fill=Some(pad),
+                Err(_) => {
+                    println!("[!]--gap-fill 需要十六进制字节");
+                    return;
+                }
+            },
+            _ => {
+                println!("[!]未知选项: {}",option);
+                return;
+            }
+        }
+    }
+    println!("[*]装载地址 {:#x}-{:#x}, {}段",image.start(),image.end(),image.chunks.len());
+    let image=match gap_fill {
+        Some(pad) => image.fill_gaps(pad),
+        None => image,
+    };
+    let data=match format.as_str() {
+        "hex" | "ihex" => image.to_ihex().map(String::into_bytes),
+        "srec" | "mot" => image.to_srec(None).map(String::into_bytes),
+        "s19" => image.to_srec(Some(parser::firmware::SrecFormat::S19)).map(String::into_bytes),
+        "s28" => image.to_srec(Some(parser::firmware::SrecFormat::S28)).map(String::into_bytes),
+        "s37" => image.to_srec(Some(parser::firmware::SrecFormat::S37)).map(String::into_bytes),
+        _ => Ok(image.to_binary(gap_fill.unwrap_or(0))),
+    };
+    match data {
+        Ok(data) => if let Err(e)=std::fs::write(output,data) {
+            println!("[!]写入文件出错: {}",e);
+        },
+        Err(e) => println!("[!]导出出错: {:?}",e),
+    }
+}
+
+fn import_image(input:&str,output:&str,options:&[String]){
+    use parser::endian::EndianParse;
+    let text=match std::fs::read_to_string(input) {
+        Ok(text) => text,
+        Err(e) => {
+            println!("[!]读取{}出错: {}",input,e);
+            return;
+        }
+    };
+    //以':'开头的是Intel HEX,否则按S-record解析
+    let image=match text.trim_start().starts_with(':') {
+        true => parser::firmware::FirmwareImage::parse_ihex(&text),
+        false => parser::firmware::FirmwareImage::parse_srec(&text),
+    };
+    let image=match image {
+        Ok(image) => image,
+        Err(e) => {
+            println!("[!]解析{}出错: {:?}",input,e);
+            return;
+        }
+    };
+    let mut class=parser::file::Class::ELF32;
+    let mut endian=parser::abi::ELFDATA2LSB;
+    let mut machine=parser::abi::EM_ARM;
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        match option {
+            "--elf64" => class=parser::file::Class::ELF64,
+            "--big-endian" => endian=parser::abi::ELFDATA2MSB,
+            "--machine" => match options.next().unwrap_or_default().parse() {
+                Ok(value) => machine=value,
+                Err(_) => {
+                    println!("[!]--machine 需要e_machine数值");
+                    return;
+                }
+            },
+            _ => {
+                println!("[!]未知选项: {}",option);
+                return;
+            }
+        }
+    }
+    println!("[*]装载地址 {:#x}-{:#x}, {}段",image.start(),image.end(),image.chunks.len());
+    let data=image.to_elf(class,parser::endian::AnyEndian::new(endian),machine);
+    if let Err(e)=std::fs::write(output,data) {
+        println!("[!]写入文件出错: {}",e);
+    }
+}
+
+fn patch_elf(input:&str,output:&str,options:&[String]){
+    let mut patches=Vec::new();
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        let result=match option {
+            //单个补丁: --at VADDR BYTES
+            "--at" => {
+                let line=format!("{}: {}",options.next().unwrap_or_default(),options.next().unwrap_or_default());
+                parser::patch::Patch::parse_text(&line)
+            }
+            file_path => parser::patch::Patch::read_patches(file_path),
+        };
+        match result {
+            Ok(list) => patches.extend(list),
+            Err(e) => {
+                println!("[!]{} 解析补丁出错: {:?}",option,e);
+                return;
+            }
+        }
+    }
+    let hex=|bytes:&[u8]| bytes.iter().map(|b| format!("{:02x}",b)).collect::<Vec<_>>().join(" ");
+    match parser::patch::patch_file(input,output,&patches) {
+        Ok(applied) => {
+            for patch in applied {
+                println!("[*]{:#x} (文件偏移 {:#x}): {} -> {}",patch.vaddr,patch.offset,hex(&patch.old),hex(&patch.new));
+            }
+        }
+        Err(e) => println!("[!]打补丁出错: {:?}",e),
+    }
+}
+
+fn write_stub(input:&str,output:&str,options:&[String]){
+    let mut machine=None;
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        match (option,options.clone().next()) {
+            ("--machine",Some("x86_64"|"x86-64")) => machine=Some(parser::abi::EM_X86_64),
+            ("--machine",Some("aarch64"|"arm64")) => machine=Some(parser::abi::EM_AARCH64),
+            _ => {
+                println!("[!]未知选项: {} (支持 --machine x86_64|aarch64)",option);
+                return;
+            }
+        }
+        options.next();
+    }
+    let library=match parser::stub::StubLibrary::read_stub(input) {
+        Ok(library) => library,
+        Err(e) => {
+            println!("[!]解析{}出错: {:?}",input,e);
+            return;
+        }
+    };
+    let data=match library.build(machine) {
+        Ok(data) => data,
+        Err(e) => {
+            println!("[!]生成桩库出错: {:?}",e);
+            return;
+        }
+    };
+    match std::fs::write(output,data) {
+        Ok(()) => println!("[*]已写入{}: SONAME {},导出符号{}个,版本定义{}个",output,library.soname.as_deref().unwrap_or("-"),library.symbols.len(),library.versions.len()),
+        Err(e) => println!("[!]写入{}出错: {}",output,e),
+    }
+}
+
+fn list_archive(file_path:&str,symbols:&[String]){
+    let archive=match parser::archive::Archive::read_archive(file_path) {
+        Ok(archive) => archive,
+        Err(e) => {
+            println!("[!]解析{}出错: {:?}",file_path,e);
+            return;
+        }
+    };
+    println!("[*]{}{}: 成员{}个,符号索引{}项",file_path,if archive.thin { " (thin)" } else { "" },archive.members.len(),archive.symbols.len());
+    for member in &archive.members {
+        let kind=match member.elf() {
+            Some(elf) => format!("ELF type {} machine {}",elf.header.e_type,elf.header.e_machine),
+            None => "非ELF".to_string(),
+        };
+        println!("    {:#010x} {:>10} {:o} {} ({})",member.offset,member.size,member.mode,member.name,kind);
+    }
+    for symbol in symbols {
+        match archive.find_symbol(symbol) {
+            Some(member) => println!("[*]{} 定义于 {}",symbol,member.name),
+            None => println!("[!]{} 未定义",symbol),
+        }
+    }
+}
+
+fn scan_container(file_path:&str){
+    let scan=match parser::container::ContainerScan::read_container(file_path) {
+        Ok(scan) => scan,
+        Err(e) => {
+            println!("[!]读取{}出错: {:?}",file_path,e);
+            return;
+        }
+    };
+    println!("[*]{}: 找到ELF {}个",file_path,scan.elves.len());
+    for found in &scan.elves {
+        let header=&found.elf.header;
+        println!("    {} (type {} machine {} 大小{})",found.path,header.e_type,header.e_machine,found.elf.data.len());
+    }
+    for (path,error) in &scan.errors {
+        println!("[!]{}: {:?}",path,error);
+    }
+    //只解gzip;默认的dpkg-deb用xz,Ubuntu用zstd
+    if scan.errors.iter().any(|(_,error)| matches!(error,parser::container::ContainerError::Unsupported(name) if name=="xz" || name=="zstd")) {
+        println!("[!]只支持gzip压缩的层,xz/zstd请先解压(例如dpkg-deb -x)再扫描");
+    }
+}
+
+fn carve_elves(file_path:&str,options:&[String]){
+    let output_dir=match options {
+        [] => None,
+        [option,dir] if option=="--extract" => Some(dir.as_str()),
+        _ => {
+            println!("[!]未知选项: {} (支持 --extract DIR)",options.join(" "));
+            return;
+        }
+    };
+    let blob=match std::fs::read(file_path) {
+        Ok(blob) => blob,
+        Err(e) => {
+            println!("[!]读取{}出错: {}",file_path,e);
+            return;
+        }
+    };
+    let carved=parser::carve::CarvedElf::carve(&blob);
+    println!("[*]{}: 找到ELF {}个",file_path,carved.len());
+    for elf in &carved {
+        let class=match elf.class() {
+            parser::file::Class::ELF32 => "ELF32",
+            parser::file::Class::ELF64 => "ELF64",
+        };
+        println!("    偏移{:#010x} {} type {} machine {} 大小{:#x}{}",elf.offset,class,elf.header.e_type,elf.header.e_machine,elf.size,if elf.truncated { " (被截断)" } else { "" });
+        if let Some(dir)=output_dir {
+            let path=std::path::Path::new(dir).join(format!("{:08x}.elf",elf.offset));
+            if let Err(e)=std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path,elf.image(&blob))) {
+                println!("[!]写入{}出错: {}",path.display(),e);
+            }
+        }
+    }
+}
+
+fn show_core(file_path:&str,options:&[String]){
+    use parser::coredump::{CoreDump, FpRegisters};
+    let Some(elf)=parser::elf_file::ElfFile::read_elf(file_path) else {
+        println!("[!]解析{}出错",file_path);
+        return;
+    };
+    let core=match CoreDump::parse_core(&elf) {
+        Ok(core) => core,
+        Err(e) => {
+            println!("[!]{}不是core文件: {:?}",file_path,e);
+            return;
+        }
+    };
+    if let Some(process)=&core.process {
+        println!("[*]进程: {} pid {} ppid {} uid {} gid {} 状态{} 命令行: {}",process.fname,process.pid,process.ppid,process.uid,process.gid,process.sname,process.psargs);
+    }
+    if let Some(siginfo)=&core.siginfo {
+        let addr=siginfo.addr.map(|addr| format!(" 地址{:#x}",addr)).unwrap_or_default();
+        let sender=siginfo.sender.map(|(pid,uid)| format!(" 发送者pid {} uid {}",pid,uid)).unwrap_or_default();
+        println!("[*]信号: {} code {} errno {}{}{}",siginfo.signo,siginfo.code,siginfo.errno,addr,sender);
+    }
+    for (i,thread) in core.threads.iter().enumerate() {
+        let status=&thread.status;
+        println!("[*]线程{}: pid {} 信号{} utime {}.{:06}s stime {}.{:06}s",i+1,status.pid,status.cursig,status.utime.0,status.utime.1,status.stime.0,status.stime.1);
+        for row in status.registers.chunks(4) {
+            let row:Vec<String>=row.iter().map(|(name,value)| format!("{:>8} {:#018x}",name,value)).collect();
+            println!("    {}",row.join(" "));
+        }
+        match &thread.fpregs {
+            Some(FpRegisters::X86_64{fcw,fsw,mxcsr,xmm,..}) => {
+                println!("    fcw {:#06x} fsw {:#06x} mxcsr {:#010x} xmm0 {:#034x}",fcw,fsw,mxcsr,xmm[0]);
+            }
+            Some(FpRegisters::Aarch64{v,fpsr,fpcr}) => {
+                println!("    fpsr {:#010x} fpcr {:#010x} v0 {:#034x}",fpsr,fpcr,v[0]);
+            }
+            Some(FpRegisters::Raw(data)) => println!("    浮点寄存器{}字节",data.len()),
+            None => {}
+        }
+    }
+    println!("[*]映射文件{}个 (页大小{:#x})",core.files.len(),core.page_size);
+    for file in &core.files {
+        println!("    {:#014x}-{:#014x} 偏移{:#010x} {}",file.start,file.end,file.offset,file.path);
+    }
+    println!("[*]auxv:");
+    for &(a_type,value) in &core.auxv {
+        //字符串类的项顺便读出内容
+        let text=match a_type {
+            parser::abi::AT_EXECFN|parser::abi::AT_PLATFORM => elf.read_memory_string(value,256).map(|s| format!(" \"{}\"",s)).unwrap_or_default(),
+            _ => String::new(),
+        };
+        println!("    {:<18} {:#x}{}",CoreDump::auxv_name(a_type),value,text);
+    }
+    let mut options=options.iter().map(|s| s.as_str());
+    while let Some(option)=options.next() {
+        //先认选项名,未知选项不去吃后面的参数
+        if option!="--read" {
+            println!("[!]未知选项: {}",option);
+            return;
+        }
+        let number=|text:Option<&str>| text.and_then(|text| match text.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex,16).ok(),
+            None => text.parse().ok(),
+        });
+        let (Some(vaddr),Some(len))=(number(options.next()),number(options.next())) else {
+            println!("[!]用法: --read VADDR LEN");
+            return;
+        };
+        match elf.read_memory(vaddr,len) {
+            Ok(data) => {
+                let backing=core.file_at(vaddr).map(|(file,offset)| format!(" ({} +{:#x})",file.path,offset)).unwrap_or_default();
+                println!("[*]{:#x}{}:",vaddr,backing);
+                for (i,line) in data.chunks(16).enumerate() {
+                    let hex:Vec<String>=line.iter().map(|b| format!("{:02x}",b)).collect();
+                    let ascii:String=line.iter().map(|&b| if b.is_ascii_graphic() || b==b' ' { b as char } else { '.' }).collect();
+                    println!("    {:#014x} {:<47} {}",vaddr+i as u64*16,hex.join(" "),ascii);
+                }
+            }
+            Err(e) => println!("[!]读取{:#x}出错: {:?}",vaddr,e),
+        }
+    }
+}
+
+fn show_backtrace(file_path:&str,options:&[String]){
+    use parser::coredump::CoreDump;
+    use parser::unwind::{FrameSource, Unwinder};
+    let sysroot=match options {
+        [] => None,
+        [option,root] if option=="--sysroot" => Some(root.as_str()),
+        _ => {
+            println!("[!]用法: backtrace CORE [--sysroot DIR]");
+            return;
+        }
+    };
+    let Some(elf)=parser::elf_file::ElfFile::read_elf(file_path) else {
+        println!("[!]解析{}出错",file_path);
+        return;
+    };
+    let core=match CoreDump::parse_core(&elf) {
+        Ok(core) => core,
+        Err(e) => {
+            println!("[!]{}不是core文件: {:?}",file_path,e);
+            return;
+        }
+    };
+    let unwinder=Unwinder::new(&elf,&core,sysroot);
+    let mut missing:Vec<&str>=Vec::new();
+    for file in &core.files {
+        if unwinder.module_at(file.start).is_none() && !missing.contains(&file.path.as_str()) {
+            println!("[!]无法加载{}",file.path);
+            missing.push(&file.path);
+        }
+    }
+    for (i,thread) in core.threads.iter().enumerate() {
+        println!("[*]线程{} (pid {}):",i+1,thread.status.pid);
+        for (n,frame) in unwinder.backtrace(thread).iter().enumerate() {
+            let symbol=match &frame.symbol {
+                Some((name,offset)) => format!("{}+{:#x}",name,offset),
+                None => "??".to_string(),
+            };
+            let source=match frame.source {
+                FrameSource::Registers => "寄存器",
+                FrameSource::Cfi => "CFI",
+                FrameSource::FramePointer => "帧指针",
+            };
+            println!("    #{:<3} {:#018x} {} ({}) [{}]",n,frame.pc,symbol,frame.module.as_deref().unwrap_or("??"),source);
+        }
+    }
+}
+
+fn show_eh_frame(file_path:&str,addresses:&[String]){
+    use parser::eh_frame::{CfaRule, EhFrame, EhFrameHdr, Pointer, RegisterRule};
+    let Some(elf)=parser::elf_file::ElfFile::read_elf(file_path) else {
+        println!("[!]解析{}出错",file_path);
+        return;
+    };
+    let eh_frame=match EhFrame::parse_eh_frame(&elf) {
+        Ok(eh_frame) => eh_frame,
+        Err(e) => {
+            println!("[!]解析.eh_frame出错: {:?}",e);
+            return;
+        }
+    };
+    let pointer=|pointer:Option<Pointer>| match pointer {
+        Some(Pointer::Direct(address)) => format!("{:#x}",address),
+        Some(Pointer::Indirect(address)) => format!("*{:#x}",address),
+        None => "-".to_string(),
+    };
+    println!("[*].eh_frame {:#x}: CIE {}个 FDE {}个",eh_frame.address,eh_frame.cies.len(),eh_frame.fdes.len());
+    for cie in &eh_frame.cies {
+        let lsda=cie.lsda_encoding.map(|encoding| format!(" LSDA编码{:#04x}",encoding)).unwrap_or_default();
+        println!("    CIE {:#010x} \"{}\" 代码对齐{} 数据对齐{} 返回地址r{} FDE编码{:#04x}{} personality {}{}",
+            cie.offset,cie.augmentation,cie.code_alignment,cie.data_alignment,cie.return_register,cie.fde_encoding,lsda,pointer(cie.personality),
+            if cie.signal_frame { " 信号帧" } else { "" });
+    }
+    match EhFrameHdr::parse_eh_frame_hdr(&elf) {
+        Ok(hdr) => {
+            let mismatches=hdr.verify(&eh_frame);
+            println!("[*].eh_frame_hdr {:#x}: {}项",hdr.address,hdr.table.len());
+            if mismatches.is_empty() {
+                println!("[*]查找表与FDE一致");
+            }
+            for mismatch in mismatches {
+                println!("[!]{:?}",mismatch);
+            }
+        }
+        Err(e) => println!("[!]没有可用的.eh_frame_hdr: {:?}",e),
+    }
+    let register=|register:u16| format!("r{}",register);
+    for address in addresses {
+        let Ok(address)=u64::from_str_radix(address.trim_start_matches("0x"),16) else {
+            println!("[!]地址格式错误: {}",address);
+            continue;
+        };
+        let Some(fde)=eh_frame.find_fde(address) else {
+            println!("[!]{:#x}没有FDE",address);
+            continue;
+        };
+        println!("[*]{:#x}: FDE {:#010x} {:#x}-{:#x} LSDA {}",address,fde.offset,fde.pc_begin,fde.pc_begin+fde.pc_range,pointer(fde.lsda));
+        let rows=match eh_frame.rows(fde) {
+            Ok(rows) => rows,
+            Err(e) => {
+                println!("[!]执行CFA指令出错: {:?}",e);
+                continue;
+            }
+        };
+        for row in rows {
+            let cfa=match &row.cfa {
+                CfaRule::RegisterOffset(r,offset) => format!("{}{:+}",register(*r),offset),
+                CfaRule::Expression(expression) => format!("表达式({}字节)",expression.len()),
+            };
+            let rules:Vec<String>=row.registers.iter().map(|(r,rule)| {
+                let rule=match rule {
+                    RegisterRule::Undefined => "未定义".to_string(),
+                    RegisterRule::SameValue => "不变".to_string(),
+                    RegisterRule::Offset(offset) => format!("c{:+}",offset),
+                    RegisterRule::ValOffset(offset) => format!("=c{:+}",offset),
+                    RegisterRule::Register(other) => register(*other),
+                    RegisterRule::Expression(expression) => format!("*表达式({}字节)",expression.len()),
+                    RegisterRule::ValExpression(expression) => format!("=表达式({}字节)",expression.len()),
+                };
+                format!("{}={}",register(*r),rule)
+            }).collect();
+            let marker=if address>=row.start && address<row.end { "=>" } else { "  " };
+            println!("  {} {:#x}-{:#x} CFA={} {}",marker,row.start,row.end,cfa,rules.join(" "));
+        }
+    }
 }
@@ -11,6 +11,14 @@ pub const ELFMAGIC: [u8; 4] = [ELFMAG0, ELFMAG1, ELFMAG2, ELFMAG3];
 pub const EI_CLASS: usize = 4;
 /// Location of data format field in ELF file header ident array
 pub const EI_DATA: usize = 5;
+/// Location of ELF version field in ELF file header ident array
+pub const EI_VERSION: usize = 6;
+/// Location of OS ABI field in ELF file header ident array
+pub const EI_OSABI: usize = 7;
+/// Location of ABI version field in ELF file header ident array
+pub const EI_ABIVERSION: usize = 8;
+/// Current ELF version
+pub const EV_CURRENT: u8 = 1;
 /// Invalid ELF data format
 pub const ELFDATANONE: u8 = 0;
 /// 2's complement values, with the least significant byte occupying the lowest address.
@@ -24,3 +32,476 @@ pub const ELFCLASS32: u8 = 1;
 /// 64-bit ELF file
 pub const ELFCLASS64: u8 = 2;
 
+
+
+/// Relocatable file
+pub const ET_REL: u16 = 1;
+/// Executable file
+pub const ET_EXEC: u16 = 2;
+/// Shared object file
+pub const ET_DYN: u16 = 3;
+/// Core file
+pub const ET_CORE: u16 = 4;
+
+/// Intel 80386
+pub const EM_386: u16 = 3;
+/// ARM 32-bit architecture (AARCH32)
+pub const EM_ARM: u16 = 40;
+/// AMD x86-64 architecture
+pub const EM_X86_64: u16 = 62;
+/// ARM 64-bit architecture (AARCH64)
+pub const EM_AARCH64: u16 = 183;
+/// RISC-V
+pub const EM_RISCV: u16 = 243;
+
+/// Program data
+pub const SHT_PROGBITS: u32 = 1;
+/// Symbol table
+pub const SHT_SYMTAB: u32 = 2;
+/// String table
+pub const SHT_STRTAB: u32 = 3;
+/// Relocation entries with addends
+pub const SHT_RELA: u32 = 4;
+/// Dynamic linking information
+pub const SHT_DYNAMIC: u32 = 6;
+/// Program space with no data (bss)
+pub const SHT_NOBITS: u32 = 8;
+/// Relocation entries, no addends
+pub const SHT_REL: u32 = 9;
+/// Dynamic linker symbol table
+pub const SHT_DYNSYM: u32 = 11;
+/// Section group
+pub const SHT_GROUP: u32 = 17;
+/// GNU-style hash table
+pub const SHT_GNU_HASH: u32 = 0x6ffffff6;
+
+/// Section occupies memory during execution
+pub const SHF_ALLOC: u64 = 0x2;
+/// Section contains executable machine instructions
+pub const SHF_EXECINSTR: u64 = 0x4;
+
+/// Unused program header entry
+pub const PT_NULL: u32 = 0;
+/// Loadable program segment
+pub const PT_LOAD: u32 = 1;
+
+/// i386: Create GOT entry
+pub const R_386_GLOB_DAT: u32 = 6;
+/// i386: Create PLT entry
+pub const R_386_JMP_SLOT: u32 = 7;
+/// x86-64: Create GOT entry
+pub const R_X86_64_GLOB_DAT: u32 = 6;
+/// x86-64: Create PLT entry
+pub const R_X86_64_JUMP_SLOT: u32 = 7;
+/// ARM: Create GOT entry
+pub const R_ARM_GLOB_DAT: u32 = 21;
+/// ARM: Create PLT entry
+pub const R_ARM_JUMP_SLOT: u32 = 22;
+/// AArch64: Create GOT entry
+pub const R_AARCH64_GLOB_DAT: u32 = 1025;
+/// AArch64: Create PLT entry
+pub const R_AARCH64_JUMP_SLOT: u32 = 1026;
+
+/// Undefined section index
+pub const SHN_UNDEF: u16 = 0;
+
+/// Local symbol
+pub const STB_LOCAL: u8 = 0;
+/// Global symbol
+pub const STB_GLOBAL: u8 = 1;
+/// Weak symbol
+pub const STB_WEAK: u8 = 2;
+/// Symbol type is not specified
+pub const STT_NOTYPE: u8 = 0;
+/// Symbol is a data object
+pub const STT_OBJECT: u8 = 1;
+/// Symbol is a code object
+pub const STT_FUNC: u8 = 2;
+/// Symbol associated with a section
+pub const STT_SECTION: u8 = 3;
+/// Symbol's name is file name
+pub const STT_FILE: u8 = 4;
+/// Symbol is a common data object
+pub const STT_COMMON: u8 = 5;
+/// Symbol is a thread-local storage object
+pub const STT_TLS: u8 = 6;
+/// Symbol is an indirect function, resolved at load time
+pub const STT_GNU_IFUNC: u8 = 10;
+/// Unique global symbol (GNU extension)
+pub const STB_GNU_UNIQUE: u8 = 10;
+
+/// x86-64: No reloc
+pub const R_X86_64_NONE: u32 = 0;
+/// x86-64: Direct 64 bit
+pub const R_X86_64_64: u32 = 1;
+/// x86-64: Adjust by program base
+pub const R_X86_64_RELATIVE: u32 = 8;
+/// x86-64: Adjust indirectly by program base
+pub const R_X86_64_IRELATIVE: u32 = 37;
+/// AArch64: No reloc
+pub const R_AARCH64_NONE: u32 = 0;
+/// AArch64: Direct 64 bit
+pub const R_AARCH64_ABS64: u32 = 257;
+/// AArch64: Adjust by program base
+pub const R_AARCH64_RELATIVE: u32 = 1027;
+/// AArch64: Adjust indirectly by program base
+pub const R_AARCH64_IRELATIVE: u32 = 1032;
+
+/// Start of reserved section indices
+pub const SHN_LORESERVE: u16 = 0xff00;
+/// Absolute value, not affected by relocation
+pub const SHN_ABS: u16 = 0xfff1;
+/// Common symbol, not yet allocated
+pub const SHN_COMMON: u16 = 0xfff2;
+/// The real index is elsewhere: e_shstrndx in section 0's sh_link, symbol indices in SHT_SYMTAB_SHNDX
+pub const SHN_XINDEX: u16 = 0xffff;
+/// e_phnum value meaning the real count is in section 0's sh_info
+pub const PN_XNUM: u16 = 0xffff;
+
+/// x86-64: PC relative 32 bit signed
+pub const R_X86_64_PC32: u32 = 2;
+/// x86-64: 32 bit PLT address
+pub const R_X86_64_PLT32: u32 = 4;
+/// x86-64: Direct 32 bit zero extended
+pub const R_X86_64_32: u32 = 10;
+/// x86-64: Direct 32 bit sign extended
+pub const R_X86_64_32S: u32 = 11;
+/// x86-64: PC relative 64 bit
+pub const R_X86_64_PC64: u32 = 24;
+
+/// AArch64: Direct 32 bit
+pub const R_AARCH64_ABS32: u32 = 258;
+/// AArch64: PC-relative 64 bit
+pub const R_AARCH64_PREL64: u32 = 260;
+/// AArch64: PC-relative 32 bit
+pub const R_AARCH64_PREL32: u32 = 261;
+/// AArch64: ADR, PC-relative 21 bit
+pub const R_AARCH64_ADR_PREL_LO21: u32 = 274;
+/// AArch64: ADRP, page-relative 21 bit
+pub const R_AARCH64_ADR_PREL_PG_HI21: u32 = 275;
+/// AArch64: ADRP, no overflow check
+pub const R_AARCH64_ADR_PREL_PG_HI21_NC: u32 = 276;
+/// AArch64: ADD, low 12 bits of address
+pub const R_AARCH64_ADD_ABS_LO12_NC: u32 = 277;
+/// AArch64: LD/ST 8 bit, low 12 bits of address
+pub const R_AARCH64_LDST8_ABS_LO12_NC: u32 = 278;
+/// AArch64: B.cond, PC-relative 19 bit
+pub const R_AARCH64_CONDBR19: u32 = 280;
+/// AArch64: B, PC-relative 26 bit
+pub const R_AARCH64_JUMP26: u32 = 282;
+/// AArch64: BL, PC-relative 26 bit
+pub const R_AARCH64_CALL26: u32 = 283;
+/// AArch64: LD/ST 16 bit, low 12 bits of address
+pub const R_AARCH64_LDST16_ABS_LO12_NC: u32 = 284;
+/// AArch64: LD/ST 32 bit, low 12 bits of address
+pub const R_AARCH64_LDST32_ABS_LO12_NC: u32 = 285;
+/// AArch64: LD/ST 64 bit, low 12 bits of address
+pub const R_AARCH64_LDST64_ABS_LO12_NC: u32 = 286;
+/// AArch64: LD/ST 128 bit, low 12 bits of address
+pub const R_AARCH64_LDST128_ABS_LO12_NC: u32 = 299;
+
+/// RISC-V: No reloc
+pub const R_RISCV_NONE: u32 = 0;
+/// RISC-V: Direct 32 bit
+pub const R_RISCV_32: u32 = 1;
+/// RISC-V: Direct 64 bit
+pub const R_RISCV_64: u32 = 2;
+/// RISC-V: PC-relative branch (B-type)
+pub const R_RISCV_BRANCH: u32 = 16;
+/// RISC-V: PC-relative jump (J-type)
+pub const R_RISCV_JAL: u32 = 17;
+/// RISC-V: PC-relative call (auipc + jalr)
+pub const R_RISCV_CALL: u32 = 18;
+/// RISC-V: PC-relative call through the PLT (auipc + jalr)
+pub const R_RISCV_CALL_PLT: u32 = 19;
+/// RISC-V: PC-relative high 20 bits (auipc)
+pub const R_RISCV_PCREL_HI20: u32 = 23;
+/// RISC-V: Low 12 bits of the PCREL_HI20 value, I-type
+pub const R_RISCV_PCREL_LO12_I: u32 = 24;
+/// RISC-V: Low 12 bits of the PCREL_HI20 value, S-type
+pub const R_RISCV_PCREL_LO12_S: u32 = 25;
+/// RISC-V: Absolute high 20 bits (lui)
+pub const R_RISCV_HI20: u32 = 26;
+/// RISC-V: Absolute low 12 bits, I-type
+pub const R_RISCV_LO12_I: u32 = 27;
+/// RISC-V: Absolute low 12 bits, S-type
+pub const R_RISCV_LO12_S: u32 = 28;
+/// RISC-V: 32 bit label addition
+pub const R_RISCV_ADD32: u32 = 35;
+/// RISC-V: 64 bit label addition
+pub const R_RISCV_ADD64: u32 = 36;
+/// RISC-V: 32 bit label subtraction
+pub const R_RISCV_SUB32: u32 = 39;
+/// RISC-V: 64 bit label subtraction
+pub const R_RISCV_SUB64: u32 = 40;
+/// RISC-V: Alignment requirement for linker relaxation
+pub const R_RISCV_ALIGN: u32 = 43;
+/// RISC-V: PC-relative compressed branch (CB-type)
+pub const R_RISCV_RVC_BRANCH: u32 = 44;
+/// RISC-V: PC-relative compressed jump (CJ-type)
+pub const R_RISCV_RVC_JUMP: u32 = 45;
+/// RISC-V: Instruction may be relaxed by the linker
+pub const R_RISCV_RELAX: u32 = 51;
+
+/// Dynamic linking information segment
+pub const PT_DYNAMIC: u32 = 2;
+/// Thread-local storage template
+pub const PT_TLS: u32 = 7;
+
+/// Symbol hash table
+pub const SHT_HASH: u32 = 5;
+/// Version definition section
+pub const SHT_GNU_VERDEF: u32 = 0x6ffffffd;
+/// Version needs section
+pub const SHT_GNU_VERNEED: u32 = 0x6ffffffe;
+/// Version symbol table
+pub const SHT_GNU_VERSYM: u32 = 0x6fffffff;
+
+/// Marks end of dynamic section
+pub const DT_NULL: i64 = 0;
+/// Name of needed library
+pub const DT_NEEDED: i64 = 1;
+/// Address of symbol hash table
+pub const DT_HASH: i64 = 4;
+/// Address of string table
+pub const DT_STRTAB: i64 = 5;
+/// Address of symbol table
+pub const DT_SYMTAB: i64 = 6;
+/// Size of string table
+pub const DT_STRSZ: i64 = 10;
+/// Size of one symbol table entry
+pub const DT_SYMENT: i64 = 11;
+/// GNU-style hash table
+pub const DT_GNU_HASH: i64 = 0x6ffffef5;
+/// Address of the version symbol table
+pub const DT_VERSYM: i64 = 0x6ffffff0;
+/// Address of version definition table
+pub const DT_VERDEF: i64 = 0x6ffffffc;
+/// Number of version definitions
+pub const DT_VERDEFNUM: i64 = 0x6ffffffd;
+/// Address of table with needed versions
+pub const DT_VERNEED: i64 = 0x6ffffffe;
+/// Number of needed versions
+pub const DT_VERNEEDNUM: i64 = 0x6fffffff;
+
+/// Symbol is local to the object (versym index)
+pub const VER_NDX_LOCAL: u16 = 0;
+/// Symbol is global and unversioned (versym index)
+pub const VER_NDX_GLOBAL: u16 = 1;
+/// Versym bit marking a hidden, non-default version
+pub const VERSYM_HIDDEN: u16 = 0x8000;
+/// Version definition of the file itself
+pub const VER_FLG_BASE: u16 = 0x1;
+/// Weak version identifier
+pub const VER_FLG_WEAK: u16 = 0x2;
+
+/// Auxiliary information segment
+pub const PT_NOTE: u32 = 4;
+/// Notes section
+pub const SHT_NOTE: u32 = 7;
+
+/// GNU: ABI information (OS and minimum kernel version)
+pub const NT_GNU_ABI_TAG: u32 = 1;
+/// GNU: Unique build ID bitstring
+pub const NT_GNU_BUILD_ID: u32 = 3;
+/// GNU: Version of gold linker
+pub const NT_GNU_GOLD_VERSION: u32 = 4;
+/// GNU: Program property
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+/// FDO: Packaging metadata (`.note.package`)
+pub const NT_FDO_PACKAGING_METADATA: u32 = 0xcafe1a7e;
+/// Go: Build ID
+pub const NT_GO_BUILD_ID: u32 = 4;
+/// Android: API level and NDK version
+pub const NT_ANDROID_TYPE_IDENT: u32 = 1;
+
+/// Core: thread status and general-purpose registers (`prstatus`)
+pub const NT_PRSTATUS: u32 = 1;
+/// Core: floating point registers
+pub const NT_FPREGSET: u32 = 2;
+/// Core: process information (`prpsinfo`)
+pub const NT_PRPSINFO: u32 = 3;
+/// Core: auxiliary vector
+pub const NT_AUXV: u32 = 6;
+/// Core: siginfo_t of the signal that caused the dump
+pub const NT_SIGINFO: u32 = 0x53494749;
+/// Core: files mapped into the process
+pub const NT_FILE: u32 = 0x46494c45;
+
+/// Auxiliary vector: end of vector
+pub const AT_NULL: u64 = 0;
+/// Auxiliary vector: program headers of the program
+pub const AT_PHDR: u64 = 3;
+/// Auxiliary vector: system page size
+pub const AT_PAGESZ: u64 = 6;
+/// Auxiliary vector: base address of the interpreter
+pub const AT_BASE: u64 = 7;
+/// Auxiliary vector: entry point of the program
+pub const AT_ENTRY: u64 = 9;
+/// Auxiliary vector: string identifying the CPU
+pub const AT_PLATFORM: u64 = 15;
+/// Auxiliary vector: address of 16 random bytes
+pub const AT_RANDOM: u64 = 25;
+/// Auxiliary vector: file name of the program
+pub const AT_EXECFN: u64 = 31;
+/// Auxiliary vector: address of the vDSO
+pub const AT_SYSINFO_EHDR: u64 = 33;
+
+/// `.eh_frame` pointer encoding: target-sized absolute value
+pub const DW_EH_PE_ABSPTR: u8 = 0x00;
+/// `.eh_frame` pointer encoding: unsigned LEB128
+pub const DW_EH_PE_ULEB128: u8 = 0x01;
+/// `.eh_frame` pointer encoding: unsigned 2-byte value
+pub const DW_EH_PE_UDATA2: u8 = 0x02;
+/// `.eh_frame` pointer encoding: unsigned 4-byte value
+pub const DW_EH_PE_UDATA4: u8 = 0x03;
+/// `.eh_frame` pointer encoding: unsigned 8-byte value
+pub const DW_EH_PE_UDATA8: u8 = 0x04;
+/// `.eh_frame` pointer encoding: signed LEB128
+pub const DW_EH_PE_SLEB128: u8 = 0x09;
+/// `.eh_frame` pointer encoding: signed 2-byte value
+pub const DW_EH_PE_SDATA2: u8 = 0x0a;
+/// `.eh_frame` pointer encoding: signed 4-byte value
+pub const DW_EH_PE_SDATA4: u8 = 0x0b;
+/// `.eh_frame` pointer encoding: signed 8-byte value
+pub const DW_EH_PE_SDATA8: u8 = 0x0c;
+/// `.eh_frame` pointer encoding: relative to the address of the field
+pub const DW_EH_PE_PCREL: u8 = 0x10;
+/// `.eh_frame` pointer encoding: relative to the start of `.text`
+pub const DW_EH_PE_TEXTREL: u8 = 0x20;
+/// `.eh_frame` pointer encoding: relative to the GOT, or to the start of `.eh_frame_hdr`
+/// in that section
+pub const DW_EH_PE_DATAREL: u8 = 0x30;
+/// `.eh_frame` pointer encoding: relative to the start of the function
+pub const DW_EH_PE_FUNCREL: u8 = 0x40;
+/// `.eh_frame` pointer encoding: absolute value aligned to the address size
+pub const DW_EH_PE_ALIGNED: u8 = 0x50;
+/// `.eh_frame` pointer encoding flag: the value is the address of the pointer
+pub const DW_EH_PE_INDIRECT: u8 = 0x80;
+/// `.eh_frame` pointer encoding: no value present
+pub const DW_EH_PE_OMIT: u8 = 0xff;
+
+/// GNU property notes for the loader
+pub const PT_GNU_PROPERTY: u32 = 0x6474e553;
+
+/// Stack size property
+pub const GNU_PROPERTY_STACK_SIZE: u32 = 1;
+/// No copy relocation on protected data symbol
+pub const GNU_PROPERTY_NO_COPY_ON_PROTECTED: u32 = 2;
+/// AArch64 processor features, AND-ed across inputs
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc0000000;
+/// x86 ISA used, as emitted before binutils 2.32
+pub const GNU_PROPERTY_X86_COMPAT_ISA_1_USED: u32 = 0xc0000000;
+/// x86 ISA needed, as emitted before binutils 2.32
+pub const GNU_PROPERTY_X86_COMPAT_ISA_1_NEEDED: u32 = 0xc0000001;
+/// x86 processor features, AND-ed across inputs
+pub const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc0000002;
+/// x86 ISA needed to run the binary
+pub const GNU_PROPERTY_X86_ISA_1_NEEDED: u32 = 0xc0008002;
+/// x86 ISA used by the binary
+pub const GNU_PROPERTY_X86_ISA_1_USED: u32 = 0xc0010002;
+
+/// Indirect branch tracking (Intel CET)
+pub const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 1 << 0;
+/// Shadow stack (Intel CET)
+pub const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 1 << 1;
+/// x86-64 baseline ISA
+pub const GNU_PROPERTY_X86_ISA_1_BASELINE: u32 = 1 << 0;
+/// x86-64-v2 ISA level
+pub const GNU_PROPERTY_X86_ISA_1_V2: u32 = 1 << 1;
+/// x86-64-v3 ISA level
+pub const GNU_PROPERTY_X86_ISA_1_V3: u32 = 1 << 2;
+/// x86-64-v4 ISA level
+pub const GNU_PROPERTY_X86_ISA_1_V4: u32 = 1 << 3;
+/// Branch target identification
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_BTI: u32 = 1 << 0;
+/// Pointer authentication
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_PAC: u32 = 1 << 1;
+
+/// Program interpreter path
+pub const PT_INTERP: u32 = 3;
+/// Stack executability, taken from p_flags
+pub const PT_GNU_STACK: u32 = 0x6474e551;
+/// Read-only after relocation
+pub const PT_GNU_RELRO: u32 = 0x6474e552;
+/// Location of `.eh_frame_hdr`
+pub const PT_GNU_EH_FRAME: u32 = 0x6474e550;
+/// Segment is executable
+pub const PF_X: u32 = 1 << 0;
+/// Segment is writable
+pub const PF_W: u32 = 1 << 1;
+/// Segment is readable
+pub const PF_R: u32 = 1 << 2;
+
+/// Name of this shared object
+pub const DT_SONAME: i64 = 14;
+/// Library search path (deprecated, searched before LD_LIBRARY_PATH)
+pub const DT_RPATH: i64 = 15;
+/// Relocations may modify a non-writable segment
+pub const DT_TEXTREL: i64 = 22;
+/// Process all relocations before transferring control
+pub const DT_BIND_NOW: i64 = 24;
+/// Library search path, searched after LD_LIBRARY_PATH
+pub const DT_RUNPATH: i64 = 29;
+/// DF_* flags
+pub const DT_FLAGS: i64 = 30;
+/// DF_1_* flags
+pub const DT_FLAGS_1: i64 = 0x6ffffffb;
+/// DT_FLAGS: relocations may modify a non-writable segment
+pub const DF_TEXTREL: u64 = 0x4;
+/// DT_FLAGS: process all relocations at load time
+pub const DF_BIND_NOW: u64 = 0x8;
+/// DT_FLAGS_1: same as DT_BIND_NOW
+pub const DF_1_NOW: u64 = 0x1;
+/// DT_FLAGS_1: object is a position-independent executable
+pub const DF_1_PIE: u64 = 0x08000000;
+
+/// Inactive section header (index 0)
+pub const SHT_NULL: u32 = 0;
+/// Array of constructor pointers
+pub const SHT_INIT_ARRAY: u32 = 14;
+/// Array of destructor pointers
+pub const SHT_FINI_ARRAY: u32 = 15;
+/// Array of pre-constructor pointers
+pub const SHT_PREINIT_ARRAY: u32 = 16;
+/// Section is writable at run time
+pub const SHF_WRITE: u64 = 0x1;
+/// sh_info holds a section header table index
+pub const SHF_INFO_LINK: u64 = 0x40;
+/// Section holds thread-local storage
+pub const SHF_TLS: u64 = 0x400;
+/// Location of the program header table itself
+pub const PT_PHDR: u32 = 6;
+
+/// Size in bytes of the PLT relocation table
+pub const DT_PLTRELSZ: i64 = 2;
+/// Address of the PLT and/or GOT
+pub const DT_PLTGOT: i64 = 3;
+/// Address of the RELA relocation table
+pub const DT_RELA: i64 = 7;
+/// Size in bytes of the RELA relocation table
+pub const DT_RELASZ: i64 = 8;
+/// Size of one RELA entry
+pub const DT_RELAENT: i64 = 9;
+/// Address of the initialization function
+pub const DT_INIT: i64 = 12;
+/// Address of the termination function
+pub const DT_FINI: i64 = 13;
+/// Address of the REL relocation table
+pub const DT_REL: i64 = 17;
+/// Size in bytes of the REL relocation table
+pub const DT_RELSZ: i64 = 18;
+/// Size of one REL entry
+pub const DT_RELENT: i64 = 19;
+/// Type of the PLT relocations (DT_REL or DT_RELA)
+pub const DT_PLTREL: i64 = 20;
+/// Address of the PLT relocation table
+pub const DT_JMPREL: i64 = 23;
+/// Address of the array of initialization functions
+pub const DT_INIT_ARRAY: i64 = 25;
+/// Address of the array of termination functions
+pub const DT_FINI_ARRAY: i64 = 26;
+/// Size in bytes of DT_INIT_ARRAY
+pub const DT_INIT_ARRAYSZ: i64 = 27;
+/// Size in bytes of DT_FINI_ARRAY
+pub const DT_FINI_ARRAYSZ: i64 = 28;
@@ -0,0 +1,360 @@
+use std::fs;
+use std::path::Path;
+use crate::parser::abi;
+use crate::parser::elf_file::ElfFile;
+
+/// `ar` magic of a regular archive
+pub const ARMAG: &[u8; 8] = b"!<arch>\n";
+/// `ar` magic of a thin archive, whose members stay in their own files
+pub const THINMAG: &[u8; 8] = b"!<thin>\n";
+//成员头固定60字节,以"`\n"结尾
+const HEADER_SIZE: usize = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveError {
+    Io(String),
+    /// Not an `ar` archive
+    Format,
+    /// Malformed member header at this offset
+    Header(u64),
+    /// Member or table at this offset runs past the end of the archive
+    Truncated(u64),
+}
+
+/// A member of the archive. `offset` is the position of its header, which is what
+/// the symbol index refers to.
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    pub name: String,
+    pub offset: u64,
+    pub date: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+    pub size: u64,
+    /// Member contents; empty for thin archive members until they are loaded
+    pub data: Vec<u8>,
+}
+
+/// Symbol index entry: a defined symbol and the header offset of its member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveSymbol {
+    pub name: String,
+    pub offset: u64,
+}
+
+/// An `ar` archive: GNU/SysV and BSD variants, including thin archives.
+///
+/// The GNU `/` and `/SYM64/` symbol index and the BSD `__.SYMDEF` variants are read
+/// into `symbols`; the long-name tables (`//` and `#1/N`) are resolved into member
+/// names. Special members do not show up in `members`.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub thin: bool,
+    pub members: Vec<ArchiveMember>,
+    pub symbols: Vec<ArchiveSymbol>,
+}
+
+impl ArchiveMember {
+    /// The member parsed as an ELF object
+    pub fn elf(&self)->Option<ElfFile>{
+        ElfFile::parse(self.data.clone())
+    }
+}
+
+impl Archive {
+    pub fn is_archive(data:&[u8])->bool{
+        data.starts_with(ARMAG) || data.starts_with(THINMAG)
+    }
+
+    /// Read an archive; thin archive members are loaded from their paths, relative to
+    /// the archive's directory
+    pub fn read_archive(file_path:&str)->Result<Archive,ArchiveError>{
+        let data=fs::read(file_path).map_err(|e| ArchiveError::Io(e.to_string()))?;
+        let mut archive=Self::parse_archive(&data)?;
+        if archive.thin {
+            let dir=Path::new(file_path).parent().unwrap_or(Path::new(""));
+            for member in archive.members.iter_mut() {
+                member.data=fs::read(dir.join(&member.name)).map_err(|e| ArchiveError::Io(format!("{}: {}",member.name,e)))?;
+            }
+        }
+        Ok(archive)
+    }
+
+    pub fn parse_archive(data:&[u8])->Result<Archive,ArchiveError>{
+        let thin=data.starts_with(THINMAG);
+        if !thin && !data.starts_with(ARMAG) {
+            return Err(ArchiveError::Format);
+        }
+        let mut archive=Archive{thin,members:Vec::new(),symbols:Vec::new()};
+        let mut long_names:&[u8]=&[];
+        let mut offset=ARMAG.len();
+        while offset+HEADER_SIZE<=data.len() {
+            let header=&data[offset..offset+HEADER_SIZE];
+            if &header[58..60]!=b"`\n" {
+                return Err(ArchiveError::Header(offset as u64));
+            }
+            let field=|start:usize,end:usize| String::from_utf8_lossy(&header[start..end]).trim_end().to_string();
+            let number=|start:usize,end:usize,radix:u32| -> Result<u64,ArchiveError> {
+                let text=field(start,end);
+                match text.is_empty() {
+                    true => Ok(0),
+                    false => u64::from_str_radix(&text,radix).map_err(|_| ArchiveError::Header(offset as u64)),
+                }
+            };
+            let raw_name=field(0,16);
+            let size=number(48,58,10)? as usize;
+            let start=offset+HEADER_SIZE;
+            let special=raw_name=="/" || raw_name=="/SYM64/" || raw_name=="//" || raw_name.starts_with("__.SYMDEF");
+            //瘦归档只有符号表和长名表的内容在归档里
+            let stored=if thin && !special { 0 } else { size };
+            if start+stored>data.len() {
+                return Err(ArchiveError::Truncated(offset as u64));
+            }
+            let mut content=&data[start..start+stored];
+            let name=match raw_name.as_str() {
+                "/" => {
+                    archive.symbols.extend(Self::parse_gnu_index(content,4).ok_or(ArchiveError::Truncated(offset as u64))?);
+                    None
+                }
+                "/SYM64/" => {
+                    archive.symbols.extend(Self::parse_gnu_index(content,8).ok_or(ArchiveError::Truncated(offset as u64))?);
+                    None
+                }
+                "//" => {
+                    long_names=content;
+                    None
+                }
+                //BSD: "#1/N"表示名字放在内容开头的N个字节里
+                bsd if bsd.starts_with("#1/") => {
+                    let length:usize=bsd[3..].parse().map_err(|_| ArchiveError::Header(offset as u64))?;
+                    if length>content.len() {
+                        return Err(ArchiveError::Truncated(offset as u64));
+                    }
+                    let name=String::from_utf8_lossy(&content[..length]).trim_end_matches('\0').to_string();
+                    content=&content[length..];
+                    match name.starts_with("__.SYMDEF") {
+                        true => {
+                            archive.symbols.extend(Self::parse_bsd_index(content,name.contains("_64")).ok_or(ArchiveError::Truncated(offset as u64))?);
+                            None
+                        }
+                        false => Some(name),
+                    }
+                }
+                bsd if bsd.starts_with("__.SYMDEF") => {
+                    archive.symbols.extend(Self::parse_bsd_index(content,bsd.contains("_64")).ok_or(ArchiveError::Truncated(offset as u64))?);
+                    None
+                }
+                //GNU: "/N"是长名表里的偏移,名字以"/\n"结尾
+                gnu if gnu.starts_with('/') => {
+                    let index:usize=gnu[1..].parse().map_err(|_| ArchiveError::Header(offset as u64))?;
+                    let rest=long_names.get(index..).ok_or(ArchiveError::Header(offset as u64))?;
+                    let end=rest.windows(2).position(|w| w==b"/\n").or_else(|| rest.iter().position(|&b| b==b'\n')).unwrap_or(rest.len());
+                    Some(String::from_utf8_lossy(&rest[..end]).to_string())
+                }
+                short => Some(short.strip_suffix('/').unwrap_or(short).to_string()),
+            };
+            if let Some(name)=name {
+                archive.members.push(ArchiveMember{
+                    name,
+                    offset:offset as u64,
+                    date:number(16,28,10)?,
+                    uid:number(28,34,10)? as u32,
+                    gid:number(34,40,10)? as u32,
+                    mode:number(40,48,8)? as u32,
+                    size:if thin { size as u64 } else { content.len() as u64 },
+                    data:content.to_vec(),
+                });
+            }
+            //成员内容按2字节对齐
+            offset=start+stored+(stored&1);
+        }
+        //末尾只允许有一个对齐用的换行
+        if data.get(offset..).is_some_and(|rest| !rest.is_empty() && rest!=b"\n") {
+            return Err(ArchiveError::Truncated(offset as u64));
+        }
+        Ok(archive)
+    }
+
+    //GNU符号表:大端的数量、各符号所在成员头的偏移,然后是以0结尾的名字
+    fn parse_gnu_index(data:&[u8],word:usize)->Option<Vec<ArchiveSymbol>>{
+        let read=|at:usize| -> Option<u64> {
+            let bytes=data.get(at..at+word)?;
+            Some(bytes.iter().fold(0u64,|value,&b| (value<<8)|b as u64))
+        };
+        let count=read(0)? as usize;
+        let names_start=word.checked_add(count.checked_mul(word)?)?;
+        let mut names=data.get(names_start..)?.split(|&b| b==0);
+        (0..count).map(|i| {
+            let name=names.next()?;
+            Some(ArchiveSymbol{name:String::from_utf8_lossy(name).to_string(),offset:read(word+i*word)?})
+        }).collect()
+    }
+
+    //BSD __.SYMDEF:ranlib数组的字节数、(名字偏移,成员头偏移)数组、字符串表大小、字符串表;
+    //字节序随生成它的机器,按小端读不通时再按大端
+    fn parse_bsd_index(data:&[u8],wide:bool)->Option<Vec<ArchiveSymbol>>{
+        let word=if wide { 8 } else { 4 };
+        let read=|at:usize,little:bool| -> Option<u64> {
+            let bytes=data.get(at..at+word)?;
+            Some(match little {
+                true => bytes.iter().rev().fold(0u64,|value,&b| (value<<8)|b as u64),
+                false => bytes.iter().fold(0u64,|value,&b| (value<<8)|b as u64),
+            })
+        };
+        let little=read(0,true).is_some_and(|size| (size as usize)<=data.len());
+        let ranlib_size=read(0,little)? as usize;
+        let strings_at=word.checked_add(ranlib_size)?;
+        let strings_size=read(strings_at,little)? as usize;
+        let strings=data.get(strings_at+word..strings_at+word+strings_size)?;
+        (0..ranlib_size/(word*2)).map(|i| {
+            let entry=word+i*word*2;
+            let name=strings.get(read(entry,little)? as usize..)?;
+            let end=name.iter().position(|&b| b==0).unwrap_or(name.len());
+            Some(ArchiveSymbol{name:String::from_utf8_lossy(&name[..end]).to_string(),offset:read(entry+word,little)?})
+        }).collect()
+    }
+
+    /// The member whose header is at `offset`
+    pub fn member_at(&self,offset:u64)->Option<&ArchiveMember>{
+        self.members.iter().find(|member| member.offset==offset)
+    }
+
+    /// The member that defines `name`, from the symbol index, or from the members'
+    /// own symbol tables when the archive has no index
+    pub fn find_symbol(&self,name:&str)->Option<&ArchiveMember>{
+        if !self.symbols.is_empty() {
+            return self.symbols.iter()
+                .find(|symbol| symbol.name==name)
+                .and_then(|symbol| self.member_at(symbol.offset));
+        }
+        self.members.iter().find(|member| Self::defined_symbols(member).iter().any(|symbol| symbol==name))
+    }
+
+    /// Global and weak symbols a member defines, i.e. what `ar s` would index
+    pub fn defined_symbols(member:&ArchiveMember)->Vec<String>{
+        let Some(elf)=member.elf() else {
+            return Vec::new();
+        };
+        let Some(symtab)=elf.find_section_by_type(abi::SHT_SYMTAB) else {
+            return Vec::new();
+        };
+        elf.symbols(symtab).into_iter()
+            .filter(|symbol| symbol.st_shndx!=abi::SHN_UNDEF && symbol.st_bind()!=abi::STB_LOCAL && !symbol.string_name.is_empty())
+            .map(|symbol| symbol.string_name)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name:&str,size:usize,content:&[u8])->Vec<u8>{
+        let mut v=format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",name,1700000000,0,0,644,size).into_bytes();
+        assert_eq!(v.len(),HEADER_SIZE);
+        v.extend_from_slice(content);
+        if content.len()%2==1 {
+            v.push(b'\n');
+        }
+        v
+    }
+
+    #[test]
+    fn gnu_long_names(){
+        let long_names=b"a_really_long_member_name.o/\nanother_long_member_name.o/\n";
+        let first=member("/0",5,b"hello");
+        let second=member("/29",4,b"data");
+        //符号表与长名表之后依次是两个成员,按它们成员头的偏移建索引
+        let index_size=4+2*4+8;
+        let first_offset=8+HEADER_SIZE+index_size+HEADER_SIZE+long_names.len().next_multiple_of(2);
+        let second_offset=first_offset+first.len();
+        let mut index=2u32.to_be_bytes().to_vec();
+        index.extend_from_slice(&(first_offset as u32).to_be_bytes());
+        index.extend_from_slice(&(second_offset as u32).to_be_bytes());
+        index.extend_from_slice(b"foo\0bar\0");
+        let mut data=ARMAG.to_vec();
+        data.extend(member("/",index.len(),&index));
+        data.extend(member("//",long_names.len(),long_names));
+        data.extend(first);
+        data.extend(second);
+        data.extend(member("short.o/",2,b"xy"));
+
+        let archive=Archive::parse_archive(&data).unwrap();
+        assert!(!archive.thin);
+        let names:Vec<&str>=archive.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names,["a_really_long_member_name.o","another_long_member_name.o","short.o"]);
+        assert_eq!((archive.members[0].size,&archive.members[0].data[..]),(5,&b"hello"[..]));
+        assert_eq!((archive.members[0].date,archive.members[0].mode),(1700000000,0o644));
+        assert_eq!(archive.members[2].data,b"xy");
+        assert_eq!(archive.symbols,vec![
+            ArchiveSymbol{name:"foo".to_string(),offset:first_offset as u64},
+            ArchiveSymbol{name:"bar".to_string(),offset:second_offset as u64},
+        ]);
+        assert_eq!(archive.find_symbol("bar").unwrap().name,"another_long_member_name.o");
+    }
+
+    #[test]
+    fn bsd_names(){
+        //名字长度算在成员大小里
+        let mut symdef_content=b"__.SYMDEF SORTED\0\0\0\0".to_vec();
+        let mut table=8u32.to_le_bytes().to_vec();
+        table.extend_from_slice(&0u32.to_le_bytes());
+        //名字20字节,ranlib数组大小、一项、字符串表大小、字符串表共20字节
+        let member_offset=8+HEADER_SIZE+40;
+        table.extend_from_slice(&(member_offset as u32).to_le_bytes());
+        table.extend_from_slice(&4u32.to_le_bytes());
+        table.extend_from_slice(b"baz\0");
+        symdef_content.extend(table);
+        let mut content=b"a_long_bsd_member.o\0".to_vec();
+        content.extend_from_slice(b"payload");
+        let mut data=ARMAG.to_vec();
+        data.extend(member("#1/20",symdef_content.len(),&symdef_content));
+        assert_eq!(data.len(),member_offset);
+        data.extend(member("#1/20",content.len(),&content));
+
+        let archive=Archive::parse_archive(&data).unwrap();
+        assert_eq!(archive.members.len(),1);
+        let m=&archive.members[0];
+        assert_eq!((m.name.as_str(),m.offset,m.size,&m.data[..]),("a_long_bsd_member.o",member_offset as u64,7,&b"payload"[..]));
+        assert_eq!(archive.symbols,vec![ArchiveSymbol{name:"baz".to_string(),offset:member_offset as u64}]);
+        assert_eq!(archive.find_symbol("baz").map(|m| m.name.as_str()),Some("a_long_bsd_member.o"));
+    }
+
+    #[test]
+    fn thin_archive(){
+        let long_names=b"objects/a_really_long_member_name.o/\n";
+        let mut data=THINMAG.to_vec();
+        data.extend(member("//",long_names.len(),long_names));
+        //瘦归档的成员头后面没有内容
+        data.extend(member("/0",1234,&[]));
+        data.extend(member("short.o/",10,&[]));
+        let archive=Archive::parse_archive(&data).unwrap();
+        assert!(archive.thin);
+        let members:Vec<(&str,u64,usize)>=archive.members.iter().map(|m| (m.name.as_str(),m.size,m.data.len())).collect();
+        assert_eq!(members,[("objects/a_really_long_member_name.o",1234,0),("short.o",10,0)]);
+        assert_eq!(archive.members[1].offset,(8+HEADER_SIZE*2+long_names.len().next_multiple_of(2)) as u64);
+
+        //从归档所在目录读入成员
+        let dir=std::env::temp_dir().join(format!("thin-archive-{}",std::process::id()));
+        fs::create_dir_all(dir.join("objects")).unwrap();
+        fs::write(dir.join("objects/a_really_long_member_name.o"),b"first").unwrap();
+        fs::write(dir.join("short.o"),b"second").unwrap();
+        fs::write(dir.join("lib.a"),&data).unwrap();
+        let loaded=Archive::read_archive(dir.join("lib.a").to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        let loaded=loaded.unwrap();
+        assert_eq!(loaded.members[0].data,b"first");
+        assert_eq!(loaded.members[1].data,b"second");
+    }
+
+    #[test]
+    fn truncated_member(){
+        let mut data=ARMAG.to_vec();
+        data.extend(member("a.o/",100,b"short"));
+        assert_eq!(Archive::parse_archive(&data).unwrap_err(),ArchiveError::Truncated(8));
+        let mut data=ARMAG.to_vec();
+        data.extend(member("/99",2,b"xy"));
+        assert_eq!(Archive::parse_archive(&data).unwrap_err(),ArchiveError::Header(8));
+    }
+}
@@ -0,0 +1,758 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_header::{elf_header, FileHeader};
+use crate::parser::endian::{AnyEndian, EndianWrite};
+use crate::parser::file::Class;
+use crate::parser::hash::hash;
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+use crate::parser::symbol::Symbol;
+use crate::parser::version::VersionDefinition;
+
+/// A symbol or section id the builder did not hand out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// Not an id returned by `add_dynamic_symbol`
+    UnknownSymbol(u32),
+    /// Not an index returned by `add_section`
+    UnknownSection(u16),
+}
+
+/// Build an ELF file from sections, symbols and relocations described in Rust.
+///
+/// Section indices are assigned in the order sections are added, starting at 1, so
+/// symbols can refer to them through `st_shndx` before the file is laid out. The
+/// builder appends `.dynsym`/`.dynstr`, the relocation sections, `.symtab`,
+/// `.strtab` and `.shstrtab` after the user sections.
+///
+/// Symbol values and relocation offsets are given relative to their section. For
+/// executables and shared objects the builder assigns addresses and rebases them;
+/// relocatable objects keep section-relative values as the spec requires. TLS
+/// symbols stay relative to the PT_TLS segment.
+///
+/// Executables and shared objects with dynamic symbols, a SONAME or DT_NEEDED
+/// entries also get `.hash`, `.gnu.version`/`.gnu.version_d` when versions are
+/// used, and a `.dynamic` section mapped by PT_DYNAMIC.
+#[derive(Debug, Clone)]
+pub struct ElfBuilder {
+    pub header: FileHeader,
+    /// Virtual address of the first byte of the file image (ET_EXEC/ET_DYN)
+    pub base_address: u64,
+    pub page_size: u64,
+    sections: Vec<(SectionHeader, Vec<u8>)>,
+    symbols: Vec<Symbol>,
+    dynamic_symbols: Vec<Symbol>,
+    relocations: Vec<(u16, Vec<Rela>)>,
+    dynamic_relocations: Vec<(String, u16, Vec<Rela>)>,
+    entry: Option<(u16, u64)>,
+    soname: Option<String>,
+    needed: Vec<String>,
+    version_definitions: Vec<VersionDefinition>,
+    //与dynamic_symbols一一对应的versym
+    dynamic_versions: Vec<u16>,
+}
+
+//排布完成后的节:头、内容以及生成它的来源
+struct Laid {
+    header: SectionHeader,
+    data: Vec<u8>,
+}
+
+impl ElfBuilder {
+    pub fn new(class:Class,endian:AnyEndian,e_type:u16,e_machine:u16)->ElfBuilder{
+        let (ehsize,phentsize,shentsize)=(elf_header::size_for(class),ProgramHeader::size_for(class),SectionHeader::size_for(class));
+        ElfBuilder{
+            header:FileHeader{
+                e_ident:elf_header::new_ident(class,endian),
+                class,
+                endianness:endian,
+                version:abi::EV_CURRENT as u32,
+                osabi:0,
+                abiversion:0,
+                e_type,
+                e_machine,
+                e_entry:0,
+                e_phoff:0,
+                e_shoff:0,
+                e_flags:0,
+                e_ehsize:ehsize as u16,
+                e_phentsize:phentsize as u16,
+                e_phnum:0,
+                e_shentsize:shentsize as u16,
+                e_shnum:0,
+                e_shstrndx:0,
+            },
+            base_address:if e_type==abi::ET_EXEC { 0x400000 } else { 0 },
+            page_size:0x1000,
+            sections:Vec::new(),
+            symbols:Vec::new(),
+            dynamic_symbols:Vec::new(),
+            relocations:Vec::new(),
+            dynamic_relocations:Vec::new(),
+            entry:None,
+            soname:None,
+            needed:Vec::new(),
+            version_definitions:Vec::new(),
+            dynamic_versions:Vec::new(),
+        }
+    }
+
+    /// Add a section and return its index. Name, type, flags, alignment, entsize,
+    /// link and info are taken from `header`; offset, address and size are computed.
+    /// For SHT_NOBITS sections `header.sh_size` gives the size and `data` is ignored.
+    pub fn add_section(&mut self,header:SectionHeader,data:Vec<u8>)->u16{
+        self.sections.push((header,data));
+        self.sections.len() as u16
+    }
+
+    /// Add a `.symtab` symbol and return the id relocations use as `r_sym`
+    pub fn add_symbol(&mut self,symbol:Symbol)->u32{
+        self.symbols.push(symbol);
+        self.symbols.len() as u32
+    }
+
+    /// Add a `.dynsym` symbol and return the id dynamic relocations use as `r_sym`
+    pub fn add_dynamic_symbol(&mut self,symbol:Symbol)->u32{
+        self.dynamic_symbols.push(symbol);
+        self.dynamic_versions.push(abi::VER_NDX_GLOBAL);
+        self.dynamic_symbols.len() as u32
+    }
+
+    /// Set the `.gnu.version` entry of a dynamic symbol, the versym index of one of
+    /// the version definitions, optionally with VERSYM_HIDDEN
+    pub fn set_symbol_version(&mut self,symbol:u32,versym:u16)->Result<(),BuildError>{
+        //id从1开始,0是空符号
+        let version=(symbol as usize).checked_sub(1)
+            .and_then(|i| self.dynamic_versions.get_mut(i))
+            .ok_or(BuildError::UnknownSymbol(symbol))?;
+        *version=versym;
+        Ok(())
+    }
+
+    /// Add a `.gnu.version_d` entry. A zero `hash` is computed from the first name.
+    pub fn add_version_definition(&mut self,mut definition:VersionDefinition){
+        if definition.hash==0 {
+            definition.hash=hash::elf_hash(definition.names.first().map_or(&[][..],|name| name.as_bytes()));
+        }
+        self.version_definitions.push(definition);
+    }
+
+    pub fn set_soname(&mut self,soname:&str){
+        self.soname=Some(soname.to_string());
+    }
+
+    pub fn add_needed(&mut self,library:&str){
+        self.needed.push(library.to_string());
+    }
+
+    /// Relocations against section `section`, emitted as `.rela<name>` (or `.rel<name>`
+    /// on machines that use REL) and linked to `.symtab`
+    pub fn add_relocations(&mut self,section:u16,relocations:Vec<Rela>)->Result<(),BuildError>{
+        if section==0 || section as usize>self.sections.len() {
+            return Err(BuildError::UnknownSection(section));
+        }
+        self.relocations.push((section,relocations));
+        Ok(())
+    }
+
+    /// Allocated relocations such as `.rela.dyn` or `.rela.plt`, linked to `.dynsym`.
+    /// `r_offset` is relative to `section`.
+    pub fn add_dynamic_relocations(&mut self,name:&str,section:u16,relocations:Vec<Rela>){
+        self.dynamic_relocations.push((name.to_string(),section,relocations));
+    }
+
+    pub fn set_entry(&mut self,section:u16,offset:u64){
+        self.entry=Some((section,offset));
+    }
+
+    pub fn write(&self,file_path:&str)->io::Result<()>{
+        fs::write(file_path,self.build())
+    }
+
+    fn uses_rel(&self)->bool{
+        self.header.e_machine==abi::EM_386 || self.header.e_machine==abi::EM_ARM
+    }
+
+    pub fn build(&self)->Vec<u8>{
+        let class=self.header.class;
+        let ident=(self.header.endianness,class);
+        let word=class.word_size() as u64;
+        let symbol_size=Symbol::size_for(class) as u64;
+        let (reloc_type,reloc_size,reloc_prefix)=if self.uses_rel() {
+            (abi::SHT_REL,Rel::size_for(class) as u64,".rel")
+        } else {
+            (abi::SHT_RELA,Rela::size_for(class) as u64,".rela")
+        };
+
+        let mut laid:Vec<Laid>=vec![Laid{header:SectionHeader::new("",abi::SHT_NULL,0),data:Vec::new()}];
+        laid[0].header.sh_addralign=0;
+        for (header,data) in &self.sections {
+            let mut header=header.clone();
+            if header.sh_type!=abi::SHT_NOBITS {
+                header.sh_size=data.len() as u64;
+            }
+            laid.push(Laid{header,data:data.clone()});
+        }
+
+        //占位,排布后再填内容
+        let placeholder=|name:&str,sh_type,sh_flags,size:u64,align,entsize| {
+            let mut header=SectionHeader::new(name,sh_type,sh_flags);
+            header.sh_size=size;
+            header.sh_addralign=align;
+            header.sh_entsize=entsize;
+            Laid{header,data:vec![0;size as usize]}
+        };
+        let dynamic=self.header.e_type!=abi::ET_REL
+            && (!self.dynamic_symbols.is_empty() || self.soname.is_some() || !self.needed.is_empty());
+        let (dynamic_order,mut dynstr,dynamic_names)=Self::order_symbols(&self.dynamic_symbols);
+        //DT_NEEDED、DT_SONAME与版本名也放进.dynstr,已有的同名字符串直接共用
+        let mut intern=|name:&str| -> u64 {
+            let mut bytes=name.as_bytes().to_vec();
+            bytes.push(0);
+            if let Some(offset)=dynstr.windows(bytes.len()).position(|w| w==bytes.as_slice()) {
+                return offset as u64;
+            }
+            dynstr.extend_from_slice(&bytes);
+            (dynstr.len()-bytes.len()) as u64
+        };
+        let mut string_tags:Vec<(i64,u64)>=self.needed.iter().map(|name| (abi::DT_NEEDED,intern(name))).collect();
+        if let Some(soname)=&self.soname {
+            string_tags.push((abi::DT_SONAME,intern(soname)));
+        }
+        let version_names:Vec<Vec<u32>>=self.version_definitions.iter()
+            .map(|definition| definition.names.iter().map(|name| intern(name) as u32).collect())
+            .collect();
+        let (dynsym_index,dynstr_index)=if self.dynamic_symbols.is_empty() && !dynamic {
+            (0,0)
+        } else {
+            laid.push(placeholder(".dynsym",abi::SHT_DYNSYM,abi::SHF_ALLOC,(dynamic_order.len() as u64+1)*symbol_size,word,symbol_size));
+            let mut strtab=placeholder(".dynstr",abi::SHT_STRTAB,abi::SHF_ALLOC,dynstr.len() as u64,1,0);
+            strtab.data=dynstr;
+            laid.push(strtab);
+            ((laid.len()-2) as u32,(laid.len()-1) as u32)
+        };
+        if dynamic {
+            let endian=self.header.endianness;
+            let mut names=vec![""];
+            names.extend(dynamic_order.iter().map(|&i| self.dynamic_symbols[i].string_name.as_str()));
+            let mut table=placeholder(".hash",abi::SHT_HASH,abi::SHF_ALLOC,0,word,4);
+            table.data=Self::hash_table(endian,&names);
+            table.header.sh_size=table.data.len() as u64;
+            table.header.sh_link=dynsym_index;
+            laid.push(table);
+            let versioned=!self.version_definitions.is_empty() || self.dynamic_versions.iter().any(|&v| v!=abi::VER_NDX_GLOBAL);
+            if versioned {
+                let mut versym=placeholder(".gnu.version",abi::SHT_GNU_VERSYM,abi::SHF_ALLOC,(dynamic_order.len() as u64+1)*2,2,2);
+                for (i,&symbol) in dynamic_order.iter().enumerate() {
+                    endian.write_u16_at((i+1)*2,&mut versym.data,self.dynamic_versions[symbol]);
+                }
+                versym.header.sh_link=dynsym_index;
+                laid.push(versym);
+            }
+            if !self.version_definitions.is_empty() {
+                let mut verdef=placeholder(".gnu.version_d",abi::SHT_GNU_VERDEF,abi::SHF_ALLOC,0,word,0);
+                verdef.data=Self::verdef_table(endian,&self.version_definitions,&version_names);
+                verdef.header.sh_size=verdef.data.len() as u64;
+                verdef.header.sh_link=dynstr_index;
+                verdef.header.sh_info=self.version_definitions.len() as u32;
+                laid.push(verdef);
+            }
+        }
+        let mut dynamic_reloc_indices=Vec::new();
+        for (name,section,relocations) in &self.dynamic_relocations {
+            let mut reloc=placeholder(name,reloc_type,abi::SHF_ALLOC,relocations.len() as u64*reloc_size,word,reloc_size);
+            reloc.header.sh_link=dynsym_index;
+            if name.ends_with(".plt") {
+                reloc.header.sh_info=*section as u32;
+                reloc.header.sh_flags|=abi::SHF_INFO_LINK;
+            }
+            laid.push(reloc);
+            dynamic_reloc_indices.push(laid.len()-1);
+        }
+        let dynamic_index=if dynamic {
+            let size=Dyn::size_for(class) as u64;
+            let count=self.dynamic_entries(&laid,&string_tags,reloc_type).len() as u64;
+            let mut section=placeholder(".dynamic",abi::SHT_DYNAMIC,abi::SHF_ALLOC|abi::SHF_WRITE,count*size,word,size);
+            section.header.sh_link=dynstr_index;
+            laid.push(section);
+            laid.len()-1
+        } else {
+            0
+        };
+        let (static_order,strtab,static_names)=Self::order_symbols(&self.symbols);
+        let symtab_index=if self.symbols.is_empty() { 0 } else { (laid.len()+self.relocations.len()) as u32 };
+        let mut reloc_indices=Vec::new();
+        for (section,relocations) in &self.relocations {
+            let target=(*section as usize).checked_sub(1)
+                .and_then(|i| self.sections.get(i))
+                .map(|(h,_)| h.string_name.clone())
+                .unwrap_or_default();
+            let mut reloc=placeholder(&format!("{}{}",reloc_prefix,target),reloc_type,abi::SHF_INFO_LINK,
+                                      relocations.len() as u64*reloc_size,word,reloc_size);
+            reloc.header.sh_link=symtab_index;
+            reloc.header.sh_info=*section as u32;
+            laid.push(reloc);
+            reloc_indices.push(laid.len()-1);
+        }
+        if !self.symbols.is_empty() {
+            let mut symtab=placeholder(".symtab",abi::SHT_SYMTAB,0,(static_order.len() as u64+1)*symbol_size,word,symbol_size);
+            symtab.header.sh_link=symtab_index+1;
+            laid.push(symtab);
+            let mut strtab_section=placeholder(".strtab",abi::SHT_STRTAB,0,strtab.len() as u64,1,0);
+            strtab_section.data=strtab;
+            laid.push(strtab_section);
+        }
+        let shstrndx=laid.len();
+        laid.push(placeholder(".shstrtab",abi::SHT_STRTAB,0,0,1,0));
+        let mut shstrtab=vec![0u8];
+        for section in laid.iter_mut().skip(1) {
+            section.header.sh_name=shstrtab.len() as u32;
+            shstrtab.extend_from_slice(section.header.string_name.as_bytes());
+            shstrtab.push(0);
+        }
+        laid[shstrndx].header.sh_size=shstrtab.len() as u64;
+        laid[shstrndx].data=shstrtab;
+        if dynsym_index!=0 {
+            let dynsym=&mut laid[dynsym_index as usize].header;
+            dynsym.sh_link=dynstr_index;
+            dynsym.sh_info=Self::first_global(&self.dynamic_symbols,&dynamic_order);
+        }
+        if symtab_index!=0 {
+            laid[symtab_index as usize].header.sh_info=Self::first_global(&self.symbols,&static_order);
+        }
+        for (i,section) in laid.iter_mut().enumerate() {
+            section.header.index=i as u16;
+        }
+
+        let mut header=self.header;
+        let program_headers=self.layout(&mut laid,&mut header);
+
+        //地址确定后生成符号表与重定位表
+        let addrs:Vec<u64>=laid.iter().map(|s| s.header.sh_addr).collect();
+        let addr_of=|shndx:u16| addrs.get(shndx as usize).copied().unwrap_or(0);
+        let rebase=|symbol:&Symbol| {
+            let mut symbol=symbol.clone();
+            if symbol.st_shndx!=abi::SHN_UNDEF && symbol.st_shndx<abi::SHN_LORESERVE && symbol.st_type()!=abi::STT_TLS {
+                symbol.st_value+=addr_of(symbol.st_shndx);
+            }
+            symbol
+        };
+        let symbol_bytes=|symbols:&[Symbol],order:&[usize],names:&[u32]| {
+            let mut table=vec![Symbol::new("",abi::STB_LOCAL,abi::STT_NOTYPE,abi::SHN_UNDEF,0,0)];
+            for &i in order {
+                let mut symbol=rebase(&symbols[i]);
+                symbol.st_name=names[i];
+                table.push(symbol);
+            }
+            Symbol::to_bytes(ident,&table)
+        };
+        let remap=|order:&[usize],r_sym:u32| -> u32 {
+            if r_sym==0 {
+                return 0;
+            }
+            order.iter().position(|&i| i as u32==r_sym-1).map(|p| p as u32+1).unwrap_or(0)
+        };
+        let reloc_bytes=|relocations:&[Rela],order:&[usize],section:u16| {
+            let entries:Vec<Rela>=relocations.iter().map(|r| Rela{
+                r_offset:r.r_offset+addr_of(section),
+                r_sym:remap(order,r.r_sym),
+                r_type:r.r_type,
+                r_addend:r.r_addend,
+            }).collect();
+            if self.uses_rel() {
+                Rel::to_bytes(ident,&entries.into_iter().map(|r| Rel{r_offset:r.r_offset,r_sym:r.r_sym,r_type:r.r_type}).collect::<Vec<_>>())
+            } else {
+                Rela::to_bytes(ident,&entries)
+            }
+        };
+        let mut tables:Vec<(usize,Vec<u8>)>=Vec::new();
+        if dynsym_index!=0 {
+            tables.push((dynsym_index as usize,symbol_bytes(&self.dynamic_symbols,&dynamic_order,&dynamic_names)));
+        }
+        if symtab_index!=0 {
+            tables.push((symtab_index as usize,symbol_bytes(&self.symbols,&static_order,&static_names)));
+        }
+        for ((_,section,relocations),&index) in self.dynamic_relocations.iter().zip(&dynamic_reloc_indices) {
+            tables.push((index,reloc_bytes(relocations,&dynamic_order,*section)));
+        }
+        for ((section,relocations),&index) in self.relocations.iter().zip(&reloc_indices) {
+            let base=if header.e_type==abi::ET_REL { 0 } else { *section };
+            tables.push((index,reloc_bytes(relocations,&static_order,base)));
+        }
+        for (index,data) in tables {
+            laid[index].data=data;
+        }
+        if dynamic_index!=0 {
+            let dyns=self.dynamic_entries(&laid,&string_tags,reloc_type);
+            laid[dynamic_index].data=Dyn::to_bytes(ident,&dyns);
+        }
+        if let Some((section,offset))=self.entry {
+            header.e_entry=addr_of(section)+offset;
+        }
+        header.e_shstrndx=shstrndx as u16;
+        Self::emit(&header,&program_headers,&laid)
+    }
+
+    /// `.dynamic` contents for the laid-out sections; also used before layout, with
+    /// zero addresses, to size the section
+    fn dynamic_entries(&self,laid:&[Laid],string_tags:&[(i64,u64)],reloc_type:u32)->Vec<Dyn>{
+        let mut dyns:Vec<Dyn>=string_tags.iter().map(|&(d_tag,d_val)| Dyn{d_tag,d_val}).collect();
+        let find=|sh_type:u32| laid.iter().map(|s| &s.header).find(|h| h.sh_type==sh_type);
+        let mut push=|d_tag:i64,d_val:u64| dyns.push(Dyn{d_tag,d_val});
+        if let Some(table)=find(abi::SHT_HASH) {
+            push(abi::DT_HASH,table.sh_addr);
+        }
+        if let Some(dynsym)=find(abi::SHT_DYNSYM) {
+            let strtab=&laid[dynsym.sh_link as usize].header;
+            push(abi::DT_STRTAB,strtab.sh_addr);
+            push(abi::DT_SYMTAB,dynsym.sh_addr);
+            push(abi::DT_STRSZ,strtab.sh_size);
+            push(abi::DT_SYMENT,dynsym.sh_entsize);
+        }
+        let (table_tag,size_tag,entsize_tag)=if reloc_type==abi::SHT_REL {
+            (abi::DT_REL,abi::DT_RELSZ,abi::DT_RELENT)
+        } else {
+            (abi::DT_RELA,abi::DT_RELASZ,abi::DT_RELAENT)
+        };
+        //只有一个DT_RELA,取第一个非.plt的动态重定位节
+        let relocations=laid.iter().map(|s| &s.header).filter(|h| h.sh_type==reloc_type && h.sh_flags&abi::SHF_ALLOC!=0);
+        let (plt,other):(Vec<&SectionHeader>,Vec<&SectionHeader>)=relocations.partition(|h| h.string_name.ends_with(".plt"));
+        if let Some(table)=other.first() {
+            push(table_tag,table.sh_addr);
+            push(size_tag,table.sh_size);
+            push(entsize_tag,table.sh_entsize);
+        }
+        if let Some(table)=plt.first() {
+            push(abi::DT_JMPREL,table.sh_addr);
+            push(abi::DT_PLTRELSZ,table.sh_size);
+            push(abi::DT_PLTREL,table_tag as u64);
+        }
+        if let Some(versym)=find(abi::SHT_GNU_VERSYM) {
+            push(abi::DT_VERSYM,versym.sh_addr);
+        }
+        if let Some(verdef)=find(abi::SHT_GNU_VERDEF) {
+            push(abi::DT_VERDEF,verdef.sh_addr);
+            push(abi::DT_VERDEFNUM,verdef.sh_info as u64);
+        }
+        push(abi::DT_NULL,0);
+        dyns
+    }
+
+    /// SysV `.hash` over the dynamic symbol names in table order, with the bucket
+    /// count GNU ld picks for the number of symbols
+    fn hash_table(endian:AnyEndian,names:&[&str])->Vec<u8>{
+        const BUCKETS:&[usize]=&[1,3,17,37,67,97,131,197,263,521,1031,2053,4099,8209,16411,32771];
+        let nbucket=BUCKETS.iter().copied().take_while(|&n| n<=names.len()).last().unwrap_or(1);
+        let mut buckets=vec![0u32;nbucket];
+        let mut chains=vec![0u32;names.len()];
+        for (i,name) in names.iter().enumerate().skip(1) {
+            let bucket=hash::elf_hash(name.as_bytes()) as usize%nbucket;
+            chains[i]=buckets[bucket];
+            buckets[bucket]=i as u32;
+        }
+        let words:Vec<u32>=[nbucket as u32,names.len() as u32].into_iter().chain(buckets).chain(chains).collect();
+        let mut data=vec![0u8;words.len()*4];
+        for (i,word) in words.iter().enumerate() {
+            endian.write_u32_at(i*4,&mut data,*word);
+        }
+        data
+    }
+
+    /// `.gnu.version_d`: an Elf_Verdef followed by its Elf_Verdaux entries per definition
+    fn verdef_table(endian:AnyEndian,definitions:&[VersionDefinition],names:&[Vec<u32>])->Vec<u8>{
+        let mut data=Vec::new();
+        for (i,(definition,names)) in definitions.iter().zip(names).enumerate() {
+            let offset=data.len();
+            let size=20+names.len()*8;
+            data.resize(offset+size,0);
+            endian.write_u16_at(offset,&mut data,1);
+            endian.write_u16_at(offset+2,&mut data,definition.flags);
+            endian.write_u16_at(offset+4,&mut data,definition.index);
+            endian.write_u16_at(offset+6,&mut data,names.len() as u16);
+            endian.write_u32_at(offset+8,&mut data,definition.hash);
+            endian.write_u32_at(offset+12,&mut data,20);
+            endian.write_u32_at(offset+16,&mut data,if i+1<definitions.len() { size as u32 } else { 0 });
+            for (j,name) in names.iter().enumerate() {
+                let aux=offset+20+j*8;
+                endian.write_u32_at(aux,&mut data,*name);
+                endian.write_u32_at(aux+4,&mut data,if j+1<names.len() { 8 } else { 0 });
+            }
+        }
+        data
+    }
+
+    /// Symbol order with locals first, the string table, and each symbol's name offset
+    fn order_symbols(symbols:&[Symbol])->(Vec<usize>,Vec<u8>,Vec<u32>){
+        let mut order:Vec<usize>=(0..symbols.len()).collect();
+        order.sort_by_key(|&i| symbols[i].st_bind()!=abi::STB_LOCAL);
+        let mut strtab=vec![0u8];
+        let mut offsets:HashMap<&str,u32>=HashMap::new();
+        let mut names=Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            let name=symbol.string_name.as_str();
+            if name.is_empty() {
+                names.push(0);
+                continue;
+            }
+            let offset=*offsets.entry(name).or_insert_with(|| {
+                let offset=strtab.len() as u32;
+                strtab.extend_from_slice(name.as_bytes());
+                strtab.push(0);
+                offset
+            });
+            names.push(offset);
+        }
+        (order,strtab,names)
+    }
+
+    //sh_info为第一个非局部符号的下标(含0号空符号)
+    fn first_global(symbols:&[Symbol],order:&[usize])->u32{
+        order.iter().position(|&i| symbols[i].st_bind()!=abi::STB_LOCAL).unwrap_or(order.len()) as u32+1
+    }
+
+    /// Assign file offsets and addresses. Allocated sections are grouped by permission
+    /// into read-only, executable and writable PT_LOAD segments, each starting on a new
+    /// page; the first one also maps the file and program headers.
+    fn layout(&self,laid:&mut [Laid],header:&mut FileHeader)->Vec<ProgramHeader>{
+        let class=header.class;
+        let word=class.word_size() as u64;
+        let loadable=header.e_type!=abi::ET_REL && laid.iter().any(|s| s.header.sh_flags&abi::SHF_ALLOC!=0);
+        let groups=[abi::PF_R,abi::PF_R|abi::PF_X,abi::PF_R|abi::PF_W];
+        let mut program_headers=Vec::new();
+        let mut cursor=header.e_ehsize as u64;
+        if loadable {
+            let phnum=(0..groups.len())
+                .filter(|&i| i==0 || laid.iter().any(|s| segment_group(&s.header)==Some(i)))
+                .count();
+            //PT_DYNAMIC与PT_TLS各占一个程序头
+            let covering=[abi::PT_DYNAMIC,abi::PT_TLS].iter().filter(|&&p_type| laid.iter().any(|s| covered_by(&s.header,p_type))).count();
+            let phnum=phnum+covering;
+            header.e_phoff=cursor;
+            header.e_phnum=phnum as u16;
+            cursor+=phnum as u64*header.e_phentsize as u64;
+            for (i,flags) in groups.iter().enumerate() {
+                let members:Vec<usize>=(0..laid.len()).filter(|&j| segment_group(&laid[j].header)==Some(i)).collect();
+                if i>0 && members.is_empty() {
+                    continue;
+                }
+                //第一个段从文件头开始映射,节排在程序头之后
+                let start=if i==0 { 0 } else { align_up(cursor,self.page_size) };
+                cursor=cursor.max(start);
+                //NOBITS放在段尾,只占内存
+                let (bits,nobits):(Vec<usize>,Vec<usize>)=members.into_iter().partition(|&j| laid[j].header.sh_type!=abi::SHT_NOBITS);
+                for j in bits {
+                    cursor=align_up(cursor,laid[j].header.sh_addralign);
+                    laid[j].header.sh_offset=cursor;
+                    laid[j].header.sh_addr=self.base_address+cursor;
+                    cursor+=laid[j].header.sh_size;
+                }
+                let filesz=cursor-start;
+                let mut memory=cursor;
+                for j in nobits {
+                    memory=align_up(memory,laid[j].header.sh_addralign);
+                    laid[j].header.sh_offset=cursor;
+                    laid[j].header.sh_addr=self.base_address+memory;
+                    memory+=laid[j].header.sh_size;
+                }
+                program_headers.push(ProgramHeader{
+                    p_type:abi::PT_LOAD,
+                    p_offset:start,
+                    p_vaddr:self.base_address+start,
+                    p_paddr:self.base_address+start,
+                    p_filesz:filesz,
+                    p_memsz:memory-start,
+                    p_flags:*flags,
+                    p_align:self.page_size,
+                });
+            }
+            for (p_type,flags) in [(abi::PT_DYNAMIC,abi::PF_R|abi::PF_W),(abi::PT_TLS,abi::PF_R)] {
+                let members:Vec<&SectionHeader>=laid.iter().map(|s| &s.header).filter(|h| covered_by(h,p_type)).collect();
+                let (Some(first),Some(last))=(members.first(),members.last()) else {
+                    continue;
+                };
+                let filesz=members.iter().filter(|h| h.sh_type!=abi::SHT_NOBITS).map(|h| h.sh_offset+h.sh_size).max().unwrap_or(first.sh_offset);
+                program_headers.push(ProgramHeader{
+                    p_type,
+                    p_offset:first.sh_offset,
+                    p_vaddr:first.sh_addr,
+                    p_paddr:first.sh_addr,
+                    p_filesz:filesz-first.sh_offset,
+                    p_memsz:last.sh_addr+last.sh_size-first.sh_addr,
+                    p_flags:flags,
+                    p_align:members.iter().map(|h| h.sh_addralign).max().unwrap_or(1).max(1),
+                });
+            }
+        }
+        for section in laid.iter_mut().skip(1) {
+            if loadable && section.header.sh_flags&abi::SHF_ALLOC!=0 {
+                continue;
+            }
+            cursor=align_up(cursor,section.header.sh_addralign);
+            section.header.sh_offset=cursor;
+            if section.header.sh_type!=abi::SHT_NOBITS {
+                cursor+=section.header.sh_size;
+            }
+        }
+        header.e_shoff=align_up(cursor,word);
+        header.e_shnum=laid.len() as u16;
+        program_headers
+    }
+
+    fn emit(header:&FileHeader,program_headers:&[ProgramHeader],laid:&[Laid])->Vec<u8>{
+        let ident=(header.endianness,header.class);
+        let shentsize=header.e_shentsize as usize;
+        let mut data=vec![0u8;header.e_shoff as usize+laid.len()*shentsize];
+        elf_header::write_header(header,&mut data);
+        for (i,ph) in program_headers.iter().enumerate() {
+            ph.write_at(ident,header.e_phoff as usize+i*header.e_phentsize as usize,&mut data);
+        }
+        for (i,section) in laid.iter().enumerate() {
+            if section.header.sh_type!=abi::SHT_NOBITS && !section.data.is_empty() {
+                let offset=section.header.sh_offset as usize;
+                data[offset..offset+section.data.len()].copy_from_slice(&section.data);
+            }
+            section.header.write_at(ident,header.e_shoff as usize+i*shentsize,&mut data);
+        }
+        data
+    }
+}
+
+//节是否落在PT_DYNAMIC或PT_TLS里
+fn covered_by(header:&SectionHeader,p_type:u32)->bool{
+    header.sh_flags&abi::SHF_ALLOC!=0 && match p_type {
+        abi::PT_DYNAMIC => header.sh_type==abi::SHT_DYNAMIC,
+        abi::PT_TLS => header.sh_flags&abi::SHF_TLS!=0,
+        _ => false,
+    }
+}
+
+//可分配节所属的PT_LOAD:0只读,1可执行,2可写
+fn segment_group(header:&SectionHeader)->Option<usize>{
+    if header.sh_flags&abi::SHF_ALLOC==0 {
+        return None;
+    }
+    if header.sh_flags&abi::SHF_EXECINSTR!=0 {
+        Some(1)
+    } else if header.sh_flags&abi::SHF_WRITE!=0 {
+        Some(2)
+    } else {
+        Some(0)
+    }
+}
+
+fn align_up(value:u64,align:u64)->u64{
+    if align<=1 {
+        return value;
+    }
+    value.div_ceil(align)*align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::elf_file::ElfFile;
+    use crate::parser::endian::EndianParse;
+    use crate::parser::loader::LoadedImage;
+    use crate::parser::version::VersionTables;
+
+    fn section(name:&str,sh_flags:u64,align:u64)->SectionHeader{
+        let mut header=SectionHeader::new(name,abi::SHT_PROGBITS,sh_flags);
+        header.sh_addralign=align;
+        header
+    }
+
+    //.text里的foo与.data里的bar,.data开头放一个指向foo的R_X86_64_64
+    fn shared_library()->ElfBuilder{
+        let mut builder=ElfBuilder::new(Class::ELF64,AnyEndian::new(abi::ELFDATA2LSB),abi::ET_DYN,abi::EM_X86_64);
+        let text=builder.add_section(section(".text",abi::SHF_ALLOC|abi::SHF_EXECINSTR,16),vec![0xc3;16]);
+        let data=builder.add_section(section(".data",abi::SHF_ALLOC|abi::SHF_WRITE,8),vec![0;16]);
+        builder.set_soname("libt.so");
+        builder.add_needed("libc.so.6");
+        builder.add_version_definition(VersionDefinition{index:1,flags:abi::VER_FLG_BASE,hash:0,names:vec!["libt.so".to_string()]});
+        builder.add_version_definition(VersionDefinition{index:2,flags:0,hash:0,names:vec!["LIB_1.0".to_string()]});
+        let foo=builder.add_dynamic_symbol(Symbol::new("foo",abi::STB_GLOBAL,abi::STT_FUNC,text,0,1));
+        let bar=builder.add_dynamic_symbol(Symbol::new("bar",abi::STB_GLOBAL,abi::STT_OBJECT,data,8,8));
+        builder.set_symbol_version(foo,2).unwrap();
+        builder.set_symbol_version(bar,2|abi::VERSYM_HIDDEN).unwrap();
+        builder.add_dynamic_relocations(".rela.dyn",data,vec![Rela{r_offset:0,r_sym:foo,r_type:abi::R_X86_64_64,r_addend:0}]);
+        builder
+    }
+
+    #[test]
+    fn shared_library_reparses(){
+        let elf=ElfFile::parse(shared_library().build()).unwrap();
+        let header=elf.header;
+        assert_eq!((header.class,header.e_type,header.e_machine),(Class::ELF64,abi::ET_DYN,abi::EM_X86_64));
+        assert_eq!(header.e_shnum as usize,elf.section_headers.len());
+        assert_eq!(elf.section_headers[header.e_shstrndx as usize].string_name,".shstrtab");
+        for name in [".text",".data",".dynsym",".dynstr",".hash",".gnu.version",".gnu.version_d",".rela.dyn",".dynamic"] {
+            assert!(elf.find_section(name).is_some(),"{}",name);
+        }
+        let text=elf.find_section(".text").unwrap();
+        let data=elf.find_section(".data").unwrap();
+        assert_eq!(elf.section_data(text).unwrap(),[0xc3;16]);
+        assert!(elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_DYNAMIC));
+
+        let symbols=elf.dynamic_symbols();
+        let names:Vec<&str>=symbols.iter().map(|symbol| symbol.string_name.as_str()).collect();
+        assert_eq!(names,["","foo","bar"]);
+        assert_eq!((symbols[1].st_value,symbols[1].st_type()),(text.sh_addr,abi::STT_FUNC));
+        assert_eq!((symbols[2].st_value,symbols[2].st_shndx),(data.sh_addr+8,data.index));
+        let versions=VersionTables::parse_versions(&elf).unwrap();
+        assert_eq!(versions.versym,[0,2,2|abi::VERSYM_HIDDEN]);
+        let foo=versions.symbol_version(1).unwrap();
+        assert_eq!((foo.name.as_str(),foo.hidden),("LIB_1.0",false));
+        assert!(versions.symbol_version(2).unwrap().hidden);
+
+        let strtab=elf.dynamic_strings().unwrap();
+        let soname=Dyn::find(&elf.dynamic(),abi::DT_SONAME).unwrap();
+        assert_eq!(SectionHeader::parse_string_at(strtab,soname as usize).as_deref(),Some("libt.so"));
+        assert_eq!(elf.needed_libraries(),["libc.so.6"]);
+    }
+
+    #[test]
+    fn shared_library_loads(){
+        const BASE: u64 = 0x7f00_0000_0000;
+        let elf=ElfFile::parse(shared_library().build()).unwrap();
+        let data=elf.find_section(".data").unwrap().sh_addr;
+        let text=elf.find_section(".text").unwrap().sh_addr;
+        let image=LoadedImage::load(&elf,BASE,|_| None).unwrap();
+        assert!(image.unmapped.is_empty());
+        assert!(image.unapplied.is_empty());
+        let slot=image.read(data,8).unwrap();
+        assert_eq!(elf.header.endianness.parse_u64_at(0,slot),BASE+text);
+    }
+
+    #[test]
+    fn relocatable_object(){
+        let mut builder=ElfBuilder::new(Class::ELF64,AnyEndian::new(abi::ELFDATA2LSB),abi::ET_REL,abi::EM_X86_64);
+        let text=builder.add_section(section(".text",abi::SHF_ALLOC|abi::SHF_EXECINSTR,16),vec![0x90;8]);
+        let f=builder.add_symbol(Symbol::new("f",abi::STB_GLOBAL,abi::STT_FUNC,text,4,1));
+        builder.add_relocations(text,vec![Rela{r_offset:1,r_sym:f,r_type:abi::R_X86_64_PC32,r_addend:-4}]).unwrap();
+        let elf=ElfFile::parse(builder.build()).unwrap();
+        assert!(elf.program_headers.is_empty());
+        let symtab=elf.find_section(".symtab").unwrap();
+        let rela=elf.find_section(".rela.text").unwrap();
+        assert_eq!((rela.sh_info,rela.sh_link),(text as u32,symtab.index as u32));
+        //ET_REL里符号值相对所在节
+        let symbols=elf.symbols(symtab);
+        let f=symbols.iter().find(|symbol| symbol.string_name=="f").unwrap();
+        assert_eq!((f.st_value,f.st_shndx),(4,text));
+        let relocations=Rela::parse(elf.ident(),elf.section_data(rela).unwrap(),rela.sh_size);
+        assert_eq!(relocations,[Rela{r_offset:1,r_sym:f.index,r_type:abi::R_X86_64_PC32,r_addend:-4}]);
+    }
+
+    #[test]
+    fn unknown_ids(){
+        let mut builder=shared_library();
+        assert_eq!(builder.set_symbol_version(0,2),Err(BuildError::UnknownSymbol(0)));
+        assert_eq!(builder.set_symbol_version(3,2),Err(BuildError::UnknownSymbol(3)));
+        assert_eq!(builder.add_relocations(0,Vec::new()),Err(BuildError::UnknownSection(0)));
+        assert_eq!(builder.add_relocations(3,Vec::new()),Err(BuildError::UnknownSection(3)));
+        assert_eq!(builder.add_relocations(2,Vec::new()),Ok(()));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::parser::abi;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::elf_header::elf_header;
+use crate::parser::elf_header::FileHeader;
+use crate::parser::file::Class;
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+
+/// An ELF image found inside a larger blob
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarvedElf {
+    /// Offset of the ELF header in the blob
+    pub offset: u64,
+    pub header: FileHeader,
+    /// Bytes from `offset` to the end of the furthest header table, segment or
+    /// section, clamped to the blob
+    pub size: u64,
+    /// The tables reach past the end of the blob
+    pub truncated: bool,
+}
+
+impl CarvedElf {
+    /// Find every ELF image in `blob`.
+    ///
+    /// Candidates are positions of ELFMAGIC whose header is consistent: a known
+    /// class, data encoding and version, entry sizes matching the class, and program
+    /// headers inside the blob. The section header table, usually at the end, may be
+    /// cut off; such images are reported as truncated. The extent is taken from the
+    /// program and section header tables, so images nested in another image are
+    /// reported too.
+    pub fn carve(blob:&[u8])->Vec<CarvedElf>{
+        let mut carved=Vec::new();
+        let mut start=0;
+        while let Some(found)=blob[start..].windows(4).position(|w| w==abi::ELFMAGIC) {
+            let offset=start+found;
+            if let Some(elf)=Self::candidate(&blob[offset..]) {
+                carved.push(CarvedElf{offset:offset as u64,..elf});
+            }
+            start=offset+1;
+        }
+        carved
+    }
+
+    //头部校验通过时返回image相对偏移0的结果
+    fn candidate(data:&[u8])->Option<CarvedElf>{
+        if data.len()<abi::EI_NIDENT || !elf_header::verify_magic(data[..4].to_vec()) {
+            return None;
+        }
+        let valid_ident=matches!(data[abi::EI_CLASS],abi::ELFCLASS32|abi::ELFCLASS64)
+            && matches!(data[abi::EI_DATA],abi::ELFDATA2LSB|abi::ELFDATA2MSB)
+            && data[abi::EI_VERSION]==abi::EV_CURRENT;
+        if !valid_ident {
+            return None;
+        }
+        let ident=elf_header::parse_ident(data[..abi::EI_NIDENT].to_vec()).ok()?;
+        let ehsize=elf_header::size_for(ident.1);
+        if data.len()<ehsize {
+            return None;
+        }
+        let header=elf_header::file_header(ident,data);
+        let (phentsize,shentsize)=(ProgramHeader::size_for(header.class),SectionHeader::size_for(header.class));
+        let valid_header=header.version==abi::EV_CURRENT as u32
+            && header.e_ehsize as usize==ehsize
+            && (header.e_phnum==0 || header.e_phentsize as usize==phentsize)
+            && (header.e_shnum==0 || header.e_shentsize as usize==shentsize)
+            && (header.e_phnum==0 || (header.e_phoff>=ehsize as u64 && header.e_phoff<data.len() as u64))
+            && (header.e_shoff==0 || header.e_shoff>=ehsize as u64)
+            && (header.e_type<=abi::ET_CORE || header.e_type>=0xfe00);
+        if !valid_header {
+            return None;
+        }
+
+        //偏移或大小相加溢出的头不是真的ELF,直接放弃这个候选
+        let mut end=ehsize as u64;
+        let mut extend=|offset:u64,size:u64|->Option<()>{
+            end=end.max(offset.checked_add(size)?);
+            Some(())
+        };
+        //节头表第0项里可能放着扩展的节数与程序头数
+        let section0=match header.e_shoff {
+            0 => None,
+            shoff => (shoff.checked_add(shentsize as u64)?<=data.len() as u64)
+                .then(|| SectionHeader::parse_at(ident,shoff as usize,data,0)),
+        };
+        let shnum=match (header.e_shnum,&section0) {
+            (0,Some(section0)) if header.e_shoff!=0 => section0.sh_size,
+            (shnum,_) => shnum as u64,
+        };
+        let phnum=match (header.e_phnum,&section0) {
+            (abi::PN_XNUM,Some(section0)) => section0.sh_info as u64,
+            (phnum,_) => phnum as u64,
+        };
+        if phnum>0 {
+            extend(header.e_phoff,phnum.checked_mul(phentsize as u64)?)?;
+            for i in 0..phnum {
+                let at=header.e_phoff.checked_add(i*phentsize as u64)?;
+                if at.checked_add(phentsize as u64)?>data.len() as u64 {
+                    break;
+                }
+                let ph=ProgramHeader::parse_at(ident,at as usize,data);
+                extend(ph.p_offset,ph.p_filesz)?;
+            }
+        }
+        if shnum>0 {
+            extend(header.e_shoff,shnum.checked_mul(shentsize as u64)?)?;
+            for i in 0..shnum {
+                let at=header.e_shoff.checked_add(i*shentsize as u64)?;
+                if at.checked_add(shentsize as u64)?>data.len() as u64 {
+                    break;
+                }
+                let sh=SectionHeader::parse_at(ident,at as usize,data,i as u16);
+                if sh.sh_type!=abi::SHT_NOBITS && sh.sh_type!=abi::SHT_NULL {
+                    extend(sh.sh_offset,sh.sh_size)?;
+                }
+            }
+        }
+        Some(CarvedElf{
+            offset:0,
+            header,
+            size:end.min(data.len() as u64),
+            truncated:end>data.len() as u64,
+        })
+    }
+
+    pub fn class(&self)->Class{
+        self.header.class
+    }
+
+    /// The carved bytes
+    pub fn image<'a>(&self,blob:&'a [u8])->&'a [u8]{
+        &blob[self.offset as usize..(self.offset+self.size) as usize]
+    }
+
+    /// The carved image, parsed
+    pub fn elf(&self,blob:&[u8])->Option<ElfFile>{
+        ElfFile::parse(self.image(blob).to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ELF: &[u8] = include_bytes!("../test_elf/de");
+
+    //ELF嵌在固件镜像中间
+    #[test]
+    fn embedded_image(){
+        let blob=[vec![0x5a;0x1234],ELF.to_vec(),vec![0;64]].concat();
+        let carved=CarvedElf::carve(&blob);
+        assert_eq!(carved.len(),1);
+        assert_eq!((carved[0].offset,carved[0].size,carved[0].truncated),(0x1234,ELF.len() as u64,false));
+        assert_eq!(carved[0].image(&blob),ELF);
+    }
+
+    #[test]
+    fn cut_off_section_headers(){
+        let carved=CarvedElf::carve(&ELF[..ELF.len()-100]);
+        assert_eq!(carved.len(),1);
+        assert!(carved[0].truncated);
+    }
+
+    //e_shoff加上表大小、节的偏移加大小溢出时放弃候选而不是panic
+    #[test]
+    fn overflowing_offsets(){
+        let mut data=ELF.to_vec();
+        data[0x28..0x30].copy_from_slice(&0xffff_ffff_ffff_fff0u64.to_le_bytes());
+        assert!(CarvedElf::carve(&data).is_empty());
+        let mut data=ELF.to_vec();
+        let shoff=u64::from_le_bytes(data[0x28..0x30].try_into().unwrap()) as usize;
+        //第1个节头的sh_offset与sh_size
+        data[shoff+64+24..shoff+64+32].copy_from_slice(&u64::MAX.to_le_bytes());
+        data[shoff+64+32..shoff+64+40].copy_from_slice(&16u64.to_le_bytes());
+        assert!(CarvedElf::carve(&data).is_empty());
+        let mut data=ELF.to_vec();
+        data[0x20..0x28].copy_from_slice(&0xffff_ffff_ffff_fff0u64.to_le_bytes());
+        assert!(CarvedElf::carve(&data).is_empty());
+    }
+}
@@ -0,0 +1,492 @@
+use std::fs;
+use crate::parser::archive::Archive;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::elf_header::elf_header;
+use crate::parser::inflate::{self, InflateError};
+
+//容器嵌套的最大层数,防止构造出来的无限嵌套
+const MAX_DEPTH: usize = 16;
+//单个gzip层或zip成员解压后的上限,防止压缩炸弹耗尽内存
+const MAX_INFLATED: usize = 1<<30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerError {
+    Io(String),
+    /// The member has ELF magic but does not parse
+    Parse,
+    /// A header or directory entry runs past the end of the container
+    Truncated,
+    /// Zip compression method, zip64, encryption, or a compressor other than gzip.
+    /// xz and zstd layers are reported here, not decoded: a `.deb` built with the
+    /// default `dpkg-deb` settings has an xz or zstd `data.tar` and yields no ELF files.
+    Unsupported(String),
+    Inflate(InflateError),
+    /// CRC-32 of a zip member does not match
+    Checksum,
+    /// Containers nested deeper than the scanner follows
+    TooDeep,
+}
+
+/// An ELF file found while scanning, with its container path, e.g.
+/// `app.apk!/lib/arm64-v8a/libfoo.so` or `foo.deb!/data.tar.gz!/usr/bin/foo`
+#[derive(Debug, Clone)]
+pub struct FoundElf {
+    pub path: String,
+    pub elf: ElfFile,
+}
+
+/// ELF files inside zip (APK, JAR), tar, newc cpio and `ar` (`.deb`, `.a`) containers.
+///
+/// Members are matched by ELF magic rather than by name and parsed in memory.
+/// Members that are containers themselves are scanned recursively; gzip layers are
+/// decompressed in place and do not add a path component. Members that cannot be
+/// read end up in `errors` instead of failing the whole scan.
+///
+/// gzip is the only compressor handled. xz, zstd and bzip2 layers are recognised and
+/// reported as `Unsupported`, so only `.deb` packages with a `data.tar.gz` (built
+/// with `dpkg-deb -Zgzip`) are scanned; unpack the others first.
+#[derive(Debug, Clone)]
+pub struct ContainerScan {
+    pub elves: Vec<FoundElf>,
+    pub errors: Vec<(String, ContainerError)>,
+    //解压上限,测试里调小
+    max_inflated: usize,
+}
+
+impl Default for ContainerScan {
+    fn default()->Self{
+        ContainerScan{elves:Vec::new(),errors:Vec::new(),max_inflated:MAX_INFLATED}
+    }
+}
+
+impl ContainerScan {
+    pub fn read_container(file_path:&str)->Result<ContainerScan,ContainerError>{
+        let data=fs::read(file_path).map_err(|e| ContainerError::Io(e.to_string()))?;
+        Ok(Self::parse_container(file_path,&data))
+    }
+
+    /// Scan `data`, naming results after `path`; plain ELF input is reported as is
+    pub fn parse_container(path:&str,data:&[u8])->ContainerScan{
+        let mut scan=ContainerScan::default();
+        scan.scan(path,data,0);
+        scan
+    }
+
+    fn scan(&mut self,path:&str,data:&[u8],depth:usize){
+        if depth>MAX_DEPTH {
+            self.errors.push((path.to_string(),ContainerError::TooDeep));
+            return;
+        }
+        let result=if data.len()>=4 && elf_header::verify_magic(data[..4].to_vec()) {
+            match ElfFile::parse(data.to_vec()) {
+                Some(elf) => {
+                    self.elves.push(FoundElf{path:path.to_string(),elf});
+                    Ok(())
+                }
+                None => Err(ContainerError::Parse),
+            }
+        } else if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+            self.scan_zip(path,data,depth)
+        } else if Archive::is_archive(data) {
+            self.scan_ar(path,data,depth)
+        } else if data.starts_with(&[0x1f,0x8b]) {
+            inflate::gunzip(data,self.max_inflated).map(|inner| self.scan(path,&inner,depth+1)).map_err(ContainerError::Inflate)
+        } else if data.starts_with(b"070701") || data.starts_with(b"070702") {
+            self.scan_cpio(path,data,depth)
+        } else if is_tar(data) {
+            self.scan_tar(path,data,depth)
+        } else if data.starts_with(&[0xfd,b'7',b'z',b'X',b'Z',0]) {
+            Err(ContainerError::Unsupported("xz".to_string()))
+        } else if data.starts_with(&[0x28,0xb5,0x2f,0xfd]) {
+            Err(ContainerError::Unsupported("zstd".to_string()))
+        } else if data.starts_with(b"BZh") {
+            Err(ContainerError::Unsupported("bzip2".to_string()))
+        } else {
+            Ok(())
+        };
+        if let Err(error)=result {
+            self.errors.push((path.to_string(),error));
+        }
+    }
+
+    fn member(&mut self,path:&str,name:&str,data:&[u8],depth:usize){
+        self.scan(&format!("{}!/{}",path,name.trim_start_matches("./")),data,depth+1);
+    }
+
+    //按中央目录遍历,本地头里的大小在带数据描述符时是0
+    fn scan_zip(&mut self,path:&str,data:&[u8],depth:usize)->Result<(),ContainerError>{
+        let u16_at=|at:usize| data.get(at..at+2).map(|b| u16::from_le_bytes([b[0],b[1]]) as usize).ok_or(ContainerError::Truncated);
+        let u32_at=|at:usize| data.get(at..at+4).map(|b| u32::from_le_bytes([b[0],b[1],b[2],b[3]]) as usize).ok_or(ContainerError::Truncated);
+        //目录结束记录在末尾,后面最多跟64K的注释
+        let search=data.len().saturating_sub(22+0xffff);
+        let end=(search..data.len().saturating_sub(21)).rev()
+            .find(|&i| data[i..].starts_with(b"PK\x05\x06"))
+            .ok_or(ContainerError::Truncated)?;
+        let count=u16_at(end+10)?;
+        let mut entry=u32_at(end+16)?;
+        if count==0xffff || entry==0xffff_ffff {
+            return Err(ContainerError::Unsupported("zip64".to_string()));
+        }
+        for _ in 0..count {
+            if u32_at(entry)?!=0x0201_4b50 {
+                return Err(ContainerError::Truncated);
+            }
+            let (flags,method)=(u16_at(entry+8)?,u16_at(entry+10)?);
+            let crc=u32_at(entry+16)? as u32;
+            let (compressed,size)=(u32_at(entry+20)?,u32_at(entry+24)?);
+            let (name_length,extra_length,comment_length)=(u16_at(entry+28)?,u16_at(entry+30)?,u16_at(entry+32)?);
+            let local=u32_at(entry+42)?;
+            let name=String::from_utf8_lossy(data.get(entry+46..entry+46+name_length).ok_or(ContainerError::Truncated)?).to_string();
+            entry+=46+name_length+extra_length+comment_length;
+            if name.ends_with('/') {
+                continue;
+            }
+            let member_path=format!("{}!/{}",path,name);
+            let start=local+30+u16_at(local+26)?+u16_at(local+28)?;
+            let Some(stored)=data.get(start..start+compressed) else {
+                self.errors.push((member_path,ContainerError::Truncated));
+                continue;
+            };
+            let contents=match (flags&1,method) {
+                (1,_) => Err(ContainerError::Unsupported("encrypted".to_string())),
+                (_,0) => Ok(stored.to_vec()),
+                (_,8) => inflate::inflate(stored,size.min(self.max_inflated)).map(|(contents,_)| contents).map_err(ContainerError::Inflate),
+                (_,method) => Err(ContainerError::Unsupported(format!("method {}",method))),
+            };
+            match contents {
+                Ok(contents) if contents.len()!=size || inflate::crc32(&contents)!=crc => self.errors.push((member_path,ContainerError::Checksum)),
+                Ok(contents) => self.member(path,&name,&contents,depth),
+                Err(error) => self.errors.push((member_path,error)),
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_ar(&mut self,path:&str,data:&[u8],depth:usize)->Result<(),ContainerError>{
+        let archive=Archive::parse_archive(data).map_err(|e| ContainerError::Unsupported(format!("{:?}",e)))?;
+        //瘦归档的成员不在归档里
+        if archive.thin {
+            return Err(ContainerError::Unsupported("thin archive".to_string()));
+        }
+        for member in &archive.members {
+            self.member(path,&member.name,&member.data,depth);
+        }
+        Ok(())
+    }
+
+    //newc:110字节的十六进制头,名字和内容各自按4字节对齐
+    fn scan_cpio(&mut self,path:&str,data:&[u8],depth:usize)->Result<(),ContainerError>{
+        let mut offset=0;
+        let mut trailer=false;
+        while offset+110<=data.len() {
+            let header=&data[offset..offset+110];
+            if !header.starts_with(b"07070") {
+                //initramfs里多个cpio之间用0填充,后面还可能接着压缩过的cpio
+                let Some(next)=data[offset..].iter().position(|&b| b!=0) else {
+                    break;
+                };
+                if next>0 {
+                    offset+=next;
+                    continue;
+                }
+                if trailer {
+                    self.scan(path,&data[offset..],depth+1);
+                    return Ok(());
+                }
+                return Err(ContainerError::Truncated);
+            }
+            let field=|i:usize| std::str::from_utf8(&header[6+i*8..14+i*8]).ok().and_then(|text| usize::from_str_radix(text,16).ok()).ok_or(ContainerError::Truncated);
+            let (mode,size,name_size)=(field(1)?,field(6)?,field(11)?);
+            let name_start=offset+110;
+            let name=data.get(name_start..name_start+name_size.saturating_sub(1)).ok_or(ContainerError::Truncated)?;
+            let name=String::from_utf8_lossy(name).to_string();
+            let start=(name_start+name_size).next_multiple_of(4);
+            let contents=data.get(start..start+size).ok_or(ContainerError::Truncated)?;
+            offset=(start+size).next_multiple_of(4);
+            if name=="TRAILER!!!" {
+                trailer=true;
+                continue;
+            }
+            //只看普通文件
+            if mode&0o170000==0o100000 {
+                self.member(path,&name,contents,depth);
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_tar(&mut self,path:&str,data:&[u8],depth:usize)->Result<(),ContainerError>{
+        let mut offset=0;
+        let mut long_name:Option<String>=None;
+        while offset+512<=data.len() {
+            let header=&data[offset..offset+512];
+            if header.iter().all(|&b| b==0) {
+                break;
+            }
+            if !is_tar(header) {
+                return Err(ContainerError::Truncated);
+            }
+            //GNU的base-256大小可以接近u64::MAX,相加前先检查
+            let size=tar_number(&header[124..136]).and_then(|size| usize::try_from(size).ok()).ok_or(ContainerError::Truncated)?;
+            let start=offset+512;
+            let end=start.checked_add(size).ok_or(ContainerError::Truncated)?;
+            let contents=data.get(start..end).ok_or(ContainerError::Truncated)?;
+            //start按512对齐,所以下一个头在end向上取整处
+            offset=end.next_multiple_of(512);
+            let text=|bytes:&[u8]| String::from_utf8_lossy(bytes.split(|&b| b==0).next().unwrap_or_default()).to_string();
+            match header[156] {
+                //GNU长名与pax扩展头作用于下一个条目
+                b'L' => long_name=Some(text(contents)),
+                b'x' => {
+                    if let Some(name)=pax_path(contents) {
+                        long_name=Some(name);
+                    }
+                }
+                b'0'|0|b'7' => {
+                    let name=long_name.take().unwrap_or_else(|| {
+                        let (prefix,name)=(text(&header[345..500]),text(&header[..100]));
+                        match header[257..262]==*b"ustar" && !prefix.is_empty() {
+                            true => format!("{}/{}",prefix,name),
+                            false => name,
+                        }
+                    });
+                    self.member(path,&name,contents,depth);
+                }
+                _ => long_name=None,
+            }
+        }
+        Ok(())
+    }
+}
+
+//头校验和:把校验和字段当作8个空格求所有字节之和
+fn is_tar(data:&[u8])->bool{
+    let Some(header)=data.get(..512) else {
+        return false;
+    };
+    let Some(checksum)=tar_number(&header[148..156]) else {
+        return false;
+    };
+    let sum:u64=header.iter().enumerate().map(|(i,&b)| if (148..156).contains(&i) { 32 } else { b as u64 }).sum();
+    sum==checksum
+}
+
+//八进制文本,或最高位置1的大端二进制(GNU对大文件的扩展)
+fn tar_number(field:&[u8])->Option<u64>{
+    if field.first().is_some_and(|&b| b&0x80!=0) {
+        return Some(field[1..].iter().fold((field[0]&0x7f) as u64,|value,&b| (value<<8)|b as u64));
+    }
+    let text=std::str::from_utf8(field).ok()?.trim_matches(|c:char| c=='\0' || c==' ');
+    match text.is_empty() {
+        true => Some(0),
+        false => u64::from_str_radix(text,8).ok(),
+    }
+}
+
+//pax记录形如"30 path=usr/lib/libfoo.so\n"
+fn pax_path(records:&[u8])->Option<String>{
+    let text=String::from_utf8_lossy(records);
+    text.lines()
+        .filter_map(|record| record.split_once(' ').map(|(_,pair)| pair))
+        .find_map(|pair| pair.strip_prefix("path=").map(|path| path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //带正确校验和的ustar头
+    fn tar_header(name:&str,size:&[u8;12])->Vec<u8>{
+        let mut header=vec![0u8;512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(size);
+        header[156]=b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        let sum:u32=header.iter().enumerate().map(|(i,&b)| if (148..156).contains(&i) { 32 } else { b as u32 }).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ",sum).as_bytes());
+        header
+    }
+
+    #[test]
+    fn tar_huge_size(){
+        let mut tar=tar_header("bin/foo",&[0xff;12]);
+        tar.resize(2048,0);
+        let scan=ContainerScan::parse_container("a.tar",&tar);
+        assert!(scan.elves.is_empty());
+        assert_eq!(scan.errors,vec![("a.tar".to_string(),ContainerError::Truncated)]);
+    }
+
+    #[test]
+    fn tar_member(){
+        let elf=include_bytes!("../test_elf/de");
+        let mut tar=tar_header("./usr/bin/de",format!("{:011o}\0",elf.len()).as_bytes().try_into().unwrap());
+        tar.extend_from_slice(elf);
+        tar.resize(tar.len().next_multiple_of(512)+1024,0);
+        let scan=ContainerScan::parse_container("a.tar",&tar);
+        assert_eq!(scan.elves.iter().map(|found| found.path.as_str()).collect::<Vec<_>>(),["a.tar!/usr/bin/de"]);
+        assert!(scan.errors.is_empty());
+    }
+
+    #[test]
+    fn xz_is_reported(){
+        let scan=ContainerScan::parse_container("data.tar.xz",&[0xfd,b'7',b'z',b'X',b'Z',0,0,0]);
+        assert_eq!(scan.errors,vec![("data.tar.xz".to_string(),ContainerError::Unsupported("xz".to_string()))]);
+    }
+
+    const ELF: &[u8] = include_bytes!("../test_elf/de");
+    //python gzip.compress(de,9,mtime=0)
+    const ELF_GZ: &[u8] = include_bytes!("../test_elf/de.gz");
+
+    fn paths(scan:&ContainerScan)->Vec<&str>{
+        scan.elves.iter().map(|found| found.path.as_str()).collect()
+    }
+
+    //只用存储块的gzip
+    fn gzip_stored(data:&[u8])->Vec<u8>{
+        let mut v=vec![0x1f,0x8b,8,0,0,0,0,0,0,3];
+        let chunks:Vec<&[u8]>=data.chunks(0xffff).collect();
+        for (i,chunk) in chunks.iter().enumerate() {
+            v.push((i+1==chunks.len()) as u8);
+            v.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            v.extend_from_slice(&(!(chunk.len() as u16)).to_le_bytes());
+            v.extend_from_slice(chunk);
+        }
+        v.extend_from_slice(&inflate::crc32(data).to_le_bytes());
+        v.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        v
+    }
+
+    //(名字,压缩方法,压缩后的内容,原始内容)
+    fn zip(entries:&[(&str,u16,&[u8],&[u8])])->Vec<u8>{
+        let mut data=Vec::new();
+        let mut directory=Vec::new();
+        for &(name,method,stored,contents) in entries {
+            let fields=|v:&mut Vec<u8>| {
+                v.extend_from_slice(&method.to_le_bytes());
+                v.extend_from_slice(&[0;4]);
+                v.extend_from_slice(&inflate::crc32(contents).to_le_bytes());
+                v.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+                v.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+                v.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                v.extend_from_slice(&[0;2]);
+            };
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            directory.extend_from_slice(&[20,0,20,0,0,0]);
+            fields(&mut directory);
+            //注释长度、磁盘号、内部与外部属性
+            directory.extend_from_slice(&[0;10]);
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            data.extend_from_slice(&[20,0,0,0]);
+            fields(&mut data);
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(stored);
+        }
+        let offset=data.len() as u32;
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        data.extend_from_slice(&[0;4]);
+        data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&[0;2]);
+        data
+    }
+
+    fn cpio_entry(name:&str,mode:u32,contents:&[u8])->Vec<u8>{
+        let mut v=format!("070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+                          1,mode,0,0,1,0,contents.len(),0,0,0,0,name.len()+1,0).into_bytes();
+        v.extend_from_slice(name.as_bytes());
+        v.push(0);
+        v.resize(v.len().next_multiple_of(4),0);
+        v.extend_from_slice(contents);
+        v.resize(v.len().next_multiple_of(4),0);
+        v
+    }
+
+    fn ar_member(name:&str,contents:&[u8])->Vec<u8>{
+        let mut v=format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",name,0,0,0,644,contents.len()).into_bytes();
+        v.extend_from_slice(contents);
+        if contents.len()%2==1 {
+            v.push(b'\n');
+        }
+        v
+    }
+
+    #[test]
+    fn zip_stored_and_deflated(){
+        let deflated=&ELF_GZ[10..ELF_GZ.len()-8];
+        let apk=zip(&[
+            ("lib/",0,b"",b""),
+            ("lib/x86_64/libde.so",0,ELF,ELF),
+            ("lib/arm64-v8a/libde.so",8,deflated,ELF),
+            ("classes.dex",0,b"dex\n035\0",b"dex\n035\0"),
+        ]);
+        let scan=ContainerScan::parse_container("app.apk",&apk);
+        assert_eq!(paths(&scan),["app.apk!/lib/x86_64/libde.so","app.apk!/lib/arm64-v8a/libde.so"]);
+        assert!(scan.errors.is_empty());
+        assert_eq!(scan.elves[1].elf.data,ELF);
+    }
+
+    #[test]
+    fn zip_checksum(){
+        let mut corrupt=ELF.to_vec();
+        corrupt[0x1000]^=1;
+        let mut apk=zip(&[("lib/libde.so",0,&corrupt,ELF)]);
+        let scan=ContainerScan::parse_container("app.apk",&apk);
+        assert_eq!(scan.errors,vec![("app.apk!/lib/libde.so".to_string(),ContainerError::Checksum)]);
+        //中央目录指向文件之外
+        let len=apk.len();
+        apk[len-6..len-2].copy_from_slice(&0xfff0u32.to_le_bytes());
+        let scan=ContainerScan::parse_container("app.apk",&apk);
+        assert_eq!(scan.errors,vec![("app.apk".to_string(),ContainerError::Truncated)]);
+    }
+
+    #[test]
+    fn cpio_newc(){
+        let mut cpio=cpio_entry("usr",0o040755,&[]);
+        cpio.extend(cpio_entry("usr/bin/de",0o100755,ELF));
+        cpio.extend(cpio_entry("usr/bin/link",0o120777,b"de"));
+        cpio.extend(cpio_entry("TRAILER!!!",0,&[]));
+        cpio.resize(cpio.len().next_multiple_of(512),0);
+        //initramfs:未压缩的cpio后面接一个gzip压缩的cpio
+        let mut initramfs=cpio.clone();
+        initramfs.extend(gzip_stored(&cpio));
+        let scan=ContainerScan::parse_container("initrd",&initramfs);
+        assert_eq!(paths(&scan),["initrd!/usr/bin/de","initrd!/usr/bin/de"]);
+        assert!(scan.errors.is_empty());
+    }
+
+    #[test]
+    fn gzip_and_deb(){
+        let scan=ContainerScan::parse_container("de.gz",ELF_GZ);
+        assert_eq!(paths(&scan),["de.gz"]);
+
+        let mut tar=tar_header("./usr/bin/de",format!("{:011o}\0",ELF.len()).as_bytes().try_into().unwrap());
+        tar.extend_from_slice(ELF);
+        tar.resize(tar.len().next_multiple_of(512)+1024,0);
+        let mut deb=b"!<arch>\n".to_vec();
+        deb.extend(ar_member("debian-binary",b"2.0\n"));
+        deb.extend(ar_member("control.tar.gz",&gzip_stored(&[0;1024])));
+        deb.extend(ar_member("data.tar.gz",&gzip_stored(&tar)));
+        let scan=ContainerScan::parse_container("de.deb",&deb);
+        assert_eq!(paths(&scan),["de.deb!/data.tar.gz!/usr/bin/de"]);
+        assert!(scan.errors.is_empty());
+    }
+
+    #[test]
+    fn inflate_cap(){
+        let mut scan=ContainerScan{max_inflated:0x1000,..Default::default()};
+        scan.scan("de.gz",ELF_GZ,0);
+        assert_eq!(scan.errors,vec![("de.gz".to_string(),ContainerError::Inflate(InflateError::TooLarge))]);
+        let apk=zip(&[("libde.so",8,&ELF_GZ[10..ELF_GZ.len()-8],ELF)]);
+        let mut scan=ContainerScan{max_inflated:0x1000,..Default::default()};
+        scan.scan("app.apk",&apk,0);
+        assert_eq!(scan.errors,vec![("app.apk!/libde.so".to_string(),ContainerError::Inflate(InflateError::TooLarge))]);
+        assert!(scan.elves.is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::parser::abi;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::file::Class;
+use crate::parser::note::NoteEntry;
+use crate::parser::section::SectionHeader;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreError {
+    Parse,
+    /// Not an ET_CORE file; carries e_type
+    NotCore(u16),
+    /// No PT_LOAD covers the address
+    Unmapped(u64),
+    /// The address is mapped but its contents were not written to the core
+    NotDumped(u64),
+}
+
+/// `struct elf_prstatus`: one per thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrStatus {
+    /// si_signo/si_code/si_errno of `pr_info`
+    pub signal: i32,
+    pub code: i32,
+    pub errno: i32,
+    /// The signal being delivered when the dump was taken
+    pub cursig: u16,
+    pub sigpend: u64,
+    pub sighold: u64,
+    pub pid: i32,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub sid: i32,
+    /// User, system and children's times as (seconds, microseconds)
+    pub utime: (u64, u64),
+    pub stime: (u64, u64),
+    pub cutime: (u64, u64),
+    pub cstime: (u64, u64),
+    /// General-purpose registers in `pr_reg` order, named for x86-64 and AArch64
+    pub registers: Vec<(String, u64)>,
+    pub fpvalid: bool,
+}
+
+/// `struct elf_prpsinfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrPsInfo {
+    pub state: u8,
+    /// State letter as in `ps`, e.g. `R` or `S`
+    pub sname: char,
+    pub zombie: bool,
+    pub nice: i8,
+    pub flag: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: i32,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub sid: i32,
+    /// Executable name, at most 16 bytes
+    pub fname: String,
+    /// Start of the command line, at most 80 bytes
+    pub psargs: String,
+}
+
+/// The fields of `siginfo_t` that apply to the signal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    /// Faulting address for SIGILL, SIGFPE, SIGSEGV, SIGBUS and SIGTRAP
+    pub addr: Option<u64>,
+    /// Sender for signals from kill(), tkill() and sigqueue()
+    pub sender: Option<(i32, u32)>,
+}
+
+/// An NT_FILE entry: a file-backed mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedFile {
+    pub start: u64,
+    pub end: u64,
+    /// Offset in the file in bytes
+    pub offset: u64,
+    pub path: String,
+}
+
+/// NT_FPREGSET contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FpRegisters {
+    /// The FXSAVE area: x87 control/status/tag, MXCSR, ST0-7 (80 bits each) and XMM0-15
+    X86_64 { fcw: u16, fsw: u16, ftw: u16, mxcsr: u32, st: Vec<u128>, xmm: Vec<u128> },
+    /// `user_fpsimd_state`: V0-V31, FPSR and FPCR
+    Aarch64 { v: Vec<u128>, fpsr: u32, fpcr: u32 },
+    Raw(Vec<u8>),
+}
+
+/// A thread: its NT_PRSTATUS and the NT_FPREGSET that follows it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoreThread {
+    pub status: PrStatus,
+    pub fpregs: Option<FpRegisters>,
+}
+
+/// The notes of a Linux core dump.
+///
+/// The kernel writes each thread's register notes together, starting with
+/// NT_PRSTATUS, and the process-wide notes after the first thread's NT_PRSTATUS,
+/// so register sets are attached to the preceding NT_PRSTATUS. Memory is read with
+/// [`ElfFile::read_memory`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoreDump {
+    pub threads: Vec<CoreThread>,
+    pub process: Option<PrPsInfo>,
+    pub siginfo: Option<SigInfo>,
+    /// (a_type, a_val) pairs without the terminating AT_NULL
+    pub auxv: Vec<(u64, u64)>,
+    pub files: Vec<MappedFile>,
+    /// Page size NT_FILE offsets were counted in
+    pub page_size: u64,
+}
+
+const X86_64_REGISTERS: [&str; 27] = [
+    "r15","r14","r13","r12","rbp","rbx","r11","r10","r9","r8","rax","rcx","rdx","rsi","rdi",
+    "orig_rax","rip","cs","eflags","rsp","ss","fs_base","gs_base","ds","es","fs","gs",
+];
+const AARCH64_SPECIAL_REGISTERS: [&str; 3] = ["sp","pc","pstate"];
+
+impl CoreDump {
+    pub fn read_core(file_path:&str)->Result<CoreDump,CoreError>{
+        let elf=ElfFile::read_elf(file_path).ok_or(CoreError::Parse)?;
+        Self::parse_core(&elf)
+    }
+
+    pub fn parse_core(elf:&ElfFile)->Result<CoreDump,CoreError>{
+        if elf.header.e_type!=abi::ET_CORE {
+            return Err(CoreError::NotCore(elf.header.e_type));
+        }
+        let ident=elf.ident();
+        let machine=elf.header.e_machine;
+        let mut core=CoreDump::default();
+        for entry in NoteEntry::parse_segment_notes(elf) {
+            //"CORE"之外的note(如LINUX的XSTATE)不在这里解析
+            if entry.name!="CORE" {
+                continue;
+            }
+            let desc=&entry.desc;
+            match entry.n_type {
+                abi::NT_PRSTATUS => {
+                    if let Some(status)=PrStatus::parse(ident,machine,desc) {
+                        core.threads.push(CoreThread{status,fpregs:None});
+                    }
+                }
+                abi::NT_FPREGSET => {
+                    if let Some(thread)=core.threads.last_mut() {
+                        thread.fpregs=Some(FpRegisters::parse(ident,machine,desc));
+                    }
+                }
+                abi::NT_PRPSINFO => core.process=PrPsInfo::parse(ident,desc),
+                abi::NT_SIGINFO => core.siginfo=SigInfo::parse(ident,desc),
+                abi::NT_AUXV => core.auxv=Self::parse_auxv(ident,desc),
+                abi::NT_FILE => (core.page_size,core.files)=Self::parse_files(ident,desc),
+                _ => {}
+            }
+        }
+        Ok(core)
+    }
+
+    fn parse_auxv(ident:(AnyEndian, Class),desc:&[u8])->Vec<(u64,u64)>{
+        let word=ident.1.word_size();
+        (0..desc.len()/(word*2))
+            .map(|i| (read_word(ident,desc,i*word*2),read_word(ident,desc,i*word*2+word)))
+            .take_while(|&(a_type,_)| a_type!=abi::AT_NULL)
+            .collect()
+    }
+
+    //count、page_size,count组(start,end,file_ofs),最后是count个以0结尾的路径
+    fn parse_files(ident:(AnyEndian, Class),desc:&[u8])->(u64,Vec<MappedFile>){
+        let word=ident.1.word_size();
+        if desc.len()<word*2 {
+            return (0,Vec::new());
+        }
+        let count=read_word(ident,desc,0) as usize;
+        let page_size=read_word(ident,desc,word);
+        //count来自core文件本身,溢出时按没有映射处理
+        let Some(names_start)=count.checked_mul(word*3).and_then(|size| size.checked_add(word*2)) else {
+            return (page_size,Vec::new());
+        };
+        let Some(names)=desc.get(names_start..) else {
+            return (page_size,Vec::new());
+        };
+        let files=names.split(|&b| b==0).take(count).enumerate().map(|(i,name)| {
+            let at=word*2+i*word*3;
+            MappedFile{
+                start:read_word(ident,desc,at),
+                end:read_word(ident,desc,at+word),
+                offset:read_word(ident,desc,at+word*2).saturating_mul(page_size),
+                path:String::from_utf8_lossy(name).to_string(),
+            }
+        }).collect();
+        (page_size,files)
+    }
+
+    /// The auxv value of `a_type`
+    pub fn auxv_value(&self,a_type:u64)->Option<u64>{
+        self.auxv.iter().find(|&&(t,_)| t==a_type).map(|&(_,value)| value)
+    }
+
+    /// The file mapped at `vaddr`, with the file offset of that address
+    pub fn file_at(&self,vaddr:u64)->Option<(&MappedFile,u64)>{
+        self.files.iter()
+            .find(|file| vaddr>=file.start && vaddr<file.end)
+            .and_then(|file| Some((file,file.offset.checked_add(vaddr-file.start)?)))
+    }
+
+    /// Name of an auxv entry type, e.g. `AT_ENTRY`
+    pub fn auxv_name(a_type:u64)->&'static str{
+        match a_type {
+            0 => "AT_NULL",
+            1 => "AT_IGNORE",
+            2 => "AT_EXECFD",
+            3 => "AT_PHDR",
+            4 => "AT_PHENT",
+            5 => "AT_PHNUM",
+            6 => "AT_PAGESZ",
+            7 => "AT_BASE",
+            8 => "AT_FLAGS",
+            9 => "AT_ENTRY",
+            10 => "AT_NOTELF",
+            11 => "AT_UID",
+            12 => "AT_EUID",
+            13 => "AT_GID",
+            14 => "AT_EGID",
+            15 => "AT_PLATFORM",
+            16 => "AT_HWCAP",
+            17 => "AT_CLKTCK",
+            23 => "AT_SECURE",
+            24 => "AT_BASE_PLATFORM",
+            25 => "AT_RANDOM",
+            26 => "AT_HWCAP2",
+            27 => "AT_RSEQ_FEATURE_SIZE",
+            28 => "AT_RSEQ_ALIGN",
+            29 => "AT_HWCAP3",
+            30 => "AT_HWCAP4",
+            31 => "AT_EXECFN",
+            32 => "AT_SYSINFO",
+            33 => "AT_SYSINFO_EHDR",
+            51 => "AT_MINSIGSTKSZ",
+            _ => "AT_UNKNOWN",
+        }
+    }
+}
+
+impl PrStatus {
+    pub fn parse(ident:(AnyEndian, Class),machine:u16,desc:&[u8])->Option<PrStatus>{
+        let (endian,class)=ident;
+        let word=class.word_size();
+        //pr_info(12字节)与pr_cursig之后按long对齐,两种位数都落在16
+        let sigpend_at=16;
+        let pid_at=sigpend_at+word*2;
+        let times_at=pid_at+16;
+        let registers_at=times_at+word*8;
+        if desc.len()<registers_at {
+            return None;
+        }
+        let time=|i:usize| (read_word(ident,desc,times_at+i*word*2),read_word(ident,desc,times_at+i*word*2+word));
+        let names:Vec<String>=match machine {
+            abi::EM_X86_64 => X86_64_REGISTERS.iter().map(|name| name.to_string()).collect(),
+            abi::EM_AARCH64 => (0..31).map(|i| format!("x{}",i)).chain(AARCH64_SPECIAL_REGISTERS.iter().map(|name| name.to_string())).collect(),
+            //其它架构按pr_fpvalid之前的整字数给出r0、r1……
+            _ => (0..desc.len().saturating_sub(registers_at+4)/word).map(|i| format!("r{}",i)).collect(),
+        };
+        let fpvalid_at=registers_at+names.len()*word;
+        if desc.len()<fpvalid_at+4 {
+            return None;
+        }
+        let registers=names.into_iter().enumerate().map(|(i,name)| (name,read_word(ident,desc,registers_at+i*word))).collect();
+        Some(PrStatus{
+            signal:endian.parse_u32_at(0,desc) as i32,
+            code:endian.parse_u32_at(4,desc) as i32,
+            errno:endian.parse_u32_at(8,desc) as i32,
+            cursig:endian.parse_u16_at(12,desc),
+            sigpend:read_word(ident,desc,sigpend_at),
+            sighold:read_word(ident,desc,sigpend_at+word),
+            pid:endian.parse_u32_at(pid_at,desc) as i32,
+            ppid:endian.parse_u32_at(pid_at+4,desc) as i32,
+            pgrp:endian.parse_u32_at(pid_at+8,desc) as i32,
+            sid:endian.parse_u32_at(pid_at+12,desc) as i32,
+            utime:time(0),
+            stime:time(1),
+            cutime:time(2),
+            cstime:time(3),
+            registers,
+            fpvalid:endian.parse_u32_at(fpvalid_at,desc)!=0,
+        })
+    }
+
+    /// A register by name, e.g. `rip` or `pc`
+    pub fn register(&self,name:&str)->Option<u64>{
+        self.registers.iter().find(|(n,_)| n==name).map(|&(_,value)| value)
+    }
+}
+
+impl PrPsInfo {
+    //ELF32上pr_flag是4字节,uid/gid是16位
+    pub fn parse(ident:(AnyEndian, Class),desc:&[u8])->Option<PrPsInfo>{
+        let (endian,class)=ident;
+        let (ids_at,pid_at,fname_at)=match class {
+            Class::ELF64 => (16,24,40),
+            Class::ELF32 => (8,12,28),
+        };
+        let text=|start:usize,length:usize| desc.get(start..start+length).map(|bytes| {
+            SectionHeader::parse_string_at(bytes,0).unwrap_or_else(|| String::from_utf8_lossy(bytes).to_string())
+        });
+        let psargs=text(fname_at+16,80)?;
+        let (uid,gid)=match class {
+            Class::ELF64 => (endian.parse_u32_at(ids_at,desc),endian.parse_u32_at(ids_at+4,desc)),
+            Class::ELF32 => (endian.parse_u16_at(ids_at,desc) as u32,endian.parse_u16_at(ids_at+2,desc) as u32),
+        };
+        Some(PrPsInfo{
+            state:desc[0],
+            sname:desc[1] as char,
+            zombie:desc[2]!=0,
+            nice:desc[3] as i8,
+            flag:match class {
+                Class::ELF64 => endian.parse_u64_at(8,desc),
+                Class::ELF32 => endian.parse_u32_at(4,desc) as u64,
+            },
+            uid,
+            gid,
+            pid:endian.parse_u32_at(pid_at,desc) as i32,
+            ppid:endian.parse_u32_at(pid_at+4,desc) as i32,
+            pgrp:endian.parse_u32_at(pid_at+8,desc) as i32,
+            sid:endian.parse_u32_at(pid_at+12,desc) as i32,
+            fname:text(fname_at,16)?,
+            psargs:psargs.trim_end().to_string(),
+        })
+    }
+}
+
+impl SigInfo {
+    pub fn parse(ident:(AnyEndian, Class),desc:&[u8])->Option<SigInfo>{
+        let (endian,class)=ident;
+        //联合体在ELF64上按指针对齐到16
+        let fields_at=match class {
+            Class::ELF64 => 16,
+            Class::ELF32 => 12,
+        };
+        if desc.len()<fields_at+class.word_size() {
+            return None;
+        }
+        let signo=endian.parse_u32_at(0,desc) as i32;
+        let code=endian.parse_u32_at(8,desc) as i32;
+        //SIGILL、SIGTRAP、SIGFPE、SIGBUS、SIGSEGV带故障地址
+        let fault=matches!(signo,4|5|7|8|11) && code>0;
+        //SI_USER、SI_QUEUE、SI_TKILL带发送者
+        let from_user=matches!(code,0|-1|-6);
+        Some(SigInfo{
+            signo,
+            errno:endian.parse_u32_at(4,desc) as i32,
+            code,
+            addr:fault.then(|| read_word(ident,desc,fields_at)),
+            sender:from_user.then(|| (endian.parse_u32_at(fields_at,desc) as i32,endian.parse_u32_at(fields_at+4,desc))),
+        })
+    }
+}
+
+impl FpRegisters {
+    pub fn parse(ident:(AnyEndian, Class),machine:u16,desc:&[u8])->FpRegisters{
+        let (endian,_)=ident;
+        let vector=|at:usize| (endian.parse_u64_at(at,desc) as u128)|((endian.parse_u64_at(at+8,desc) as u128)<<64);
+        match machine {
+            abi::EM_X86_64 if desc.len()>=512 => FpRegisters::X86_64{
+                fcw:endian.parse_u16_at(0,desc),
+                fsw:endian.parse_u16_at(2,desc),
+                ftw:endian.parse_u16_at(4,desc),
+                mxcsr:endian.parse_u32_at(24,desc),
+                //ST寄存器占16字节槽位中的低10字节
+                st:(0..8).map(|i| vector(32+i*16)&((1u128<<80)-1)).collect(),
+                xmm:(0..16).map(|i| vector(160+i*16)).collect(),
+            },
+            abi::EM_AARCH64 if desc.len()>=520 => FpRegisters::Aarch64{
+                v:(0..32).map(|i| vector(i*16)).collect(),
+                fpsr:endian.parse_u32_at(512,desc),
+                fpcr:endian.parse_u32_at(516,desc),
+            },
+            _ => FpRegisters::Raw(desc.to_vec()),
+        }
+    }
+}
+
+impl ElfFile {
+    /// Read `len` bytes at a virtual address through PT_LOAD segments; the range
+    /// may span adjacent segments. Parts of a segment beyond p_filesz were not dumped.
+    pub fn read_memory(&self,vaddr:u64,len:u64)->Result<Vec<u8>,CoreError>{
+        let mut data=Vec::new();
+        let mut address=vaddr;
+        let end=vaddr.checked_add(len).ok_or(CoreError::Unmapped(vaddr))?;
+        while address<end {
+            let ph=self.program_headers.iter()
+                .filter(|ph| ph.p_type==abi::PT_LOAD)
+                .find(|ph| address>=ph.p_vaddr && address-ph.p_vaddr<ph.p_memsz)
+                .ok_or(CoreError::Unmapped(address))?;
+            let relative=address-ph.p_vaddr;
+            if relative>=ph.p_filesz {
+                return Err(CoreError::NotDumped(address));
+            }
+            let count=(end-address).min(ph.p_filesz-relative);
+            let offset=(ph.p_offset+relative) as usize;
+            data.extend_from_slice(self.data.get(offset..offset+count as usize).ok_or(CoreError::NotDumped(address))?);
+            address+=count;
+        }
+        Ok(data)
+    }
+
+    /// Read a NUL-terminated string at a virtual address, at most `max` bytes
+    pub fn read_memory_string(&self,vaddr:u64,max:u64)->Result<String,CoreError>{
+        let mut bytes=Vec::new();
+        //逐页读取,避免越过映射末尾
+        let mut address=vaddr;
+        while (bytes.len() as u64)<max {
+            let chunk=(0x1000-address%0x1000).min(max-bytes.len() as u64);
+            let data=self.read_memory(address,chunk)?;
+            if let Some(end)=data.iter().position(|&b| b==0) {
+                bytes.extend_from_slice(&data[..end]);
+                break;
+            }
+            bytes.extend_from_slice(&data);
+            address+=chunk;
+        }
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+}
+
+fn read_word(ident:(AnyEndian, Class),data:&[u8],offset:usize)->u64{
+    match ident.1 {
+        Class::ELF32 => ident.0.parse_u32_at(offset,data) as u64,
+        Class::ELF64 => ident.0.parse_u64_at(offset,data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //NT_FILE的描述:count、page_size、(start,end,file_ofs)组,之后是路径
+    fn nt_file(count:u64,entries:&[(u64,u64,u64)],names:&[&str])->Vec<u8>{
+        let mut desc:Vec<u8>=[count,0x1000].iter().flat_map(|word| word.to_le_bytes()).collect();
+        for &(start,end,offset) in entries {
+            desc.extend([start,end,offset].iter().flat_map(|word| word.to_le_bytes()));
+        }
+        for name in names {
+            desc.extend_from_slice(name.as_bytes());
+            desc.push(0);
+        }
+        desc
+    }
+
+    #[test]
+    fn mapped_files(){
+        let ident=(AnyEndian::new(abi::ELFDATA2LSB),Class::ELF64);
+        let desc=nt_file(2,&[(0x40_0000,0x40_1000,0),(0x40_1000,0x40_3000,1)],&["/bin/a","/lib/b.so"]);
+        let (page_size,files)=CoreDump::parse_files(ident,&desc);
+        assert_eq!(page_size,0x1000);
+        assert_eq!(files,vec![
+            MappedFile{start:0x40_0000,end:0x40_1000,offset:0,path:"/bin/a".to_string()},
+            MappedFile{start:0x40_1000,end:0x40_3000,offset:0x1000,path:"/lib/b.so".to_string()},
+        ]);
+    }
+
+    //count大到算名字起点时溢出,按没有映射处理
+    #[test]
+    fn huge_file_count(){
+        let ident=(AnyEndian::new(abi::ELFDATA2LSB),Class::ELF64);
+        for count in [u64::MAX,u64::MAX/24,u64::MAX/24-1] {
+            let desc=nt_file(count,&[(0x40_0000,0x40_1000,0)],&["/bin/a"]);
+            assert_eq!(CoreDump::parse_files(ident,&desc),(0x1000,Vec::new()));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::parser::abi;
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
+use crate::parser::file::Class;
+
+/// C-style 32-bit ELF dynamic section entry definition
+///
+/// These C-style definitions are for users who want to implement their own ELF manipulation logic.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Elf32_Dyn {
+    pub d_tag: i32,
+    pub d_val: u32,
+}
+
+/// C-style 64-bit ELF dynamic section entry definition
+///
+/// These C-style definitions are for users who want to implement their own ELF manipulation logic.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Elf64_Dyn {
+    pub d_tag: i64,
+    pub d_val: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dyn {
+    /// Entry type (DT_*)
+    pub d_tag: i64,
+    /// Integer value or address, depending on d_tag
+    pub d_val: u64,
+}
+
+impl Dyn {
+    /// Parse entries up to and including the terminating DT_NULL
+    pub fn parse(ident: (AnyEndian, Class),data:&[u8])->Vec<Dyn>{
+        let (_,class)=ident;
+        let size=Self::size_for(class);
+        let mut v=Vec::new();
+        let mut offset=0;
+        while offset+size<=data.len() {
+            let entry=Self::parse_at(ident,offset,data);
+            v.push(entry);
+            if entry.d_tag==abi::DT_NULL {
+                break;
+            }
+            offset+=size;
+        }
+        v
+    }
+
+    pub fn parse_at(ident: (AnyEndian, Class),offset:usize,data:&[u8])->Dyn{
+        let (endian,class)=ident;
+        match class {
+            Class::ELF32 => Dyn{
+                d_tag:endian.parse_i32_at(offset,data) as i64,
+                d_val:endian.parse_u32_at(offset+4,data) as u64,
+            },
+            Class::ELF64 => Dyn{
+                d_tag:endian.parse_i64_at(offset,data),
+                d_val:endian.parse_u64_at(offset+8,data),
+            },
+        }
+    }
+
+    pub fn write_at(&self,ident: (AnyEndian, Class),offset:usize,data:&mut [u8]){
+        let (endian,class)=ident;
+        match class {
+            Class::ELF32 => {
+                endian.write_u32_at(offset,data,self.d_tag as u32);
+                endian.write_u32_at(offset+4,data,self.d_val as u32);
+            }
+            Class::ELF64 => {
+                endian.write_u64_at(offset,data,self.d_tag as u64);
+                endian.write_u64_at(offset+8,data,self.d_val);
+            }
+        }
+    }
+
+    /// Serialize entries, the inverse of `parse`
+    pub fn to_bytes(ident: (AnyEndian, Class),dyns:&[Dyn])->Vec<u8>{
+        let size=Self::size_for(ident.1);
+        let mut data=vec![0u8;dyns.len()*size];
+        for (i,entry) in dyns.iter().enumerate() {
+            entry.write_at(ident,i*size,&mut data);
+        }
+        data
+    }
+
+    /// Value of the first entry with `d_tag`
+    pub fn find(dyns:&[Dyn],d_tag:i64)->Option<u64>{
+        dyns.iter().find(|d| d.d_tag==d_tag).map(|d| d.d_val)
+    }
+
+    /// Values of every entry with `d_tag`, e.g. all DT_NEEDED string offsets
+    pub fn find_all(dyns:&[Dyn],d_tag:i64)->Vec<u64>{
+        dyns.iter().filter(|d| d.d_tag==d_tag).map(|d| d.d_val).collect()
+    }
+
+    pub fn size_for(class: Class) -> usize {
+        match class {
+            Class::ELF32 => 8,
+            Class::ELF64 => 16,
+        }
+    }
+}
@@ -0,0 +1,626 @@
+use std::fs;
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The file could not be parsed as ELF
+    Parse,
+    /// There is no dynamic section to edit
+    NotDynamic,
+    /// There is no PT_INTERP segment to change
+    NoInterpreter,
+    /// No DT_NEEDED entry names the library
+    NeededNotFound(String),
+    /// No section has the name
+    NoSection(String),
+    /// Only non-alloc sections other than the section name table can be removed
+    CannotRemove(String),
+    /// The file has no PT_LOAD to add a loaded section next to
+    NotLoadable,
+    /// A segment other than PT_LOAD covers part of a section that would have to move
+    SegmentOverlap(String),
+    /// Offset or address plus size of this program header wraps around
+    SegmentOverflow(usize),
+    /// Offset plus size of the section wraps around
+    SectionOverflow(String),
+    /// The output would reach past `MAX_OUTPUT_SIZE` bytes
+    TooLarge(u64),
+    Io(String),
+}
+
+/// Largest file the editors lay out; offsets and sizes of the untouched parts come
+/// straight from the input headers
+pub const MAX_OUTPUT_SIZE: u64 = 1<<30;
+
+/// patchelf-style editor for the interpreter and the string-valued dynamic entries
+/// (DT_NEEDED, DT_SONAME, DT_RPATH, DT_RUNPATH).
+///
+/// Edits are collected first and applied by `to_bytes`. New strings are appended to
+/// a copy of `.dynstr`, so existing offsets (symbol names, version records) stay
+/// valid. Whatever no longer fits where it was — the interpreter path, `.dynstr`, the
+/// dynamic table — is moved into a new PT_LOAD appended to the file, and the program
+/// header table moves with it when there is no room for the extra entry.
+#[derive(Debug, Clone)]
+pub struct ElfEditor {
+    pub elf: ElfFile,
+    interpreter: Option<String>,
+    interpreter_changed: bool,
+    dyns: Vec<Dyn>,
+    strtab: Vec<u8>,
+    //原.dynstr大小,超出部分需要搬迁
+    strtab_len: usize,
+    //输入文件的权限,写出时沿用
+    permissions: Option<fs::Permissions>,
+}
+
+/// Write `data` to `file_path` and apply `permissions`, so an edited executable
+/// stays executable
+pub(crate) fn write_output(file_path:&str,data:&[u8],permissions:Option<&fs::Permissions>)->std::io::Result<()>{
+    fs::write(file_path,data)?;
+    match permissions {
+        Some(permissions) => fs::set_permissions(file_path,permissions.clone()),
+        None => Ok(()),
+    }
+}
+
+//值为.dynstr偏移的表项
+const STRING_TAGS:[i64;4]=[abi::DT_NEEDED,abi::DT_SONAME,abi::DT_RPATH,abi::DT_RUNPATH];
+
+impl ElfEditor {
+    pub fn read_editor(file_path:&str)->Result<ElfEditor,EditError>{
+        let data=fs::read(file_path).map_err(|e| EditError::Io(e.to_string()))?;
+        let mut editor=Self::new(ElfFile::parse(data).ok_or(EditError::Parse)?)?;
+        editor.permissions=fs::metadata(file_path).map(|m| m.permissions()).ok();
+        Ok(editor)
+    }
+
+    pub fn new(elf:ElfFile)->Result<ElfEditor,EditError>{
+        let mut dyns=elf.dynamic();
+        if dyns.is_empty() {
+            return Err(EditError::NotDynamic);
+        }
+        while dyns.last().is_some_and(|d| d.d_tag==abi::DT_NULL) {
+            dyns.pop();
+        }
+        let strtab=elf.dynamic_strings().ok_or(EditError::NotDynamic)?.to_vec();
+        let interpreter=elf.program_headers.iter()
+            .find(|ph| ph.p_type==abi::PT_INTERP)
+            .and_then(|ph| elf.segment_data(ph))
+            .and_then(|bytes| SectionHeader::parse_string_at(bytes,0));
+        Ok(ElfEditor{
+            strtab_len:strtab.len(),
+            elf,
+            interpreter,
+            interpreter_changed:false,
+            dyns,
+            strtab,
+            permissions:None,
+        })
+    }
+
+    pub fn interpreter(&self)->Option<&str>{
+        self.interpreter.as_deref()
+    }
+
+    pub fn soname(&self)->Option<String>{
+        self.string_of(abi::DT_SONAME)
+    }
+
+    pub fn rpath(&self)->Option<String>{
+        self.string_of(abi::DT_RPATH)
+    }
+
+    pub fn runpath(&self)->Option<String>{
+        self.string_of(abi::DT_RUNPATH)
+    }
+
+    pub fn needed(&self)->Vec<String>{
+        Dyn::find_all(&self.dyns,abi::DT_NEEDED).into_iter()
+            .filter_map(|offset| SectionHeader::parse_string_at(&self.strtab,offset as usize))
+            .collect()
+    }
+
+    pub fn set_interpreter(&mut self,path:&str)->Result<(),EditError>{
+        if self.interpreter.is_none() {
+            return Err(EditError::NoInterpreter);
+        }
+        self.interpreter=Some(path.to_string());
+        self.interpreter_changed=true;
+        Ok(())
+    }
+
+    /// Turn PT_INTERP into PT_NULL; the `.interp` bytes are left in place
+    pub fn remove_interpreter(&mut self)->Result<(),EditError>{
+        if self.interpreter.is_none() {
+            return Err(EditError::NoInterpreter);
+        }
+        self.interpreter=None;
+        self.interpreter_changed=true;
+        Ok(())
+    }
+
+    pub fn set_rpath(&mut self,path:&str){
+        self.set_search_path(abi::DT_RPATH,path);
+    }
+
+    /// Append a directory to DT_RPATH, creating the entry if needed
+    pub fn append_rpath(&mut self,path:&str){
+        let joined=Self::join_path(self.rpath(),path);
+        self.set_search_path(abi::DT_RPATH,&joined);
+    }
+
+    pub fn remove_rpath(&mut self){
+        self.dyns.retain(|d| d.d_tag!=abi::DT_RPATH);
+    }
+
+    pub fn set_runpath(&mut self,path:&str){
+        self.set_search_path(abi::DT_RUNPATH,path);
+    }
+
+    /// Append a directory to DT_RUNPATH, creating the entry if needed
+    pub fn append_runpath(&mut self,path:&str){
+        let joined=Self::join_path(self.runpath(),path);
+        self.set_search_path(abi::DT_RUNPATH,&joined);
+    }
+
+    pub fn remove_runpath(&mut self){
+        self.dyns.retain(|d| d.d_tag!=abi::DT_RUNPATH);
+    }
+
+    /// The old name is not overwritten, since the version definition of a library
+    /// usually shares its string
+    pub fn set_soname(&mut self,soname:&str){
+        let offset=self.add_string(soname);
+        self.set_entry(abi::DT_SONAME,offset);
+    }
+
+    /// Add a DT_NEEDED entry after the existing ones; a library that is already
+    /// needed is left alone
+    pub fn add_needed(&mut self,library:&str){
+        if self.needed().iter().any(|name| name==library) {
+            return;
+        }
+        let offset=self.add_string(library);
+        let position=self.dyns.iter().rposition(|d| d.d_tag==abi::DT_NEEDED).map(|i| i+1).unwrap_or(0);
+        self.dyns.insert(position,Dyn{d_tag:abi::DT_NEEDED,d_val:offset});
+    }
+
+    pub fn remove_needed(&mut self,library:&str)->Result<(),EditError>{
+        let index=self.find_needed(library)?;
+        self.dyns.remove(index);
+        Ok(())
+    }
+
+    pub fn replace_needed(&mut self,library:&str,replacement:&str)->Result<(),EditError>{
+        let index=self.find_needed(library)?;
+        let offset=self.add_string(replacement);
+        self.dyns[index].d_val=offset;
+        Ok(())
+    }
+
+    fn find_needed(&self,library:&str)->Result<usize,EditError>{
+        self.dyns.iter()
+            .position(|d| d.d_tag==abi::DT_NEEDED && SectionHeader::parse_string_at(&self.strtab,d.d_val as usize).as_deref()==Some(library))
+            .ok_or_else(|| EditError::NeededNotFound(library.to_string()))
+    }
+
+    fn string_of(&self,d_tag:i64)->Option<String>{
+        let offset=Dyn::find(&self.dyns,d_tag)?;
+        SectionHeader::parse_string_at(&self.strtab,offset as usize)
+    }
+
+    fn join_path(current:Option<String>,path:&str)->String{
+        match current {
+            Some(current) if !current.is_empty() => format!("{}:{}",current,path),
+            _ => path.to_string(),
+        }
+    }
+
+    //搜索路径一般不与其他字符串共享,放得下时原地改写
+    fn set_search_path(&mut self,d_tag:i64,path:&str){
+        if let Some(old)=Dyn::find(&self.dyns,d_tag) {
+            let old=old as usize;
+            let shared=self.dyns.iter().filter(|d| STRING_TAGS.contains(&d.d_tag) && d.d_val as usize==old).count()>1;
+            let old_len=self.strtab[old.min(self.strtab.len())..].iter().position(|&b| b==0);
+            if let Some(old_len)=old_len {
+                if !shared && path.len()<=old_len {
+                    self.strtab[old..old+old_len].fill(0);
+                    self.strtab[old..old+path.len()].copy_from_slice(path.as_bytes());
+                    return;
+                }
+            }
+        }
+        let offset=self.add_string(path);
+        self.set_entry(d_tag,offset);
+    }
+
+    fn set_entry(&mut self,d_tag:i64,d_val:u64){
+        match self.dyns.iter_mut().find(|d| d.d_tag==d_tag) {
+            Some(entry) => entry.d_val=d_val,
+            None => {
+                let position=self.dyns.iter().rposition(|d| d.d_tag==abi::DT_NEEDED).map(|i| i+1).unwrap_or(0);
+                self.dyns.insert(position,Dyn{d_tag,d_val});
+            }
+        }
+    }
+
+    /// Offset of `value` in the string table, reusing an existing string or suffix
+    fn add_string(&mut self,value:&str)->u64{
+        let mut needle=value.as_bytes().to_vec();
+        needle.push(0);
+        if let Some(offset)=self.strtab.windows(needle.len()).position(|w| w==needle.as_slice()) {
+            return offset as u64;
+        }
+        let offset=self.strtab.len();
+        self.strtab.extend_from_slice(&needle);
+        offset as u64
+    }
+
+    /// Write the edited file, with the input's permissions when it was read from disk
+    pub fn write(&self,file_path:&str)->Result<(),EditError>{
+        write_output(file_path,&self.to_bytes()?,self.permissions.as_ref()).map_err(|e| EditError::Io(e.to_string()))
+    }
+
+    /// Apply the edits and serialize the file
+    pub fn to_bytes(&self)->Result<Vec<u8>,EditError>{
+        let mut elf=self.elf.clone();
+        let ident=elf.ident();
+        let class=elf.header.class;
+        let word=class.word_size() as u64;
+        let dyn_size=Dyn::size_for(class) as u64;
+
+        let interp_index=elf.program_headers.iter().position(|ph| ph.p_type==abi::PT_INTERP);
+        let dynamic_index=elf.program_headers.iter().position(|ph| ph.p_type==abi::PT_DYNAMIC);
+        let dynamic_section=elf.section_headers.iter().position(|sh| sh.sh_type==abi::SHT_DYNAMIC);
+        let strtab_addr=Dyn::find(&self.dyns,abi::DT_STRTAB).unwrap_or(0);
+        let strtab_offset=elf.vaddr_to_offset(strtab_addr)
+            .or_else(|| elf.find_section(".dynstr").map(|sh| sh.sh_offset));
+
+        //解释器:放得下就原地写入
+        let mut new_interp=None;
+        if self.interpreter_changed {
+            if let Some(i)=interp_index {
+                match &self.interpreter {
+                    Some(path) => {
+                        let mut bytes=path.as_bytes().to_vec();
+                        bytes.push(0);
+                        let ph=elf.program_headers[i];
+                        if bytes.len() as u64<=ph.p_filesz {
+                            let old=ph.p_offset.checked_add(ph.p_filesz)
+                                .and_then(|end| elf.data.get_mut(ph.p_offset as usize..end as usize))
+                                .ok_or(EditError::SegmentOverflow(i))?;
+                            old.fill(0);
+                            old[..bytes.len()].copy_from_slice(&bytes);
+                            Self::resize_interp(&mut elf,i,bytes.len() as u64);
+                        } else {
+                            new_interp=Some(bytes);
+                        }
+                    }
+                    None => elf.program_headers[i].p_type=abi::PT_NULL,
+                }
+            }
+        }
+        let move_strtab=self.strtab.len()>self.strtab_len || strtab_offset.is_none();
+        if !move_strtab {
+            let offset=strtab_offset.unwrap_or(0) as usize;
+            elf.data[offset..offset+self.strtab.len()].copy_from_slice(&self.strtab);
+        }
+        let mut dyns=self.dyns.clone();
+        dyns.push(Dyn{d_tag:abi::DT_NULL,d_val:0});
+        let capacity=dynamic_index.map(|i| elf.program_headers[i].p_filesz).unwrap_or(0)
+            .max(dynamic_section.map(|i| elf.section_headers[i].sh_size).unwrap_or(0))/dyn_size;
+        let move_dynamic=dyns.len() as u64>capacity || dynamic_index.is_none();
+
+        if new_interp.is_some() || move_strtab || move_dynamic {
+            //新段依次存放:程序头表、解释器、.dynstr、.dynamic
+            let mut segment=NewSegment::begin(&elf)?;
+            let interp_offset=new_interp.as_ref().map(|bytes| segment.place(bytes.len() as u64,1));
+            let strtab_new=move_strtab.then(|| segment.place(self.strtab.len() as u64,1));
+            let dynamic_new=move_dynamic.then(|| segment.place(dyns.len() as u64*dyn_size,word));
+            let addr=|offset:u64| segment.addr(offset);
+            if segment.end()>MAX_OUTPUT_SIZE {
+                return Err(EditError::TooLarge(segment.end()));
+            }
+            elf.data.resize(segment.end() as usize,0);
+
+            if let Some(offset)=strtab_new {
+                elf.data[offset as usize..offset as usize+self.strtab.len()].copy_from_slice(&self.strtab);
+                Self::move_section(&mut elf,strtab_offset,abi::SHT_STRTAB,offset,addr(offset),self.strtab.len() as u64);
+                for entry in dyns.iter_mut() {
+                    match entry.d_tag {
+                        abi::DT_STRTAB => entry.d_val=addr(offset),
+                        abi::DT_STRSZ => entry.d_val=self.strtab.len() as u64,
+                        _ => {}
+                    }
+                }
+            }
+            if let (Some(offset),Some(bytes),Some(i))=(interp_offset,&new_interp,interp_index) {
+                elf.data[offset as usize..offset as usize+bytes.len()].copy_from_slice(bytes);
+                let old=elf.program_headers[i].p_offset;
+                Self::move_section(&mut elf,Some(old),abi::SHT_PROGBITS,offset,addr(offset),bytes.len() as u64);
+                let ph=&mut elf.program_headers[i];
+                ph.p_offset=offset;
+                ph.p_vaddr=addr(offset);
+                ph.p_paddr=addr(offset);
+                ph.p_filesz=bytes.len() as u64;
+                ph.p_memsz=bytes.len() as u64;
+            }
+            if let Some(offset)=dynamic_new {
+                let size=dyns.len() as u64*dyn_size;
+                if let Some(i)=dynamic_section {
+                    let sh=&mut elf.section_headers[i];
+                    sh.sh_offset=offset;
+                    sh.sh_addr=addr(offset);
+                    sh.sh_size=size;
+                }
+                if let Some(i)=dynamic_index {
+                    let ph=&mut elf.program_headers[i];
+                    ph.p_offset=offset;
+                    ph.p_vaddr=addr(offset);
+                    ph.p_paddr=addr(offset);
+                    ph.p_filesz=size;
+                    ph.p_memsz=size;
+                }
+            }
+            //.dynamic需要可写,动态链接器会填写DT_DEBUG
+            let flags=if move_dynamic { abi::PF_R|abi::PF_W } else { abi::PF_R };
+            segment.finish(&mut elf,flags);
+        }
+
+        //新段插入后下标会变,重新查找
+        let dynamic_offset=elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_DYNAMIC).map(|ph| ph.p_offset)
+            .or_else(|| dynamic_section.map(|i| elf.section_headers[i].sh_offset));
+        if let Some(offset)=dynamic_offset {
+            //原地写入时用DT_NULL填满剩余空间
+            let slots=if move_dynamic { dyns.len() as u64 } else { capacity };
+            while (dyns.len() as u64)<slots {
+                dyns.push(Dyn{d_tag:abi::DT_NULL,d_val:0});
+            }
+            let bytes=Dyn::to_bytes(ident,&dyns);
+            let len=bytes.len().min(elf.data.len().saturating_sub(offset as usize));
+            elf.data[offset as usize..offset as usize+len].copy_from_slice(&bytes[..len]);
+        }
+        Ok(elf.to_bytes())
+    }
+
+    fn resize_interp(elf:&mut ElfFile,index:usize,size:u64){
+        let offset=elf.program_headers[index].p_offset;
+        elf.program_headers[index].p_filesz=size;
+        elf.program_headers[index].p_memsz=size;
+        if let Some(sh)=elf.section_headers.iter_mut().find(|sh| sh.sh_offset==offset && sh.string_name==".interp") {
+            sh.sh_size=size;
+        }
+    }
+
+    fn move_section(elf:&mut ElfFile,old_offset:Option<u64>,sh_type:u32,offset:u64,addr:u64,size:u64){
+        let Some(old_offset)=old_offset else {
+            return;
+        };
+        if let Some(sh)=elf.section_headers.iter_mut().find(|sh| sh.sh_offset==old_offset && sh.sh_type==sh_type && sh.sh_flags&abi::SHF_ALLOC!=0) {
+            sh.sh_offset=offset;
+            sh.sh_addr=addr;
+            sh.sh_size=size;
+        }
+    }
+}
+
+/// A PT_LOAD appended after the end of the file, above every mapped address, for
+/// contents that no longer fit where they were. The program header table moves to
+/// the start of the segment when it has no room for the extra entry.
+pub(crate) struct NewSegment {
+    start: u64,
+    vaddr: u64,
+    cursor: u64,
+    align: u64,
+    phdrs_offset: Option<u64>,
+}
+
+impl NewSegment {
+    pub(crate) fn begin(elf:&ElfFile)->Result<NewSegment,EditError>{
+        let word=elf.header.class.word_size() as u64;
+        let align=elf.program_headers.iter().filter(|ph| ph.p_type==abi::PT_LOAD)
+            .map(|ph| ph.p_align).max().unwrap_or(0x1000).max(0x1000);
+        let mut load_end=0u64;
+        for (i,ph) in elf.program_headers.iter().enumerate().filter(|(_,ph)| ph.p_type==abi::PT_LOAD) {
+            load_end=load_end.max(ph.p_vaddr.checked_add(ph.p_memsz).ok_or(EditError::SegmentOverflow(i))?);
+        }
+        let start=align_up(elf.data.len() as u64,16);
+        //文件偏移与地址模对齐同余,整个新段的地址都不能回绕
+        let vaddr=load_end.checked_next_multiple_of(align)
+            .and_then(|base| base.checked_add(start%align))
+            .filter(|vaddr| vaddr.checked_add(MAX_OUTPUT_SIZE).is_some())
+            .ok_or(EditError::TooLarge(load_end))?;
+        let mut segment=NewSegment{
+            start,
+            vaddr,
+            cursor:start,
+            align,
+            phdrs_offset:None,
+        };
+        let phnum=elf.program_headers.len() as u64+1;
+        if !Self::phdrs_fit(elf,phnum)? {
+            segment.phdrs_offset=Some(segment.place(phnum*elf.header.e_phentsize as u64,word));
+        }
+        Ok(segment)
+    }
+
+    /// Reserve `size` bytes and return their file offset
+    pub(crate) fn place(&mut self,size:u64,alignment:u64)->u64{
+        self.cursor=align_up(self.cursor,alignment);
+        let offset=self.cursor;
+        self.cursor+=size;
+        offset
+    }
+
+    pub(crate) fn addr(&self,offset:u64)->u64{
+        self.vaddr+offset-self.start
+    }
+
+    pub(crate) fn end(&self)->u64{
+        self.cursor
+    }
+
+    /// Grow the file to the end of the segment, insert its PT_LOAD after the last one
+    /// and point e_phoff and PT_PHDR at the enlarged table
+    pub(crate) fn finish(self,elf:&mut ElfFile,flags:u32){
+        if elf.data.len()<self.cursor as usize {
+            elf.data.resize(self.cursor as usize,0);
+        }
+        let segment=ProgramHeader{
+            p_type:abi::PT_LOAD,
+            p_offset:self.start,
+            p_vaddr:self.vaddr,
+            p_paddr:self.vaddr,
+            p_filesz:self.cursor-self.start,
+            p_memsz:self.cursor-self.start,
+            p_flags:flags,
+            p_align:self.align,
+        };
+        //PT_LOAD按地址升序排列,新段地址最高
+        let position=elf.program_headers.iter().rposition(|ph| ph.p_type==abi::PT_LOAD).map(|i| i+1).unwrap_or(elf.program_headers.len());
+        elf.program_headers.insert(position,segment);
+        let phnum=elf.program_headers.len() as u64;
+        let phentsize=elf.header.e_phentsize as u64;
+        if let Some(offset)=self.phdrs_offset {
+            elf.header.e_phoff=offset;
+        }
+        elf.header.e_phnum=phnum as u16;
+        let phdr_addr=match self.phdrs_offset {
+            Some(offset) => self.addr(offset),
+            None => elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_PHDR).map(|ph| ph.p_vaddr).unwrap_or(0),
+        };
+        let phoff=elf.header.e_phoff;
+        if let Some(ph)=elf.program_headers.iter_mut().find(|ph| ph.p_type==abi::PT_PHDR) {
+            ph.p_offset=phoff;
+            ph.p_vaddr=phdr_addr;
+            ph.p_paddr=phdr_addr;
+            ph.p_filesz=phnum*phentsize;
+            ph.p_memsz=phnum*phentsize;
+        }
+    }
+
+    /// Whether the program header table can grow to `phnum` entries where it is,
+    /// without running into a section or segment that follows it
+    fn phdrs_fit(elf:&ElfFile,phnum:u64)->Result<bool,EditError>{
+        let start=elf.header.e_phoff;
+        let phentsize=elf.header.e_phentsize as u64;
+        let (Some(current),Some(end))=(start.checked_add(elf.program_headers.len() as u64*phentsize),start.checked_add(phnum*phentsize)) else {
+            return Err(EditError::TooLarge(start));
+        };
+        let sections=elf.section_headers.iter()
+            .filter(|sh| sh.sh_type!=abi::SHT_NULL && sh.sh_type!=abi::SHT_NOBITS && sh.sh_size>0)
+            .map(|sh| sh.sh_offset);
+        let segments=elf.program_headers.iter()
+            .filter(|ph| ph.p_type!=abi::PT_LOAD && ph.p_type!=abi::PT_PHDR && ph.p_type!=abi::PT_NULL && ph.p_filesz>0)
+            .map(|ph| ph.p_offset);
+        let next=sections.chain(segments).filter(|&offset| offset>=current).min();
+        //表本身还要落在第一个PT_LOAD里才会被映射
+        let mapped=elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_LOAD && ph.p_offset<=start
+            && ph.p_offset.checked_add(ph.p_filesz).is_some_and(|load_end| end<=load_end));
+        let shoff=elf.header.e_shoff;
+        Ok(mapped && next.is_none_or(|next| end<=next) && (shoff<current || end<=shoff))
+    }
+}
+
+pub(crate) fn align_up(value:u64,align:u64)->u64{
+    if align<=1 {
+        return value;
+    }
+    value.div_ceil(align)*align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor()->ElfEditor{
+        ElfEditor::new(ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap()).unwrap()
+    }
+
+    fn reparse(editor:&ElfEditor)->ElfEditor{
+        ElfEditor::new(ElfFile::parse(editor.to_bytes().unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn set_rpath(){
+        let mut editor=editor();
+        assert_eq!(editor.rpath(),None);
+        editor.set_rpath("$ORIGIN/../lib:/opt/some/rather/long/library/path");
+        let edited=reparse(&editor);
+        assert_eq!(edited.rpath().as_deref(),Some("$ORIGIN/../lib:/opt/some/rather/long/library/path"));
+        assert_eq!(edited.needed(),editor.needed());
+    }
+
+    #[test]
+    fn add_needed(){
+        let mut editor=editor();
+        editor.add_needed("libm.so.6");
+        editor.add_needed("libextra.so.1");
+        let edited=reparse(&editor);
+        assert_eq!(edited.needed(),vec!["libz.so.1".to_string(),"libc.so.6".to_string(),"libm.so.6".to_string(),"libextra.so.1".to_string()]);
+        assert_eq!(edited.interpreter(),Some("/lib64/ld-linux-x86-64.so.2"));
+    }
+
+    #[test]
+    fn set_interpreter(){
+        let mut editor=editor();
+        let path="/opt/toolchain/sysroot/lib64/ld-linux-x86-64.so.2";
+        editor.set_interpreter(path).unwrap();
+        let edited=reparse(&editor);
+        assert_eq!(edited.interpreter(),Some(path));
+        //新的PT_INTERP要落在某个PT_LOAD的文件范围内
+        let interp=edited.elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_INTERP).unwrap();
+        assert!(edited.elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_LOAD
+            && ph.p_offset<=interp.p_offset && interp.p_offset+interp.p_filesz<=ph.p_offset+ph.p_filesz));
+        assert_eq!(edited.needed(),editor.needed());
+    }
+
+    #[test]
+    fn set_soname(){
+        let mut editor=editor();
+        editor.set_soname("libde.so.1");
+        let edited=reparse(&editor);
+        assert_eq!(edited.soname().as_deref(),Some("libde.so.1"));
+        assert_eq!(edited.needed(),editor.needed());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_keeps_permissions(){
+        use std::os::unix::fs::PermissionsExt;
+        let dir=std::env::temp_dir().join(format!("edit-permissions-{}",std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input=dir.join("input");
+        let output=dir.join("output");
+        fs::write(&input,include_bytes!("../test_elf/de")).unwrap();
+        fs::set_permissions(&input,fs::Permissions::from_mode(0o750)).unwrap();
+        let mut editor=ElfEditor::read_editor(input.to_str().unwrap()).unwrap();
+        editor.set_soname("libde.so.1");
+        editor.write(output.to_str().unwrap()).unwrap();
+        let mode=fs::metadata(&output).unwrap().permissions().mode();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(mode&0o777,0o750);
+    }
+
+    #[test]
+    fn crafted_sizes(){
+        let set=|data:&mut Vec<u8>,at:usize,value:u64| data[at..at+8].copy_from_slice(&value.to_le_bytes());
+        let move_interp=|data:Vec<u8>| {
+            let mut editor=ElfEditor::new(ElfFile::parse(data).unwrap()).unwrap();
+            editor.set_interpreter("/opt/toolchain/sysroot/lib64/ld-linux-x86-64.so.2").unwrap();
+            editor.to_bytes()
+        };
+        //LOAD5的p_vaddr
+        let mut data=include_bytes!("../test_elf/de").to_vec();
+        set(&mut data,0x40+5*56+16,u64::MAX-0x100);
+        assert_eq!(move_interp(data),Err(EditError::SegmentOverflow(5)));
+        //新段放不下
+        let mut data=include_bytes!("../test_elf/de").to_vec();
+        set(&mut data,0x40+5*56+16,u64::MAX-0x10000);
+        assert!(matches!(move_interp(data),Err(EditError::TooLarge(_))));
+    }
+}
@@ -0,0 +1,1027 @@
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::file::Class;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EhFrameError {
+    Parse,
+    /// Neither a `.eh_frame` section nor PT_GNU_EH_FRAME
+    Missing,
+    /// An entry or field runs past the end of the section; carries the entry offset
+    Truncated(u64),
+    /// Pointer encoding this reader does not handle
+    Encoding(u8),
+    /// Unknown augmentation of the CIE at this offset
+    Augmentation(u64),
+    /// The FDE at this offset does not point at a CIE
+    Cie(u64),
+    /// Unknown CFA instruction
+    Instruction(u8),
+    /// DW_CFA_restore_state without a matching DW_CFA_remember_state
+    State,
+    /// A location advance or a factored offset of the entry at this offset overflows
+    Malformed(u64),
+    /// `.eh_frame_hdr` version other than 1
+    Version(u8),
+}
+
+/// Common Information Entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cie {
+    /// Offset of the entry in `.eh_frame`
+    pub offset: u64,
+    pub version: u8,
+    pub augmentation: String,
+    pub code_alignment: u64,
+    pub data_alignment: i64,
+    /// DWARF register number holding the return address
+    pub return_register: u16,
+    /// Encoding of the FDE addresses ('R')
+    pub fde_encoding: u8,
+    /// Encoding of the FDE LSDA pointers ('L')
+    pub lsda_encoding: Option<u8>,
+    /// Personality routine ('P')
+    pub personality: Option<Pointer>,
+    /// Frames of this CIE are signal trampolines ('S'), so their return address is
+    /// not a call site
+    pub signal_frame: bool,
+    pub instructions: Vec<u8>,
+    /// Offset of `instructions` in the section
+    pub instructions_offset: u64,
+}
+
+/// Frame Description Entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fde {
+    pub offset: u64,
+    /// Offset of its CIE in `.eh_frame`
+    pub cie: u64,
+    pub pc_begin: u64,
+    pub pc_range: u64,
+    /// Language-specific data area
+    pub lsda: Option<Pointer>,
+    pub instructions: Vec<u8>,
+    pub instructions_offset: u64,
+}
+
+/// A decoded pointer. With DW_EH_PE_indirect the encoded value is the address of
+/// a word holding the pointer, typically a GOT entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pointer {
+    Direct(u64),
+    Indirect(u64),
+}
+
+/// Base addresses of DW_EH_PE_textrel and DW_EH_PE_datarel pointers. In
+/// `.eh_frame` the text base is `.text` and the data base is the GOT; in
+/// `.eh_frame_hdr` the data base is the start of the section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PointerBases {
+    pub text: Option<u64>,
+    pub data: Option<u64>,
+}
+
+/// A disagreement between `.eh_frame_hdr` and `.eh_frame`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HdrMismatch {
+    /// eh_frame_ptr is not the address of `.eh_frame`
+    EhFramePtr { hdr: u64, eh_frame: u64 },
+    /// The table has a different number of entries than there are FDEs
+    Count { table: usize, fdes: usize },
+    /// The entry at this index is not above the previous one
+    Unsorted(usize),
+    /// The entry at this index points at no FDE
+    NoFde { index: usize, fde: u64 },
+    /// The entry's location is not its FDE's initial location
+    Location { index: usize, table: u64, fde: u64 },
+    /// The FDE at this offset has no entry
+    Missing(u64),
+}
+
+/// How to compute the CFA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfaRule {
+    RegisterOffset(u16, i64),
+    /// DWARF expression whose result is the CFA
+    Expression(Vec<u8>),
+}
+
+/// Where the caller's value of a register is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterRule {
+    Undefined,
+    SameValue,
+    /// Saved at CFA+N
+    Offset(i64),
+    /// The value is CFA+N
+    ValOffset(i64),
+    /// Held in another register
+    Register(u16),
+    /// Saved at the address the expression computes
+    Expression(Vec<u8>),
+    /// The value is what the expression computes
+    ValExpression(Vec<u8>),
+}
+
+/// Unwind rules for the addresses `start..end`. Registers not listed have no rule
+/// (callee-saved registers keep their value).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwindRow {
+    pub start: u64,
+    pub end: u64,
+    pub cfa: CfaRule,
+    pub registers: Vec<(u16, RegisterRule)>,
+}
+
+/// The CIEs and FDEs of `.eh_frame`, in section order
+#[derive(Debug, Clone, PartialEq)]
+pub struct EhFrame {
+    ident: (AnyEndian, Class),
+    /// Virtual address of the section, which pc-relative pointers are relative to
+    pub address: u64,
+    pub bases: PointerBases,
+    pub cies: Vec<Cie>,
+    pub fdes: Vec<Fde>,
+}
+
+/// `.eh_frame_hdr`: the location of `.eh_frame` and a table of (initial location,
+/// FDE address) sorted by location for binary search
+#[derive(Debug, Clone, PartialEq)]
+pub struct EhFrameHdr {
+    pub address: u64,
+    pub version: u8,
+    pub eh_frame_ptr: u64,
+    pub table: Vec<(u64, u64)>,
+}
+
+//按游标顺序读取;address是data[0]的虚拟地址
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    ident: (AnyEndian, Class),
+    address: u64,
+    bases: PointerBases,
+    /// Base of DW_EH_PE_funcrel pointers: the initial location of the FDE
+    function: Option<u64>,
+    /// Entry the reader is in, for error reports
+    entry: u64,
+}
+
+impl Reader<'_> {
+    fn take(&mut self,count:usize)->Result<&[u8],EhFrameError>{
+        let bytes=self.data.get(self.offset..self.offset.saturating_add(count)).ok_or(EhFrameError::Truncated(self.entry))?;
+        self.offset+=count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self)->Result<u8,EhFrameError>{
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self)->Result<u16,EhFrameError>{
+        let endian=self.ident.0;
+        Ok(endian.parse_u16_at(0,self.take(2)?))
+    }
+
+    fn u32(&mut self)->Result<u32,EhFrameError>{
+        let endian=self.ident.0;
+        Ok(endian.parse_u32_at(0,self.take(4)?))
+    }
+
+    fn u64(&mut self)->Result<u64,EhFrameError>{
+        let endian=self.ident.0;
+        Ok(endian.parse_u64_at(0,self.take(8)?))
+    }
+
+    fn uleb128(&mut self)->Result<u64,EhFrameError>{
+        let (mut value,mut shift)=(0u64,0u32);
+        loop {
+            let byte=self.u8()?;
+            if shift<64 {
+                value|=((byte&0x7f) as u64)<<shift;
+            }
+            shift+=7;
+            if byte&0x80==0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb128(&mut self)->Result<i64,EhFrameError>{
+        let (mut value,mut shift)=(0i64,0u32);
+        loop {
+            let byte=self.u8()?;
+            if shift<64 {
+                value|=((byte&0x7f) as i64)<<shift;
+            }
+            shift+=7;
+            if byte&0x80==0 {
+                //符号扩展
+                if shift<64 && byte&0x40!=0 {
+                    value|=-1i64<<shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    fn block(&mut self)->Result<Vec<u8>,EhFrameError>{
+        let length=self.uleb128()? as usize;
+        Ok(self.take(length)?.to_vec())
+    }
+
+    //编码的低4位决定格式,不加基址
+    fn value(&mut self,encoding:u8)->Result<u64,EhFrameError>{
+        Ok(match encoding&0x0f {
+            abi::DW_EH_PE_ABSPTR => match self.ident.1 {
+                Class::ELF32 => self.u32()? as u64,
+                Class::ELF64 => self.u64()?,
+            },
+            abi::DW_EH_PE_ULEB128 => self.uleb128()?,
+            abi::DW_EH_PE_SLEB128 => self.sleb128()? as u64,
+            abi::DW_EH_PE_UDATA2 => self.u16()? as u64,
+            abi::DW_EH_PE_SDATA2 => self.u16()? as i16 as i64 as u64,
+            abi::DW_EH_PE_UDATA4 => self.u32()? as u64,
+            abi::DW_EH_PE_SDATA4 => self.u32()? as i32 as i64 as u64,
+            abi::DW_EH_PE_UDATA8|abi::DW_EH_PE_SDATA8 => self.u64()?,
+            _ => return Err(EhFrameError::Encoding(encoding)),
+        })
+    }
+
+    //高位决定基址,最高位表示间接;DW_EH_PE_omit表示没有值
+    fn pointer(&mut self,encoding:u8)->Result<Option<Pointer>,EhFrameError>{
+        if encoding==abi::DW_EH_PE_OMIT {
+            return Ok(None);
+        }
+        //aligned:先按字长对齐,值是绝对地址
+        if encoding&0x70==abi::DW_EH_PE_ALIGNED {
+            let word=self.ident.1.word_size() as u64;
+            let field=self.address.wrapping_add(self.offset as u64);
+            self.offset+=(field.next_multiple_of(word)-field) as usize;
+        }
+        let field=self.address.wrapping_add(self.offset as u64);
+        let value=self.value(encoding)?;
+        let missing=EhFrameError::Encoding(encoding);
+        let base=match encoding&0x70 {
+            0|abi::DW_EH_PE_ALIGNED => 0,
+            abi::DW_EH_PE_PCREL => field,
+            abi::DW_EH_PE_TEXTREL => self.bases.text.ok_or(missing)?,
+            abi::DW_EH_PE_DATAREL => self.bases.data.ok_or(missing)?,
+            abi::DW_EH_PE_FUNCREL => self.function.ok_or(missing)?,
+            _ => return Err(missing),
+        };
+        let pointer=match self.ident.1 {
+            Class::ELF32 => base.wrapping_add(value)&0xffff_ffff,
+            Class::ELF64 => base.wrapping_add(value),
+        };
+        Ok(Some(match encoding&abi::DW_EH_PE_INDIRECT {
+            0 => Pointer::Direct(pointer),
+            _ => Pointer::Indirect(pointer),
+        }))
+    }
+
+    //代码地址必须是直接指针
+    fn address(&mut self,encoding:u8)->Result<Option<u64>,EhFrameError>{
+        match self.pointer(encoding)? {
+            Some(Pointer::Direct(address)) => Ok(Some(address)),
+            Some(Pointer::Indirect(_)) => Err(EhFrameError::Encoding(encoding)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl PointerBases {
+    /// `.text` and the GOT of an ELF file; the GOT is `_GLOBAL_OFFSET_TABLE_`, the
+    /// start of `.got.plt`, or DT_PLTGOT without section headers
+    pub fn for_elf(elf:&ElfFile)->PointerBases{
+        let data=elf.find_section(".got.plt").or_else(|| elf.find_section(".got"))
+            .map(|section| section.sh_addr)
+            .or_else(|| Dyn::find(&elf.dynamic(),abi::DT_PLTGOT));
+        PointerBases{text:elf.find_section(".text").map(|section| section.sh_addr),data}
+    }
+}
+
+impl EhFrame {
+    pub fn read_eh_frame(file_path:&str)->Result<EhFrame,EhFrameError>{
+        let elf=ElfFile::read_elf(file_path).ok_or(EhFrameError::Parse)?;
+        Self::parse_eh_frame(&elf)
+    }
+
+    /// `.eh_frame` of an ELF file, found by section name or else through
+    /// PT_GNU_EH_FRAME, in which case it extends to the end of its segment
+    pub fn parse_eh_frame(elf:&ElfFile)->Result<EhFrame,EhFrameError>{
+        if let Some(section)=elf.find_section(".eh_frame") {
+            let data=elf.section_data(section).ok_or(EhFrameError::Truncated(0))?;
+            return Self::parse(elf.ident(),data,section.sh_addr,PointerBases::for_elf(elf));
+        }
+        let hdr=EhFrameHdr::parse_eh_frame_hdr(elf)?;
+        let ph=elf.program_headers.iter()
+            .filter(|ph| ph.p_type==abi::PT_LOAD)
+            .find(|ph| hdr.eh_frame_ptr>=ph.p_vaddr && hdr.eh_frame_ptr-ph.p_vaddr<ph.p_filesz)
+            .ok_or(EhFrameError::Missing)?;
+        let data=elf.vaddr_data(hdr.eh_frame_ptr,ph.p_filesz-(hdr.eh_frame_ptr-ph.p_vaddr)).ok_or(EhFrameError::Missing)?;
+        Self::parse(elf.ident(),data,hdr.eh_frame_ptr,PointerBases::for_elf(elf))
+    }
+
+    /// Parse section contents loaded at `address`. A zero length word ends the section.
+    pub fn parse(ident:(AnyEndian, Class),data:&[u8],address:u64,bases:PointerBases)->Result<EhFrame,EhFrameError>{
+        let mut frame=EhFrame{ident,address,bases,cies:Vec::new(),fdes:Vec::new()};
+        //CIE不一定在引用它的FDE之前,先分出各个条目
+        let mut entries=Vec::new();
+        let mut offset=0;
+        while offset+4<=data.len() {
+            let mut reader=Reader{data,offset,ident,address,bases,function:None,entry:offset as u64};
+            let mut length=reader.u32()? as u64;
+            if length==0 {
+                break;
+            }
+            if length==0xffff_ffff {
+                length=reader.u64()?;
+            }
+            let body=reader.offset;
+            let end=body.checked_add(length as usize).filter(|&end| end<=data.len()).ok_or(EhFrameError::Truncated(offset as u64))?;
+            let id=reader.u32()?;
+            entries.push((offset,body,id,end));
+            offset=end;
+        }
+        for &(offset,body,id,end) in &entries {
+            if id==0 {
+                frame.cies.push(Self::parse_cie(ident,&data[..end],address,bases,offset,body+4)?);
+            }
+        }
+        for &(offset,body,id,end) in &entries {
+            if id==0 {
+                continue;
+            }
+            //CIE指针是从这个字段往回的距离
+            let cie_offset=(body as u64).checked_sub(id as u64).ok_or(EhFrameError::Cie(offset as u64))?;
+            let cie=frame.cies.iter().find(|cie| cie.offset==cie_offset).ok_or(EhFrameError::Cie(offset as u64))?;
+            let mut reader=Reader{data:&data[..end],offset:body+4,ident,address,bases,function:None,entry:offset as u64};
+            let pc_begin=reader.address(cie.fde_encoding)?.unwrap_or(0);
+            let pc_range=reader.value(cie.fde_encoding)?;
+            reader.function=Some(pc_begin);
+            let mut lsda=None;
+            if cie.augmentation.starts_with('z') {
+                let length=reader.uleb128()?;
+                let augmentation_end=usize::try_from(length).ok()
+                    .and_then(|length| reader.offset.checked_add(length))
+                    .filter(|&end| end<=reader.data.len())
+                    .ok_or(EhFrameError::Truncated(offset as u64))?;
+                if let Some(encoding)=cie.lsda_encoding {
+                    lsda=reader.pointer(encoding)?;
+                }
+                reader.offset=augmentation_end;
+            }
+            let instructions=reader.data.get(reader.offset..).ok_or(EhFrameError::Truncated(offset as u64))?.to_vec();
+            let instructions_offset=reader.offset as u64;
+            frame.fdes.push(Fde{offset:offset as u64,cie:cie_offset,pc_begin,pc_range,lsda,instructions,instructions_offset});
+        }
+        Ok(frame)
+    }
+
+    fn parse_cie(ident:(AnyEndian, Class),data:&[u8],address:u64,bases:PointerBases,offset:usize,start:usize)->Result<Cie,EhFrameError>{
+        let mut reader=Reader{data,offset:start,ident,address,bases,function:None,entry:offset as u64};
+        let version=reader.u8()?;
+        let rest=data.get(reader.offset..).ok_or(EhFrameError::Truncated(offset as u64))?;
+        let length=rest.iter().position(|&b| b==0).ok_or(EhFrameError::Truncated(offset as u64))?;
+        let augmentation=String::from_utf8_lossy(&rest[..length]).to_string();
+        reader.offset+=length+1;
+        //GCC 2.x的"eh"增强后面跟着一个字长的eh_ptr
+        if augmentation.contains("eh") {
+            reader.value(abi::DW_EH_PE_ABSPTR)?;
+        }
+        let code_alignment=reader.uleb128()?;
+        let data_alignment=reader.sleb128()?;
+        //版本1里返回地址寄存器是一个字节
+        let return_register=match version {
+            1 => reader.u8()? as u16,
+            _ => reader.uleb128()? as u16,
+        };
+        let mut cie=Cie{
+            offset:offset as u64,
+            version,
+            augmentation:augmentation.clone(),
+            code_alignment,
+            data_alignment,
+            return_register,
+            fde_encoding:abi::DW_EH_PE_ABSPTR,
+            lsda_encoding:None,
+            personality:None,
+            signal_frame:false,
+            instructions:Vec::new(),
+            instructions_offset:0,
+        };
+        if let Some(letters)=augmentation.strip_prefix('z') {
+            let length=reader.uleb128()?;
+            let end=usize::try_from(length).ok()
+                .and_then(|length| reader.offset.checked_add(length))
+                .filter(|&end| end<=data.len())
+                .ok_or(EhFrameError::Truncated(offset as u64))?;
+            for letter in letters.chars() {
+                match letter {
+                    'R' => cie.fde_encoding=reader.u8()?,
+                    'L' => cie.lsda_encoding=Some(reader.u8()?),
+                    'P' => {
+                        let encoding=reader.u8()?;
+                        cie.personality=reader.pointer(encoding)?;
+                    }
+                    'S' => cie.signal_frame=true,
+                    //AArch64:返回地址用B密钥签名;MTE带标签的栈帧。都没有数据
+                    'B'|'G' => {}
+                    _ => return Err(EhFrameError::Augmentation(offset as u64)),
+                }
+            }
+            reader.offset=end;
+        } else if !augmentation.is_empty() && augmentation!="eh" {
+            return Err(EhFrameError::Augmentation(offset as u64));
+        }
+        cie.instructions=data.get(reader.offset..).ok_or(EhFrameError::Truncated(offset as u64))?.to_vec();
+        cie.instructions_offset=reader.offset as u64;
+        Ok(cie)
+    }
+
+    pub fn cie(&self,fde:&Fde)->Option<&Cie>{
+        self.cies.iter().find(|cie| cie.offset==fde.cie)
+    }
+
+    /// The FDE at this offset in the section
+    pub fn fde_at(&self,offset:u64)->Option<&Fde>{
+        self.fdes.binary_search_by_key(&offset,|fde| fde.offset).ok().map(|i| &self.fdes[i])
+    }
+
+    /// The FDE covering `pc`, by linear search
+    pub fn find_fde(&self,pc:u64)->Option<&Fde>{
+        self.fdes.iter().find(|fde| pc>=fde.pc_begin && pc-fde.pc_begin<fde.pc_range)
+    }
+
+    /// Run the CIE's initial instructions and then the FDE's, giving one row per
+    /// address range
+    pub fn rows(&self,fde:&Fde)->Result<Vec<UnwindRow>,EhFrameError>{
+        let cie=self.cie(fde).ok_or(EhFrameError::Cie(fde.offset))?;
+        let mut interpreter=Interpreter{
+            cie,
+            address:self.address,
+            bases:self.bases,
+            ident:self.ident,
+            row:UnwindRow{start:fde.pc_begin,end:fde.pc_begin.wrapping_add(fde.pc_range),cfa:CfaRule::RegisterOffset(0,0),registers:Vec::new()},
+            initial:Vec::new(),
+            stack:Vec::new(),
+            rows:Vec::new(),
+        };
+        interpreter.run(&cie.instructions,cie.instructions_offset,None)?;
+        interpreter.initial=interpreter.row.registers.clone();
+        interpreter.run(&fde.instructions,fde.instructions_offset,Some(fde))?;
+        let mut row=interpreter.row;
+        row.end=fde.pc_begin.wrapping_add(fde.pc_range);
+        if row.start<row.end {
+            interpreter.rows.push(row);
+        }
+        Ok(interpreter.rows)
+    }
+
+    /// The row in effect at `pc`
+    pub fn row_at(&self,fde:&Fde,pc:u64)->Result<Option<UnwindRow>,EhFrameError>{
+        Ok(self.rows(fde)?.into_iter().find(|row| pc>=row.start && pc<row.end))
+    }
+}
+
+impl UnwindRow {
+    pub fn rule(&self,register:u16)->Option<&RegisterRule>{
+        self.registers.iter().find(|(r,_)| *r==register).map(|(_,rule)| rule)
+    }
+
+    fn set(&mut self,register:u16,rule:RegisterRule){
+        match self.registers.iter_mut().find(|(r,_)| *r==register) {
+            Some(entry) => entry.1=rule,
+            None => self.registers.push((register,rule)),
+        }
+    }
+}
+
+struct Interpreter<'a> {
+    cie: &'a Cie,
+    address: u64,
+    bases: PointerBases,
+    ident: (AnyEndian, Class),
+    row: UnwindRow,
+    /// Rules after the CIE's instructions, for DW_CFA_restore
+    initial: Vec<(u16, RegisterRule)>,
+    /// DW_CFA_remember_state; the CFA is saved too, as libgcc does
+    stack: Vec<(CfaRule, Vec<(u16, RegisterRule)>)>,
+    rows: Vec<UnwindRow>,
+}
+
+impl Interpreter<'_> {
+    fn run(&mut self,instructions:&[u8],instructions_offset:u64,fde:Option<&Fde>)->Result<(),EhFrameError>{
+        let entry=fde.map(|fde| fde.offset).unwrap_or(self.cie.offset);
+        let mut reader=Reader{
+            data:instructions,
+            offset:0,
+            ident:self.ident,
+            address:self.address.wrapping_add(instructions_offset),
+            bases:self.bases,
+            function:fde.map(|fde| fde.pc_begin),
+            entry,
+        };
+        let data_alignment=self.cie.data_alignment;
+        //乘以数据对齐因子,溢出说明条目是坏的
+        let signed=|value:i64| value.checked_mul(data_alignment).ok_or(EhFrameError::Malformed(entry));
+        let unsigned=|value:u64| i64::try_from(value).ok().and_then(|value| value.checked_mul(data_alignment)).ok_or(EhFrameError::Malformed(entry));
+        while reader.offset<instructions.len() {
+            let opcode=reader.u8()?;
+            let low=(opcode&0x3f) as u16;
+            match opcode>>6 {
+                1 => {
+                    self.advance_by(low as u64,entry)?;
+                    continue;
+                }
+                2 => {
+                    let offset=unsigned(reader.uleb128()?)?;
+                    self.row.set(low,RegisterRule::Offset(offset));
+                    continue;
+                }
+                3 => {
+                    self.restore(low);
+                    continue;
+                }
+                _ => {}
+            }
+            match opcode {
+                //DW_CFA_nop
+                0x00 => {}
+                //DW_CFA_set_loc
+                0x01 => {
+                    let location=reader.address(self.cie.fde_encoding)?.unwrap_or(self.row.start);
+                    self.advance(location);
+                }
+                //DW_CFA_advance_loc1/2/4
+                0x02 => {
+                    let delta=reader.u8()? as u64;
+                    self.advance_by(delta,entry)?;
+                }
+                0x03 => {
+                    let delta=reader.u16()? as u64;
+                    self.advance_by(delta,entry)?;
+                }
+                0x04 => {
+                    let delta=reader.u32()? as u64;
+                    self.advance_by(delta,entry)?;
+                }
+                //DW_CFA_offset_extended
+                0x05 => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=unsigned(reader.uleb128()?)?;
+                    self.row.set(register,RegisterRule::Offset(offset));
+                }
+                //DW_CFA_restore_extended
+                0x06 => {
+                    let register=reader.uleb128()? as u16;
+                    self.restore(register);
+                }
+                //DW_CFA_undefined
+                0x07 => {
+                    let register=reader.uleb128()? as u16;
+                    self.row.set(register,RegisterRule::Undefined);
+                }
+                //DW_CFA_same_value
+                0x08 => {
+                    let register=reader.uleb128()? as u16;
+                    self.row.set(register,RegisterRule::SameValue);
+                }
+                //DW_CFA_register
+                0x09 => {
+                    let register=reader.uleb128()? as u16;
+                    let other=reader.uleb128()? as u16;
+                    self.row.set(register,RegisterRule::Register(other));
+                }
+                //DW_CFA_remember_state
+                0x0a => self.stack.push((self.row.cfa.clone(),self.row.registers.clone())),
+                //DW_CFA_restore_state
+                0x0b => {
+                    let (cfa,registers)=self.stack.pop().ok_or(EhFrameError::State)?;
+                    self.row.cfa=cfa;
+                    self.row.registers=registers;
+                }
+                //DW_CFA_def_cfa
+                0x0c => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=reader.uleb128()? as i64;
+                    self.row.cfa=CfaRule::RegisterOffset(register,offset);
+                }
+                //DW_CFA_def_cfa_register
+                0x0d => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=match self.row.cfa {
+                        CfaRule::RegisterOffset(_,offset) => offset,
+                        CfaRule::Expression(_) => 0,
+                    };
+                    self.row.cfa=CfaRule::RegisterOffset(register,offset);
+                }
+                //DW_CFA_def_cfa_offset
+                0x0e => {
+                    let offset=reader.uleb128()? as i64;
+                    self.set_cfa_offset(offset);
+                }
+                //DW_CFA_def_cfa_expression
+                0x0f => self.row.cfa=CfaRule::Expression(reader.block()?),
+                //DW_CFA_expression
+                0x10 => {
+                    let register=reader.uleb128()? as u16;
+                    self.row.set(register,RegisterRule::Expression(reader.block()?));
+                }
+                //DW_CFA_offset_extended_sf
+                0x11 => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=signed(reader.sleb128()?)?;
+                    self.row.set(register,RegisterRule::Offset(offset));
+                }
+                //DW_CFA_def_cfa_sf
+                0x12 => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=signed(reader.sleb128()?)?;
+                    self.row.cfa=CfaRule::RegisterOffset(register,offset);
+                }
+                //DW_CFA_def_cfa_offset_sf
+                0x13 => {
+                    let offset=signed(reader.sleb128()?)?;
+                    self.set_cfa_offset(offset);
+                }
+                //DW_CFA_val_offset
+                0x14 => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=unsigned(reader.uleb128()?)?;
+                    self.row.set(register,RegisterRule::ValOffset(offset));
+                }
+                //DW_CFA_val_offset_sf
+                0x15 => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=signed(reader.sleb128()?)?;
+                    self.row.set(register,RegisterRule::ValOffset(offset));
+                }
+                //DW_CFA_val_expression
+                0x16 => {
+                    let register=reader.uleb128()? as u16;
+                    self.row.set(register,RegisterRule::ValExpression(reader.block()?));
+                }
+                //DW_CFA_GNU_window_save,AArch64上是DW_CFA_AARCH64_negate_ra_state,对展开没有影响
+                0x2d => {}
+                //DW_CFA_GNU_args_size
+                0x2e => {
+                    reader.uleb128()?;
+                }
+                //DW_CFA_GNU_negative_offset_extended
+                0x2f => {
+                    let register=reader.uleb128()? as u16;
+                    let offset=unsigned(reader.uleb128()?)?.checked_neg().ok_or(EhFrameError::Malformed(entry))?;
+                    self.row.set(register,RegisterRule::Offset(offset));
+                }
+                _ => return Err(EhFrameError::Instruction(opcode)),
+            }
+        }
+        Ok(())
+    }
+
+    //DW_CFA_advance_loc*:增量乘以代码对齐因子
+    fn advance_by(&mut self,delta:u64,entry:u64)->Result<(),EhFrameError>{
+        let location=delta.checked_mul(self.cie.code_alignment)
+            .and_then(|delta| self.row.start.checked_add(delta))
+            .ok_or(EhFrameError::Malformed(entry))?;
+        self.advance(location);
+        Ok(())
+    }
+
+    fn advance(&mut self,location:u64){
+        if location>self.row.start {
+            let mut row=self.row.clone();
+            row.end=location;
+            self.rows.push(row);
+            self.row.start=location;
+        }
+    }
+
+    fn restore(&mut self,register:u16){
+        match self.initial.iter().find(|(r,_)| *r==register) {
+            Some((_,rule)) => self.row.set(register,rule.clone()),
+            None => self.row.registers.retain(|(r,_)| *r!=register),
+        }
+    }
+
+    fn set_cfa_offset(&mut self,offset:i64){
+        if let CfaRule::RegisterOffset(register,_)=self.row.cfa {
+            self.row.cfa=CfaRule::RegisterOffset(register,offset);
+        }
+    }
+}
+
+impl EhFrameHdr {
+    pub fn read_eh_frame_hdr(file_path:&str)->Result<EhFrameHdr,EhFrameError>{
+        let elf=ElfFile::read_elf(file_path).ok_or(EhFrameError::Parse)?;
+        Self::parse_eh_frame_hdr(&elf)
+    }
+
+    /// `.eh_frame_hdr` of an ELF file, from PT_GNU_EH_FRAME or else the section
+    pub fn parse_eh_frame_hdr(elf:&ElfFile)->Result<EhFrameHdr,EhFrameError>{
+        if let Some(ph)=elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_GNU_EH_FRAME) {
+            let data=elf.segment_data(ph).ok_or(EhFrameError::Truncated(0))?;
+            return Self::parse(elf.ident(),data,ph.p_vaddr);
+        }
+        let section=elf.find_section(".eh_frame_hdr").ok_or(EhFrameError::Missing)?;
+        let data=elf.section_data(section).ok_or(EhFrameError::Truncated(0))?;
+        Self::parse(elf.ident(),data,section.sh_addr)
+    }
+
+    //版本、三个编码字节、eh_frame_ptr、fde_count,然后是表
+    pub fn parse(ident:(AnyEndian, Class),data:&[u8],address:u64)->Result<EhFrameHdr,EhFrameError>{
+        let bases=PointerBases{text:None,data:Some(address)};
+        let mut reader=Reader{data,offset:0,ident,address,bases,function:None,entry:0};
+        let version=reader.u8()?;
+        if version!=1 {
+            return Err(EhFrameError::Version(version));
+        }
+        let (pointer_encoding,count_encoding,table_encoding)=(reader.u8()?,reader.u8()?,reader.u8()?);
+        let eh_frame_ptr=reader.address(pointer_encoding)?.ok_or(EhFrameError::Encoding(pointer_encoding))?;
+        let mut table=Vec::new();
+        if let Some(count)=reader.address(count_encoding)? {
+            if table_encoding!=abi::DW_EH_PE_OMIT {
+                for _ in 0..count {
+                    let location=reader.address(table_encoding)?.unwrap_or(0);
+                    let fde=reader.address(table_encoding)?.unwrap_or(0);
+                    table.push((location,fde));
+                }
+            }
+        }
+        Ok(EhFrameHdr{address,version,eh_frame_ptr,table})
+    }
+
+    /// Address of the FDE whose initial location is the last one at or below `pc`;
+    /// the FDE's range still has to be checked
+    pub fn lookup(&self,pc:u64)->Option<u64>{
+        let index=self.table.partition_point(|&(location,_)| location<=pc);
+        index.checked_sub(1).map(|i| self.table[i].1)
+    }
+
+    /// Check the search table against the FDEs of `eh_frame`: one entry per FDE,
+    /// sorted by initial location, each pointing at the FDE it names
+    pub fn verify(&self,eh_frame:&EhFrame)->Vec<HdrMismatch>{
+        let mut mismatches=Vec::new();
+        if self.eh_frame_ptr!=eh_frame.address {
+            mismatches.push(HdrMismatch::EhFramePtr{hdr:self.eh_frame_ptr,eh_frame:eh_frame.address});
+        }
+        if self.table.len()!=eh_frame.fdes.len() {
+            mismatches.push(HdrMismatch::Count{table:self.table.len(),fdes:eh_frame.fdes.len()});
+        }
+        let mut listed=vec![false;eh_frame.fdes.len()];
+        for (index,&(location,fde)) in self.table.iter().enumerate() {
+            if index>0 && location<=self.table[index-1].0 {
+                mismatches.push(HdrMismatch::Unsorted(index));
+            }
+            let found=fde.checked_sub(eh_frame.address)
+                .and_then(|offset| eh_frame.fdes.binary_search_by_key(&offset,|fde| fde.offset).ok());
+            match found {
+                Some(i) => {
+                    listed[i]=true;
+                    if eh_frame.fdes[i].pc_begin!=location {
+                        mismatches.push(HdrMismatch::Location{index,table:location,fde:eh_frame.fdes[i].pc_begin});
+                    }
+                }
+                None => mismatches.push(HdrMismatch::NoFde{index,fde}),
+            }
+        }
+        for (fde,listed) in eh_frame.fdes.iter().zip(listed) {
+            if !listed {
+                mismatches.push(HdrMismatch::Missing(fde.offset));
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: u64 = 0x2000;
+    const BASES: PointerBases = PointerBases{text:None,data:Some(0x1000)};
+
+    fn ident()->(AnyEndian, Class){
+        (AnyEndian::new(abi::ELFDATA2LSB),Class::ELF64)
+    }
+
+    fn uleb(mut value:u64)->Vec<u8>{
+        let mut v=Vec::new();
+        loop {
+            let byte=(value&0x7f) as u8;
+            value>>=7;
+            if value==0 {
+                v.push(byte);
+                return v;
+            }
+            v.push(byte|0x80);
+        }
+    }
+
+    fn sleb(mut value:i64)->Vec<u8>{
+        let mut v=Vec::new();
+        loop {
+            let byte=(value&0x7f) as u8;
+            value>>=7;
+            if (value==0 && byte&0x40==0) || (value==-1 && byte&0x40!=0) {
+                v.push(byte);
+                return v;
+            }
+            v.push(byte|0x80);
+        }
+    }
+
+    //pc相对的sdata4:目标减去字段自身的地址
+    fn pcrel(target:u64,field:u64)->[u8;4]{
+        (target.wrapping_sub(field) as i32).to_le_bytes()
+    }
+
+    /// Append an entry and return its offset. `cie` is the CIE offset for an FDE;
+    /// `body` gets the address of the first byte after the id and returns the rest
+    /// of the entry, which is padded with DW_CFA_nop.
+    fn entry(data:&mut Vec<u8>,cie:Option<usize>,body:impl FnOnce(u64)->Vec<u8>)->usize{
+        let offset=data.len();
+        let mut body=body(ADDRESS+offset as u64+8);
+        body.resize(body.len().next_multiple_of(4),0);
+        data.extend_from_slice(&(body.len() as u32+4).to_le_bytes());
+        let id=cie.map(|cie| (offset+4-cie) as u32).unwrap_or(0);
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend(body);
+        offset
+    }
+
+    //CIE "zPLR":间接的pc相对personality;CIE "zRS":datarel+udata8的信号帧
+    fn fixture()->(Vec<u8>,usize,usize){
+        let mut data=Vec::new();
+        let cie1=entry(&mut data,None,|at| {
+            let mut v=vec![1];
+            v.extend_from_slice(b"zPLR\0");
+            v.extend(uleb(1));
+            v.extend(sleb(-8));
+            v.push(16);
+            v.extend(uleb(7));
+            v.push(abi::DW_EH_PE_INDIRECT|abi::DW_EH_PE_PCREL|abi::DW_EH_PE_SDATA4);
+            v.extend_from_slice(&pcrel(0x3fe0,at+v.len() as u64));
+            v.push(abi::DW_EH_PE_PCREL|abi::DW_EH_PE_SDATA4);
+            v.push(abi::DW_EH_PE_PCREL|abi::DW_EH_PE_SDATA4);
+            //DW_CFA_def_cfa r7+8; DW_CFA_offset r16, cfa-8
+            v.extend_from_slice(&[0x0c,7,8,0x90,1]);
+            v
+        });
+        let cie2=entry(&mut data,None,|_| {
+            let mut v=vec![3];
+            v.extend_from_slice(b"zRS\0");
+            v.extend(uleb(1));
+            v.extend(sleb(-8));
+            v.extend(uleb(16));
+            v.extend(uleb(1));
+            v.push(abi::DW_EH_PE_DATAREL|abi::DW_EH_PE_UDATA8);
+            v.extend_from_slice(&[0x0c,7,8]);
+            v
+        });
+        let fde1=entry(&mut data,Some(cie1),|at| {
+            let mut v=pcrel(0x1100,at).to_vec();
+            v.extend_from_slice(&0x20u32.to_le_bytes());
+            v.extend(uleb(4));
+            v.extend_from_slice(&pcrel(0x2400,at+v.len() as u64));
+            v.extend_from_slice(&[
+                0x41,           //advance_loc 1
+                0x0e,16,        //def_cfa_offset 16
+                0x86,2,         //offset r6, cfa-16
+                0x44,           //advance_loc 4
+                0x0a,           //remember_state
+                0x0e,8,         //def_cfa_offset 8
+                0xc6,           //restore r6
+                0x42,           //advance_loc 2
+                0x0b,           //restore_state
+            ]);
+            v
+        });
+        let fde2=entry(&mut data,Some(cie2),|_| {
+            let mut v=0x200u64.to_le_bytes().to_vec();
+            v.extend_from_slice(&0x10u64.to_le_bytes());
+            v.extend(uleb(0));
+            v.extend_from_slice(&[0x0e,16]);
+            v
+        });
+        (data,fde1,fde2)
+    }
+
+    #[test]
+    fn pointer_encodings_and_augmentations(){
+        let (data,fde1,fde2)=fixture();
+        let frame=EhFrame::parse(ident(),&data,ADDRESS,BASES).unwrap();
+        let (cie1,cie2)=(&frame.cies[0],&frame.cies[1]);
+        assert_eq!((cie1.version,cie1.augmentation.as_str(),cie1.return_register),(1,"zPLR",16));
+        assert_eq!(cie1.personality,Some(Pointer::Indirect(0x3fe0)));
+        assert_eq!((cie1.lsda_encoding,cie1.fde_encoding,cie1.signal_frame),(Some(0x1b),0x1b,false));
+        assert_eq!((cie2.version,cie2.augmentation.as_str(),cie2.fde_encoding,cie2.signal_frame),(3,"zRS",0x34,true));
+
+        let fdes:Vec<(u64,u64,u64,Option<Pointer>)>=frame.fdes.iter().map(|fde| (fde.offset,fde.pc_begin,fde.pc_range,fde.lsda)).collect();
+        assert_eq!(fdes,[(fde1 as u64,0x1100,0x20,Some(Pointer::Direct(0x2400))),(fde2 as u64,0x1200,0x10,None)]);
+        assert_eq!(frame.find_fde(0x120f).map(|fde| fde.offset),Some(fde2 as u64));
+        assert!(frame.find_fde(0x1120).is_none());
+        assert_eq!(frame.cie(&frame.fdes[1]),Some(cie2));
+    }
+
+    #[test]
+    fn remember_and_restore_state(){
+        let (data,_,_)=fixture();
+        let frame=EhFrame::parse(ident(),&data,ADDRESS,BASES).unwrap();
+        let rows:Vec<_>=frame.rows(&frame.fdes[0]).unwrap().into_iter()
+            .map(|row| (row.start,row.end,row.cfa,row.registers)).collect();
+        let ra=(16,RegisterRule::Offset(-8));
+        let rbp=(6,RegisterRule::Offset(-16));
+        assert_eq!(rows,[
+            (0x1100,0x1101,CfaRule::RegisterOffset(7,8),vec![ra.clone()]),
+            (0x1101,0x1105,CfaRule::RegisterOffset(7,16),vec![ra.clone(),rbp.clone()]),
+            (0x1105,0x1107,CfaRule::RegisterOffset(7,8),vec![ra.clone()]),
+            (0x1107,0x1120,CfaRule::RegisterOffset(7,16),vec![ra,rbp]),
+        ]);
+        let row=frame.row_at(&frame.fdes[0],0x1106).unwrap().unwrap();
+        assert_eq!(row.rule(6),None);
+    }
+
+    //eh_frame_hdr:pc相对的eh_frame_ptr,udata4的数量,相对自身的sdata4表
+    fn hdr(table:&[(u64,u64)])->EhFrameHdr{
+        const HDR: u64 = 0x1f00;
+        let mut data=vec![1,abi::DW_EH_PE_PCREL|abi::DW_EH_PE_SDATA4,abi::DW_EH_PE_UDATA4,abi::DW_EH_PE_DATAREL|abi::DW_EH_PE_SDATA4];
+        data.extend_from_slice(&pcrel(ADDRESS,HDR+4));
+        data.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        for &(location,fde) in table {
+            data.extend_from_slice(&pcrel(location,HDR));
+            data.extend_from_slice(&pcrel(fde,HDR));
+        }
+        EhFrameHdr::parse(ident(),&data,HDR).unwrap()
+    }
+
+    #[test]
+    fn hdr_verification(){
+        let (data,fde1,fde2)=fixture();
+        let frame=EhFrame::parse(ident(),&data,ADDRESS,BASES).unwrap();
+        let (fde1,fde2)=(ADDRESS+fde1 as u64,ADDRESS+fde2 as u64);
+        let matching=hdr(&[(0x1100,fde1),(0x1200,fde2)]);
+        assert_eq!(matching.eh_frame_ptr,ADDRESS);
+        assert_eq!(matching.verify(&frame),[]);
+        assert_eq!(matching.lookup(0x1204),Some(fde2));
+        assert_eq!(matching.lookup(0x10ff),None);
+
+        let mismatching=hdr(&[(0x1200,fde2),(0x1104,fde1),(0x1300,ADDRESS+2)]);
+        assert_eq!(mismatching.verify(&frame),[
+            HdrMismatch::Count{table:3,fdes:2},
+            HdrMismatch::Unsorted(1),
+            HdrMismatch::Location{index:1,table:0x1104,fde:0x1100},
+            HdrMismatch::NoFde{index:2,fde:ADDRESS+2},
+        ]);
+    }
+
+    //code/data对齐因子取极大值,或增强数据长度超出条目
+    #[test]
+    fn crafted_entries(){
+        let cie=|code_alignment:u64,data_alignment:i64,augmentation_length:u64| {
+            let mut data=Vec::new();
+            let cie=entry(&mut data,None,|_| {
+                let mut v=vec![1];
+                v.extend_from_slice(b"zR\0");
+                v.extend(uleb(code_alignment));
+                v.extend(sleb(data_alignment));
+                v.push(16);
+                v.extend(uleb(augmentation_length));
+                v.push(abi::DW_EH_PE_UDATA4);
+                v
+            });
+            (data,cie)
+        };
+        let fde=|data:&mut Vec<u8>,cie:usize,augmentation_length:u64,instructions:&[u8]| entry(data,Some(cie),|_| {
+            let mut v=0x1000u32.to_le_bytes().to_vec();
+            v.extend_from_slice(&0x100u32.to_le_bytes());
+            v.extend(uleb(augmentation_length));
+            v.extend_from_slice(instructions);
+            v
+        });
+        let (data,_)=cie(1,-8,u64::MAX);
+        assert_eq!(EhFrame::parse(ident(),&data,ADDRESS,BASES),Err(EhFrameError::Truncated(0)));
+
+        let (mut data,offset)=cie(1,-8,1);
+        let fde_offset=fde(&mut data,offset,u64::MAX,&[]);
+        assert_eq!(EhFrame::parse(ident(),&data,ADDRESS,BASES),Err(EhFrameError::Truncated(fde_offset as u64)));
+
+        for (code_alignment,data_alignment,instructions) in [(1<<62,-8,&[0x44][..]),(1,-8,&[0x04,0xff,0xff,0xff,0xff,0x04,0xff,0xff,0xff,0xff][..]),
+                                                             (1,i64::MIN/4,&[0x86,8][..]),(1,i64::MIN/4,&[0x13,0x78][..])] {
+            let (mut data,offset)=cie(code_alignment,data_alignment,1);
+            let fde_offset=fde(&mut data,offset,0,instructions);
+            let frame=EhFrame::parse(ident(),&data,ADDRESS,BASES).unwrap();
+            let result=frame.rows(&frame.fdes[0]);
+            if code_alignment==1 && data_alignment==-8 {
+                //两次advance_loc4累加没有溢出
+                assert!(result.is_ok());
+            } else {
+                assert_eq!(result,Err(EhFrameError::Malformed(fde_offset as u64)));
+            }
+        }
+    }
+}
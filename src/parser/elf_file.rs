@@ -0,0 +1,364 @@
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_header::{elf_header, FileHeader};
+use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::file;
+use crate::parser::file::Class;
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+use crate::parser::symbol::Symbol;
+
+/// A whole ELF file held in memory together with its parsed header tables.
+///
+/// Unlike the `read_*` helpers, which re-open the file for every table, this keeps the
+/// bytes around so section and segment contents can be sliced out directly. All table
+/// accesses are bounds checked, so truncated files yield empty tables instead of panics.
+#[derive(Debug, Clone)]
+pub struct ElfFile {
+    pub data: Vec<u8>,
+    pub header: FileHeader,
+    pub program_headers: Vec<ProgramHeader>,
+    pub section_headers: Vec<SectionHeader>,
+}
+
+impl ElfFile {
+    pub fn read_elf(file_path:&str)->Option<ElfFile>{
+        let data=file::file_utils::read_file(file_path).ok()?;
+        Self::parse(data)
+    }
+
+    pub fn parse(data:Vec<u8>)->Option<ElfFile>{
+        if data.len()<abi::EI_NIDENT || !elf_header::verify_magic(data[..4].to_vec()) {
+            return None;
+        }
+        let ident=elf_header::parse_ident(data[..abi::EI_NIDENT].to_vec()).ok()?;
+        let header_size=match ident.1 {
+            Class::ELF32 => 0x34,
+            Class::ELF64 => 0x40,
+        };
+        if data.len()<header_size {
+            return None;
+        }
+        let header=elf_header::file_header(ident,&data);
+        let program_headers=Self::parse_program_headers(header,&data);
+        let section_headers=Self::parse_section_headers(header,&data);
+        Some(ElfFile{
+            data,
+            header,
+            program_headers,
+            section_headers,
+        })
+    }
+
+    /// Serialize the file. The file, program and section headers are written back from
+    /// their parsed form over a copy of the original bytes; table contents are kept as
+    /// they are in `data`, where editors store them with `Symbol::to_bytes`,
+    /// `Rela::to_bytes` and friends. An unmodified file comes out byte for byte identical.
+    pub fn to_bytes(&self)->Vec<u8>{
+        let ident=self.ident();
+        let mut data=self.data.clone();
+        let header_size=elf_header::size_for(self.header.class);
+        if data.len()<header_size {
+            data.resize(header_size,0);
+        }
+        elf_header::write_header(&self.header,&mut data);
+
+        let phentsize=self.header.e_phentsize as usize;
+        for (i,ph) in self.program_headers.iter().enumerate() {
+            let offset=self.header.e_phoff as usize+i*phentsize;
+            Self::ensure(&mut data,offset+ProgramHeader::size_for(self.header.class));
+            ph.write_at(ident,offset,&mut data);
+        }
+        let shentsize=self.header.e_shentsize as usize;
+        for (i,sh) in self.section_headers.iter().enumerate() {
+            let offset=self.header.e_shoff as usize+i*shentsize;
+            Self::ensure(&mut data,offset+SectionHeader::size_for(self.header.class));
+            sh.write_at(ident,offset,&mut data);
+        }
+
+        data
+    }
+
+    pub fn write(&self,file_path:&str)->std::io::Result<()>{
+        std::fs::write(file_path,self.to_bytes())
+    }
+
+    fn ensure(data:&mut Vec<u8>,len:usize){
+        if data.len()<len {
+            data.resize(len,0);
+        }
+    }
+
+    pub fn ident(&self)->(AnyEndian, Class){
+        (self.header.endianness,self.header.class)
+    }
+
+    //按e_phoff读取全部程序头,不依赖第一个表项是PT_PHDR
+    fn parse_program_headers(header:FileHeader,data:&[u8])->Vec<ProgramHeader>{
+        let ident=(header.endianness,header.class);
+        let entsize=header.e_phentsize as usize;
+        if header.e_phoff==0 || entsize<ProgramHeader::size_for(header.class) {
+            return Vec::new();
+        }
+        (0..header.e_phnum as usize)
+            .map_while(|i| Self::table_entry(header.e_phoff,i,entsize,data.len()))
+            .map(|offset| ProgramHeader::parse_at(ident,offset,data))
+            .collect()
+    }
+
+    fn parse_section_headers(header:FileHeader,data:&[u8])->Vec<SectionHeader>{
+        let ident=(header.endianness,header.class);
+        let entsize=header.e_shentsize as usize;
+        if header.e_shoff==0 || entsize<SectionHeader::size_for(header.class) {
+            return Vec::new();
+        }
+        let mut section_headers:Vec<SectionHeader>=(0..header.e_shnum as usize)
+            .map_while(|i| Self::table_entry(header.e_shoff,i,entsize,data.len()))
+            .enumerate()
+            .map(|(i,offset)| SectionHeader::parse_at(ident,offset,data,i as u16))
+            .collect();
+        let shstrndx=header.e_shstrndx as usize;
+        if shstrndx<section_headers.len() {
+            let strtab=section_headers[shstrndx].clone();
+            if let Some(bytes)=Self::slice(data,strtab.sh_offset,strtab.sh_size) {
+                section_headers=SectionHeader::fix_section_name_from_bytes(bytes,section_headers);
+            }
+        }
+        section_headers
+    }
+
+    //表项i的文件偏移,溢出或超出文件时返回None
+    pub(crate) fn table_entry(table_offset:u64,i:usize,entsize:usize,len:usize)->Option<usize>{
+        let offset=usize::try_from(table_offset).ok()?.checked_add(i.checked_mul(entsize)?)?;
+        (offset.checked_add(entsize)?<=len).then_some(offset)
+    }
+
+    fn slice(data:&[u8],offset:u64,size:u64)->Option<&[u8]>{
+        let end=offset.checked_add(size)?;
+        data.get(offset as usize..usize::try_from(end).ok()?)
+    }
+
+    /// File contents of a section, or None for SHT_NOBITS and out-of-file ranges
+    pub fn section_data(&self,section_header:&SectionHeader)->Option<&[u8]>{
+        if section_header.sh_type==abi::SHT_NOBITS {
+            return None;
+        }
+        Self::slice(&self.data,section_header.sh_offset,section_header.sh_size)
+    }
+
+    /// File contents of a segment (the first p_filesz bytes)
+    pub fn segment_data(&self,program_header:&ProgramHeader)->Option<&[u8]>{
+        Self::slice(&self.data,program_header.p_offset,program_header.p_filesz)
+    }
+
+    pub fn find_section(&self,name:&str)->Option<&SectionHeader>{
+        self.section_headers.iter().find(|sh| sh.string_name==name)
+    }
+
+    pub fn find_section_by_type(&self,sh_type:u32)->Option<&SectionHeader>{
+        self.section_headers.iter().find(|sh| sh.sh_type==sh_type)
+    }
+
+    /// Parse the symbol table held in `section_header` and name it from its linked string table
+    pub fn symbols(&self,section_header:&SectionHeader)->Vec<Symbol>{
+        let Some(bytes)=self.section_data(section_header) else {
+            return Vec::new();
+        };
+        let size=Symbol::size_for(self.header.class);
+        let bytes=&bytes[..bytes.len()/size*size];
+        let symbols=Symbol::parser_Symbol(self.ident(),bytes,0);
+        match self.section_headers.get(section_header.sh_link as usize).and_then(|sh| self.section_data(sh)) {
+            Some(strtab) => Symbol::fix_symbol_name_from_bytes(strtab,symbols),
+            None => symbols,
+        }
+    }
+
+    /// Symbols of `.dynsym`. Without section headers the table is found through
+    /// DT_SYMTAB and sized from the hash tables.
+    pub fn dynamic_symbols(&self)->Vec<Symbol>{
+        if let Some(section_header)=self.find_section_by_type(abi::SHT_DYNSYM) {
+            return self.symbols(section_header);
+        }
+        let dyns=self.dynamic();
+        let (Some(symtab),Some(count))=(Dyn::find(&dyns,abi::DT_SYMTAB),self.dynamic_symbol_count()) else {
+            return Vec::new();
+        };
+        let size=Symbol::size_for(self.header.class);
+        let Some(bytes)=self.vaddr_data(symtab,count*size as u64) else {
+            return Vec::new();
+        };
+        let symbols=Symbol::parser_Symbol(self.ident(),bytes,0);
+        match self.dynamic_strings() {
+            Some(strtab) => Symbol::fix_symbol_name_from_bytes(strtab,symbols),
+            None => symbols,
+        }
+    }
+
+    /// Entries of the dynamic section, from PT_DYNAMIC or else the SHT_DYNAMIC section
+    pub fn dynamic(&self)->Vec<Dyn>{
+        let bytes=match self.program_headers.iter().find(|ph| ph.p_type==abi::PT_DYNAMIC) {
+            Some(ph) => self.segment_data(ph),
+            None => self.find_section_by_type(abi::SHT_DYNAMIC).and_then(|sh| self.section_data(sh)),
+        };
+        match bytes {
+            Some(bytes) => Dyn::parse(self.ident(),bytes),
+            None => Vec::new(),
+        }
+    }
+
+    /// The dynamic string table, from DT_STRTAB/DT_STRSZ or else `.dynstr`
+    pub fn dynamic_strings(&self)->Option<&[u8]>{
+        let dyns=self.dynamic();
+        if let (Some(addr),Some(size))=(Dyn::find(&dyns,abi::DT_STRTAB),Dyn::find(&dyns,abi::DT_STRSZ)) {
+            if let Some(bytes)=self.vaddr_data(addr,size) {
+                return Some(bytes);
+            }
+        }
+        self.find_section(".dynstr").and_then(|sh| self.section_data(sh))
+    }
+
+    /// Library names listed by DT_NEEDED, in load order
+    pub fn needed_libraries(&self)->Vec<String>{
+        let strtab=self.dynamic_strings().unwrap_or(&[]);
+        Dyn::find_all(&self.dynamic(),abi::DT_NEEDED).into_iter()
+            .filter_map(|offset| SectionHeader::parse_string_at(strtab,offset as usize))
+            .collect()
+    }
+
+    /// Number of `.dynsym` entries as recorded by DT_HASH or DT_GNU_HASH
+    pub fn dynamic_symbol_count(&self)->Option<u64>{
+        let dyns=self.dynamic();
+        let endian=self.header.endianness;
+        if let Some(hash)=Dyn::find(&dyns,abi::DT_HASH) {
+            //nchain与符号数相同
+            let bytes=self.vaddr_data(hash,8)?;
+            return Some(endian.parse_u32_at(4,bytes) as u64);
+        }
+        let gnu_hash=Dyn::find(&dyns,abi::DT_GNU_HASH)?;
+        let head=self.vaddr_data(gnu_hash,16)?;
+        let nbucket=endian.parse_u32_at(0,head) as u64;
+        let symoffset=endian.parse_u32_at(4,head) as u64;
+        let bloom_size=endian.parse_u32_at(8,head) as u64;
+        let word=self.header.class.word_size() as u64;
+        let buckets_addr=gnu_hash+16+bloom_size*word;
+        let buckets=self.vaddr_data(buckets_addr,nbucket*4)?;
+        let max_bucket=(0..nbucket as usize).map(|i| endian.parse_u32_at(i*4,buckets) as u64).max().unwrap_or(0);
+        if max_bucket<symoffset {
+            return Some(symoffset);
+        }
+        //沿最大bucket的链走到结束标记
+        let chains_addr=buckets_addr+nbucket*4;
+        let mut index=max_bucket;
+        loop {
+            let chain=self.vaddr_data(chains_addr+(index-symoffset)*4,4)?;
+            if endian.parse_u32_at(0,chain)&1!=0 {
+                return Some(index+1);
+            }
+            index+=1;
+        }
+    }
+
+    /// File bytes backing `size` bytes at a virtual address
+    pub fn vaddr_data(&self,vaddr:u64,size:u64)->Option<&[u8]>{
+        let offset=self.vaddr_to_offset(vaddr)?;
+        Self::slice(&self.data,offset,size)
+    }
+
+    /// Translate a virtual address to a file offset through the PT_LOAD segments.
+    /// Addresses in the zero-filled tail of a segment have no file offset.
+    pub fn vaddr_to_offset(&self,vaddr:u64)->Option<u64>{
+        self.program_headers.iter()
+            .filter(|ph| ph.p_type==abi::PT_LOAD)
+            .find(|ph| vaddr>=ph.p_vaddr && vaddr-ph.p_vaddr<ph.p_filesz)
+            .map(|ph| vaddr-ph.p_vaddr+ph.p_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::endian::EndianParse;
+    use crate::parser::relocation::{Rel, Rela};
+
+    const TEST_ELF: &[u8] = include_bytes!("../test_elf/de");
+
+    #[test]
+    fn round_trip(){
+        let elf=ElfFile::parse(TEST_ELF.to_vec()).unwrap();
+        assert_eq!(elf.to_bytes(),TEST_ELF);
+    }
+
+    //未知的EI_CLASS、非1的EI_VERSION与非0的EI_PAD都原样写回
+    #[test]
+    fn round_trip_odd_ident(){
+        let mut data=TEST_ELF.to_vec();
+        data[abi::EI_CLASS]=0x42;
+        data[abi::EI_VERSION]=7;
+        data[abi::EI_OSABI]=3;
+        data[abi::EI_ABIVERSION+1..abi::EI_NIDENT].copy_from_slice(b"padding");
+        let elf=ElfFile::parse(data.clone()).unwrap();
+        assert_eq!(elf.header.class,Class::ELF64);
+        assert_eq!(elf.to_bytes(),data);
+        //改了类别时EI_CLASS随之改写,其余字节不动
+        let mut header=elf.header;
+        header.class=Class::ELF32;
+        let mut out=vec![0u8;0x40];
+        elf_header::write_header(&header,&mut out);
+        assert_eq!(out[abi::EI_CLASS],abi::ELFCLASS32);
+        assert_eq!(out[abi::EI_VERSION],7);
+        assert_eq!(&out[abi::EI_ABIVERSION+1..abi::EI_NIDENT],b"padding");
+    }
+
+    #[test]
+    fn round_trip_non_utf8_strings(){
+        //.dynstr和.shstrtab里各放一个非UTF-8字节
+        let mut data=TEST_ELF.to_vec();
+        data[0x561]=0xff;
+        data[0x3087+0x87+4]=0xff;
+        let elf=ElfFile::parse(data.clone()).unwrap();
+        assert_eq!(elf.to_bytes(),data);
+    }
+
+    #[test]
+    fn tables_re_encode(){
+        let elf=ElfFile::parse(TEST_ELF.to_vec()).unwrap();
+        let ident=elf.ident();
+        let dynsym=elf.find_section(".dynsym").unwrap();
+        let bytes=elf.section_data(dynsym).unwrap();
+        assert_eq!(Symbol::to_bytes(ident,&elf.symbols(dynsym)),bytes);
+        let rela_dyn=elf.find_section(".rela.dyn").unwrap();
+        let bytes=elf.section_data(rela_dyn).unwrap();
+        assert_eq!(Rela::to_bytes(ident,&Rela::parse(ident,bytes,rela_dyn.sh_size)),bytes);
+        let dynstr=elf.find_section(".dynstr").unwrap();
+        let bytes=elf.section_data(dynstr).unwrap();
+        let entries:Vec<(u32,&[u8])>=bytes.split(|&b| b==0)
+            .scan(0u32,|offset,s| { let start=*offset; *offset+=s.len() as u32+1; Some((start,s)) })
+            .filter(|(start,_)| (*start as usize)<bytes.len())
+            .collect();
+        assert_eq!(SectionHeader::build_string_table(&entries),bytes);
+    }
+
+    #[test]
+    fn tables_re_encode_big_endian_elf32(){
+        let ident=(AnyEndian::new(2),Class::ELF32);
+        let symbols:Vec<u8>=(0..48u8).collect();
+        assert_eq!(Symbol::to_bytes(ident,&Symbol::parser_Symbol(ident,&symbols,0)),symbols);
+        let rels:Vec<u8>=(0..24u8).collect();
+        assert_eq!(Rel::to_bytes(ident,&Rel::parse(ident,&rels,24)),rels);
+        let relas:Vec<u8>=(0..36u8).collect();
+        assert_eq!(Rela::to_bytes(ident,&Rela::parse(ident,&relas,36)),relas);
+        let header:Vec<u8>=(0..40u8).collect();
+        let section_header=SectionHeader::parse_at(ident,0,&header,0);
+        let mut out=vec![0u8;40];
+        section_header.write_at(ident,0,&mut out);
+        assert_eq!(out,header);
+    }
+
+    #[test]
+    fn symbol_index_past_u16(){
+        let ident=(AnyEndian::new(1),Class::ELF64);
+        let symbols=Symbol::parser_Symbol(ident,&vec![0u8;70000*24],0);
+        assert_eq!(symbols.len(),70000);
+        assert_eq!(symbols[69999].index,69999);
+    }
+}
@@ -4,6 +4,9 @@ use crate::parser::file::Class;
 
 #[derive(Debug,PartialEq,Copy,Clone)]
 pub struct FileHeader {
+    /// The identification bytes as read. `write_header` writes them back unchanged
+    /// except where `class`, `endianness`, `osabi` or `abiversion` say otherwise.
+    pub e_ident: [u8; abi::EI_NIDENT],
     /// 32-bit vs 64-bit
     pub class: Class,
     // file byte order
@@ -104,10 +107,13 @@ pub mod elf_header {
     use crate::parser;
     use crate::parser::{abi, endian, file, symbol};
     use crate::parser::elf_header::{Class, FileHeader};
-    use crate::parser::endian::{AnyEndian, EndianParse};
+    use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
     use crate::parser::segment::ProgramHeader;
 
-    fn verify_magic(data: Vec<u8>) -> bool {
+    pub fn verify_magic(data: Vec<u8>) -> bool {
+        if data.len() < 4 {
+            return false;
+        }
         if data[0] == abi::ELFMAG0 && data[1] == abi::ELFMAG1 && data[2] == abi::ELFMAG2 && data[3] == abi::ELFMAG3 {
             return true;
         }
@@ -180,12 +186,15 @@ pub mod elf_header {
         let e_shstrndx = file_endian.parse_u16_at(offset, data);
 
 
+        let mut e_ident=[0u8;abi::EI_NIDENT];
+        e_ident.copy_from_slice(&data[..abi::EI_NIDENT]);
         return  FileHeader{
+            e_ident,
             class:class,
             endianness:file_endian ,
             version:version,
-            osabi: 0,
-            abiversion: 0,
+            osabi: data[abi::EI_OSABI],
+            abiversion: data[abi::EI_ABIVERSION],
             e_type:e_type,
             e_machine:e_machine,
             e_entry:e_entry,
@@ -202,8 +211,69 @@ pub mod elf_header {
 
     }
 
+    /// The identification bytes of a new file: magic, class, data encoding and the
+    /// current version, with a SYSV OS ABI
+    pub fn new_ident(class: Class, endian: AnyEndian) -> [u8; abi::EI_NIDENT] {
+        let mut ident=[0u8;abi::EI_NIDENT];
+        ident[..4].copy_from_slice(&abi::ELFMAGIC);
+        ident[abi::EI_CLASS]=match class {
+            Class::ELF32 => abi::ELFCLASS32,
+            Class::ELF64 => abi::ELFCLASS64,
+        };
+        ident[abi::EI_DATA]=endian.endian_type;
+        ident[abi::EI_VERSION]=abi::EV_CURRENT;
+        ident
+    }
+
+    /// Write the ident and the file header at the start of `data`, using the layout
+    /// `file_header` reads. `data` must hold at least e_ehsize bytes.
+    pub fn write_header(header: &FileHeader, data: &mut [u8]) {
+        let endian=header.endianness;
+        //原样保留e_ident,只有类别、字节序与读到的不一致时才改写
+        let mut ident=header.e_ident;
+        let (_,class)=parse_ident(ident.to_vec()).unwrap_or((endian,header.class));
+        if class!=header.class {
+            ident[abi::EI_CLASS]=new_ident(header.class,endian)[abi::EI_CLASS];
+        }
+        ident[abi::EI_DATA]=endian.endian_type;
+        ident[abi::EI_OSABI]=header.osabi;
+        ident[abi::EI_ABIVERSION]=header.abiversion;
+        data[..abi::EI_NIDENT].copy_from_slice(&ident);
+        let mut offset=0x10;
+        endian.write_u16_at(offset, data, header.e_type);
+        endian.write_u16_at(offset+2, data, header.e_machine);
+        endian.write_u32_at(offset+4, data, header.version);
+        offset+=8;
+        if header.class == Class::ELF32 {
+            endian.write_u32_at(offset, data, header.e_entry as u32);
+            endian.write_u32_at(offset+4, data, header.e_phoff as u32);
+            endian.write_u32_at(offset+8, data, header.e_shoff as u32);
+            offset+=12;
+        } else {
+            endian.write_u64_at(offset, data, header.e_entry);
+            endian.write_u64_at(offset+8, data, header.e_phoff);
+            endian.write_u64_at(offset+16, data, header.e_shoff);
+            offset+=24;
+        }
+        endian.write_u32_at(offset, data, header.e_flags);
+        endian.write_u16_at(offset+4, data, header.e_ehsize);
+        endian.write_u16_at(offset+6, data, header.e_phentsize);
+        endian.write_u16_at(offset+8, data, header.e_phnum);
+        endian.write_u16_at(offset+10, data, header.e_shentsize);
+        endian.write_u16_at(offset+12, data, header.e_shnum);
+        endian.write_u16_at(offset+14, data, header.e_shstrndx);
+    }
+
+    /// Size of the file header for `class`
+    pub fn size_for(class: Class) -> usize {
+        match class {
+            Class::ELF32 => 0x34,
+            Class::ELF64 => 0x40,
+        }
+    }
+
     pub fn read_header(file_path: &str)->Option<(FileHeader)>{
-        let headr = file::file_utils::read_file_range(file_path, 0, 16).unwrap();
+        let headr = file::file_utils::read_file_range(file_path, 0, 16).ok()?;
         if headr.len() != 16 {
             return None;
         }
@@ -218,9 +288,9 @@ pub mod elf_header {
             Class::ELF64 => 0x40,
         };
 
-        let headr = file::file_utils::read_file_range(file_path, 0, header_size);
+        let headr = file::file_utils::read_file_range(file_path, 0, header_size).ok()?;
         //读取文件头
-        let binary_header=file_header(idents,&headr.unwrap());
+        let binary_header=file_header(idents,&headr);
         println!("[*]程序头解析成功:");
         println!("{:?}\n", binary_header);
 
@@ -113,3 +113,24 @@ impl EndianParse for AnyEndian {
         }
     }
 }
+
+/// Counterpart of [`EndianParse`] for patching values back into a byte buffer
+pub trait EndianWrite: EndianParse + Copy {
+    fn write_u8_at(self, offset: usize, data: &mut [u8], value: u8) {
+        data[offset] = value;
+    }
+    fn write_u16_at(self, offset: usize, data: &mut [u8], value: u16) {
+        let buf = if self.is_little() { value.to_le_bytes() } else { value.to_be_bytes() };
+        data[offset..offset + buf.len()].copy_from_slice(&buf);
+    }
+    fn write_u32_at(self, offset: usize, data: &mut [u8], value: u32) {
+        let buf = if self.is_little() { value.to_le_bytes() } else { value.to_be_bytes() };
+        data[offset..offset + buf.len()].copy_from_slice(&buf);
+    }
+    fn write_u64_at(self, offset: usize, data: &mut [u8], value: u64) {
+        let buf = if self.is_little() { value.to_le_bytes() } else { value.to_be_bytes() };
+        data[offset..offset + buf.len()].copy_from_slice(&buf);
+    }
+}
+
+impl EndianWrite for AnyEndian {}
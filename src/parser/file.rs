@@ -3,6 +3,16 @@ pub enum Class {
     ELF32,
     ELF64,
 }
+
+impl Class {
+    /// Size of an address, and of the words in `.got`, `.dynamic` and notes, in bytes
+    pub fn word_size(&self)->usize{
+        match self {
+            Class::ELF32 => 4,
+            Class::ELF64 => 8,
+        }
+    }
+}
 pub mod file_utils{
     use std::fs::File;
     use std::io::{self, Read, Seek, SeekFrom};
@@ -10,6 +20,10 @@ pub mod file_utils{
     //read offest file data
     pub fn read_file_range(path: &str, start_offset: u64, end_offset: u64) -> io::Result<Vec<u8>> {
         let mut file = File::open(path)?;
+        //畸形文件的偏移可能越界或首尾颠倒
+        if end_offset < start_offset || end_offset > file.metadata()?.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "range outside of file"));
+        }
 
         // 设置文件的读取偏移量
         file.seek(SeekFrom::Start(start_offset))?;
@@ -26,6 +40,14 @@ pub mod file_utils{
         Ok(buffer)
     }
 
+    //read whole file data
+    pub fn read_file(path: &str) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+
 
 
 
@@ -0,0 +1,440 @@
+use std::fmt::Write;
+use std::fs;
+use crate::parser::abi;
+use crate::parser::builder::ElfBuilder;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::AnyEndian;
+use crate::parser::file::Class;
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    Io(String),
+    /// Line (1-based) that is not a well-formed record
+    Syntax(usize),
+    /// Line (1-based) whose checksum does not match
+    Checksum(usize),
+    /// Address does not fit the chosen record format
+    AddressRange(u64),
+}
+
+/// Motorola S-record flavour, named after the data record it uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrecFormat {
+    /// S1 records, 16-bit addresses, S9 termination
+    S19,
+    /// S2 records, 24-bit addresses, S8 termination
+    S28,
+    /// S3 records, 32-bit addresses, S7 termination
+    S37,
+}
+
+impl SrecFormat {
+    /// The smallest format that can address everything below `end`
+    pub fn for_end(end:u64)->SrecFormat{
+        match end {
+            0..=0x1_0000 => SrecFormat::S19,
+            0x1_0001..=0x100_0000 => SrecFormat::S28,
+            _ => SrecFormat::S37,
+        }
+    }
+
+    fn address_len(self)->usize{
+        match self {
+            SrecFormat::S19 => 2,
+            SrecFormat::S28 => 3,
+            SrecFormat::S37 => 4,
+        }
+    }
+}
+
+/// A run of bytes at a physical address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageChunk {
+    pub address: u64,
+    pub data: Vec<u8>,
+}
+
+impl ImageChunk {
+    pub fn end(&self)->u64{
+        self.address+self.data.len() as u64
+    }
+}
+
+/// The bytes a flash programmer writes: the file contents of the PT_LOAD segments
+/// at their load (physical) addresses, sorted, with adjacent runs merged. Memory-only
+/// parts of segments (`.bss`) are not part of the image.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FirmwareImage {
+    pub chunks: Vec<ImageChunk>,
+    /// Start address: `e_entry`, or the start record of a HEX/SREC file
+    pub entry: Option<u64>,
+}
+
+//每条数据记录的字节数,与objcopy一致
+const RECORD_LEN: usize = 16;
+
+impl FirmwareImage {
+    pub fn read_image(file_path:&str)->Option<FirmwareImage>{
+        Some(Self::parse_image(&ElfFile::read_elf(file_path)?))
+    }
+
+    pub fn parse_image(elf:&ElfFile)->FirmwareImage{
+        let chunks=elf.program_headers.iter()
+            .filter(|ph| ph.p_type==abi::PT_LOAD && ph.p_filesz>0)
+            .filter_map(|ph| Some(ImageChunk{address:ph.p_paddr,data:elf.segment_data(ph)?.to_vec()}))
+            .collect();
+        FirmwareImage::from_chunks(chunks,Some(elf.header.e_entry))
+    }
+
+    /// Sort the chunks and merge the ones that touch; where chunks overlap the later
+    /// one wins
+    pub fn from_chunks(mut chunks:Vec<ImageChunk>,entry:Option<u64>)->FirmwareImage{
+        chunks.retain(|chunk| !chunk.data.is_empty());
+        chunks.sort_by_key(|chunk| chunk.address);
+        let mut merged:Vec<ImageChunk>=Vec::new();
+        for chunk in chunks {
+            match merged.last_mut() {
+                Some(last) if chunk.address<=last.end() => {
+                    let start=(chunk.address-last.address) as usize;
+                    let end=start+chunk.data.len();
+                    if end>last.data.len() {
+                        last.data.resize(end,0);
+                    }
+                    last.data[start..end].copy_from_slice(&chunk.data);
+                }
+                _ => merged.push(chunk),
+            }
+        }
+        FirmwareImage{chunks:merged,entry}
+    }
+
+    /// Lowest load address, 0 for an empty image
+    pub fn start(&self)->u64{
+        self.chunks.first().map_or(0,|chunk| chunk.address)
+    }
+
+    pub fn end(&self)->u64{
+        self.chunks.last().map_or(0,|chunk| chunk.end())
+    }
+
+    /// One contiguous chunk from `start()` to `end()` with the gaps set to `pad`
+    pub fn fill_gaps(&self,pad:u8)->FirmwareImage{
+        if self.chunks.is_empty() {
+            return self.clone();
+        }
+        FirmwareImage{
+            chunks:vec![ImageChunk{address:self.start(),data:self.to_binary(pad)}],
+            entry:self.entry,
+        }
+    }
+
+    /// Raw image as `objcopy -O binary` writes it: starts at the lowest load address
+    pub fn to_binary(&self,pad:u8)->Vec<u8>{
+        let start=self.start();
+        let mut data=vec![pad;(self.end()-start) as usize];
+        for chunk in &self.chunks {
+            let offset=(chunk.address-start) as usize;
+            data[offset..offset+chunk.data.len()].copy_from_slice(&chunk.data);
+        }
+        data
+    }
+
+    /// Intel HEX with extended linear address (04) records and a start linear
+    /// address (05) record for the entry point
+    pub fn to_ihex(&self)->Result<String,ImageError>{
+        if self.end()>0x1_0000_0000 {
+            return Err(ImageError::AddressRange(self.end()));
+        }
+        let mut out=String::new();
+        let mut upper=0u64;
+        for chunk in &self.chunks {
+            let mut offset=0;
+            while offset<chunk.data.len() {
+                let address=chunk.address+offset as u64;
+                if address>>16!=upper {
+                    upper=address>>16;
+                    Self::ihex_record(&mut out,0,4,&(upper as u16).to_be_bytes());
+                }
+                //记录不能跨越64K边界
+                let len=RECORD_LEN.min(chunk.data.len()-offset).min((0x1_0000-(address&0xffff)) as usize);
+                Self::ihex_record(&mut out,address as u16,0,&chunk.data[offset..offset+len]);
+                offset+=len;
+            }
+        }
+        if let Some(entry)=self.entry.filter(|&entry| entry!=0) {
+            Self::ihex_record(&mut out,0,5,&(entry as u32).to_be_bytes());
+        }
+        Self::ihex_record(&mut out,0,1,&[]);
+        Ok(out)
+    }
+
+    fn ihex_record(out:&mut String,address:u16,record_type:u8,data:&[u8]){
+        let mut bytes=vec![data.len() as u8];
+        bytes.extend_from_slice(&address.to_be_bytes());
+        bytes.push(record_type);
+        bytes.extend_from_slice(data);
+        let checksum=bytes.iter().fold(0u8,|sum,b| sum.wrapping_add(*b)).wrapping_neg();
+        out.push(':');
+        for byte in bytes.iter().chain([checksum].iter()) {
+            let _=write!(out,"{:02X}",byte);
+        }
+        out.push('\n');
+    }
+
+    /// Motorola S-records; `format` None picks the smallest one that fits
+    pub fn to_srec(&self,format:Option<SrecFormat>)->Result<String,ImageError>{
+        let format=format.unwrap_or_else(|| SrecFormat::for_end(self.end().max(self.entry.unwrap_or(0)+1)));
+        let address_len=format.address_len();
+        let limit=1u64<<(address_len*8);
+        if self.end()>limit {
+            return Err(ImageError::AddressRange(self.end()));
+        }
+        let (data_type,end_type)=match format {
+            SrecFormat::S19 => (1,9),
+            SrecFormat::S28 => (2,8),
+            SrecFormat::S37 => (3,7),
+        };
+        let mut out=String::new();
+        Self::srec_record(&mut out,0,0,2,&[]);
+        let mut count=0u64;
+        for chunk in &self.chunks {
+            for (i,data) in chunk.data.chunks(RECORD_LEN).enumerate() {
+                Self::srec_record(&mut out,data_type,chunk.address+(i*RECORD_LEN) as u64,address_len,data);
+                count+=1;
+            }
+        }
+        //记录数超过16位时用S6
+        if count<=0xffff {
+            Self::srec_record(&mut out,5,count,2,&[]);
+        } else if count<=0xff_ffff {
+            Self::srec_record(&mut out,6,count,3,&[]);
+        }
+        Self::srec_record(&mut out,end_type,self.entry.unwrap_or(0)%limit,address_len,&[]);
+        Ok(out)
+    }
+
+    fn srec_record(out:&mut String,record_type:u8,address:u64,address_len:usize,data:&[u8]){
+        let mut bytes=vec![(address_len+data.len()+1) as u8];
+        bytes.extend_from_slice(&address.to_be_bytes()[8-address_len..]);
+        bytes.extend_from_slice(data);
+        let checksum=!bytes.iter().fold(0u8,|sum,b| sum.wrapping_add(*b));
+        let _=write!(out,"S{}",record_type);
+        for byte in bytes.iter().chain([checksum].iter()) {
+            let _=write!(out,"{:02X}",byte);
+        }
+        out.push('\n');
+    }
+
+    pub fn read_ihex(file_path:&str)->Result<FirmwareImage,ImageError>{
+        Self::parse_ihex(&fs::read_to_string(file_path).map_err(|e| ImageError::Io(e.to_string()))?)
+    }
+
+    pub fn parse_ihex(text:&str)->Result<FirmwareImage,ImageError>{
+        let mut chunks=Vec::new();
+        let mut base=0u64;
+        let mut entry=None;
+        for (i,line) in text.lines().enumerate().map(|(i,line)| (i+1,line.trim())) {
+            if line.is_empty() {
+                continue;
+            }
+            let bytes=line.strip_prefix(':').and_then(Self::parse_hex_bytes).ok_or(ImageError::Syntax(i))?;
+            if bytes.len()<5 || bytes.len()!=bytes[0] as usize+5 {
+                return Err(ImageError::Syntax(i));
+            }
+            if bytes.iter().fold(0u8,|sum,b| sum.wrapping_add(*b))!=0 {
+                return Err(ImageError::Checksum(i));
+            }
+            let address=u16::from_be_bytes([bytes[1],bytes[2]]) as u64;
+            let data=&bytes[4..bytes.len()-1];
+            let value=data.iter().fold(0u64,|value,b| value<<8|*b as u64);
+            match bytes[3] {
+                0 => chunks.push(ImageChunk{address:base+address,data:data.to_vec()}),
+                1 => break,
+                //扩展段地址:段值乘16
+                2 => base=value<<4,
+                //起始段地址CS:IP
+                3 => entry=Some((value>>16)*16+(value&0xffff)),
+                4 => base=value<<16,
+                5 => entry=Some(value),
+                _ => return Err(ImageError::Syntax(i)),
+            }
+        }
+        Ok(FirmwareImage::from_chunks(chunks,entry))
+    }
+
+    pub fn read_srec(file_path:&str)->Result<FirmwareImage,ImageError>{
+        Self::parse_srec(&fs::read_to_string(file_path).map_err(|e| ImageError::Io(e.to_string()))?)
+    }
+
+    pub fn parse_srec(text:&str)->Result<FirmwareImage,ImageError>{
+        let mut chunks=Vec::new();
+        let mut entry=None;
+        for (i,line) in text.lines().enumerate().map(|(i,line)| (i+1,line.trim())) {
+            if line.is_empty() {
+                continue;
+            }
+            let record_type=line.strip_prefix('S').and_then(|rest| rest.chars().next()).and_then(|c| c.to_digit(10)).ok_or(ImageError::Syntax(i))?;
+            let bytes=Self::parse_hex_bytes(&line[2..]).ok_or(ImageError::Syntax(i))?;
+            if bytes.is_empty() || bytes.len()!=bytes[0] as usize+1 {
+                return Err(ImageError::Syntax(i));
+            }
+            if bytes.iter().fold(0u8,|sum,b| sum.wrapping_add(*b))!=0xff {
+                return Err(ImageError::Checksum(i));
+            }
+            let address_len=match record_type {
+                0 | 1 | 5 | 9 => 2,
+                2 | 6 | 8 => 3,
+                3 | 7 => 4,
+                _ => return Err(ImageError::Syntax(i)),
+            };
+            if bytes.len()<address_len+2 {
+                return Err(ImageError::Syntax(i));
+            }
+            let address=bytes[1..1+address_len].iter().fold(0u64,|value,b| value<<8|*b as u64);
+            let data=&bytes[1+address_len..bytes.len()-1];
+            match record_type {
+                1..=3 => chunks.push(ImageChunk{address,data:data.to_vec()}),
+                7..=9 => entry=Some(address),
+                _ => {}
+            }
+        }
+        Ok(FirmwareImage::from_chunks(chunks,entry))
+    }
+
+    fn parse_hex_bytes(text:&str)->Option<Vec<u8>>{
+        if !text.len().is_multiple_of(2) || !text.is_ascii() {
+            return None;
+        }
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i+2],16).ok()).collect()
+    }
+
+    /// A minimal ET_EXEC holding the image: one `.secN` section and one PT_LOAD per
+    /// chunk at its address (virtual and physical alike), as `objcopy -I ihex` names them
+    pub fn to_elf(&self,class:Class,endian:AnyEndian,e_machine:u16)->Vec<u8>{
+        let mut header=ElfBuilder::new(class,endian,abi::ET_EXEC,e_machine).header;
+        let mut sections=vec![SectionHeader::new("",abi::SHT_NULL,0)];
+        sections[0].sh_addralign=0;
+        let mut program_headers=Vec::new();
+        let phdrs_size=self.chunks.len() as u64*header.e_phentsize as u64;
+        header.e_phoff=if self.chunks.is_empty() { 0 } else { header.e_ehsize as u64 };
+        header.e_phnum=self.chunks.len() as u16;
+        let mut data=vec![0u8;header.e_ehsize as usize+phdrs_size as usize];
+        for (i,chunk) in self.chunks.iter().enumerate() {
+            //文件偏移与地址模4同余,p_align取4
+            let offset=data.len() as u64+(chunk.address.wrapping_sub(data.len() as u64)&3);
+            data.resize(offset as usize,0);
+            data.extend_from_slice(&chunk.data);
+            let mut section=SectionHeader::new(&format!(".sec{}",i+1),abi::SHT_PROGBITS,abi::SHF_ALLOC|abi::SHF_WRITE|abi::SHF_EXECINSTR);
+            section.sh_addr=chunk.address;
+            section.sh_offset=offset;
+            section.sh_size=chunk.data.len() as u64;
+            sections.push(section);
+            program_headers.push(ProgramHeader{
+                p_type:abi::PT_LOAD,
+                p_offset:offset,
+                p_vaddr:chunk.address,
+                p_paddr:chunk.address,
+                p_filesz:chunk.data.len() as u64,
+                p_memsz:chunk.data.len() as u64,
+                p_flags:abi::PF_R|abi::PF_W|abi::PF_X,
+                p_align:4,
+            });
+        }
+        sections.push(SectionHeader::new(".shstrtab",abi::SHT_STRTAB,0));
+        let mut names=vec![0u8];
+        for (i,section) in sections.iter_mut().enumerate() {
+            section.index=i as u16;
+            if i!=0 {
+                section.sh_name=names.len() as u32;
+                names.extend_from_slice(section.string_name.as_bytes());
+                names.push(0);
+            }
+        }
+        let shstrndx=sections.len()-1;
+        sections[shstrndx].sh_offset=data.len() as u64;
+        sections[shstrndx].sh_size=names.len() as u64;
+        data.extend_from_slice(&names);
+        let word=class.word_size();
+        header.e_shoff=data.len().next_multiple_of(word) as u64;
+        header.e_shnum=sections.len() as u16;
+        header.e_shstrndx=shstrndx as u16;
+        header.e_entry=self.entry.unwrap_or_else(|| self.start());
+        data.resize(header.e_shoff as usize,0);
+        ElfFile{
+            data,
+            header,
+            program_headers,
+            section_headers:sections,
+        }.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: [u8;16] = [0x21,0x46,0x01,0x36,0x01,0x21,0x47,0x01,0x36,0x00,0x7e,0xfe,0x09,0xd2,0x19,0x01];
+
+    fn image(address:u64,data:&[u8],entry:Option<u64>)->FirmwareImage{
+        FirmwareImage::from_chunks(vec![ImageChunk{address,data:data.to_vec()}],entry)
+    }
+
+    #[test]
+    fn ihex_records(){
+        let hex=image(0x100,&DATA,None).to_ihex().unwrap();
+        assert_eq!(hex,":10010000214601360121470136007EFE09D2190140\n:00000001FF\n");
+        let hex=image(0x0800_fffe,&[0xaa,0xbb,0xcc],Some(0x0800_0101)).to_ihex().unwrap();
+        assert_eq!(hex,concat!(
+            ":020000040800F2\n",
+            ":02FFFE00AABB9C\n",
+            ":020000040801F1\n",
+            ":01000000CC33\n",
+            ":0400000508000101ED\n",
+            ":00000001FF\n",
+        ));
+    }
+
+    #[test]
+    fn ihex_round_trip(){
+        let original=FirmwareImage::from_chunks(vec![
+            ImageChunk{address:0x0800_0000,data:(0..=255).collect()},
+            ImageChunk{address:0x2000_0000,data:vec![1,2,3]},
+        ],Some(0x0800_0041));
+        assert_eq!(FirmwareImage::parse_ihex(&original.to_ihex().unwrap()).unwrap(),original);
+    }
+
+    #[test]
+    fn ihex_errors(){
+        assert_eq!(FirmwareImage::parse_ihex(":10010000214601360121470136007EFE09D2190141"),Err(ImageError::Checksum(1)));
+        assert_eq!(FirmwareImage::parse_ihex(":00000001FF\n:0100"),Ok(FirmwareImage::default()));
+        assert_eq!(FirmwareImage::parse_ihex("\n:0100000000"),Err(ImageError::Syntax(2)));
+        assert!(matches!(image(0xffff_ffff,&[0,0],None).to_ihex(),Err(ImageError::AddressRange(_))));
+    }
+
+    #[test]
+    fn srec_records(){
+        let data=[0x28,0x5f,0x24,0x5f,0x22,0x12,0x22,0x6a,0x00,0x04,0x24,0x29,0x00,0x08,0x23,0x7c];
+        let srec=image(0,&data,None).to_srec(None).unwrap();
+        assert_eq!(srec,"S0030000FC\nS1130000285F245F2212226A000424290008237C2A\nS5030001FB\nS9030000FC\n");
+        let srec=image(0x10_0000,&[0xab],Some(0x10_0000)).to_srec(None).unwrap();
+        assert_eq!(srec,"S0030000FC\nS205100000AB3F\nS5030001FB\nS804100000EB\n");
+        assert!(matches!(image(0x1_0000,&[0],None).to_srec(Some(SrecFormat::S19)),Err(ImageError::AddressRange(_))));
+    }
+
+    #[test]
+    fn srec_round_trip(){
+        for format in [SrecFormat::S19,SrecFormat::S28,SrecFormat::S37] {
+            let original=image(0x1234,&DATA.repeat(5),Some(0x1234));
+            assert_eq!(FirmwareImage::parse_srec(&original.to_srec(Some(format)).unwrap()).unwrap(),original);
+        }
+    }
+
+    #[test]
+    fn srec_errors(){
+        assert_eq!(FirmwareImage::parse_srec("S0030000FC\nS1130000285F245F2212226A000424290008237C2B"),Err(ImageError::Checksum(2)));
+        assert_eq!(FirmwareImage::parse_srec("S4030000FC"),Err(ImageError::Syntax(1)));
+        assert_eq!(FirmwareImage::parse_srec("X1030000FC"),Err(ImageError::Syntax(1)));
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use crate::parser::abi;
+use crate::parser::elf_header::FileHeader;
+use crate::parser::file;
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::section::SectionHeader;
+use crate::parser::symbol::Symbol;
+
+/// One GOT slot that the dynamic linker fills through a GLOB_DAT or JUMP_SLOT relocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GotEntry {
+    /// Virtual address of the GOT slot (the relocation's r_offset)
+    pub got_addr: u64,
+    /// Name of the section holding the slot, usually `.got` or `.got.plt`
+    pub section: String,
+    /// Name of the imported symbol
+    pub symbol: String,
+    /// Index of the symbol in `.dynsym`
+    pub sym_index: u32,
+    /// Relocation type that fills the slot
+    pub r_type: u32,
+    /// Address of the PLT stub which jumps through this slot (JUMP_SLOT only)
+    pub plt_addr: Option<u64>,
+}
+
+/// Map between GOT slots, PLT stubs and the symbols they resolve to
+#[derive(Debug, Clone, Default)]
+pub struct GotMap {
+    pub entries: Vec<GotEntry>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_addr: HashMap<u64, usize>,
+}
+
+impl GotMap {
+    /// Read the relocation tables of a file and build its map. Missing tables count as
+    /// empty, so a file without `.rela.plt` still lists its `.got` entries.
+    pub fn read_got(file_path:&str,section_headers:Vec<SectionHeader>,symbols:&[Symbol],binary_header:FileHeader)->Option<GotMap>{
+        //-z now且没有延迟绑定时可能没有.rela.plt,.rela.dyn不存在时(例如全部走plt)同样按空处理
+        let plt_relocs=Self::read_relocs(file_path,&section_headers,&[".rela.plt",".rel.plt"],binary_header)
+            .unwrap_or_default();
+        let dyn_relocs=Self::read_relocs(file_path,&section_headers,&[".rela.dyn",".rel.dyn"],binary_header)
+            .unwrap_or_default();
+        Some(Self::parse_got(binary_header,&section_headers,symbols,&plt_relocs,&dyn_relocs))
+    }
+
+    /// Build the map from already parsed relocation tables.
+    ///
+    /// PLT stubs are numbered in the order of the JUMP_SLOT relocations, which is the
+    /// order the static linker emits both `.rela.plt` and the PLT entries in.
+    pub fn parse_got(binary_header:FileHeader,section_headers:&[SectionHeader],symbols:&[Symbol],
+                     plt_relocs:&[Rela],dyn_relocs:&[Rela])->GotMap{
+        let (glob_dat,jump_slot)=Self::slot_types(binary_header.e_machine);
+        let plt_base=Self::plt_base(binary_header.e_machine,section_headers);
+        let mut map=GotMap::default();
+
+        let mut plt_index:u64=0;
+        for rela in plt_relocs{
+            //.rela.plt里的IRELATIVE等其他类型没有对应的PLT桩,不计入序号
+            if rela.r_type!=jump_slot{
+                continue;
+            }
+            let plt_addr=plt_base.map(|(start,entry_size)| start+plt_index*entry_size);
+            plt_index+=1;
+            map.push(Self::entry(rela,section_headers,symbols,plt_addr));
+        }
+        for rela in dyn_relocs{
+            if rela.r_type==glob_dat{
+                map.push(Self::entry(rela,section_headers,symbols,None));
+            }
+        }
+        map
+    }
+
+    /// Look up every slot bound to `name`
+    pub fn find_by_name(&self,name:&str)->Vec<&GotEntry>{
+        match self.by_name.get(name) {
+            Some(indexes) => indexes.iter().map(|&i| &self.entries[i]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Look up a slot by the address of either the GOT slot or its PLT stub
+    pub fn find_by_addr(&self,addr:u64)->Option<&GotEntry>{
+        self.by_addr.get(&addr).map(|&i| &self.entries[i])
+    }
+
+    fn push(&mut self,entry:GotEntry){
+        let idx=self.entries.len();
+        self.by_name.entry(entry.symbol.clone()).or_default().push(idx);
+        self.by_addr.insert(entry.got_addr,idx);
+        if let Some(plt_addr)=entry.plt_addr{
+            self.by_addr.insert(plt_addr,idx);
+        }
+        self.entries.push(entry);
+    }
+
+    fn entry(rela:&Rela,section_headers:&[SectionHeader],symbols:&[Symbol],plt_addr:Option<u64>)->GotEntry{
+        let section=section_headers.iter()
+            .find(|sh| sh.sh_addr!=0 && rela.r_offset>=sh.sh_addr && rela.r_offset<sh.sh_addr+sh.sh_size)
+            .map(|sh| sh.string_name.clone())
+            .unwrap_or_default();
+        let symbol=symbols.get(rela.r_sym as usize)
+            .map(|sym| sym.string_name.clone())
+            .unwrap_or_default();
+        GotEntry{
+            got_addr:rela.r_offset,
+            section,
+            symbol,
+            sym_index:rela.r_sym,
+            r_type:rela.r_type,
+            plt_addr,
+        }
+    }
+
+    //各架构GLOB_DAT/JUMP_SLOT的重定位类型
+    fn slot_types(e_machine:u16)->(u32,u32){
+        match e_machine {
+            abi::EM_386 => (abi::R_386_GLOB_DAT,abi::R_386_JMP_SLOT),
+            abi::EM_ARM => (abi::R_ARM_GLOB_DAT,abi::R_ARM_JUMP_SLOT),
+            abi::EM_AARCH64 => (abi::R_AARCH64_GLOB_DAT,abi::R_AARCH64_JUMP_SLOT),
+            _ => (abi::R_X86_64_GLOB_DAT,abi::R_X86_64_JUMP_SLOT),
+        }
+    }
+
+    /// Address of the first PLT stub and the size of one stub.
+    ///
+    /// With IBT enabled (`.plt.sec`) x86 keeps the lazy-binding trampolines in `.plt`
+    /// and the stubs the code calls in `.plt.sec`, which has no header entry.
+    fn plt_base(e_machine:u16,section_headers:&[SectionHeader])->Option<(u64,u64)>{
+        let find=|name:&str| section_headers.iter().find(|sh| sh.string_name==name);
+        let (header_size,entry_size)=match e_machine {
+            abi::EM_386 | abi::EM_X86_64 => {
+                if let Some(plt_sec)=find(".plt.sec"){
+                    return Some((plt_sec.sh_addr,16));
+                }
+                (16,16)
+            }
+            abi::EM_ARM => (20,12),
+            abi::EM_AARCH64 => (32,16),
+            _ => return None,
+        };
+        let plt=find(".plt")?;
+        Some((plt.sh_addr+header_size,entry_size))
+    }
+
+    //按名字依次尝试读取REL/RELA重定位节,REL表按addend为0处理
+    fn read_relocs(file_path:&str,section_headers:&[SectionHeader],names:&[&str],binary_header:FileHeader)->Option<Vec<Rela>>{
+        let idents=(binary_header.endianness,binary_header.class);
+        for name in names{
+            let Some(sh)=section_headers.iter().find(|sh| sh.string_name==*name) else {
+                continue;
+            };
+            let bytes=file::file_utils::read_file_range(file_path,sh.sh_offset,sh.sh_offset.checked_add(sh.sh_size)?).ok()?;
+            return match sh.sh_type {
+                abi::SHT_RELA => Some(Rela::parse(idents,&bytes,sh.sh_size)),
+                abi::SHT_REL => Some(Rel::parse(idents,&bytes,sh.sh_size).into_iter().map(Rela::from).collect()),
+                _ => None,
+            };
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::elf_file::ElfFile;
+
+    fn relocs(elf:&ElfFile,name:&str)->Vec<Rela>{
+        let sh=elf.find_section(name).unwrap();
+        Rela::parse(elf.ident(),elf.section_data(sh).unwrap(),sh.sh_size)
+    }
+
+    #[test]
+    fn slots_and_stubs(){
+        let elf=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        let symbols=elf.dynamic_symbols();
+        let map=GotMap::parse_got(elf.header,&elf.section_headers,&symbols,&relocs(&elf,".rela.plt"),&relocs(&elf,".rela.dyn"));
+        //11个JUMP_SLOT加5个GLOB_DAT,RELATIVE不算
+        assert_eq!(map.entries.len(),16);
+
+        //.plt在0x1020,跳过16字节的头,第二个桩是puts
+        let puts=map.find_by_name("puts");
+        assert_eq!(puts.len(),1);
+        assert_eq!(puts[0],&GotEntry{got_addr:0x4008,section:".got.plt".to_string(),symbol:"puts".to_string(),sym_index:4,
+                                     r_type:abi::R_X86_64_JUMP_SLOT,plt_addr:Some(0x1040)});
+        assert_eq!(map.find_by_addr(0x1040),Some(puts[0]));
+        assert_eq!(map.find_by_addr(0x4008),Some(puts[0]));
+        assert_eq!(map.find_by_addr(0x10d0).map(|entry| entry.symbol.as_str()),Some("fwrite"));
+
+        let finalize=map.find_by_addr(0x3fe0).unwrap();
+        assert_eq!((finalize.symbol.as_str(),finalize.section.as_str(),finalize.r_type,finalize.plt_addr),
+                   ("__cxa_finalize",".got",abi::R_X86_64_GLOB_DAT,None));
+        assert!(map.find_by_addr(0x4060).is_none());
+        assert!(map.find_by_name("main").is_empty());
+    }
+
+    //-z now时没有.rela.plt,只剩.got里的GLOB_DAT
+    #[test]
+    fn without_plt_relocations(){
+        let elf=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        let map=GotMap::parse_got(elf.header,&elf.section_headers,&elf.dynamic_symbols(),&[],&relocs(&elf,".rela.dyn"));
+        assert_eq!(map.entries.len(),5);
+        assert!(map.entries.iter().all(|entry| entry.section==".got" && entry.plt_addr.is_none()));
+        assert_eq!(map.find_by_name("__libc_start_main")[0].got_addr,0x3fc0);
+    }
+}
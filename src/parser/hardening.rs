@@ -0,0 +1,279 @@
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::section::SectionHeader;
+use crate::parser::symbol::Symbol;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Some protection is present but not all of it, e.g. partial RELRO
+    Partial,
+    Fail,
+}
+
+/// Outcome of a single hardening check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub status: CheckStatus,
+    /// Short explanation, e.g. `full RELRO` or the offending RPATH
+    pub detail: String,
+}
+
+/// checksec-style summary of the exploit mitigations a binary was built with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardeningReport {
+    /// PT_GNU_RELRO, plus immediate binding for full RELRO
+    pub relro: Check,
+    /// Non-executable stack from PT_GNU_STACK
+    pub nx: Check,
+    /// ET_DYN with an interpreter (or DF_1_PIE)
+    pub pie: Check,
+    /// Imports `__stack_chk_fail`
+    pub canary: Check,
+    /// Imports `*_chk` functions from _FORTIFY_SOURCE
+    pub fortify: Check,
+    /// No relocations against read-only segments
+    pub textrel: Check,
+    /// No DT_RPATH or DT_RUNPATH
+    pub rpath: Check,
+    /// No PT_LOAD segment that is both writable and executable
+    pub segments: Check,
+}
+
+//有_chk版本的常见libc函数
+const FORTIFIABLE: &[&str] = &[
+    "memcpy", "memmove", "mempcpy", "memset", "strcpy", "stpcpy", "strncpy", "stpncpy",
+    "strcat", "strncat", "sprintf", "snprintf", "vsprintf", "vsnprintf", "printf", "fprintf",
+    "vprintf", "vfprintf", "dprintf", "vdprintf", "asprintf", "vasprintf", "gets", "fgets",
+    "read", "pread", "pread64", "recv", "recvfrom", "readlink", "readlinkat", "getcwd",
+    "realpath", "wcscpy", "wmemcpy", "wmemset", "fread", "poll", "ppoll", "syslog", "vsyslog",
+];
+
+impl Check {
+    fn new(status:CheckStatus,detail:&str)->Check{
+        Check{status,detail:detail.to_string()}
+    }
+}
+
+impl HardeningReport {
+    pub fn read_report(file_path:&str)->Option<HardeningReport>{
+        let elf=ElfFile::read_elf(file_path)?;
+        Some(Self::parse_report(&elf))
+    }
+
+    pub fn parse_report(elf:&ElfFile)->HardeningReport{
+        let dyns=elf.dynamic();
+        //动态链接看.dynsym的未定义符号,静态链接看.symtab
+        let mut symbols=elf.dynamic_symbols();
+        if let Some(symtab)=elf.find_section_by_type(abi::SHT_SYMTAB) {
+            symbols.extend(elf.symbols(symtab));
+        }
+        let strtab=elf.dynamic_strings().unwrap_or(&[]);
+        HardeningReport{
+            relro:Self::check_relro(elf,&dyns),
+            nx:Self::check_nx(elf),
+            pie:Self::check_pie(elf,&dyns),
+            canary:Self::check_canary(&symbols),
+            fortify:Self::check_fortify(&symbols),
+            textrel:Self::check_textrel(&dyns),
+            rpath:Self::check_rpath(&dyns,strtab),
+            segments:Self::check_segments(elf),
+        }
+    }
+
+    /// Every check with its display name, in checksec order
+    pub fn checks(&self)->[(&'static str,&Check);8]{
+        [
+            ("RELRO",&self.relro),
+            ("NX",&self.nx),
+            ("PIE",&self.pie),
+            ("Canary",&self.canary),
+            ("FORTIFY",&self.fortify),
+            ("TEXTREL",&self.textrel),
+            ("RPATH",&self.rpath),
+            ("RWX segments",&self.segments),
+        ]
+    }
+
+    pub fn check_relro(elf:&ElfFile,dyns:&[Dyn])->Check{
+        if !elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_GNU_RELRO) {
+            return Check::new(CheckStatus::Fail,"no RELRO");
+        }
+        let bind_now=Dyn::find(dyns,abi::DT_BIND_NOW).is_some()
+            || Dyn::find(dyns,abi::DT_FLAGS).is_some_and(|flags| flags&abi::DF_BIND_NOW!=0)
+            || Dyn::find(dyns,abi::DT_FLAGS_1).is_some_and(|flags| flags&abi::DF_1_NOW!=0);
+        if bind_now {
+            Check::new(CheckStatus::Pass,"full RELRO")
+        } else {
+            Check::new(CheckStatus::Partial,"partial RELRO")
+        }
+    }
+
+    //没有PT_GNU_STACK时大多数架构默认栈可执行
+    pub fn check_nx(elf:&ElfFile)->Check{
+        match elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_GNU_STACK) {
+            Some(ph) if ph.p_flags&abi::PF_X==0 => Check::new(CheckStatus::Pass,"NX enabled"),
+            Some(_) => Check::new(CheckStatus::Fail,"executable stack"),
+            None => Check::new(CheckStatus::Fail,"no PT_GNU_STACK"),
+        }
+    }
+
+    pub fn check_pie(elf:&ElfFile,dyns:&[Dyn])->Check{
+        if elf.header.e_type!=abi::ET_DYN {
+            return Check::new(CheckStatus::Fail,"not PIE");
+        }
+        let interp=elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_INTERP);
+        let pie_flag=Dyn::find(dyns,abi::DT_FLAGS_1).is_some_and(|flags| flags&abi::DF_1_PIE!=0);
+        if interp || pie_flag {
+            Check::new(CheckStatus::Pass,"PIE enabled")
+        } else {
+            Check::new(CheckStatus::Pass,"shared object")
+        }
+    }
+
+    pub fn check_canary(symbols:&[Symbol])->Check{
+        if symbols.iter().any(|sym| sym.string_name=="__stack_chk_fail" || sym.string_name=="__stack_chk_guard") {
+            Check::new(CheckStatus::Pass,"canary found")
+        } else {
+            Check::new(CheckStatus::Fail,"no canary found")
+        }
+    }
+
+    /// Pass when only fortified variants are imported, partial when both
+    /// `foo` and `__foo_chk` appear, fail when fortifiable calls are unprotected
+    pub fn check_fortify(symbols:&[Symbol])->Check{
+        let imports:Vec<&str>=symbols.iter()
+            .filter(|sym| sym.st_shndx==abi::SHN_UNDEF)
+            .map(|sym| sym.string_name.as_str())
+            .collect();
+        let fortified:Vec<&str>=imports.iter().copied()
+            .filter(|name| name.starts_with("__") && name.ends_with("_chk") && *name!="__stack_chk_fail")
+            .collect();
+        let unfortified:Vec<&str>=imports.iter().copied()
+            .filter(|name| FORTIFIABLE.contains(name))
+            .collect();
+        match (fortified.is_empty(),unfortified.is_empty()) {
+            (true,true) => Check::new(CheckStatus::Pass,"no fortifiable calls"),
+            (false,true) => Check::new(CheckStatus::Pass,&format!("{} fortified",fortified.len())),
+            (false,false) => Check::new(CheckStatus::Partial,
+                &format!("{} fortified, {} fortifiable",fortified.len(),unfortified.len())),
+            (true,false) => Check::new(CheckStatus::Fail,&format!("{} fortifiable",unfortified.len())),
+        }
+    }
+
+    pub fn check_textrel(dyns:&[Dyn])->Check{
+        let textrel=Dyn::find(dyns,abi::DT_TEXTREL).is_some()
+            || Dyn::find(dyns,abi::DT_FLAGS).is_some_and(|flags| flags&abi::DF_TEXTREL!=0);
+        if textrel {
+            Check::new(CheckStatus::Fail,"text relocations")
+        } else {
+            Check::new(CheckStatus::Pass,"no text relocations")
+        }
+    }
+
+    /// DT_RPATH is searched before LD_LIBRARY_PATH and fails the check;
+    /// DT_RUNPATH can still be overridden and only counts as partial
+    pub fn check_rpath(dyns:&[Dyn],strtab:&[u8])->Check{
+        let path=|tag| Dyn::find(dyns,tag)
+            .map(|offset| SectionHeader::parse_string_at(strtab,offset as usize).unwrap_or_default());
+        if let Some(rpath)=path(abi::DT_RPATH) {
+            return Check::new(CheckStatus::Fail,&format!("RPATH {}",rpath));
+        }
+        if let Some(runpath)=path(abi::DT_RUNPATH) {
+            return Check::new(CheckStatus::Partial,&format!("RUNPATH {}",runpath));
+        }
+        Check::new(CheckStatus::Pass,"no RPATH/RUNPATH")
+    }
+
+    pub fn check_segments(elf:&ElfFile)->Check{
+        let rwx:Vec<String>=elf.program_headers.iter()
+            .filter(|ph| ph.p_type==abi::PT_LOAD && ph.p_flags&abi::PF_W!=0 && ph.p_flags&abi::PF_X!=0)
+            .map(|ph| format!("{:#x}",ph.p_vaddr))
+            .collect();
+        if rwx.is_empty() {
+            Check::new(CheckStatus::Pass,"no RWX segments")
+        } else {
+            Check::new(CheckStatus::Fail,&format!("RWX segment at {}",rwx.join(", ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn de()->ElfFile{
+        ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap()
+    }
+
+    fn status(report:&HardeningReport)->Vec<(&'static str,CheckStatus)>{
+        report.checks().iter().map(|(name,check)| (*name,check.status)).collect()
+    }
+
+    #[test]
+    fn de_report(){
+        let report=HardeningReport::parse_report(&de());
+        assert_eq!(status(&report),[
+            ("RELRO",CheckStatus::Partial),
+            ("NX",CheckStatus::Pass),
+            ("PIE",CheckStatus::Pass),
+            ("Canary",CheckStatus::Fail),
+            ("FORTIFY",CheckStatus::Fail),
+            ("TEXTREL",CheckStatus::Pass),
+            ("RPATH",CheckStatus::Pass),
+            ("RWX segments",CheckStatus::Pass),
+        ]);
+        //printf和fread有_chk版本
+        assert_eq!(report.fortify.detail,"2 fortifiable");
+        assert_eq!(report.pie.detail,"PIE enabled");
+    }
+
+    //改写程序头:可执行栈、RWX的PT_LOAD、去掉RELRO、ET_EXEC
+    #[test]
+    fn weakened_segments(){
+        let mut elf=de();
+        for ph in elf.program_headers.iter_mut() {
+            if ph.p_type==abi::PT_GNU_STACK || (ph.p_type==abi::PT_LOAD && ph.p_vaddr==0x3dc0) {
+                ph.p_flags|=abi::PF_X;
+            }
+        }
+        elf.program_headers.retain(|ph| ph.p_type!=abi::PT_GNU_RELRO);
+        elf.header.e_type=abi::ET_EXEC;
+        let report=HardeningReport::parse_report(&elf);
+        assert_eq!(report.nx,Check::new(CheckStatus::Fail,"executable stack"));
+        assert_eq!(report.segments,Check::new(CheckStatus::Fail,"RWX segment at 0x3dc0"));
+        assert_eq!(report.relro,Check::new(CheckStatus::Fail,"no RELRO"));
+        assert_eq!(report.pie,Check::new(CheckStatus::Fail,"not PIE"));
+
+        elf.program_headers.retain(|ph| ph.p_type!=abi::PT_GNU_STACK);
+        assert_eq!(HardeningReport::check_nx(&elf).detail,"no PT_GNU_STACK");
+    }
+
+    #[test]
+    fn dynamic_checks(){
+        let elf=de();
+        let entry=|d_tag:i64,d_val:u64| Dyn{d_tag,d_val};
+        assert_eq!(HardeningReport::check_relro(&elf,&[entry(abi::DT_FLAGS,abi::DF_BIND_NOW)]).status,CheckStatus::Pass);
+        assert_eq!(HardeningReport::check_relro(&elf,&[entry(abi::DT_FLAGS_1,abi::DF_1_NOW)]).status,CheckStatus::Pass);
+        assert_eq!(HardeningReport::check_textrel(&[entry(abi::DT_FLAGS,abi::DF_TEXTREL)]).status,CheckStatus::Fail);
+        let strtab=b"\0/opt/lib\0$ORIGIN\0";
+        assert_eq!(HardeningReport::check_rpath(&[entry(abi::DT_RUNPATH,10),entry(abi::DT_RPATH,1)],strtab),
+                   Check::new(CheckStatus::Fail,"RPATH /opt/lib"));
+        assert_eq!(HardeningReport::check_rpath(&[entry(abi::DT_RUNPATH,10)],strtab),Check::new(CheckStatus::Partial,"RUNPATH $ORIGIN"));
+    }
+
+    #[test]
+    fn imported_functions(){
+        let mut symbols=de().dynamic_symbols();
+        let rename=|symbols:&mut Vec<Symbol>,from:&str,to:&str| {
+            symbols.iter_mut().find(|symbol| symbol.string_name==from).unwrap().string_name=to.to_string();
+        };
+        rename(&mut symbols,"puts","__stack_chk_fail");
+        rename(&mut symbols,"printf","__printf_chk");
+        assert_eq!(HardeningReport::check_canary(&symbols).status,CheckStatus::Pass);
+        assert_eq!(HardeningReport::check_fortify(&symbols),Check::new(CheckStatus::Partial,"1 fortified, 1 fortifiable"));
+        rename(&mut symbols,"fread","__fread_chk");
+        assert_eq!(HardeningReport::check_fortify(&symbols),Check::new(CheckStatus::Pass,"2 fortified"));
+    }
+}
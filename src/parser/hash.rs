@@ -60,8 +60,11 @@ impl hash {
 
 
         //读取hash表
-        let hash_bytes=file::file_utils::read_file_range(file_path,e_hash_offset,e_hash_offset+e_hash_size);
-        let hash_tables=hash::parser_hash_tables(idents,&hash_bytes.unwrap());
+        let hash_bytes=file::file_utils::read_file_range(file_path,e_hash_offset,e_hash_offset.checked_add(e_hash_size)?).ok()?;
+        if hash_bytes.len()<16{
+            return None;
+        }
+        let hash_tables=hash::parser_hash_tables(idents,&hash_bytes);
         println!("{:?}",hash_tables);
         return Some(hash_tables);
     }
@@ -72,8 +75,30 @@ impl hash {
         }
         hash
     }
+    /// The SysV ELF hash used by `.hash` and for version names in `.gnu.version_d`
+    pub fn elf_hash(name: &[u8]) -> u32 {
+        let mut hash = 0u32;
+        for byte in name {
+            hash = (hash << 4).wrapping_add(u32::from(*byte));
+            let high = hash & 0xf000_0000;
+            if high != 0 {
+                hash ^= high >> 24;
+            }
+            hash &= !high;
+        }
+        hash
+    }
     //跟据符号名 寻找符号表
     pub fn find(&self,symbol_table:Vec<Symbol>,name:&[u8],class:Class)->Option<(usize, Symbol)>{
+        self.find_by(&symbol_table,name,class,|_| true)
+    }
+    /// Version-aware lookup: `version` None finds the default version, as the dynamic
+    /// linker does for unversioned references; the hash chain holds every version of a name.
+    pub fn find_versioned(&self,symbol_table:&[Symbol],name:&[u8],version:Option<&str>,class:Class)->Option<(usize, Symbol)>{
+        self.find_by(symbol_table,name,class,|symbol| symbol.matches_version(version))
+    }
+    fn find_by<F>(&self,symbol_table:&[Symbol],name:&[u8],class:Class,accept:F)->Option<(usize, Symbol)>
+        where F: Fn(&Symbol) -> bool{
 
         if self.buckets.is_empty() || self.bloom_size == 0 {
             return None;
@@ -84,12 +109,12 @@ impl hash {
             Class::ELF32 => {
                 let bloom_width: u32 = 8 * size_of::<u32>() as u32; // 32
                 let bloom_idx = (hash / (bloom_width)) % self.bloom_size;
-                (bloom_width, *self.bloom.get(bloom_idx as usize).unwrap())
+                (bloom_width, *self.bloom.get(bloom_idx as usize)?)
             }
             Class::ELF64 => {
                 let bloom_width: u32 = 8 * size_of::<u64>() as u32; // 64
                 let bloom_idx = (hash / (bloom_width)) % self.bloom_size;
-                (bloom_width, *self.bloom.get(bloom_idx as usize).unwrap())
+                (bloom_width, *self.bloom.get(bloom_idx as usize)?)
             }
             _=>{
                 let bloom_width=0;
@@ -114,13 +139,13 @@ impl hash {
 
         let chain_len = self.chains.len();
         for chain_idx in (chain_start_idx - table_start_idx)..chain_len {
-            let chain_hash = *self.chains.get(chain_idx).unwrap();
+            let chain_hash = *self.chains.get(chain_idx)?;
 
             if hash | 1 == (chain_hash | 1) as u32 {
                 let sym_idx = chain_idx+table_start_idx;
-                let symbol = symbol_table.get(sym_idx).unwrap();
+                let symbol = symbol_table.get(sym_idx)?;
 
-                if symbol.string_name == String::from_utf8(name.to_vec()).unwrap() {
+                if symbol.string_name.as_bytes() == name && accept(symbol) {
                     return (Some((sym_idx, symbol.clone())));
                 }
             }
@@ -138,7 +163,7 @@ impl hash {
         let mut offset:usize=0;
         const U64SIZE: usize = core::mem::size_of::<u64>();
         const U32SIZE: usize = core::mem::size_of::<u32>();
-        while  offset<(buf_size as usize){
+        while  offset<(buf_size as usize) && offset+esize<=bytes.len(){
             let ele = match esize {
                 U32SIZE => endian.parse_u32_at(offset,bytes) as u64,
                 U64SIZE => endian.parse_u64_at(offset,bytes),
@@ -165,24 +190,25 @@ impl hash {
         offset+=U32SIZE;
         let  bloom_shift=endian.parse_u32_at(offset,hash_bytes);
         offset+=U32SIZE;
+        //畸形的bloom_size/nbucket会越界,越界的部分按空表处理
         let bloom_len = match class {
-            Class::ELF32 => bloom_size*(U32SIZE as u32),
-            Class::ELF64 => bloom_size*(U64SIZE as u32),
+            Class::ELF32 => bloom_size.saturating_mul(U32SIZE as u32),
+            Class::ELF64 => bloom_size.saturating_mul(U64SIZE as u32),
         };
-        let bloom_end:usize= (bloom_len + (offset as u32)) as usize;
-        let buckets_buf = hash_bytes.get(offset..bloom_end).unwrap();
+        let bloom_end:usize= (bloom_len as usize + offset).min(hash_bytes.len());
+        let buckets_buf = hash_bytes.get(offset..bloom_end).unwrap_or(&[]);
         let bloom = match class {
             Class::ELF32 =>Self::parser_buf_vec(U32SIZE,buckets_buf,endian,bloom_len),
             Class::ELF64 =>Self::parser_buf_vec(U64SIZE,buckets_buf,endian,bloom_len),
         };
         offset=bloom_end;
 
-        let buckets_size:u32 = (nbucket * (U32SIZE as u32)) as u32;
-        let buckets_end:usize = offset+(buckets_size as usize);
-        let buckets_buf = hash_bytes.get(offset..buckets_end).unwrap();
+        let buckets_size:u32 = nbucket.saturating_mul(U32SIZE as u32);
+        let buckets_end:usize = (offset+(buckets_size as usize)).min(hash_bytes.len());
+        let buckets_buf = hash_bytes.get(offset..buckets_end).unwrap_or(&[]);
         let buckets = Self::parser_buf_vec(U32SIZE,buckets_buf,endian,buckets_size);
         offset = buckets_end;
-        let chains_buf = hash_bytes.get(offset..).unwrap();
+        let chains_buf = hash_bytes.get(offset..).unwrap_or(&[]);
         let chains_size=hash_bytes.len()-offset;
         let chains = Self::parser_buf_vec(U32SIZE, chains_buf, endian, chains_size as u32);
 
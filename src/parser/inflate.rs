@@ -0,0 +1,362 @@
+/// Errors from decoding a DEFLATE stream or its gzip wrapper
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    /// The input ends inside a block
+    Truncated,
+    /// Block type 3
+    BlockType,
+    /// A stored block whose LEN and NLEN do not match
+    StoredLength,
+    /// Over-subscribed or incomplete code lengths, or an unassigned code in the data
+    Code,
+    /// A back-reference before the start of the output
+    Distance,
+    /// Bad gzip header
+    Header,
+    /// CRC-32 or length in the gzip trailer does not match
+    Checksum,
+    /// The output would grow past the caller's limit
+    TooLarge,
+}
+
+//长度码257..285的基础值与额外位数
+const LENGTH_BASE: [u16; 29] = [3,4,5,6,7,8,9,10,11,13,15,17,19,23,27,31,35,43,51,59,67,83,99,115,131,163,195,227,258];
+const LENGTH_EXTRA: [u8; 29] = [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2,3,3,3,3,4,4,4,4,5,5,5,5,0];
+const DISTANCE_BASE: [u16; 30] = [1,2,3,4,5,7,9,13,17,25,33,49,65,97,129,193,257,385,513,769,1025,1537,2049,3073,4097,6145,8193,12289,16385,24577];
+const DISTANCE_EXTRA: [u8; 30] = [0,0,0,0,1,1,2,2,3,3,4,4,5,5,6,6,7,7,8,8,9,9,10,10,11,11,12,12,13,13];
+//动态块中码长的码长按这个顺序给出
+const CODE_LENGTH_ORDER: [usize; 19] = [16,17,18,0,8,7,9,6,10,5,11,4,12,3,13,2,14,1,15];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    //DEFLATE从每个字节的最低位开始取
+    fn bits(&mut self,need:u32)->Result<u32,InflateError>{
+        while self.count<need {
+            let byte=*self.data.get(self.position).ok_or(InflateError::Truncated)?;
+            self.position+=1;
+            self.bits|=(byte as u32)<<self.count;
+            self.count+=8;
+        }
+        let value=self.bits&((1u64<<need)-1) as u32;
+        self.bits>>=need;
+        self.count-=need;
+        Ok(value)
+    }
+
+    fn align(&mut self){
+        self.bits=0;
+        self.count=0;
+    }
+}
+
+//规范霍夫曼码:各长度的码数与按码值排好的符号
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths:&[u8])->Result<Huffman,InflateError>{
+        let mut counts=[0u16;16];
+        for &length in lengths {
+            counts[length as usize]+=1;
+        }
+        //码长不能超额;距离码允许只有一个码
+        let mut left=1i32;
+        for &count in &counts[1..] {
+            left=left*2-count as i32;
+            if left<0 {
+                return Err(InflateError::Code);
+            }
+        }
+        let mut offsets=[0u16;16];
+        for i in 1..15 {
+            offsets[i+1]=offsets[i]+counts[i];
+        }
+        let mut symbols=vec![0u16;lengths.len()];
+        for (symbol,&length) in lengths.iter().enumerate() {
+            if length!=0 {
+                symbols[offsets[length as usize] as usize]=symbol as u16;
+                offsets[length as usize]+=1;
+            }
+        }
+        Ok(Huffman{counts,symbols})
+    }
+
+    fn decode(&self,reader:&mut BitReader)->Result<u16,InflateError>{
+        let (mut code,mut first,mut index)=(0i32,0i32,0i32);
+        for &count in &self.counts[1..] {
+            code|=reader.bits(1)? as i32;
+            let count=count as i32;
+            if code-first<count {
+                return Ok(self.symbols[(index+code-first) as usize]);
+            }
+            index+=count;
+            first=(first+count)<<1;
+            code<<=1;
+        }
+        Err(InflateError::Code)
+    }
+}
+
+/// Decode a raw DEFLATE stream (RFC 1951); returns the output and the number of
+/// input bytes the stream used. Output longer than `limit` bytes is refused.
+pub fn inflate(data:&[u8],limit:usize)->Result<(Vec<u8>,usize),InflateError>{
+    let mut reader=BitReader{data,position:0,bits:0,count:0};
+    let mut output=Vec::new();
+    loop {
+        let last=reader.bits(1)?==1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header=data.get(reader.position..reader.position+4).ok_or(InflateError::Truncated)?;
+                let length=u16::from_le_bytes([header[0],header[1]]);
+                if length!=!u16::from_le_bytes([header[2],header[3]]) {
+                    return Err(InflateError::StoredLength);
+                }
+                let start=reader.position+4;
+                if output.len()+length as usize>limit {
+                    return Err(InflateError::TooLarge);
+                }
+                output.extend_from_slice(data.get(start..start+length as usize).ok_or(InflateError::Truncated)?);
+                reader.position=start+length as usize;
+            }
+            1 => {
+                let mut lengths=[0u8;288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals=Huffman::new(&lengths)?;
+                let distances=Huffman::new(&[5u8;30])?;
+                inflate_block(&mut reader,&mut output,&literals,&distances,limit)?;
+            }
+            2 => {
+                let (literals,distances)=dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader,&mut output,&literals,&distances,limit)?;
+            }
+            _ => return Err(InflateError::BlockType),
+        }
+        if last {
+            return Ok((output,reader.position));
+        }
+    }
+}
+
+fn dynamic_codes(reader:&mut BitReader)->Result<(Huffman,Huffman),InflateError>{
+    let literal_count=reader.bits(5)? as usize+257;
+    let distance_count=reader.bits(5)? as usize+1;
+    let code_count=reader.bits(4)? as usize+4;
+    let mut code_lengths=[0u8;19];
+    for &i in &CODE_LENGTH_ORDER[..code_count] {
+        code_lengths[i]=reader.bits(3)? as u8;
+    }
+    let code=Huffman::new(&code_lengths)?;
+    let mut lengths=vec![0u8;literal_count+distance_count];
+    let mut i=0;
+    while i<lengths.len() {
+        let symbol=code.decode(reader)?;
+        //16重复前一个码长,17、18重复0
+        let (value,repeat)=match symbol {
+            0..=15 => (symbol as u8,1),
+            16 => (*lengths[..i].last().ok_or(InflateError::Code)?,3+reader.bits(2)? as usize),
+            17 => (0,3+reader.bits(3)? as usize),
+            _ => (0,11+reader.bits(7)? as usize),
+        };
+        if i+repeat>lengths.len() {
+            return Err(InflateError::Code);
+        }
+        lengths[i..i+repeat].fill(value);
+        i+=repeat;
+    }
+    if lengths[256]==0 {
+        return Err(InflateError::Code);
+    }
+    Ok((Huffman::new(&lengths[..literal_count])?,Huffman::new(&lengths[literal_count..])?))
+}
+
+fn inflate_block(reader:&mut BitReader,output:&mut Vec<u8>,literals:&Huffman,distances:&Huffman,limit:usize)->Result<(),InflateError>{
+    loop {
+        let symbol=literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 if output.len()>=limit => return Err(InflateError::TooLarge),
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index=symbol-257;
+                if index>=LENGTH_BASE.len() {
+                    return Err(InflateError::Code);
+                }
+                let length=LENGTH_BASE[index] as usize+reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let index=distances.decode(reader)? as usize;
+                if index>=DISTANCE_BASE.len() {
+                    return Err(InflateError::Code);
+                }
+                let distance=DISTANCE_BASE[index] as usize+reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance>output.len() {
+                    return Err(InflateError::Distance);
+                }
+                if output.len()+length>limit {
+                    return Err(InflateError::TooLarge);
+                }
+                //允许与正在写出的内容重叠,逐字节复制
+                let start=output.len()-distance;
+                for i in 0..length {
+                    output.push(output[start+i]);
+                }
+            }
+        }
+    }
+}
+
+/// Decode gzip data (RFC 1952), including several concatenated members, checking
+/// each member's CRC-32 and length. At most `limit` bytes are produced in total.
+pub fn gunzip(data:&[u8],limit:usize)->Result<Vec<u8>,InflateError>{
+    let mut output=Vec::new();
+    let mut offset=0;
+    while offset<data.len() {
+        let header=data.get(offset..offset+10).ok_or(InflateError::Header)?;
+        if header[..3]!=[0x1f,0x8b,8] {
+            //成员之后的填充0可以忽略
+            if offset>0 && data[offset..].iter().all(|&b| b==0) {
+                break;
+            }
+            return Err(InflateError::Header);
+        }
+        let flags=header[3];
+        let mut position=offset+10;
+        //FEXTRA、FNAME、FCOMMENT、FHCRC
+        if flags&4!=0 {
+            let length=data.get(position..position+2).ok_or(InflateError::Header)?;
+            position+=2+u16::from_le_bytes([length[0],length[1]]) as usize;
+        }
+        for flag in [8,16] {
+            if flags&flag!=0 {
+                position+=data.get(position..).and_then(|rest| rest.iter().position(|&b| b==0)).ok_or(InflateError::Header)?+1;
+            }
+        }
+        if flags&2!=0 {
+            position+=2;
+        }
+        let (member,used)=inflate(data.get(position..).ok_or(InflateError::Header)?,limit-output.len())?;
+        position+=used;
+        let trailer=data.get(position..position+8).ok_or(InflateError::Truncated)?;
+        let crc=u32::from_le_bytes([trailer[0],trailer[1],trailer[2],trailer[3]]);
+        let size=u32::from_le_bytes([trailer[4],trailer[5],trailer[6],trailer[7]]);
+        if crc!=crc32(&member) || size!=member.len() as u32 {
+            return Err(InflateError::Checksum);
+        }
+        output.extend_from_slice(&member);
+        offset=position+8;
+    }
+    Ok(output)
+}
+
+/// CRC-32 as used by gzip, zip and gdb's `.gnu_debuglink` (reflected, polynomial 0xedb88320)
+pub fn crc32(data:&[u8])->u32{
+    let mut crc=!0u32;
+    for &byte in data {
+        crc^=byte as u32;
+        for _ in 0..8 {
+            crc=if crc&1!=0 { (crc>>1)^0xedb8_8320 } else { crc>>1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //zlib生成的原始DEFLATE流,依次是存储块、固定码表块和动态码表块
+    const STORED: [u8;10] = [0x01,0x05,0x00,0xfa,0xff,0x68,0x65,0x6c,0x6c,0x6f];
+    const FIXED: [u8;10] = [0xcb,0x48,0xcd,0xc9,0xc9,0x57,0xc8,0x40,0x90,0x00];
+    const RUN: [u8;6] = [0x4b,0x4c,0xa4,0x3d,0x00,0x00];
+    const DYNAMIC: [u8;91] = [
+        0x1d,0x8c,0xc9,0x0d,0x04,0x41,0x08,0xc4,0x12,0xf2,0xa3,0xb9,0x21,0xff,0xc4,0xb6,0x76,0x24,0x84,0x38,
+        0xec,0x7a,0x18,0xc9,0x61,0x8d,0x17,0xd1,0xe4,0xd1,0xc9,0x1a,0x81,0x27,0x39,0x8c,0xe3,0x84,0xd1,0xce,
+        0x89,0x08,0xe6,0x61,0x4e,0x05,0x27,0x5a,0xae,0x02,0x34,0x27,0xf5,0x58,0xba,0xf0,0x61,0x97,0x4a,0xdc,
+        0x59,0xa5,0x29,0xaa,0x30,0x63,0x9b,0x96,0xb1,0x84,0x63,0x22,0xb9,0x60,0x8b,0x39,0x46,0x3d,0xbe,0xfa,
+        0x56,0x1d,0xf5,0xea,0x3f,0x24,0x54,0x82,0x34,0xc9,0x3f,
+    ];
+    //gzip.compress(b"hello world\n",mtime=0)与gzip.compress(b"again\n",mtime=0)
+    const HELLO_GZ: [u8;32] = [
+        0x1f,0x8b,0x08,0x00,0x00,0x00,0x00,0x00,0x02,0x03,0xcb,0x48,0xcd,0xc9,0xc9,0x57,
+        0x28,0xcf,0x2f,0xca,0x49,0xe1,0x02,0x00,0x2d,0x3b,0x08,0xaf,0x0c,0x00,0x00,0x00,
+    ];
+    const AGAIN_GZ: [u8;26] = [
+        0x1f,0x8b,0x08,0x00,0x00,0x00,0x00,0x00,0x02,0x03,0x4b,0x4c,0x4f,0xcc,0xcc,0xe3,
+        0x02,0x00,0xff,0x19,0x4f,0x86,0x06,0x00,0x00,0x00,
+    ];
+
+    #[test]
+    fn crc32_check_values(){
+        assert_eq!(crc32(b""),0);
+        assert_eq!(crc32(b"123456789"),0xcbf4_3926);
+        assert_eq!(crc32(b"hello world\n"),0xaf08_3b2d);
+    }
+
+    #[test]
+    fn inflate_blocks(){
+        assert_eq!(inflate(&STORED,usize::MAX),Ok((b"hello".to_vec(),STORED.len())));
+        assert_eq!(inflate(&FIXED,usize::MAX),Ok((b"hello hello hello".to_vec(),FIXED.len())));
+        //距离1的回溯与正在写出的内容重叠
+        assert_eq!(inflate(&RUN,usize::MAX),Ok((vec![b'a';100],RUN.len())));
+        let text:String=(0..60).map(|i| format!("{},",i*i%97)).collect();
+        assert_eq!(inflate(&DYNAMIC,usize::MAX),Ok((text.into_bytes(),DYNAMIC.len())));
+        //流结束后的字节不算在内
+        let mut padded=FIXED.to_vec();
+        padded.extend_from_slice(&[0xaa;4]);
+        assert_eq!(inflate(&padded,usize::MAX).map(|(_,used)| used),Ok(FIXED.len()));
+    }
+
+    #[test]
+    fn inflate_errors(){
+        assert_eq!(inflate(&[],usize::MAX),Err(InflateError::Truncated));
+        assert_eq!(inflate(&FIXED[..5],usize::MAX),Err(InflateError::Truncated));
+        assert_eq!(inflate(&[0x07],usize::MAX),Err(InflateError::BlockType));
+        assert_eq!(inflate(&[0x01,0x05,0x00,0x00,0x00],usize::MAX),Err(InflateError::StoredLength));
+        assert_eq!(inflate(&STORED[..8],usize::MAX),Err(InflateError::Truncated));
+    }
+
+    #[test]
+    fn inflate_limit(){
+        assert_eq!(inflate(&STORED,5).map(|(output,_)| output.len()),Ok(5));
+        assert_eq!(inflate(&STORED,4),Err(InflateError::TooLarge));
+        assert_eq!(inflate(&FIXED,16),Err(InflateError::TooLarge));
+        assert_eq!(inflate(&RUN,99),Err(InflateError::TooLarge));
+        assert_eq!(inflate(&DYNAMIC,100),Err(InflateError::TooLarge));
+    }
+
+    #[test]
+    fn gunzip_members(){
+        assert_eq!(gunzip(&HELLO_GZ,usize::MAX),Ok(b"hello world\n".to_vec()));
+        let mut joined=[HELLO_GZ.as_slice(),&AGAIN_GZ,&[0;16]].concat();
+        assert_eq!(gunzip(&joined,usize::MAX),Ok(b"hello world\nagain\n".to_vec()));
+        //上限按所有成员的总输出计算
+        assert_eq!(gunzip(&joined,18).map(|output| output.len()),Ok(18));
+        assert_eq!(gunzip(&joined,17),Err(InflateError::TooLarge));
+        joined.truncate(HELLO_GZ.len()+AGAIN_GZ.len()-1);
+        assert_eq!(gunzip(&joined,usize::MAX),Err(InflateError::Truncated));
+    }
+
+    #[test]
+    fn gunzip_errors(){
+        let mut corrupt=HELLO_GZ;
+        corrupt[24]^=1;
+        assert_eq!(gunzip(&corrupt,usize::MAX),Err(InflateError::Checksum));
+        let mut corrupt=HELLO_GZ;
+        corrupt[28]=13;
+        assert_eq!(gunzip(&corrupt,usize::MAX),Err(InflateError::Checksum));
+        assert_eq!(gunzip(&HELLO_GZ[..8],usize::MAX),Err(InflateError::Header));
+        assert_eq!(gunzip(&[0x1f,0x8b,0x07,0,0,0,0,0,0,0],usize::MAX),Err(InflateError::Header));
+        //FNAME没有结尾的0
+        assert_eq!(gunzip(&[0x1f,0x8b,0x08,0x08,0,0,0,0,0,3,b'a'],usize::MAX),Err(InflateError::Header));
+    }
+}
@@ -0,0 +1,489 @@
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::elf_header::FileHeader;
+use crate::parser::file::Class;
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+use crate::parser::symbol::Symbol;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Unusual but allowed by the spec, e.g. a writable and executable segment
+    Info,
+    /// Tolerated by loaders but inconsistent, often a sign of tampering
+    Warning,
+    /// Violates the ELF specification
+    Error,
+}
+
+/// One finding of the linter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// File offset of the offending header field or table entry
+    pub offset: u64,
+    pub message: String,
+}
+
+/// Structural checks over the file header, program headers and section headers.
+///
+/// Only the headers and the file length are trusted, so this works on files the
+/// regular `read_*` helpers reject.
+pub struct Linter<'a> {
+    header: &'a FileHeader,
+    program_headers: &'a [ProgramHeader],
+    section_headers: &'a [SectionHeader],
+    file_len: u64,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn read_lint(file_path:&str)->Option<Vec<Diagnostic>>{
+        let elf=ElfFile::read_elf(file_path)?;
+        Some(Self::lint_elf(&elf))
+    }
+
+    pub fn lint_elf(elf:&ElfFile)->Vec<Diagnostic>{
+        let mut diagnostics=Linter::lint(&elf.header,&elf.program_headers,&elf.section_headers,elf.data.len() as u64);
+        diagnostics.extend(Self::lint_dynamic(elf));
+        diagnostics.sort_by_key(|d| (std::cmp::Reverse(d.severity),d.offset));
+        diagnostics
+    }
+
+    pub fn lint(header:&'a FileHeader,program_headers:&'a [ProgramHeader],section_headers:&'a [SectionHeader],file_len:u64)->Vec<Diagnostic>{
+        let mut linter=Linter{header,program_headers,section_headers,file_len,diagnostics:Vec::new()};
+        linter.check_file_header();
+        linter.check_program_headers();
+        linter.check_section_headers();
+        linter.check_section_overlap();
+        linter.check_section_segment_mapping();
+        linter.diagnostics
+    }
+
+    fn report(&mut self,severity:Severity,offset:u64,message:String){
+        self.diagnostics.push(Diagnostic{severity,offset,message});
+    }
+
+    fn phdr_offset(&self,index:usize)->u64{
+        self.header.e_phoff.saturating_add(index as u64*self.header.e_phentsize as u64)
+    }
+
+    fn shdr_offset(&self,index:usize)->u64{
+        self.header.e_shoff.saturating_add(index as u64*self.header.e_shentsize as u64)
+    }
+
+    //文件头字段的偏移,e_entry之后的字段随类别变化
+    fn header_field(&self,field:&str)->u64{
+        let base=0x18;
+        let word=self.header.class.word_size() as u64;
+        match field {
+            "e_entry" => base,
+            "e_phoff" => base+word,
+            "e_shoff" => base+2*word,
+            "e_ehsize" => base+3*word+4,
+            "e_phentsize" => base+3*word+6,
+            "e_phnum" => base+3*word+8,
+            "e_shentsize" => base+3*word+10,
+            "e_shnum" => base+3*word+12,
+            _ => base+3*word+14,
+        }
+    }
+
+    fn check_file_header(&mut self){
+        let class=self.header.class;
+        let ehsize=match class {
+            Class::ELF32 => 0x34,
+            Class::ELF64 => 0x40,
+        };
+        if self.header.e_ehsize!=ehsize {
+            self.report(Severity::Warning,self.header_field("e_ehsize"),
+                format!("e_ehsize is {:#x}, expected {:#x}",self.header.e_ehsize,ehsize));
+        }
+        if self.header.e_phnum>0 {
+            if self.header.e_phentsize as usize!=ProgramHeader::size_for(class) {
+                self.report(Severity::Error,self.header_field("e_phentsize"),
+                    format!("e_phentsize is {}, expected {}",self.header.e_phentsize,ProgramHeader::size_for(class)));
+            }
+            let end=self.header.e_phoff as u128+self.header.e_phnum as u128*self.header.e_phentsize as u128;
+            if end>self.file_len as u128 {
+                self.report(Severity::Error,self.header_field("e_phoff"),
+                    format!("program header table {:#x}..{:#x} extends past end of file {:#x}",self.header.e_phoff,end,self.file_len));
+            }
+        }
+        if self.header.e_shoff!=0 {
+            if self.header.e_shentsize as usize!=SectionHeader::size_for(class) {
+                self.report(Severity::Error,self.header_field("e_shentsize"),
+                    format!("e_shentsize is {}, expected {}",self.header.e_shentsize,SectionHeader::size_for(class)));
+            }
+            let end=self.header.e_shoff as u128+self.header.e_shnum as u128*self.header.e_shentsize as u128;
+            if end>self.file_len as u128 {
+                self.report(Severity::Error,self.header_field("e_shoff"),
+                    format!("section header table {:#x}..{:#x} extends past end of file {:#x}",self.header.e_shoff,end,self.file_len));
+            }
+            if self.section_headers.len()<self.header.e_shnum as usize {
+                self.report(Severity::Error,self.header_field("e_shnum"),
+                    format!("only {} of {} section headers could be read",self.section_headers.len(),self.header.e_shnum));
+            }
+        }
+        let shstrndx=self.header.e_shstrndx as usize;
+        if shstrndx!=abi::SHN_UNDEF as usize {
+            match self.section_headers.get(shstrndx) {
+                None => self.report(Severity::Error,self.header_field("e_shstrndx"),
+                    format!("e_shstrndx {} is out of range ({} sections)",shstrndx,self.section_headers.len())),
+                Some(sh) if sh.sh_type!=abi::SHT_STRTAB => self.report(Severity::Error,self.header_field("e_shstrndx"),
+                    format!("e_shstrndx {} points to a section of type {:#x}, not SHT_STRTAB",shstrndx,sh.sh_type)),
+                Some(_) => {}
+            }
+        } else if !self.section_headers.is_empty() {
+            self.report(Severity::Warning,self.header_field("e_shstrndx"),
+                "section headers present but e_shstrndx is SHN_UNDEF".to_string());
+        }
+        let entry=self.header.e_entry;
+        if entry!=0 && (self.header.e_type==abi::ET_EXEC || self.header.e_type==abi::ET_DYN) && !self.program_headers.is_empty() {
+            let executable=self.program_headers.iter().any(|ph| ph.p_type==abi::PT_LOAD && ph.p_flags&abi::PF_X!=0
+                && entry>=ph.p_vaddr && entry-ph.p_vaddr<ph.p_memsz);
+            if !executable {
+                self.report(Severity::Warning,self.header_field("e_entry"),
+                    format!("entry point {:#x} is not inside an executable PT_LOAD",entry));
+            }
+        }
+    }
+
+    fn check_program_headers(&mut self){
+        let mut last_load:Option<&ProgramHeader>=None;
+        let mut seen_load=false;
+        let mut counts=[0usize;3];
+        for (i,ph) in self.program_headers.iter().enumerate() {
+            let offset=self.phdr_offset(i);
+            if ph.p_type!=abi::PT_GNU_STACK && ph.p_offset as u128+ph.p_filesz as u128>self.file_len as u128 {
+                self.report(Severity::Error,offset,
+                    format!("segment {} file range {:#x}+{:#x} extends past end of file",i,ph.p_offset,ph.p_filesz));
+            }
+            if ph.p_align>1 && !ph.p_align.is_power_of_two() {
+                self.report(Severity::Error,offset,format!("segment {} p_align {:#x} is not a power of two",i,ph.p_align));
+            }
+            match ph.p_type {
+                abi::PT_LOAD => {
+                    if ph.p_filesz>ph.p_memsz {
+                        self.report(Severity::Error,offset,
+                            format!("PT_LOAD {} p_filesz {:#x} exceeds p_memsz {:#x}",i,ph.p_filesz,ph.p_memsz));
+                    }
+                    if ph.p_align>1 && ph.p_align.is_power_of_two() && ph.p_offset%ph.p_align!=ph.p_vaddr%ph.p_align {
+                        self.report(Severity::Error,offset,
+                            format!("PT_LOAD {} p_offset {:#x} and p_vaddr {:#x} are not congruent modulo p_align {:#x}",
+                                    i,ph.p_offset,ph.p_vaddr,ph.p_align));
+                    }
+                    if ph.p_flags&abi::PF_W!=0 && ph.p_flags&abi::PF_X!=0 {
+                        self.report(Severity::Info,offset,format!("PT_LOAD {} is writable and executable",i));
+                    }
+                    if let Some(last)=last_load {
+                        if ph.p_vaddr<last.p_vaddr {
+                            self.report(Severity::Error,offset,
+                                format!("PT_LOAD {} at {:#x} is not sorted by p_vaddr (previous at {:#x})",i,ph.p_vaddr,last.p_vaddr));
+                        } else if ph.p_vaddr<last.p_vaddr.saturating_add(last.p_memsz) {
+                            self.report(Severity::Error,offset,
+                                format!("PT_LOAD {} at {:#x} overlaps the previous PT_LOAD in memory",i,ph.p_vaddr));
+                        }
+                    }
+                    last_load=Some(ph);
+                    seen_load=true;
+                }
+                abi::PT_PHDR => {
+                    counts[0]+=1;
+                    if seen_load {
+                        self.report(Severity::Error,offset,"PT_PHDR follows a PT_LOAD entry".to_string());
+                    }
+                    if ph.p_offset!=self.header.e_phoff {
+                        self.report(Severity::Warning,offset,
+                            format!("PT_PHDR p_offset {:#x} differs from e_phoff {:#x}",ph.p_offset,self.header.e_phoff));
+                    }
+                }
+                abi::PT_INTERP => {
+                    counts[1]+=1;
+                    if seen_load {
+                        self.report(Severity::Error,offset,"PT_INTERP follows a PT_LOAD entry".to_string());
+                    }
+                }
+                abi::PT_DYNAMIC => counts[2]+=1,
+                _ => {}
+            }
+        }
+        for (count,name) in counts.iter().zip(["PT_PHDR","PT_INTERP","PT_DYNAMIC"]) {
+            if *count>1 {
+                self.report(Severity::Error,self.header.e_phoff,format!("{} appears {} times",name,count));
+            }
+        }
+    }
+
+    fn expected_entsize(&self,sh_type:u32)->Option<u64>{
+        let class=self.header.class;
+        let word=class.word_size() as u64;
+        match sh_type {
+            abi::SHT_SYMTAB | abi::SHT_DYNSYM => Some(Symbol::size_for(class) as u64),
+            abi::SHT_RELA => Some(Rela::size_for(class) as u64),
+            abi::SHT_REL => Some(Rel::size_for(class) as u64),
+            abi::SHT_DYNAMIC => Some(Dyn::size_for(class) as u64),
+            abi::SHT_HASH => Some(4),
+            abi::SHT_GNU_VERSYM => Some(2),
+            abi::SHT_INIT_ARRAY | abi::SHT_FINI_ARRAY | abi::SHT_PREINIT_ARRAY => Some(word),
+            _ => None,
+        }
+    }
+
+    fn check_section_headers(&mut self){
+        let count=self.section_headers.len();
+        let shstrtab_size=self.section_headers.get(self.header.e_shstrndx as usize).map(|sh| sh.sh_size);
+        if let Some(first)=self.section_headers.first() {
+            if first.sh_type!=abi::SHT_NULL || first.sh_offset!=0 || first.sh_addr!=0 {
+                self.report(Severity::Warning,self.shdr_offset(0),"section 0 is not a null section".to_string());
+            }
+        }
+        for (i,sh) in self.section_headers.iter().enumerate().skip(1) {
+            let offset=self.shdr_offset(i);
+            let name=if sh.string_name.is_empty() { format!("[{}]",i) } else { sh.string_name.clone() };
+            if let Some(size)=shstrtab_size {
+                if sh.sh_name as u64>=size {
+                    self.report(Severity::Error,offset,format!("section {} sh_name {:#x} is outside .shstrtab",i,sh.sh_name));
+                }
+            }
+            if sh.sh_type!=abi::SHT_NOBITS && sh.sh_offset as u128+sh.sh_size as u128>self.file_len as u128 {
+                self.report(Severity::Error,offset,
+                    format!("section {} range {:#x}+{:#x} extends past end of file {:#x}",name,sh.sh_offset,sh.sh_size,self.file_len));
+            }
+            if sh.sh_addralign>1 {
+                if !sh.sh_addralign.is_power_of_two() {
+                    self.report(Severity::Error,offset,format!("section {} sh_addralign {:#x} is not a power of two",name,sh.sh_addralign));
+                } else if sh.sh_addr%sh.sh_addralign!=0 {
+                    self.report(Severity::Warning,offset,
+                        format!("section {} sh_addr {:#x} is not aligned to {:#x}",name,sh.sh_addr,sh.sh_addralign));
+                }
+            }
+            if let Some(entsize)=self.expected_entsize(sh.sh_type) {
+                if sh.sh_entsize!=entsize {
+                    self.report(Severity::Error,offset,
+                        format!("section {} sh_entsize is {}, expected {}",name,sh.sh_entsize,entsize));
+                } else if sh.sh_size%entsize!=0 {
+                    self.report(Severity::Error,offset,
+                        format!("section {} size {:#x} is not a multiple of sh_entsize {}",name,sh.sh_size,entsize));
+                }
+            }
+            //sh_link按节类型检查
+            let link_type=match sh.sh_type {
+                abi::SHT_SYMTAB | abi::SHT_DYNSYM | abi::SHT_DYNAMIC | abi::SHT_GNU_VERDEF | abi::SHT_GNU_VERNEED => Some(abi::SHT_STRTAB),
+                abi::SHT_HASH | abi::SHT_GNU_HASH | abi::SHT_GNU_VERSYM => Some(abi::SHT_DYNSYM),
+                _ => None,
+            };
+            if let Some(expected)=link_type {
+                match self.section_headers.get(sh.sh_link as usize) {
+                    None => self.report(Severity::Error,offset,
+                        format!("section {} sh_link {} is out of range ({} sections)",name,sh.sh_link,count)),
+                    Some(link) if link.sh_type!=expected => self.report(Severity::Error,offset,
+                        format!("section {} sh_link {} points to {} of type {:#x}, expected {:#x}",
+                                name,sh.sh_link,link.string_name,link.sh_type,expected)),
+                    Some(_) => {}
+                }
+            }
+            if sh.sh_type==abi::SHT_REL || sh.sh_type==abi::SHT_RELA {
+                if let Some(link)=self.section_headers.get(sh.sh_link as usize) {
+                    if sh.sh_link!=0 && link.sh_type!=abi::SHT_SYMTAB && link.sh_type!=abi::SHT_DYNSYM {
+                        self.report(Severity::Error,offset,
+                            format!("section {} sh_link points to {}, which is not a symbol table",name,link.string_name));
+                    }
+                } else {
+                    self.report(Severity::Error,offset,format!("section {} sh_link {} is out of range",name,sh.sh_link));
+                }
+                if sh.sh_flags&abi::SHF_INFO_LINK!=0 && sh.sh_info as usize>=count {
+                    self.report(Severity::Error,offset,format!("section {} sh_info {} is out of range",name,sh.sh_info));
+                }
+            }
+            if sh.sh_type==abi::SHT_SYMTAB || sh.sh_type==abi::SHT_DYNSYM {
+                let entsize=Symbol::size_for(self.header.class) as u64;
+                if sh.sh_info as u64>sh.sh_size/entsize {
+                    self.report(Severity::Error,offset,
+                        format!("section {} sh_info {} (first global) exceeds the symbol count {}",name,sh.sh_info,sh.sh_size/entsize));
+                }
+            }
+        }
+    }
+
+    //文件中的节不能重叠(SHT_NOBITS与空节除外)
+    fn check_section_overlap(&mut self){
+        let mut ranges:Vec<(u64,u64,usize)>=self.section_headers.iter().enumerate().skip(1)
+            .filter(|(_,sh)| sh.sh_type!=abi::SHT_NOBITS && sh.sh_type!=abi::SHT_NULL && sh.sh_size>0)
+            .map(|(i,sh)| (sh.sh_offset,sh.sh_offset.saturating_add(sh.sh_size),i))
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            let (_,end,a)=pair[0];
+            let (start,_,b)=pair[1];
+            if start<end {
+                let message=format!("section {} overlaps section {} at file offset {:#x}",
+                                    self.section_headers[b].string_name,self.section_headers[a].string_name,start);
+                self.report(Severity::Error,self.shdr_offset(b),message);
+            }
+        }
+        let table_start=self.header.e_shoff;
+        let table_end=table_start.saturating_add(self.header.e_shnum as u64*self.header.e_shentsize as u64);
+        if table_start!=0 {
+            for &(start,end,i) in &ranges {
+                if start<table_end && table_start<end {
+                    let message=format!("section {} overlaps the section header table",self.section_headers[i].string_name);
+                    self.report(Severity::Error,self.shdr_offset(i),message);
+                }
+            }
+        }
+    }
+
+    //SHF_ALLOC节的地址与文件偏移要与覆盖它的PT_LOAD一致
+    fn check_section_segment_mapping(&mut self){
+        if self.program_headers.is_empty() {
+            return;
+        }
+        let loads:Vec<&ProgramHeader>=self.program_headers.iter().filter(|ph| ph.p_type==abi::PT_LOAD).collect();
+        for (i,sh) in self.section_headers.iter().enumerate().skip(1) {
+            if sh.sh_flags&abi::SHF_ALLOC==0 || sh.sh_size==0 {
+                continue;
+            }
+            //TLS .tbss不占用内存映像
+            if sh.sh_type==abi::SHT_NOBITS && sh.sh_flags&abi::SHF_TLS!=0 {
+                continue;
+            }
+            let offset=self.shdr_offset(i);
+            let Some(ph)=loads.iter().find(|ph| sh.sh_addr>=ph.p_vaddr && sh.sh_addr-ph.p_vaddr<ph.p_memsz.max(1)) else {
+                self.report(Severity::Warning,offset,
+                    format!("allocated section {} at {:#x} is not covered by any PT_LOAD",sh.string_name,sh.sh_addr));
+                continue;
+            };
+            if sh.sh_addr.saturating_add(sh.sh_size)>ph.p_vaddr.saturating_add(ph.p_memsz) {
+                self.report(Severity::Error,offset,
+                    format!("section {} extends past the end of its PT_LOAD at {:#x}",sh.string_name,ph.p_vaddr));
+            }
+            if sh.sh_type==abi::SHT_NOBITS {
+                continue;
+            }
+            let delta=sh.sh_addr-ph.p_vaddr;
+            let expected=ph.p_offset.saturating_add(delta);
+            if delta>=ph.p_filesz {
+                self.report(Severity::Error,offset,
+                    format!("section {} has file contents but lies in the zero-filled part of its PT_LOAD",sh.string_name));
+            } else if expected!=sh.sh_offset {
+                self.report(Severity::Error,offset,
+                    format!("section {} sh_offset {:#x} disagrees with PT_LOAD mapping (expected {:#x})",
+                            sh.string_name,sh.sh_offset,expected));
+            }
+        }
+        for (i,ph) in self.program_headers.iter().enumerate() {
+            if ph.p_type!=abi::PT_DYNAMIC && ph.p_type!=abi::PT_INTERP {
+                continue;
+            }
+            let sh_type=if ph.p_type==abi::PT_DYNAMIC { Some(abi::SHT_DYNAMIC) } else { None };
+            let section=match sh_type {
+                Some(sh_type) => self.section_headers.iter().find(|sh| sh.sh_type==sh_type),
+                None => self.section_headers.iter().find(|sh| sh.string_name==".interp"),
+            };
+            if let Some(sh)=section {
+                if sh.sh_offset!=ph.p_offset || sh.sh_addr!=ph.p_vaddr {
+                    let message=format!("segment {} disagrees with section {} ({:#x}/{:#x} vs {:#x}/{:#x})",
+                                        i,sh.string_name,ph.p_offset,ph.p_vaddr,sh.sh_offset,sh.sh_addr);
+                    self.report(Severity::Error,self.phdr_offset(i),message);
+                }
+            }
+        }
+    }
+
+    /// Checks that need section contents: `.dynsym` string table and PT_INTERP termination
+    pub fn lint_dynamic(elf:&ElfFile)->Vec<Diagnostic>{
+        let mut diagnostics=Vec::new();
+        let phdr_offset=|i:usize| elf.header.e_phoff.saturating_add(i as u64*elf.header.e_phentsize as u64);
+        for (i,ph) in elf.program_headers.iter().enumerate() {
+            if ph.p_type==abi::PT_INTERP {
+                match elf.segment_data(ph) {
+                    Some(bytes) if bytes.last()==Some(&0) => {}
+                    _ => diagnostics.push(Diagnostic{severity:Severity::Warning,offset:phdr_offset(i),
+                        message:"PT_INTERP is not NUL terminated".to_string()}),
+                }
+            }
+        }
+        if let Some(dynsym)=elf.find_section_by_type(abi::SHT_DYNSYM) {
+            let dyns=elf.dynamic();
+            let strtab=elf.section_headers.get(dynsym.sh_link as usize);
+            if let (Some(strtab),Some(addr))=(strtab,Dyn::find(&dyns,abi::DT_STRTAB)) {
+                if strtab.sh_addr!=addr {
+                    let offset=elf.header.e_shoff.saturating_add(dynsym.index as u64*elf.header.e_shentsize as u64);
+                    diagnostics.push(Diagnostic{severity:Severity::Warning,offset,
+                        message:format!(".dynsym sh_link names {} at {:#x}, but DT_STRTAB is {:#x}",strtab.string_name,strtab.sh_addr,addr)});
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ELF: &[u8] = include_bytes!("../test_elf/de");
+
+    fn lint_bytes(data:Vec<u8>)->(ElfFile,Vec<Diagnostic>){
+        let elf=ElfFile::parse(data).unwrap();
+        let diagnostics=Linter::lint_elf(&elf);
+        (elf,diagnostics)
+    }
+
+    #[test]
+    fn huge_phoff(){
+        let mut data=TEST_ELF.to_vec();
+        data[0x20..0x28].copy_from_slice(&0xfffffffffffffff0u64.to_le_bytes());
+        let (elf,diagnostics)=lint_bytes(data);
+        assert!(elf.program_headers.is_empty());
+        assert!(diagnostics.iter().any(|d| d.severity==Severity::Error && d.offset==0x20));
+    }
+
+    #[test]
+    fn huge_shoff(){
+        let mut data=TEST_ELF.to_vec();
+        data[0x28..0x30].copy_from_slice(&0xfffffffffffffff0u64.to_le_bytes());
+        let (elf,diagnostics)=lint_bytes(data);
+        assert!(elf.section_headers.is_empty());
+        assert!(diagnostics.iter().any(|d| d.severity==Severity::Error && d.offset==0x28 && d.message.contains("extends past end of file")));
+    }
+
+    //第一个PT_LOAD的p_offset加上节的偏移量会溢出
+    #[test]
+    fn huge_load_offset(){
+        let mut data=TEST_ELF.to_vec();
+        data[0x40+2*56+8..0x40+2*56+16].copy_from_slice(&0xffff_ffff_ffff_fff0u64.to_le_bytes());
+        let (_,diagnostics)=lint_bytes(data);
+        assert!(diagnostics.iter().any(|d| d.message.contains("section .interp sh_offset 0x318 disagrees")));
+    }
+
+    #[test]
+    fn non_utf8_shstrtab(){
+        //.rela.plt的'a'改成0xff,.plt是它的后缀
+        let mut data=TEST_ELF.to_vec();
+        data[0x3087+0x87+4]=0xff;
+        let (elf,_)=lint_bytes(data.clone());
+        assert_eq!(elf.section_headers[13].string_name,".plt");
+        assert_eq!(elf.section_headers[11].string_name,".rel\u{fffd}.plt");
+        let string_map=SectionHeader::parser_string_table(data[0x3087..0x3087+0x10a].to_vec());
+        let section_headers=SectionHeader::fix_section_name(string_map,elf.section_headers.clone());
+        assert_eq!(section_headers.len(),elf.section_headers.len());
+    }
+
+    #[test]
+    fn corrupted_header_bytes(){
+        //文件头、程序头表和节区头表的每个字节依次改成0xff,都不能panic
+        let shoff=0x3198;
+        let offsets=(0..0x318).chain(shoff..TEST_ELF.len());
+        for offset in offsets {
+            let mut data=TEST_ELF.to_vec();
+            data[offset]=0xff;
+            if let Some(elf)=ElfFile::parse(data) {
+                Linter::lint_elf(&elf);
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
+use crate::parser::file::Class;
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::symbol::Symbol;
+
+const PAGE_SIZE: u64 = 0x1000;
+/// Largest image `LoadedImage::load` maps; the size comes from p_memsz, which a
+/// crafted file can set to anything
+pub const MAX_IMAGE_SIZE: u64 = 1<<30;
+
+/// Why a file could not be mapped at all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The file could not be read or is not ELF
+    Parse,
+    NoLoadSegments,
+    /// p_vaddr+p_memsz of this program header wraps around
+    AddressOverflow(usize),
+    /// The PT_LOAD segments span more than `MAX_IMAGE_SIZE` bytes
+    TooLarge(u64),
+}
+
+/// What the caller is asked to resolve while relocating an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Import<'a> {
+    /// An undefined symbol referenced through GLOB_DAT, JUMP_SLOT or ABS relocations
+    Symbol { name: &'a str, r_type: u32 },
+    /// An IRELATIVE relocation: the caller runs the resolver at this (already rebased)
+    /// address and returns the function it selects
+    IFunc { resolver: u64 },
+}
+
+/// Why a relocation was left unapplied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelocIssue {
+    /// The relocation type is not handled for this machine
+    UnsupportedType,
+    /// The callback could not resolve the import
+    Unresolved,
+    /// r_offset points outside the mapped segments
+    OutOfImage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnappliedReloc {
+    pub r_offset: u64,
+    pub r_type: u32,
+    pub symbol: String,
+    pub issue: RelocIssue,
+}
+
+/// The PT_LOAD segments of a file laid out the way the dynamic loader maps them
+#[derive(Debug, Clone)]
+pub struct LoadedImage {
+    /// Address the first byte of `data` is loaded at
+    pub base: u64,
+    /// Page aligned virtual address of the lowest PT_LOAD segment, which lands on `base`
+    pub min_vaddr: u64,
+    pub data: Vec<u8>,
+    /// Indexes in `program_headers` of PT_LOAD segments whose file contents lie
+    /// outside the file; their pages are left zero
+    pub unmapped: Vec<usize>,
+    /// Relocations that could not be applied
+    pub unapplied: Vec<UnappliedReloc>,
+}
+
+impl LoadedImage {
+    pub fn read_image<F>(file_path:&str,base:u64,resolve:F)->Result<LoadedImage,LoadError>
+        where F: FnMut(Import) -> Option<u64>{
+        let elf=ElfFile::read_elf(file_path).ok_or(LoadError::Parse)?;
+        Self::load(&elf,base,resolve)
+    }
+
+    /// Map every PT_LOAD segment into one buffer starting at `base` and apply the
+    /// dynamic relocations. Imports and IFUNC resolvers go through `resolve`.
+    /// Symbols defined by the file itself bind locally; there is no interposition.
+    /// Segments whose file contents cannot be read are listed in `unmapped` rather
+    /// than failing the load.
+    pub fn load<F>(elf:&ElfFile,base:u64,mut resolve:F)->Result<LoadedImage,LoadError>
+        where F: FnMut(Import) -> Option<u64>{
+        let loads:Vec<(usize,_)>=elf.program_headers.iter().enumerate().filter(|(_,ph)| ph.p_type==abi::PT_LOAD).collect();
+        let min_vaddr=loads.iter().map(|(_,ph)| ph.p_vaddr).min().ok_or(LoadError::NoLoadSegments)? & !(PAGE_SIZE-1);
+        let mut max_vaddr=0;
+        for &(i,ph) in &loads {
+            max_vaddr=ph.p_vaddr.checked_add(ph.p_memsz).ok_or(LoadError::AddressOverflow(i))?.max(max_vaddr);
+        }
+        //先检查大小再分配,p_memsz完全由文件决定
+        let size=(max_vaddr-min_vaddr).div_ceil(PAGE_SIZE).saturating_mul(PAGE_SIZE);
+        if size>MAX_IMAGE_SIZE {
+            return Err(LoadError::TooLarge(size));
+        }
+        //超出p_filesz部分(.bss)保持为0
+        let mut data=vec![0u8;size as usize];
+        let mut unmapped=Vec::new();
+        for &(i,ph) in &loads {
+            let Some(bytes)=elf.segment_data(ph) else {
+                unmapped.push(i);
+                continue;
+            };
+            let start=(ph.p_vaddr-min_vaddr) as usize;
+            let len=bytes.len().min(ph.p_memsz as usize);
+            data[start..start+len].copy_from_slice(&bytes[..len]);
+        }
+        let mut image=LoadedImage{
+            base,
+            min_vaddr,
+            data,
+            unmapped,
+            unapplied:Vec::new(),
+        };
+
+        let symbols=elf.dynamic_symbols();
+        for (relas,implicit) in Self::relocation_tables(elf) {
+            for rela in &relas {
+                image.apply(elf,rela,implicit,&symbols,&mut resolve);
+            }
+        }
+        Ok(image)
+    }
+
+    /// Dynamic relocation tables from DT_RELA, DT_REL and DT_JMPREL, or from the
+    /// allocated relocation sections when the file has no dynamic section. The flag
+    /// marks REL tables, whose addend is stored at the relocated location.
+    fn relocation_tables(elf:&ElfFile)->Vec<(Vec<Rela>,bool)>{
+        let ident=elf.ident();
+        let dyns=elf.dynamic();
+        let mut ranges=Vec::new();
+        if let (Some(addr),Some(size))=(Dyn::find(&dyns,abi::DT_RELA),Dyn::find(&dyns,abi::DT_RELASZ)) {
+            ranges.push((addr,size,false));
+        }
+        if let (Some(addr),Some(size))=(Dyn::find(&dyns,abi::DT_REL),Dyn::find(&dyns,abi::DT_RELSZ)) {
+            ranges.push((addr,size,true));
+        }
+        if let (Some(addr),Some(size))=(Dyn::find(&dyns,abi::DT_JMPREL),Dyn::find(&dyns,abi::DT_PLTRELSZ)) {
+            //旧链接器会把.rela.plt算进DT_RELASZ,已覆盖的不再重复应用
+            if !ranges.iter().any(|&(start,len,_)| addr>=start && addr-start<len) {
+                ranges.push((addr,size,Dyn::find(&dyns,abi::DT_PLTREL)!=Some(abi::DT_RELA as u64)));
+            }
+        }
+        if !ranges.is_empty() {
+            return ranges.into_iter()
+                .filter_map(|(addr,size,implicit)| {
+                    let bytes=elf.vaddr_data(addr,size)?;
+                    Some((Self::parse_table(ident,bytes,size,implicit),implicit))
+                })
+                .collect();
+        }
+        elf.section_headers.iter()
+            .filter(|sh| matches!(sh.sh_type,abi::SHT_RELA|abi::SHT_REL) && sh.sh_flags&abi::SHF_ALLOC!=0)
+            .filter_map(|sh| {
+                let implicit=sh.sh_type==abi::SHT_REL;
+                let bytes=elf.section_data(sh)?;
+                Some((Self::parse_table(ident,bytes,sh.sh_size,implicit),implicit))
+            })
+            .collect()
+    }
+
+    fn parse_table(ident:(AnyEndian, Class),bytes:&[u8],size:u64,implicit:bool)->Vec<Rela>{
+        if implicit {
+            Rel::parse(ident,bytes,size).into_iter().map(Rela::from).collect()
+        } else {
+            Rela::parse(ident,bytes,size)
+        }
+    }
+
+    /// Bias added to link-time addresses
+    pub fn load_bias(&self)->u64{
+        self.base.wrapping_sub(self.min_vaddr)
+    }
+
+    /// Bytes at a link-time virtual address
+    pub fn read(&self,vaddr:u64,len:usize)->Option<&[u8]>{
+        let start=usize::try_from(vaddr.checked_sub(self.min_vaddr)?).ok()?;
+        self.data.get(start..start.checked_add(len)?)
+    }
+
+    fn apply<F>(&mut self,elf:&ElfFile,rela:&Rela,implicit:bool,symbols:&[Symbol],resolve:&mut F)
+        where F: FnMut(Import) -> Option<u64>{
+        let kind=match Self::kind(elf.header.e_machine,rela.r_type) {
+            Some(Kind::None) => return,
+            Some(kind) => kind,
+            None => return self.report(rela,symbols,RelocIssue::UnsupportedType),
+        };
+        let width=elf.header.class.word_size() as u64;
+        let offset=rela.r_offset.wrapping_sub(self.min_vaddr);
+        if offset.checked_add(width).is_none_or(|end| end>self.data.len() as u64) {
+            return self.report(rela,symbols,RelocIssue::OutOfImage);
+        }
+        let endian=elf.header.endianness;
+        let bias=self.load_bias();
+        let addend=match (implicit,elf.header.class) {
+            (false,_) => rela.r_addend as u64,
+            (true,Class::ELF32) => endian.parse_u32_at(offset as usize,&self.data) as u64,
+            (true,Class::ELF64) => endian.parse_u64_at(offset as usize,&self.data),
+        };
+        let value=match kind {
+            Kind::Relative => bias.wrapping_add(addend),
+            Kind::IRelative => match resolve(Import::IFunc{resolver:bias.wrapping_add(addend)}) {
+                Some(value) => value,
+                None => return self.report(rela,symbols,RelocIssue::Unresolved),
+            },
+            Kind::Symbol{addend:with_addend} => {
+                let addend=if with_addend { addend } else { 0 };
+                let Some(symbol)=symbols.get(rela.r_sym as usize) else {
+                    return self.report(rela,symbols,RelocIssue::Unresolved);
+                };
+                //SHN_ABS符号的值是绝对值,不随装载地址变化
+                let value=if symbol.st_shndx==abi::SHN_ABS {
+                    Some(symbol.st_value)
+                } else if symbol.st_shndx!=abi::SHN_UNDEF {
+                    Some(bias.wrapping_add(symbol.st_value))
+                } else {
+                    match resolve(Import::Symbol{name:&symbol.string_name,r_type:rela.r_type}) {
+                        Some(value) => Some(value),
+                        //未解析的弱符号按0处理,与ld.so一致
+                        None if symbol.st_bind()==abi::STB_WEAK => Some(0),
+                        None => None,
+                    }
+                };
+                match value {
+                    Some(value) => value.wrapping_add(addend),
+                    None => return self.report(rela,symbols,RelocIssue::Unresolved),
+                }
+            }
+            Kind::None => return,
+        };
+
+        match elf.header.class {
+            Class::ELF32 => endian.write_u32_at(offset as usize,&mut self.data,value as u32),
+            Class::ELF64 => endian.write_u64_at(offset as usize,&mut self.data,value),
+        }
+    }
+
+    fn report(&mut self,rela:&Rela,symbols:&[Symbol],issue:RelocIssue){
+        let symbol=symbols.get(rela.r_sym as usize)
+            .filter(|_| rela.r_sym!=0)
+            .map(|sym| sym.string_name.clone())
+            .unwrap_or_default();
+        self.unapplied.push(UnappliedReloc{
+            r_offset:rela.r_offset,
+            r_type:rela.r_type,
+            symbol,
+            issue,
+        });
+    }
+
+    //x86-64与AArch64的动态重定位类型分类
+    fn kind(e_machine:u16,r_type:u32)->Option<Kind>{
+        match (e_machine,r_type) {
+            (abi::EM_X86_64,abi::R_X86_64_NONE) => Some(Kind::None),
+            (abi::EM_X86_64,abi::R_X86_64_64) => Some(Kind::Symbol{addend:true}),
+            (abi::EM_X86_64,abi::R_X86_64_GLOB_DAT) |
+            (abi::EM_X86_64,abi::R_X86_64_JUMP_SLOT) => Some(Kind::Symbol{addend:false}),
+            (abi::EM_X86_64,abi::R_X86_64_RELATIVE) => Some(Kind::Relative),
+            (abi::EM_X86_64,abi::R_X86_64_IRELATIVE) => Some(Kind::IRelative),
+            (abi::EM_AARCH64,abi::R_AARCH64_NONE) => Some(Kind::None),
+            (abi::EM_AARCH64,abi::R_AARCH64_ABS64) |
+            (abi::EM_AARCH64,abi::R_AARCH64_GLOB_DAT) |
+            (abi::EM_AARCH64,abi::R_AARCH64_JUMP_SLOT) => Some(Kind::Symbol{addend:true}),
+            (abi::EM_AARCH64,abi::R_AARCH64_RELATIVE) => Some(Kind::Relative),
+            (abi::EM_AARCH64,abi::R_AARCH64_IRELATIVE) => Some(Kind::IRelative),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    None,
+    Relative,
+    IRelative,
+    Symbol { addend: bool },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ELF: &[u8] = include_bytes!("../test_elf/de");
+    const BASE: u64 = 0x5555_5555_0000;
+
+    //第i个程序头的字段,偏移按ELF64布局
+    fn set_phdr(data:&mut [u8],i:usize,field:usize,value:u64){
+        let at=0x40+i*56+field;
+        data[at..at+8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn word(image:&LoadedImage,vaddr:u64)->u64{
+        u64::from_le_bytes(image.read(vaddr,8).unwrap().try_into().unwrap())
+    }
+
+    fn load(data:Vec<u8>)->Result<LoadedImage,LoadError>{
+        let elf=ElfFile::parse(data).unwrap();
+        LoadedImage::load(&elf,BASE,|import| match import {
+            Import::Symbol{name:"__gmon_start__",..} => None,
+            Import::Symbol{name:"puts",..} => Some(0x7000_1000),
+            _ => Some(0x7000_0000),
+        })
+    }
+
+    #[test]
+    fn relocates_at_base(){
+        let image=load(ELF.to_vec()).unwrap();
+        assert_eq!((image.min_vaddr,image.data.len(),image.load_bias()),(0,0x5000,BASE));
+        //RELATIVE、JUMP_SLOT、GLOB_DAT,未解析的弱符号填0
+        assert_eq!(word(&image,0x3dc0),BASE+0x11d0);
+        assert_eq!(word(&image,0x4060),BASE+0x4060);
+        assert_eq!(word(&image,0x4008),0x7000_1000);
+        assert_eq!(word(&image,0x3fc0),0x7000_0000);
+        assert_eq!(word(&image,0x3fd0),0);
+        assert_eq!(&image.data[..4],b"\x7fELF");
+        assert!(image.unmapped.is_empty());
+        assert!(image.unapplied.is_empty());
+    }
+
+    #[test]
+    fn unresolved_imports_are_reported(){
+        let elf=ElfFile::parse(ELF.to_vec()).unwrap();
+        let image=LoadedImage::load(&elf,BASE,|_| None).unwrap();
+        let puts=image.unapplied.iter().find(|reloc| reloc.r_offset==0x4008).unwrap();
+        assert_eq!((puts.symbol.as_str(),puts.r_type,&puts.issue),("puts",abi::R_X86_64_JUMP_SLOT,&RelocIssue::Unresolved));
+        //弱符号与RELATIVE不算
+        assert!(image.unapplied.iter().all(|reloc| reloc.symbol!="__gmon_start__" && reloc.r_type!=abi::R_X86_64_RELATIVE));
+        assert_eq!(word(&image,0x3dc8),BASE+0x1190);
+    }
+
+    #[test]
+    fn unreadable_segment_is_reported(){
+        let mut data=ELF.to_vec();
+        set_phdr(&mut data,4,8,0xffff_0000);
+        let image=load(data).unwrap();
+        assert_eq!(image.unmapped,vec![4]);
+        assert!(image.read(0x2000,0x188).unwrap().iter().all(|&b| b==0));
+        assert_eq!(word(&image,0x3dc0),BASE+0x11d0);
+    }
+
+    #[test]
+    fn crafted_sizes(){
+        let mut data=ELF.to_vec();
+        set_phdr(&mut data,5,40,u64::MAX);
+        assert_eq!(load(data).unwrap_err(),LoadError::AddressOverflow(5));
+        let mut data=ELF.to_vec();
+        set_phdr(&mut data,5,40,0x1_0000_0000);
+        assert!(matches!(load(data),Err(LoadError::TooLarge(size)) if size>MAX_IMAGE_SIZE));
+        let mut data=ELF.to_vec();
+        for i in 2..6 {
+            data[0x40+i*56..0x40+i*56+4].copy_from_slice(&abi::PT_NULL.to_le_bytes());
+        }
+        assert_eq!(load(data).unwrap_err(),LoadError::NoLoadSegments);
+    }
+}
@@ -8,5 +8,31 @@ pub mod section;
 pub mod symbol;
 pub mod hash;
 pub mod relocation;
+pub mod dynamic;
+pub mod version;
+pub mod policy;
+pub mod note;
+pub mod got;
+pub mod elf_file;
+pub mod loader;
+pub mod static_reloc;
+pub mod hardening;
+pub mod lint;
+pub mod rebuild;
+pub mod builder;
+pub mod edit;
+pub mod section_edit;
+pub mod strip;
+pub mod symbol_edit;
+pub mod firmware;
+pub mod patch;
+pub mod stub;
+pub mod archive;
+pub mod inflate;
+pub mod container;
+pub mod carve;
+pub mod coredump;
+pub mod eh_frame;
+pub mod unwind;
 
 pub use file::file_utils;
\ No newline at end of file
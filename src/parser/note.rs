@@ -0,0 +1,370 @@
+use crate::parser::abi;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::file::Class;
+use crate::parser::section::SectionHeader;
+
+/// A note record: `namesz/descsz/type` header followed by the owner name and descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteEntry {
+    /// Owner name without the trailing NUL, e.g. `GNU`
+    pub name: String,
+    pub n_type: u32,
+    pub desc: Vec<u8>,
+}
+
+/// A decoded note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Note {
+    /// NT_GNU_ABI_TAG: target OS and the minimum kernel version
+    GnuAbiTag { os: u32, major: u32, minor: u32, subminor: u32 },
+    /// NT_GNU_BUILD_ID
+    GnuBuildId(Vec<u8>),
+    /// NT_GNU_GOLD_VERSION
+    GnuGoldVersion(String),
+    /// FDO packaging metadata, the JSON document of `.note.package`
+    FdoPackage(String),
+    /// Go build ID
+    GoBuildId(String),
+    /// Android ident: API level, plus the NDK version and build number when present
+    AndroidIdent { api_level: u32, ndk_version: Option<String>, ndk_build: Option<String> },
+    /// NT_GNU_PROPERTY_TYPE_0 (`.note.gnu.property`)
+    GnuProperty(Vec<GnuProperty>),
+    /// Any other note, kept as raw bytes with its owner name
+    Unknown(NoteEntry),
+}
+
+impl Note {
+    pub fn read_notes(file_path:&str)->Option<Vec<Note>>{
+        let elf=ElfFile::read_elf(file_path)?;
+        Some(Self::parse_notes(&elf))
+    }
+
+    /// Notes of SHT_NOTE sections, or of PT_NOTE segments when there are no note sections
+    pub fn parse_notes(elf:&ElfFile)->Vec<Note>{
+        let entries=if elf.find_section_by_type(abi::SHT_NOTE).is_some() {
+            NoteEntry::parse_section_notes(elf)
+        } else {
+            NoteEntry::parse_segment_notes(elf)
+        };
+        entries.into_iter().map(|entry| Self::decode(elf.ident(),elf.header.e_machine,entry)).collect()
+    }
+
+    /// `e_machine` selects how processor-specific GNU properties are decoded
+    pub fn decode(ident:(AnyEndian, Class),e_machine:u16,entry:NoteEntry)->Note{
+        let (endian,_)=ident;
+        let desc=&entry.desc;
+        match (entry.name.as_str(),entry.n_type) {
+            ("GNU",abi::NT_GNU_ABI_TAG) if desc.len()>=16 => Note::GnuAbiTag{
+                os:endian.parse_u32_at(0,desc),
+                major:endian.parse_u32_at(4,desc),
+                minor:endian.parse_u32_at(8,desc),
+                subminor:endian.parse_u32_at(12,desc),
+            },
+            ("GNU",abi::NT_GNU_BUILD_ID) => Note::GnuBuildId(entry.desc),
+            ("GNU",abi::NT_GNU_GOLD_VERSION) => Note::GnuGoldVersion(c_string(desc)),
+            ("GNU",abi::NT_GNU_PROPERTY_TYPE_0) => Note::GnuProperty(GnuProperty::parse(ident,e_machine,desc)),
+            ("FDO",abi::NT_FDO_PACKAGING_METADATA) => Note::FdoPackage(c_string(desc)),
+            ("Go",abi::NT_GO_BUILD_ID) => Note::GoBuildId(c_string(desc)),
+            ("Android",abi::NT_ANDROID_TYPE_IDENT) if desc.len()>=4 => {
+                //r14之后的NDK会附加两个char[64]
+                let field=|start:usize| desc.get(start..start+64).map(c_string);
+                Note::AndroidIdent{
+                    api_level:endian.parse_u32_at(0,desc),
+                    ndk_version:field(4),
+                    ndk_build:field(68),
+                }
+            }
+            _ => Note::Unknown(entry),
+        }
+    }
+
+    /// Name of the OS in an NT_GNU_ABI_TAG note
+    pub fn abi_os_name(os:u32)->&'static str{
+        match os {
+            0 => "Linux",
+            1 => "Hurd",
+            2 => "Solaris",
+            3 => "FreeBSD",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// One property of an NT_GNU_PROPERTY_TYPE_0 note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GnuProperty {
+    /// GNU_PROPERTY_STACK_SIZE
+    StackSize(u64),
+    /// GNU_PROPERTY_NO_COPY_ON_PROTECTED
+    NoCopyOnProtected,
+    /// GNU_PROPERTY_X86_FEATURE_1_AND: Intel CET support
+    X86Feature1And { ibt: bool, shstk: bool },
+    /// GNU_PROPERTY_X86_ISA_1_NEEDED: microarchitecture levels required to run
+    X86IsaNeeded { baseline: bool, v2: bool, v3: bool, v4: bool },
+    /// GNU_PROPERTY_X86_ISA_1_USED: microarchitecture levels used by the code
+    X86IsaUsed { baseline: bool, v2: bool, v3: bool, v4: bool },
+    /// Pre-2.32 GNU_PROPERTY_X86_ISA_1_USED, raw bits in the old ISA numbering
+    X86CompatIsaUsed(u32),
+    /// Pre-2.32 GNU_PROPERTY_X86_ISA_1_NEEDED, raw bits in the old ISA numbering
+    X86CompatIsaNeeded(u32),
+    /// GNU_PROPERTY_AARCH64_FEATURE_1_AND: BTI and PAC support
+    Aarch64Feature1And { bti: bool, pac: bool },
+    Unknown { pr_type: u32, data: Vec<u8> },
+}
+
+impl GnuProperty {
+    pub fn read_properties(file_path:&str)->Option<Vec<GnuProperty>>{
+        let elf=ElfFile::read_elf(file_path)?;
+        Some(Self::parse_properties(&elf))
+    }
+
+    /// Properties from PT_GNU_PROPERTY, or from `.note.gnu.property` when the
+    /// segment is missing (relocatable objects have no program headers)
+    pub fn parse_properties(elf:&ElfFile)->Vec<GnuProperty>{
+        let entries=match elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_GNU_PROPERTY) {
+            Some(ph) => elf.segment_data(ph)
+                .map(|bytes| NoteEntry::parse(elf.ident(),bytes,ph.p_align))
+                .unwrap_or_default(),
+            None => match elf.find_section(".note.gnu.property") {
+                Some(sh) => elf.section_data(sh)
+                    .map(|bytes| NoteEntry::parse(elf.ident(),bytes,sh.sh_addralign))
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            },
+        };
+        entries.into_iter()
+            .filter(|entry| entry.name=="GNU" && entry.n_type==abi::NT_GNU_PROPERTY_TYPE_0)
+            .flat_map(|entry| Self::parse(elf.ident(),elf.header.e_machine,&entry.desc))
+            .collect()
+    }
+
+    /// Parse the `pr_type/pr_datasz/pr_data` array of a property note descriptor.
+    /// Each property is padded to 8 bytes on ELF64 and 4 bytes on ELF32.
+    /// Processor-specific types (0xc0000000..=0xdfffffff) are decoded for `e_machine` only.
+    pub fn parse(ident:(AnyEndian, Class),e_machine:u16,desc:&[u8])->Vec<GnuProperty>{
+        let (endian,class)=ident;
+        let align=class.word_size();
+        let mut v=Vec::new();
+        let mut offset=0usize;
+        while offset+8<=desc.len() {
+            let pr_type=endian.parse_u32_at(offset,desc);
+            let pr_datasz=endian.parse_u32_at(offset+4,desc) as usize;
+            let start=offset+8;
+            let Some(data)=desc.get(start..start.saturating_add(pr_datasz)) else {
+                break;
+            };
+            let bits=if data.len()>=4 { endian.parse_u32_at(0,data) } else { 0 };
+            let isa=|bits:u32| (bits&abi::GNU_PROPERTY_X86_ISA_1_BASELINE!=0,bits&abi::GNU_PROPERTY_X86_ISA_1_V2!=0,
+                                bits&abi::GNU_PROPERTY_X86_ISA_1_V3!=0,bits&abi::GNU_PROPERTY_X86_ISA_1_V4!=0);
+            let x86=matches!(e_machine,abi::EM_386|abi::EM_X86_64);
+            let property=match pr_type {
+                abi::GNU_PROPERTY_STACK_SIZE => GnuProperty::StackSize(match data.len() {
+                    8 => endian.parse_u64_at(0,data),
+                    _ => bits as u64,
+                }),
+                abi::GNU_PROPERTY_NO_COPY_ON_PROTECTED => GnuProperty::NoCopyOnProtected,
+                //处理器相关的类型在不同架构上含义不同,0xc0000000在x86上是旧版ISA_1_USED
+                abi::GNU_PROPERTY_X86_FEATURE_1_AND if x86 => GnuProperty::X86Feature1And{
+                    ibt:bits&abi::GNU_PROPERTY_X86_FEATURE_1_IBT!=0,
+                    shstk:bits&abi::GNU_PROPERTY_X86_FEATURE_1_SHSTK!=0,
+                },
+                abi::GNU_PROPERTY_X86_ISA_1_NEEDED if x86 => {
+                    let (baseline,v2,v3,v4)=isa(bits);
+                    GnuProperty::X86IsaNeeded{baseline,v2,v3,v4}
+                }
+                abi::GNU_PROPERTY_X86_ISA_1_USED if x86 => {
+                    let (baseline,v2,v3,v4)=isa(bits);
+                    GnuProperty::X86IsaUsed{baseline,v2,v3,v4}
+                }
+                abi::GNU_PROPERTY_X86_COMPAT_ISA_1_USED if x86 => GnuProperty::X86CompatIsaUsed(bits),
+                abi::GNU_PROPERTY_X86_COMPAT_ISA_1_NEEDED if x86 => GnuProperty::X86CompatIsaNeeded(bits),
+                abi::GNU_PROPERTY_AARCH64_FEATURE_1_AND if e_machine==abi::EM_AARCH64 => GnuProperty::Aarch64Feature1And{
+                    bti:bits&abi::GNU_PROPERTY_AARCH64_FEATURE_1_BTI!=0,
+                    pac:bits&abi::GNU_PROPERTY_AARCH64_FEATURE_1_PAC!=0,
+                },
+                _ => GnuProperty::Unknown{pr_type,data:data.to_vec()},
+            };
+            v.push(property);
+            offset=align_up(start+pr_datasz,align);
+        }
+        v
+    }
+}
+
+impl NoteEntry {
+    pub fn parse_section_notes(elf:&ElfFile)->Vec<NoteEntry>{
+        let mut v=Vec::new();
+        for sh in elf.section_headers.iter().filter(|sh| sh.sh_type==abi::SHT_NOTE) {
+            if let Some(bytes)=elf.section_data(sh) {
+                v.extend(Self::parse(elf.ident(),bytes,sh.sh_addralign));
+            }
+        }
+        v
+    }
+
+    pub fn parse_segment_notes(elf:&ElfFile)->Vec<NoteEntry>{
+        let mut v=Vec::new();
+        for ph in elf.program_headers.iter().filter(|ph| ph.p_type==abi::PT_NOTE) {
+            if let Some(bytes)=elf.segment_data(ph) {
+                v.extend(Self::parse(elf.ident(),bytes,ph.p_align));
+            }
+        }
+        v
+    }
+
+    /// Iterate the records of one note section or segment.
+    ///
+    /// The header words are always 4 bytes; name and descriptor are padded to the
+    /// section/segment alignment, which is 8 for `.note.gnu.property` on ELF64.
+    pub fn parse(ident:(AnyEndian, Class),data:&[u8],align:u64)->Vec<NoteEntry>{
+        let (endian,_)=ident;
+        let align=if align==8 { 8 } else { 4 };
+        let mut v=Vec::new();
+        let mut offset=0usize;
+        while offset+12<=data.len() {
+            let namesz=endian.parse_u32_at(offset,data) as usize;
+            let descsz=endian.parse_u32_at(offset+4,data) as usize;
+            let n_type=endian.parse_u32_at(offset+8,data);
+            let name_start=offset+12;
+            let desc_start=align_up(name_start+namesz,align);
+            let desc_end=desc_start.saturating_add(descsz);
+            if desc_end>data.len() {
+                break;
+            }
+            v.push(NoteEntry{
+                name:SectionHeader::parse_string_at(&data[name_start..name_start+namesz],0).unwrap_or_default(),
+                n_type,
+                desc:data[desc_start..desc_end].to_vec(),
+            });
+            offset=align_up(desc_end,align);
+        }
+        v
+    }
+}
+
+fn align_up(value:usize,align:usize)->usize{
+    value.div_ceil(align)*align
+}
+
+fn c_string(bytes:&[u8])->String{
+    SectionHeader::parse_string_at(bytes,0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(pr_type:u32,bits:u32)->Vec<u8>{
+        let mut v=Vec::new();
+        v.extend_from_slice(&pr_type.to_le_bytes());
+        v.extend_from_slice(&4u32.to_le_bytes());
+        v.extend_from_slice(&bits.to_le_bytes());
+        v.extend_from_slice(&[0;4]);
+        v
+    }
+
+    fn property_note(desc:&[u8])->Vec<u8>{
+        let mut v=Vec::new();
+        v.extend_from_slice(&4u32.to_le_bytes());
+        v.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+        v.extend_from_slice(&abi::NT_GNU_PROPERTY_TYPE_0.to_le_bytes());
+        v.extend_from_slice(b"GNU\0");
+        v.extend_from_slice(desc);
+        v
+    }
+
+    fn decode(e_machine:u16,desc:&[u8])->Vec<GnuProperty>{
+        let ident=(AnyEndian::new(abi::ELFDATA2LSB),Class::ELF64);
+        let entries=NoteEntry::parse(ident,&property_note(desc),8);
+        assert_eq!(entries.len(),1);
+        match Note::decode(ident,e_machine,entries.into_iter().next().unwrap()) {
+            Note::GnuProperty(properties) => properties,
+            note => panic!("{:?}",note),
+        }
+    }
+
+    #[test]
+    fn x86_64_properties(){
+        let mut desc=property(0xc0000000,0x3);
+        desc.extend(property(abi::GNU_PROPERTY_X86_FEATURE_1_AND,abi::GNU_PROPERTY_X86_FEATURE_1_IBT|abi::GNU_PROPERTY_X86_FEATURE_1_SHSTK));
+        desc.extend(property(abi::GNU_PROPERTY_X86_ISA_1_NEEDED,abi::GNU_PROPERTY_X86_ISA_1_BASELINE|abi::GNU_PROPERTY_X86_ISA_1_V2));
+        assert_eq!(decode(abi::EM_X86_64,&desc),vec![
+            GnuProperty::X86CompatIsaUsed(0x3),
+            GnuProperty::X86Feature1And{ibt:true,shstk:true},
+            GnuProperty::X86IsaNeeded{baseline:true,v2:true,v3:false,v4:false},
+        ]);
+    }
+
+    #[test]
+    fn aarch64_properties(){
+        let mut desc=property(abi::GNU_PROPERTY_AARCH64_FEATURE_1_AND,abi::GNU_PROPERTY_AARCH64_FEATURE_1_BTI);
+        desc.extend(property(0xc0000002,0x3));
+        assert_eq!(decode(abi::EM_AARCH64,&desc),vec![
+            GnuProperty::Aarch64Feature1And{bti:true,pac:false},
+            GnuProperty::Unknown{pr_type:0xc0000002,data:vec![3,0,0,0]},
+        ]);
+    }
+
+    #[test]
+    fn processor_types_need_machine(){
+        let desc=property(0xc0000000,0x1);
+        assert_eq!(decode(abi::EM_RISCV,&desc),vec![GnuProperty::Unknown{pr_type:0xc0000000,data:vec![1,0,0,0]}]);
+    }
+
+    fn note(name:&str,n_type:u32,desc:&[u8])->Vec<u8>{
+        let mut v=Vec::new();
+        v.extend_from_slice(&(name.len() as u32+1).to_le_bytes());
+        v.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+        v.extend_from_slice(&n_type.to_le_bytes());
+        v.extend_from_slice(name.as_bytes());
+        v.push(0);
+        v.resize(v.len().next_multiple_of(4),0);
+        v.extend_from_slice(desc);
+        v.resize(v.len().next_multiple_of(4),0);
+        v
+    }
+
+    #[test]
+    fn de_notes(){
+        let mut elf=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        let build_id=vec![0x51,0x93,0x75,0x1d,0xc0,0xac,0x38,0xab,0xfc,0xe5,0x2c,0xb6,0xe7,0x31,0x28,0xb5,0xf0,0xba,0xb6,0x54];
+        let expected=vec![
+            Note::GnuProperty(vec![GnuProperty::X86IsaNeeded{baseline:true,v2:false,v3:false,v4:false}]),
+            Note::GnuBuildId(build_id),
+            Note::GnuAbiTag{os:0,major:3,minor:2,subminor:0},
+        ];
+        assert_eq!(Note::parse_notes(&elf),expected);
+        assert_eq!(Note::abi_os_name(0),"Linux");
+        //没有节头时从PT_NOTE读取,8字节对齐的属性段与4字节对齐的段分开解析
+        elf.section_headers.clear();
+        assert_eq!(Note::parse_notes(&elf),expected);
+    }
+
+    //名字长度不是4的倍数时按4字节对齐,截断的记录被丢弃
+    #[test]
+    fn common_notes(){
+        let ident=(AnyEndian::new(abi::ELFDATA2LSB),Class::ELF64);
+        let mut android=29u32.to_le_bytes().to_vec();
+        android.extend_from_slice(&[b'r';64]);
+        android.extend_from_slice(&[0;64]);
+        let mut data=note("FDO",abi::NT_FDO_PACKAGING_METADATA,b"{\"type\":\"rpm\"}\0");
+        data.extend(note("Go",abi::NT_GO_BUILD_ID,b"abc/def\0"));
+        data.extend(note("GNU",abi::NT_GNU_GOLD_VERSION,b"gold 1.16\0"));
+        data.extend(note("Android",abi::NT_ANDROID_TYPE_IDENT,&android));
+        data.extend(note("Xen",1,&[1,2,3]));
+        let truncated=note("GNU",abi::NT_GNU_BUILD_ID,&[0;20]);
+        data.extend_from_slice(&truncated[..truncated.len()-4]);
+
+        let entries=NoteEntry::parse(ident,&data,4);
+        assert_eq!(entries.len(),5);
+        let notes:Vec<Note>=entries.into_iter().map(|entry| Note::decode(ident,abi::EM_X86_64,entry)).collect();
+        assert_eq!(notes,vec![
+            Note::FdoPackage("{\"type\":\"rpm\"}".to_string()),
+            Note::GoBuildId("abc/def".to_string()),
+            Note::GnuGoldVersion("gold 1.16".to_string()),
+            Note::AndroidIdent{api_level:29,ndk_version:Some("r".repeat(64)),ndk_build:Some(String::new())},
+            Note::Unknown(NoteEntry{name:"Xen".to_string(),n_type:1,desc:vec![1,2,3]}),
+        ]);
+    }
+}
@@ -0,0 +1,402 @@
+use std::fs;
+use crate::parser::{abi, edit};
+use crate::parser::elf_file::ElfFile;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    Parse,
+    Io(String),
+    /// Patch file line (1-based; 0 for a well-formed JSON entry that is not a valid
+    /// patch) and reason
+    Syntax(usize, String),
+    /// No PT_LOAD maps the address
+    Unmapped(u64),
+    /// The bytes at the address are not backed by the file (`.bss` and the like)
+    Nobits(u64),
+    /// The patch runs past the end of the segment that maps its first byte
+    CrossesSegment(u64),
+}
+
+/// Bytes to write at a virtual address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub vaddr: u64,
+    pub bytes: Vec<u8>,
+}
+
+/// A patch as applied: where it landed in the file and what it replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedPatch {
+    pub vaddr: u64,
+    pub offset: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl Patch {
+    pub fn read_patches(file_path:&str)->Result<Vec<Patch>,PatchError>{
+        Self::parse_patches(&fs::read_to_string(file_path).map_err(|e| PatchError::Io(e.to_string()))?)
+    }
+
+    /// Parse a patch file, JSON if it starts with `[` or `{`, the text format otherwise
+    pub fn parse_patches(text:&str)->Result<Vec<Patch>,PatchError>{
+        match text.trim_start().starts_with(['[','{']) {
+            true => Self::parse_json(text),
+            false => Self::parse_text(text),
+        }
+    }
+
+    /// One patch per line, `ADDRESS: BYTES`, e.g. `0x401126: 90 90` or `0x401126 eb05`.
+    /// Addresses are hex with `0x` or decimal; `#` starts a comment.
+    pub fn parse_text(text:&str)->Result<Vec<Patch>,PatchError>{
+        let mut patches=Vec::new();
+        for (i,line) in text.lines().enumerate().map(|(i,line)| (i+1,line)) {
+            let line=line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (address,bytes)=line.split_once(':')
+                .or_else(|| line.split_once(char::is_whitespace))
+                .ok_or_else(|| PatchError::Syntax(i,"缺少字节".to_string()))?;
+            let vaddr=parse_address(address.trim()).ok_or_else(|| PatchError::Syntax(i,format!("无效地址 {}",address.trim())))?;
+            let bytes=parse_hex_bytes(bytes).ok_or_else(|| PatchError::Syntax(i,format!("无效字节 {}",bytes.trim())))?;
+            patches.push(Patch{vaddr,bytes});
+        }
+        Ok(patches)
+    }
+
+    /// A JSON array of `{"vaddr": ..., "bytes": ...}` objects, or an object holding
+    /// that array under `"patches"`. `vaddr` is a number or a string such as
+    /// `"0x401126"`; `bytes` is a hex string or an array of numbers.
+    pub fn parse_json(text:&str)->Result<Vec<Patch>,PatchError>{
+        let mut parser=JsonParser{text:text.as_bytes(),pos:0};
+        let value=parser.parse_document()?;
+        let entries=match value {
+            Json::Array(entries) => entries,
+            Json::Object(fields) => match fields.into_iter().find(|(key,_)| key=="patches") {
+                Some((_,Json::Array(entries))) => entries,
+                _ => return Err(parser.error("需要patches数组")),
+            },
+            _ => return Err(parser.error("需要数组或对象")),
+        };
+        let mut patches=Vec::new();
+        for (i,entry) in entries.into_iter().enumerate() {
+            let Json::Object(fields)=entry else {
+                return Err(PatchError::Syntax(0,format!("第{}个补丁不是对象",i+1)));
+            };
+            let field=|name:&str| fields.iter().find(|(key,_)| key==name).map(|(_,value)| value);
+            let vaddr=match field("vaddr") {
+                Some(Json::Number(text)) | Some(Json::String(text)) => parse_address(text),
+                _ => None,
+            };
+            let bytes=match field("bytes") {
+                Some(Json::String(text)) => parse_hex_bytes(text),
+                Some(Json::Array(values)) => values.iter().map(|value| match value {
+                    Json::Number(text) => parse_address(text).and_then(|byte| u8::try_from(byte).ok()),
+                    _ => None,
+                }).collect(),
+                _ => None,
+            };
+            match (vaddr,bytes) {
+                (Some(vaddr),Some(bytes)) if !bytes.is_empty() => patches.push(Patch{vaddr,bytes}),
+                _ => return Err(PatchError::Syntax(0,format!("第{}个补丁缺少有效的vaddr或bytes",i+1))),
+            }
+        }
+        Ok(patches)
+    }
+}
+
+/// Patch `input` and write the result to `output`, which is left alone on error
+pub fn patch_file(input:&str,output:&str,patches:&[Patch])->Result<Vec<AppliedPatch>,PatchError>{
+    let mut elf=ElfFile::read_elf(input).ok_or(PatchError::Parse)?;
+    let applied=elf.apply_patches(patches)?;
+    let permissions=fs::metadata(input).map(|m| m.permissions()).ok();
+    edit::write_output(output,&elf.data,permissions.as_ref()).map_err(|e| PatchError::Io(e.to_string()))?;
+    Ok(applied)
+}
+
+impl ElfFile {
+    /// File offset of `len` bytes at `vaddr`, all inside the file-backed part of one
+    /// PT_LOAD and outside any SHT_NOBITS section
+    pub fn patch_offset(&self,vaddr:u64,len:u64)->Result<u64,PatchError>{
+        let ph=self.program_headers.iter()
+            .filter(|ph| ph.p_type==abi::PT_LOAD)
+            .find(|ph| vaddr>=ph.p_vaddr && vaddr-ph.p_vaddr<ph.p_memsz)
+            .ok_or(PatchError::Unmapped(vaddr))?;
+        let end=vaddr.checked_add(len).ok_or(PatchError::CrossesSegment(vaddr))?;
+        if end-ph.p_vaddr>ph.p_memsz {
+            return Err(PatchError::CrossesSegment(vaddr));
+        }
+        if end-ph.p_vaddr>ph.p_filesz {
+            return Err(PatchError::Nobits(vaddr));
+        }
+        //.tbss不占地址空间,与后面的节地址重叠,不算
+        let nobits=self.section_headers.iter().any(|sh| {
+            sh.sh_type==abi::SHT_NOBITS && sh.sh_flags&abi::SHF_ALLOC!=0 && sh.sh_flags&abi::SHF_TLS==0
+                && vaddr<sh.sh_addr.saturating_add(sh.sh_size) && sh.sh_addr<end
+        });
+        if nobits {
+            return Err(PatchError::Nobits(vaddr));
+        }
+        let offset=(vaddr-ph.p_vaddr).checked_add(ph.p_offset)
+            .filter(|offset| offset.checked_add(len).is_some_and(|end| end<=self.data.len() as u64))
+            .ok_or(PatchError::Unmapped(vaddr))?;
+        Ok(offset)
+    }
+
+    /// Apply the patches in order. Every patch is checked first, so either all of them
+    /// are applied or none is.
+    pub fn apply_patches(&mut self,patches:&[Patch])->Result<Vec<AppliedPatch>,PatchError>{
+        let offsets=patches.iter()
+            .map(|patch| self.patch_offset(patch.vaddr,patch.bytes.len() as u64))
+            .collect::<Result<Vec<u64>,PatchError>>()?;
+        let mut applied=Vec::new();
+        for (patch,offset) in patches.iter().zip(offsets) {
+            let range=offset as usize..offset as usize+patch.bytes.len();
+            applied.push(AppliedPatch{
+                vaddr:patch.vaddr,
+                offset,
+                old:self.data[range.clone()].to_vec(),
+                new:patch.bytes.clone(),
+            });
+            self.data[range].copy_from_slice(&patch.bytes);
+        }
+        Ok(applied)
+    }
+}
+
+fn parse_address(text:&str)->Option<u64>{
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex,16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+//允许字节之间有空白
+fn parse_hex_bytes(text:&str)->Option<Vec<u8>>{
+    let digits:String=text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i+2],16).ok()).collect()
+}
+
+//数组与对象的最大嵌套层数,补丁文件只用到两三层
+const MAX_JSON_DEPTH: usize = 32;
+
+//补丁文件用到的JSON子集;数字保留原文,避免大地址丢精度
+enum Json {
+    //true/false/null,补丁里用不到它们的值
+    Literal,
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self,message:&str)->PatchError{
+        let line=self.text[..self.pos.min(self.text.len())].iter().filter(|&&c| c==b'\n').count()+1;
+        PatchError::Syntax(line,message.to_string())
+    }
+
+    fn parse_document(&mut self)->Result<Json,PatchError>{
+        let value=self.parse_value(0)?;
+        self.skip_whitespace();
+        match self.pos==self.text.len() {
+            true => Ok(value),
+            false => Err(self.error("多余的内容")),
+        }
+    }
+
+    fn skip_whitespace(&mut self){
+        while self.text.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos+=1;
+        }
+    }
+
+    fn expect(&mut self,c:u8)->Result<(),PatchError>{
+        self.skip_whitespace();
+        match self.text.get(self.pos)==Some(&c) {
+            true => {
+                self.pos+=1;
+                Ok(())
+            }
+            false => Err(self.error(&format!("需要 '{}'",c as char))),
+        }
+    }
+
+    fn parse_value(&mut self,depth:usize)->Result<Json,PatchError>{
+        self.skip_whitespace();
+        if depth>MAX_JSON_DEPTH {
+            return Err(self.error("嵌套过深"));
+        }
+        match self.text.get(self.pos) {
+            Some(b'[') => {
+                self.pos+=1;
+                let mut values=Vec::new();
+                self.skip_whitespace();
+                if self.text.get(self.pos)==Some(&b']') {
+                    self.pos+=1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.parse_value(depth+1)?);
+                    self.skip_whitespace();
+                    match self.text.get(self.pos) {
+                        Some(b',') => self.pos+=1,
+                        Some(b']') => {
+                            self.pos+=1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(self.error("需要 ',' 或 ']'")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos+=1;
+                let mut fields=Vec::new();
+                self.skip_whitespace();
+                if self.text.get(self.pos)==Some(&b'}') {
+                    self.pos+=1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let Json::String(key)=self.parse_value(depth+1)? else {
+                        return Err(self.error("键必须是字符串"));
+                    };
+                    self.expect(b':')?;
+                    fields.push((key,self.parse_value(depth+1)?));
+                    self.skip_whitespace();
+                    match self.text.get(self.pos) {
+                        Some(b',') => self.pos+=1,
+                        Some(b'}') => {
+                            self.pos+=1;
+                            return Ok(Json::Object(fields));
+                        }
+                        _ => return Err(self.error("需要 ',' 或 '}'")),
+                    }
+                }
+            }
+            Some(b'"') => self.parse_string().map(Json::String),
+            Some(b't') | Some(b'f') | Some(b'n') => {
+                for word in ["true","false","null"] {
+                    if self.text[self.pos..].starts_with(word.as_bytes()) {
+                        self.pos+=word.len();
+                        return Ok(Json::Literal);
+                    }
+                }
+                Err(self.error("无效的字面量"))
+            }
+            Some(c) if *c==b'-' || c.is_ascii_digit() => {
+                let start=self.pos;
+                while self.text.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c,b'-'|b'+'|b'.')) {
+                    self.pos+=1;
+                }
+                Ok(Json::Number(String::from_utf8_lossy(&self.text[start..self.pos]).into_owned()))
+            }
+            _ => Err(self.error("需要JSON值")),
+        }
+    }
+
+    fn parse_string(&mut self)->Result<String,PatchError>{
+        self.pos+=1;
+        let mut bytes=Vec::new();
+        loop {
+            match self.text.get(self.pos) {
+                Some(b'"') => {
+                    self.pos+=1;
+                    return String::from_utf8(bytes).map_err(|_| self.error("字符串不是UTF-8"));
+                }
+                Some(b'\\') => {
+                    let escaped=match self.text.get(self.pos+1) {
+                        Some(b'n') => b'\n',
+                        Some(b't') => b'\t',
+                        Some(b'r') => b'\r',
+                        Some(&c @ (b'"' | b'\\' | b'/')) => c,
+                        _ => return Err(self.error("不支持的转义")),
+                    };
+                    bytes.push(escaped);
+                    self.pos+=2;
+                }
+                Some(&c) => {
+                    bytes.push(c);
+                    self.pos+=1;
+                }
+                None => return Err(self.error("字符串未结束")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ELF: &[u8] = include_bytes!("../test_elf/de");
+
+    #[test]
+    fn text_format(){
+        let patches=Patch::parse_patches("# 跳过检查\n0x10f0: 90 90\n\n4336 eb05  # 十进制地址\n").unwrap();
+        assert_eq!(patches,vec![Patch{vaddr:0x10f0,bytes:vec![0x90,0x90]},Patch{vaddr:4336,bytes:vec![0xeb,0x05]}]);
+        assert_eq!(Patch::parse_text("0x10f0: 9"),Err(PatchError::Syntax(1,"无效字节 9".to_string())));
+        assert!(matches!(Patch::parse_text("\nzz 90"),Err(PatchError::Syntax(2,_))));
+    }
+
+    #[test]
+    fn json_format(){
+        let text=r#"{"patches": [{"vaddr": "0x10f0", "bytes": "90 90"}, {"vaddr": 4336, "bytes": [235, 5], "note": null}]}"#;
+        let patches=Patch::parse_patches(text).unwrap();
+        assert_eq!(patches,vec![Patch{vaddr:0x10f0,bytes:vec![0x90,0x90]},Patch{vaddr:4336,bytes:vec![0xeb,0x05]}]);
+        assert_eq!(Patch::parse_json(r#"[{"vaddr": 1}]"#),Err(PatchError::Syntax(0,"第1个补丁缺少有效的vaddr或bytes".to_string())));
+        assert!(matches!(Patch::parse_json("[{\"vaddr\": 1,\n}]"),Err(PatchError::Syntax(2,_))));
+    }
+
+    #[test]
+    fn json_depth_is_bounded(){
+        let text="[".repeat(100_000);
+        assert_eq!(Patch::parse_json(&text),Err(PatchError::Syntax(1,"嵌套过深".to_string())));
+        //外层数组与对象占两层
+        let nested=|n:usize| format!(r#"[{{"vaddr": 1, "bytes": "90", "extra": {}{}}}]"#,"[".repeat(n),"]".repeat(n));
+        assert!(Patch::parse_json(&nested(MAX_JSON_DEPTH-1)).is_ok());
+        assert_eq!(Patch::parse_json(&nested(MAX_JSON_DEPTH)),Err(PatchError::Syntax(1,"嵌套过深".to_string())));
+    }
+
+    #[test]
+    fn applies_in_place(){
+        let mut elf=ElfFile::parse(TEST_ELF.to_vec()).unwrap();
+        let applied=elf.apply_patches(&[Patch{vaddr:0x10f0,bytes:vec![0x90,0x90]}]).unwrap();
+        assert_eq!(applied,vec![AppliedPatch{vaddr:0x10f0,offset:0x10f0,old:TEST_ELF[0x10f0..0x10f2].to_vec(),new:vec![0x90,0x90]}]);
+        assert_eq!(elf.data[0x10f0..0x10f2],[0x90,0x90]);
+        //.data在LOAD5里,偏移与地址差0x1000
+        assert_eq!(elf.patch_offset(0x4058,8),Ok(0x3058));
+    }
+
+    #[test]
+    fn rejects_unbacked_bytes(){
+        let mut elf=ElfFile::parse(TEST_ELF.to_vec()).unwrap();
+        //.bss位于0x4068,LOAD5的内存范围到0x4070为止
+        assert_eq!(elf.patch_offset(0x4068,1),Err(PatchError::Nobits(0x4068)));
+        assert_eq!(elf.patch_offset(0x4064,8),Err(PatchError::Nobits(0x4064)));
+        assert_eq!(elf.patch_offset(0x406c,8),Err(PatchError::CrossesSegment(0x406c)));
+        assert_eq!(elf.patch_offset(0x5000,1),Err(PatchError::Unmapped(0x5000)));
+        //有一个补丁不合法时全部不写入
+        let patches=[Patch{vaddr:0x10f0,bytes:vec![0x90]},Patch{vaddr:0x4068,bytes:vec![1]}];
+        assert_eq!(elf.apply_patches(&patches),Err(PatchError::Nobits(0x4068)));
+        assert_eq!(elf.data,TEST_ELF);
+        //.bss挪到0x4060且sh_size为极大值,按节判断也不能溢出
+        let mut data=TEST_ELF.to_vec();
+        let bss=0x3198+26*64;
+        data[bss+16..bss+24].copy_from_slice(&0x4060u64.to_le_bytes());
+        data[bss+32..bss+40].copy_from_slice(&u64::MAX.to_le_bytes());
+        let elf=ElfFile::parse(data).unwrap();
+        assert_eq!(elf.patch_offset(0x4058,8),Ok(0x3058));
+        assert_eq!(elf.patch_offset(0x4060,4),Err(PatchError::Nobits(0x4060)));
+    }
+}
@@ -0,0 +1,241 @@
+use std::cmp::Ordering;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::version::VersionTables;
+
+/// Highest version required from one library for one version namespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRequirement {
+    /// Library the version is needed from (vn_file)
+    pub library: String,
+    /// Version namespace, e.g. `GLIBC`, `GLIBCXX`, `CXXABI`
+    pub prefix: String,
+    /// Full version name, e.g. `GLIBC_2.34`
+    pub name: String,
+    /// Numeric components, empty for non-numeric versions such as `GLIBC_PRIVATE`
+    pub version: Vec<u32>,
+}
+
+/// A manylinux or musllinux platform policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub name: &'static str,
+    /// Libraries a wheel may link against without bundling them
+    pub libraries: &'static [&'static str],
+    /// Highest allowed version per namespace; namespaces not listed are not allowed
+    pub symbol_versions: &'static [(&'static str, &'static str)],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyResult {
+    pub name: String,
+    pub compatible: bool,
+    /// Reasons the binary does not satisfy the policy
+    pub violations: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyReport {
+    /// DT_NEEDED libraries
+    pub needed: Vec<String>,
+    pub requirements: Vec<VersionRequirement>,
+    /// One result per built-in policy, oldest first
+    pub results: Vec<PolicyResult>,
+}
+
+const GLIBC_LIBRARIES: &[&str] = &[
+    "libgcc_s.so.1", "libstdc++.so.6", "libm.so.6", "libdl.so.2", "librt.so.1", "libc.so.6",
+    "libnsl.so.1", "libutil.so.1", "libpthread.so.0", "libresolv.so.2", "libX11.so.6",
+    "libXext.so.6", "libXrender.so.1", "libICE.so.6", "libSM.so.6", "libGL.so.1",
+    "libgobject-2.0.so.0", "libgthread-2.0.so.0", "libglib-2.0.so.0",
+    "ld-linux-x86-64.so.2", "ld-linux-aarch64.so.1", "ld-linux.so.2",
+];
+
+const MANYLINUX1_LIBRARIES: &[&str] = &[
+    "libgcc_s.so.1", "libstdc++.so.6", "libm.so.6", "libdl.so.2", "librt.so.1", "libc.so.6",
+    "libnsl.so.1", "libutil.so.1", "libpthread.so.0", "libresolv.so.2", "libX11.so.6",
+    "libXext.so.6", "libXrender.so.1", "libICE.so.6", "libSM.so.6", "libGL.so.1",
+    "libgobject-2.0.so.0", "libgthread-2.0.so.0", "libglib-2.0.so.0",
+    "libpanelw.so.5", "libncursesw.so.5",
+    "ld-linux-x86-64.so.2", "ld-linux.so.2",
+];
+
+const MUSL_LIBRARIES: &[&str] = &[
+    "libc.so", "libc.musl-x86_64.so.1", "libc.musl-aarch64.so.1", "ld-musl-x86_64.so.1",
+    "ld-musl-aarch64.so.1", "libgcc_s.so.1", "libstdc++.so.6",
+];
+
+/// Built-in policies, from the widest compatibility to the narrowest
+pub const POLICIES: &[Policy] = &[
+    Policy { name: "manylinux_2_5", libraries: MANYLINUX1_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.5"), ("CXXABI", "1.3.1"), ("GLIBCXX", "3.4.8"), ("GCC", "4.2.0")] },
+    Policy { name: "manylinux_2_12", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.12"), ("CXXABI", "1.3.3"), ("GLIBCXX", "3.4.13"), ("GCC", "4.5.0")] },
+    Policy { name: "manylinux_2_17", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.17"), ("CXXABI", "1.3.7"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.19"), ("GCC", "4.8.0")] },
+    Policy { name: "manylinux_2_24", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.24"), ("CXXABI", "1.3.10"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.22"), ("GCC", "6.0.0")] },
+    Policy { name: "manylinux_2_27", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.27"), ("CXXABI", "1.3.11"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.24"), ("GCC", "7.0.0")] },
+    Policy { name: "manylinux_2_28", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.28"), ("CXXABI", "1.3.11"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.25"), ("GCC", "7.0.0")] },
+    Policy { name: "manylinux_2_31", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.31"), ("CXXABI", "1.3.12"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.28"), ("GCC", "7.0.0")] },
+    Policy { name: "manylinux_2_34", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.34"), ("CXXABI", "1.3.13"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.29"), ("GCC", "7.0.0")] },
+    Policy { name: "manylinux_2_35", libraries: GLIBC_LIBRARIES,
+        symbol_versions: &[("GLIBC", "2.35"), ("CXXABI", "1.3.13"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.30"), ("GCC", "12.0.0")] },
+    //musl没有符号版本,只限制libstdc++/libgcc的版本
+    Policy { name: "musllinux_1_1", libraries: MUSL_LIBRARIES,
+        symbol_versions: &[("CXXABI", "1.3.12"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.28"), ("GCC", "7.0.0")] },
+    Policy { name: "musllinux_1_2", libraries: MUSL_LIBRARIES,
+        symbol_versions: &[("CXXABI", "1.3.13"), ("CXXABI_TM", "1"), ("GLIBCXX", "3.4.30"), ("GCC", "12.0.0")] },
+];
+
+impl PolicyReport {
+    pub fn read_report(file_path:&str)->Option<PolicyReport>{
+        let elf=ElfFile::read_elf(file_path)?;
+        Some(Self::parse_report(&elf))
+    }
+
+    pub fn parse_report(elf:&ElfFile)->PolicyReport{
+        let needed=elf.needed_libraries();
+        let requirements=match VersionTables::parse_versions(elf) {
+            Some(versions) => Self::requirements(&versions),
+            None => Vec::new(),
+        };
+        let results=POLICIES.iter().map(|policy| policy.check(&needed,&requirements)).collect();
+        PolicyReport{needed,requirements,results}
+    }
+
+    /// Highest required version per library and namespace, from `.gnu.version_r`
+    pub fn requirements(versions:&VersionTables)->Vec<VersionRequirement>{
+        let mut v:Vec<VersionRequirement>=Vec::new();
+        for need in &versions.verneed {
+            for aux in &need.entries {
+                let (prefix,version)=split_version(&aux.name);
+                let candidate=VersionRequirement{
+                    library:need.file.clone(),
+                    prefix:prefix.to_string(),
+                    name:aux.name.clone(),
+                    version,
+                };
+                match v.iter_mut().find(|r| r.library==candidate.library && r.prefix==candidate.prefix) {
+                    Some(existing) => {
+                        if compare(&candidate.version,&existing.version)==Ordering::Greater {
+                            *existing=candidate;
+                        }
+                    }
+                    None => v.push(candidate),
+                }
+            }
+        }
+        v
+    }
+
+    /// The widest compatible policy, i.e. the tag a wheel should carry
+    pub fn best_policy(&self)->Option<&PolicyResult>{
+        self.results.iter().find(|r| r.compatible && r.name.starts_with("manylinux"))
+            .or_else(|| self.results.iter().find(|r| r.compatible))
+    }
+}
+
+impl Policy {
+    pub fn check(&self,needed:&[String],requirements:&[VersionRequirement])->PolicyResult{
+        let mut violations=Vec::new();
+        for library in needed {
+            if !self.libraries.contains(&library.as_str()) {
+                violations.push(format!("{} is not an allowed library",library));
+            }
+        }
+        for requirement in requirements {
+            let allowed=self.symbol_versions.iter().find(|(prefix,_)| *prefix==requirement.prefix);
+            match allowed {
+                Some((_,max)) if !requirement.version.is_empty() => {
+                    if compare(&requirement.version,&split_version(&format!("_{}",max)).1)==Ordering::Greater {
+                        violations.push(format!("{} from {} is newer than {}_{}",requirement.name,requirement.library,requirement.prefix,max));
+                    }
+                }
+                _ => violations.push(format!("{} from {} is not allowed",requirement.name,requirement.library)),
+            }
+        }
+        PolicyResult{
+            name:self.name.to_string(),
+            compatible:violations.is_empty(),
+            violations,
+        }
+    }
+}
+
+//"GLIBCXX_3.4.29" -> ("GLIBCXX",[3,4,29]); 非数字版本返回空列表
+fn split_version(name:&str)->(&str,Vec<u32>){
+    if let Some((prefix,number))=name.rsplit_once('_') {
+        let parts:Option<Vec<u32>>=number.split('.').map(|p| p.parse().ok()).collect();
+        if let Some(parts)=parts {
+            return (prefix,parts);
+        }
+    }
+    (name,Vec::new())
+}
+
+fn compare(a:&[u32],b:&[u32])->Ordering{
+    for i in 0..a.len().max(b.len()) {
+        let ordering=a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering!=Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::version::{VersionNeed, VersionNeedAux};
+
+    fn need(file:&str,names:&[&str])->VersionNeed{
+        VersionNeed{
+            file:file.to_string(),
+            entries:names.iter().map(|name| VersionNeedAux{name:name.to_string(),hash:0,flags:0,index:0}).collect(),
+        }
+    }
+
+    #[test]
+    fn de_report(){
+        let elf=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        let report=PolicyReport::parse_report(&elf);
+        assert_eq!(report.requirements,[VersionRequirement{library:"libc.so.6".to_string(),prefix:"GLIBC".to_string(),
+                                                           name:"GLIBC_2.34".to_string(),version:vec![2,34]}]);
+        //libz不在任何策略的白名单里
+        assert_eq!(report.results.len(),POLICIES.len());
+        assert!(report.results.iter().all(|result| result.violations.contains(&"libz.so.1 is not an allowed library".to_string())));
+        assert_eq!(report.best_policy(),None);
+
+        let needed=vec!["libc.so.6".to_string()];
+        let compatible:Vec<&str>=POLICIES.iter().filter(|policy| policy.check(&needed,&report.requirements).compatible)
+            .map(|policy| policy.name).collect();
+        assert_eq!(compatible,["manylinux_2_34","manylinux_2_35"]);
+        let result=POLICIES[6].check(&needed,&report.requirements);
+        assert_eq!(result.violations,["GLIBC_2.34 from libc.so.6 is newer than GLIBC_2.31"]);
+    }
+
+    //每个库和命名空间只保留最高的版本,按数字而不是字符串比较
+    #[test]
+    fn highest_versions(){
+        let versions=VersionTables{versym:Vec::new(),verdef:Vec::new(),verneed:vec![
+            need("libstdc++.so.6",&["GLIBCXX_3.4.9","GLIBCXX_3.4.29","CXXABI_1.3.13","GLIBCXX_3.4"]),
+            need("libc.so.6",&["GLIBC_2.2.5","GLIBC_2.17","GLIBC_PRIVATE"]),
+        ]};
+        let requirements=PolicyReport::requirements(&versions);
+        let names:Vec<(&str,&str)>=requirements.iter().map(|r| (r.library.as_str(),r.name.as_str())).collect();
+        assert_eq!(names,[("libstdc++.so.6","GLIBCXX_3.4.29"),("libstdc++.so.6","CXXABI_1.3.13"),
+                          ("libc.so.6","GLIBC_2.17"),("libc.so.6","GLIBC_PRIVATE")]);
+
+        let needed=vec!["libstdc++.so.6".to_string(),"libc.so.6".to_string()];
+        let result=POLICIES.iter().find(|policy| policy.name=="manylinux_2_34").unwrap().check(&needed,&requirements);
+        assert_eq!(result.violations,["GLIBC_PRIVATE from libc.so.6 is not allowed"]);
+        //musl的策略不允许GLIBC命名空间和glibc的库
+        let result=POLICIES.iter().find(|policy| policy.name=="musllinux_1_2").unwrap().check(&needed[..1],&requirements[..2]);
+        assert!(result.compatible);
+        assert!(!POLICIES[POLICIES.len()-1].check(&needed,&requirements[2..3]).compatible);
+    }
+}
@@ -0,0 +1,532 @@
+use std::fs;
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::elf_header::elf_header;
+use crate::parser::endian::{EndianParse, EndianWrite};
+use crate::parser::file::Class;
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::section::SectionHeader;
+use crate::parser::segment::ProgramHeader;
+use crate::parser::symbol::Symbol;
+
+/// Errors reported while rebuilding a section header table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebuildError {
+    /// The file could not be parsed as ELF
+    Parse,
+    /// There is no PT_DYNAMIC segment to recover the tables from
+    NoDynamic,
+    /// Offset or address plus size of this program header wraps around
+    SegmentOverflow(usize),
+    /// The segments reach past `MAX_IMAGE_SIZE` bytes of file
+    TooLarge(u64),
+    Io(String),
+}
+
+/// Largest output `RebuiltFile::rebuild` grows the image to; segment sizes come
+/// straight from the program headers
+pub const MAX_IMAGE_SIZE: u64 = 1<<30;
+
+/// Where the input came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RebuildOptions {
+    /// The input is a memory dump starting at the first PT_LOAD page, so every
+    /// segment sits at its virtual address instead of its file offset
+    pub dumped: bool,
+    /// Load bias of the dumped image. Dynamic entries the loader relocated in
+    /// place are translated back to link-time addresses.
+    pub load_bias: u64,
+}
+
+/// An ELF image with a synthesized section header table and `.shstrtab`
+#[derive(Debug, Clone)]
+pub struct RebuiltFile {
+    pub data: Vec<u8>,
+    /// The new section headers, including the null section and `.shstrtab`
+    pub section_headers: Vec<SectionHeader>,
+}
+
+//按地址收集的待生成节
+struct Recovered {
+    name: String,
+    sh_type: u32,
+    addr: u64,
+    size: u64,
+    entsize: u64,
+    align: u64,
+}
+
+impl RebuiltFile {
+    pub fn read_rebuild(file_path:&str,options:RebuildOptions)->Result<RebuiltFile,RebuildError>{
+        let data=fs::read(file_path).map_err(|e| RebuildError::Io(e.to_string()))?;
+        Self::rebuild(data,options)
+    }
+
+    pub fn write(&self,file_path:&str)->Result<(),RebuildError>{
+        fs::write(file_path,&self.data).map_err(|e| RebuildError::Io(e.to_string()))
+    }
+
+    /// Synthesize section headers for `.dynsym`, `.dynstr`, the hash tables, the
+    /// dynamic relocations, `.plt`, `.got`, the init/fini arrays, `.dynamic`,
+    /// `.text`, `.data` and `.bss` from PT_LOAD and PT_DYNAMIC alone. Any
+    /// existing section header table is discarded.
+    pub fn rebuild(data:Vec<u8>,options:RebuildOptions)->Result<RebuiltFile,RebuildError>{
+        let mut elf=ElfFile::parse(data).ok_or(RebuildError::Parse)?;
+        elf.section_headers.clear();
+        if options.dumped {
+            Self::fix_dumped_segments(&mut elf)?;
+            Self::unrelocate_dynamic(&mut elf,options.load_bias);
+        }
+        if !elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_DYNAMIC) {
+            return Err(RebuildError::NoDynamic);
+        }
+        //丢掉最后一个段之后的内容(旧的节头表等)
+        let image_end=Self::image_end(&elf,true)?.unwrap_or(elf.data.len() as u64);
+        elf.data.resize(image_end as usize,0);
+
+        let recovered=Self::recover_sections(&elf);
+        let section_headers=Self::layout_sections(&elf,recovered);
+        Ok(Self::emit(elf,section_headers))
+    }
+
+    /// Segments of a memory dump are found at `p_vaddr - start`, where `start` is the
+    /// page holding the first PT_LOAD; the whole memory image is file backed.
+    fn fix_dumped_segments(elf:&mut ElfFile)->Result<(),RebuildError>{
+        let Some(first)=elf.program_headers.iter().filter(|ph| ph.p_type==abi::PT_LOAD).min_by_key(|ph| ph.p_vaddr) else {
+            return Ok(());
+        };
+        let page=first.p_align.max(1);
+        let start=first.p_vaddr-first.p_vaddr%page;
+        for ph in elf.program_headers.iter_mut() {
+            if ph.p_memsz==0 || ph.p_vaddr<start {
+                continue;
+            }
+            ph.p_offset=ph.p_vaddr-start;
+            if ph.p_type==abi::PT_LOAD {
+                ph.p_filesz=ph.p_memsz;
+            }
+        }
+        let end=Self::image_end(elf,false)?.unwrap_or(0);
+        if (elf.data.len() as u64)<end {
+            elf.data.resize(end as usize,0);
+        }
+        let ident=elf.ident();
+        let entsize=elf.header.e_phentsize as usize;
+        for (i,ph) in elf.program_headers.iter().enumerate() {
+            if let Some(offset)=ElfFile::table_entry(elf.header.e_phoff,i,entsize,elf.data.len()) {
+                ph.write_at(ident,offset,&mut elf.data);
+            }
+        }
+        Ok(())
+    }
+
+    /// Largest `p_offset+p_filesz` of the (PT_LOAD) segments, None without any.
+    /// Every segment's offset and address must leave room for its sizes, which the
+    /// layout code relies on.
+    fn image_end(elf:&ElfFile,loads_only:bool)->Result<Option<u64>,RebuildError>{
+        let mut end=None;
+        for (i,ph) in elf.program_headers.iter().enumerate() {
+            if loads_only && ph.p_type!=abi::PT_LOAD {
+                continue;
+            }
+            let size=ph.p_filesz.max(ph.p_memsz);
+            if ph.p_offset.checked_add(size).is_none() || ph.p_vaddr.checked_add(size).is_none() {
+                return Err(RebuildError::SegmentOverflow(i));
+            }
+            end=end.max(Some(ph.p_offset+ph.p_filesz));
+        }
+        match end {
+            Some(end) if end>MAX_IMAGE_SIZE => Err(RebuildError::TooLarge(end)),
+            end => Ok(end),
+        }
+    }
+
+    //glibc会把.dynamic中的地址就地加上装载基址,这里减回去
+    fn unrelocate_dynamic(elf:&mut ElfFile,load_bias:u64){
+        const ADDRESS_TAGS:&[i64]=&[
+            abi::DT_HASH,abi::DT_GNU_HASH,abi::DT_STRTAB,abi::DT_SYMTAB,abi::DT_RELA,abi::DT_REL,
+            abi::DT_JMPREL,abi::DT_PLTGOT,abi::DT_VERSYM,abi::DT_VERDEF,abi::DT_VERNEED,
+            abi::DT_INIT,abi::DT_FINI,abi::DT_INIT_ARRAY,abi::DT_FINI_ARRAY,
+        ];
+        if load_bias==0 {
+            return;
+        }
+        let Some(dynamic)=elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_DYNAMIC).copied() else {
+            return;
+        };
+        let ident=elf.ident();
+        let (endian,class)=ident;
+        let size=Dyn::size_for(class);
+        let dyns=elf.dynamic();
+        for (i,entry) in dyns.iter().enumerate() {
+            if !ADDRESS_TAGS.contains(&entry.d_tag) || elf.vaddr_to_offset(entry.d_val).is_some() {
+                continue;
+            }
+            let Some(unrelocated)=entry.d_val.checked_sub(load_bias) else {
+                continue;
+            };
+            if elf.vaddr_to_offset(unrelocated).is_none() {
+                continue;
+            }
+            let offset=dynamic.p_offset as usize+i*size;
+            match class {
+                Class::ELF32 => endian.write_u32_at(offset+4,&mut elf.data,unrelocated as u32),
+                Class::ELF64 => endian.write_u64_at(offset+8,&mut elf.data,unrelocated),
+            }
+        }
+    }
+
+    fn recover_sections(elf:&ElfFile)->Vec<Recovered>{
+        let class=elf.header.class;
+        let word=class.word_size() as u64;
+        let endian=elf.header.endianness;
+        let dyns=elf.dynamic();
+        let find=|tag| Dyn::find(&dyns,tag);
+        let mut v=Vec::new();
+        let mut push=|name:&str,sh_type,addr,size,entsize,align| {
+            if size>0 {
+                v.push(Recovered{name:name.to_string(),sh_type,addr,size,entsize,align});
+            }
+        };
+
+        let symbol_size=Symbol::size_for(class) as u64;
+        let symbol_count=elf.dynamic_symbol_count().or_else(|| {
+            let (symtab,strtab)=(find(abi::DT_SYMTAB)?,find(abi::DT_STRTAB)?);
+            (strtab>symtab).then(|| (strtab-symtab)/symbol_size)
+        });
+        if let (Some(addr),Some(count))=(find(abi::DT_SYMTAB),symbol_count) {
+            push(".dynsym",abi::SHT_DYNSYM,addr,count*symbol_size,symbol_size,word);
+        }
+        if let (Some(addr),Some(size))=(find(abi::DT_STRTAB),find(abi::DT_STRSZ)) {
+            push(".dynstr",abi::SHT_STRTAB,addr,size,0,1);
+        }
+        if let Some(addr)=find(abi::DT_HASH) {
+            if let Some(head)=elf.vaddr_data(addr,8) {
+                let nbucket=endian.parse_u32_at(0,head) as u64;
+                let nchain=endian.parse_u32_at(4,head) as u64;
+                push(".hash",abi::SHT_HASH,addr,8+(nbucket+nchain)*4,4,word);
+            }
+        }
+        if let (Some(addr),Some(count))=(find(abi::DT_GNU_HASH),symbol_count) {
+            if let Some(head)=elf.vaddr_data(addr,16) {
+                let nbucket=endian.parse_u32_at(0,head) as u64;
+                let symoffset=endian.parse_u32_at(4,head) as u64;
+                let bloom_size=endian.parse_u32_at(8,head) as u64;
+                let size=16+bloom_size*word+nbucket*4+count.saturating_sub(symoffset)*4;
+                push(".gnu.hash",abi::SHT_GNU_HASH,addr,size,0,word);
+            }
+        }
+
+        //GLOB_DAT的目标给出.got的范围
+        let (glob_dat,jump_slot)=Self::slot_types(elf.header.e_machine);
+        let mut got_slots=Vec::new();
+        if let (Some(addr),Some(size))=(find(abi::DT_RELA),find(abi::DT_RELASZ)) {
+            push(".rela.dyn",abi::SHT_RELA,addr,size,Rela::size_for(class) as u64,word);
+            if let Some(bytes)=elf.vaddr_data(addr,size) {
+                got_slots.extend(Rela::parse(elf.ident(),bytes,size).iter().filter(|r| r.r_type==glob_dat).map(|r| r.r_offset));
+            }
+        }
+        if let (Some(addr),Some(size))=(find(abi::DT_REL),find(abi::DT_RELSZ)) {
+            push(".rel.dyn",abi::SHT_REL,addr,size,Rel::size_for(class) as u64,word);
+            if let Some(bytes)=elf.vaddr_data(addr,size) {
+                got_slots.extend(Rel::parse(elf.ident(),bytes,size).iter().filter(|r| r.r_type==glob_dat).map(|r| r.r_offset));
+            }
+        }
+        let mut plt_slots=0;
+        let jmprel=find(abi::DT_JMPREL).zip(find(abi::DT_PLTRELSZ));
+        if let Some((addr,size))=jmprel {
+            let (name,sh_type,entsize)=if find(abi::DT_PLTREL)==Some(abi::DT_RELA as u64) {
+                (".rela.plt",abi::SHT_RELA,Rela::size_for(class) as u64)
+            } else {
+                (".rel.plt",abi::SHT_REL,Rel::size_for(class) as u64)
+            };
+            push(name,sh_type,addr,size,entsize,word);
+            plt_slots=size/entsize;
+            if let Some(bytes)=elf.vaddr_data(addr,size) {
+                let slots:Vec<u64>=match sh_type {
+                    abi::SHT_RELA => Rela::parse(elf.ident(),bytes,size).iter().filter(|r| r.r_type==jump_slot).map(|r| r.r_offset).collect(),
+                    _ => Rel::parse(elf.ident(),bytes,size).iter().filter(|r| r.r_type==jump_slot).map(|r| r.r_offset).collect(),
+                };
+                got_slots.extend(slots);
+            }
+        }
+        let pltgot=find(abi::DT_PLTGOT);
+        if let Some(pltgot)=pltgot {
+            //保留的三项
+            got_slots.push(pltgot);
+            got_slots.push(pltgot+(2+plt_slots)*word);
+        }
+        if let (Some(start),Some(end))=(got_slots.iter().min(),got_slots.iter().max()) {
+            push(".got",abi::SHT_PROGBITS,*start,end+word-start,word,word);
+        }
+        if let Some((addr,size,entsize))=Self::locate_plt(elf,pltgot,jmprel,plt_slots,word) {
+            push(".plt",abi::SHT_PROGBITS,addr,size,entsize,16);
+        }
+
+        if let (Some(addr),Some(size))=(find(abi::DT_INIT_ARRAY),find(abi::DT_INIT_ARRAYSZ)) {
+            push(".init_array",abi::SHT_INIT_ARRAY,addr,size,word,word);
+        }
+        if let (Some(addr),Some(size))=(find(abi::DT_FINI_ARRAY),find(abi::DT_FINI_ARRAYSZ)) {
+            push(".fini_array",abi::SHT_FINI_ARRAY,addr,size,word,word);
+        }
+        if let Some(ph)=elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_DYNAMIC) {
+            push(".dynamic",abi::SHT_DYNAMIC,ph.p_vaddr,ph.p_memsz,Dyn::size_for(class) as u64,word);
+        }
+        v
+    }
+
+    fn slot_types(e_machine:u16)->(u32,u32){
+        match e_machine {
+            abi::EM_386 => (abi::R_386_GLOB_DAT,abi::R_386_JMP_SLOT),
+            abi::EM_ARM => (abi::R_ARM_GLOB_DAT,abi::R_ARM_JUMP_SLOT),
+            abi::EM_AARCH64 => (abi::R_AARCH64_GLOB_DAT,abi::R_AARCH64_JUMP_SLOT),
+            _ => (abi::R_X86_64_GLOB_DAT,abi::R_X86_64_JUMP_SLOT),
+        }
+    }
+
+    /// Find `.plt` through the lazy-binding pointer in the first PLT GOT slot.
+    /// x86 slots point at (or 6 bytes into) their own stub, ARM and AArch64 slots
+    /// point at PLT0. When the dump already holds resolved pointers, ARM and AArch64
+    /// fall back to the linker's layout, which places `.plt` right after `.rel[a].plt`.
+    fn locate_plt(elf:&ElfFile,pltgot:Option<u64>,jmprel:Option<(u64,u64)>,slots:u64,word:u64)->Option<(u64,u64,u64)>{
+        if slots==0 {
+            return None;
+        }
+        let (header_size,entry_size)=match elf.header.e_machine {
+            abi::EM_386 | abi::EM_X86_64 => (16,16),
+            abi::EM_ARM => (20,12),
+            abi::EM_AARCH64 => (32,16),
+            _ => return None,
+        };
+        let executable=|addr:u64| elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_LOAD && ph.p_flags&abi::PF_X!=0
+            && addr>=ph.p_vaddr && addr-ph.p_vaddr<ph.p_memsz);
+        let size=header_size+slots*entry_size;
+        let endian=elf.header.endianness;
+        let target=pltgot
+            .and_then(|pltgot| elf.vaddr_data(pltgot+3*word,word))
+            .map(|bytes| match word {
+                4 => endian.parse_u32_at(0,bytes) as u64,
+                _ => endian.parse_u64_at(0,bytes),
+            })
+            .filter(|&target| executable(target));
+        let start=match (elf.header.e_machine,target) {
+            (abi::EM_386 | abi::EM_X86_64,Some(target)) => (target&!0xf).checked_sub(header_size)?,
+            (_,Some(target)) => target,
+            (abi::EM_ARM | abi::EM_AARCH64,None) => {
+                let (addr,size)=jmprel?;
+                let align=if elf.header.e_machine==abi::EM_ARM { 4 } else { 16 };
+                (addr+size).div_ceil(align)*align
+            }
+            _ => return None,
+        };
+        if !executable(start) {
+            return None;
+        }
+        let entsize=if header_size==entry_size { entry_size } else { 0 };
+        Some((start,size,entsize))
+    }
+
+    //补上.text/.data/.bss,排序并填好sh_link/sh_info
+    fn layout_sections(elf:&ElfFile,mut recovered:Vec<Recovered>)->Vec<SectionHeader>{
+        let phdr_end=elf.header.e_phoff+elf.header.e_phnum as u64*elf.header.e_phentsize as u64;
+        let loads:Vec<ProgramHeader>=elf.program_headers.iter().filter(|ph| ph.p_type==abi::PT_LOAD).copied().collect();
+        let known_end=|recovered:&[Recovered],ph:&ProgramHeader| recovered.iter()
+            .filter(|s| s.addr>=ph.p_vaddr && s.addr<ph.p_vaddr+ph.p_memsz)
+            .map(|s| s.addr+s.size)
+            .max();
+        let mut tail=Vec::new();
+        if let Some(ph)=loads.iter().find(|ph| ph.p_flags&abi::PF_X!=0) {
+            let mut start=known_end(&recovered,ph).unwrap_or(ph.p_vaddr).max(ph.p_vaddr);
+            if ph.p_offset==0 {
+                start=start.max(ph.p_vaddr+phdr_end);
+            }
+            let end=ph.p_vaddr+ph.p_filesz;
+            if start<end {
+                tail.push(Recovered{name:".text".to_string(),sh_type:abi::SHT_PROGBITS,addr:start,size:end-start,entsize:0,align:natural_align(start,16)});
+            }
+        }
+        if let Some(ph)=loads.iter().rfind(|ph| ph.p_flags&abi::PF_W!=0) {
+            let start=known_end(&recovered,ph).unwrap_or(ph.p_vaddr).max(ph.p_vaddr);
+            let end=ph.p_vaddr+ph.p_filesz;
+            if start<end {
+                tail.push(Recovered{name:".data".to_string(),sh_type:abi::SHT_PROGBITS,addr:start,size:end-start,entsize:0,align:natural_align(start,32)});
+            }
+            if ph.p_memsz>ph.p_filesz {
+                tail.push(Recovered{name:".bss".to_string(),sh_type:abi::SHT_NOBITS,addr:end,size:ph.p_memsz-ph.p_filesz,entsize:0,align:natural_align(end,32)});
+            }
+        }
+        recovered.extend(tail);
+        recovered.sort_by_key(|s| s.addr);
+
+        let mut section_headers=vec![SectionHeader{
+            sh_name:0,sh_type:abi::SHT_NULL,sh_flags:0,sh_addr:0,sh_offset:0,sh_size:0,
+            sh_link:0,sh_info:0,sh_addralign:0,sh_entsize:0,string_name:String::new(),index:0,
+        }];
+        for s in recovered {
+            let segment=loads.iter().find(|ph| s.addr>=ph.p_vaddr && s.addr<ph.p_vaddr+ph.p_memsz);
+            let offset=match (s.sh_type,segment) {
+                (abi::SHT_NOBITS,Some(ph)) => ph.p_offset+(s.addr-ph.p_vaddr),
+                _ => match elf.vaddr_to_offset(s.addr) {
+                    Some(offset) => offset,
+                    None => continue,
+                },
+            };
+            let mut sh_flags=abi::SHF_ALLOC;
+            if let Some(ph)=segment {
+                if ph.p_flags&abi::PF_W!=0 {
+                    sh_flags|=abi::SHF_WRITE;
+                }
+                if ph.p_flags&abi::PF_X!=0 && (s.name==".plt" || s.name==".text") {
+                    sh_flags|=abi::SHF_EXECINSTR;
+                }
+            }
+            let index=section_headers.len() as u16;
+            section_headers.push(SectionHeader{
+                sh_name:0,sh_type:s.sh_type,sh_flags,sh_addr:s.addr,sh_offset:offset,sh_size:s.size,
+                sh_link:0,sh_info:0,sh_addralign:s.align,sh_entsize:s.entsize,string_name:s.name,index,
+            });
+        }
+
+        let index_of=|name:&str| section_headers.iter().position(|sh| sh.string_name==name).unwrap_or(0) as u32;
+        let (dynsym,dynstr,got)=(index_of(".dynsym"),index_of(".dynstr"),index_of(".got"));
+        let first_global=elf.dynamic_symbols().iter().position(|sym| sym.st_bind()!=abi::STB_LOCAL)
+            .unwrap_or(elf.dynamic_symbols().len()) as u32;
+        for sh in section_headers.iter_mut() {
+            match sh.string_name.as_str() {
+                ".dynsym" => {
+                    sh.sh_link=dynstr;
+                    sh.sh_info=first_global;
+                }
+                ".dynamic" => sh.sh_link=dynstr,
+                ".hash" | ".gnu.hash" | ".rela.dyn" | ".rel.dyn" => sh.sh_link=dynsym,
+                ".rela.plt" | ".rel.plt" => {
+                    sh.sh_link=dynsym;
+                    sh.sh_info=got;
+                    if got!=0 {
+                        sh.sh_flags|=abi::SHF_INFO_LINK;
+                    }
+                }
+                _ => {}
+            }
+        }
+        section_headers
+    }
+
+    /// Append `.shstrtab` and the section header table, then point the file header at them
+    fn emit(elf:ElfFile,mut section_headers:Vec<SectionHeader>)->RebuiltFile{
+        let ident=elf.ident();
+        let class=elf.header.class;
+        let mut header=elf.header;
+        let mut data=elf.data;
+
+        let shstrtab_index=section_headers.len() as u16;
+        section_headers.push(SectionHeader{
+            sh_name:0,sh_type:abi::SHT_STRTAB,sh_flags:0,sh_addr:0,sh_offset:0,sh_size:0,
+            sh_link:0,sh_info:0,sh_addralign:1,sh_entsize:0,string_name:".shstrtab".to_string(),index:shstrtab_index,
+        });
+        let mut shstrtab=vec![0u8];
+        for sh in section_headers.iter_mut().skip(1) {
+            sh.sh_name=shstrtab.len() as u32;
+            shstrtab.extend_from_slice(sh.string_name.as_bytes());
+            shstrtab.push(0);
+        }
+        let last=section_headers.len()-1;
+        section_headers[last].sh_offset=data.len() as u64;
+        section_headers[last].sh_size=shstrtab.len() as u64;
+        data.extend_from_slice(&shstrtab);
+
+        let word=class.word_size();
+        let shoff=data.len().div_ceil(word)*word;
+        let entsize=SectionHeader::size_for(class);
+        data.resize(shoff+section_headers.len()*entsize,0);
+        for (i,sh) in section_headers.iter().enumerate() {
+            sh.write_at(ident,shoff+i*entsize,&mut data);
+        }
+        header.e_shoff=shoff as u64;
+        header.e_shentsize=entsize as u16;
+        header.e_shnum=section_headers.len() as u16;
+        header.e_shstrndx=shstrtab_index;
+        elf_header::write_header(&header,&mut data);
+        RebuiltFile{data,section_headers}
+    }
+}
+
+//地址本身能满足的最大对齐,不超过max
+fn natural_align(addr:u64,max:u64)->u64{
+    let mut align=max;
+    while align>1 && !addr.is_multiple_of(align) {
+        align/=2;
+    }
+    align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::loader::LoadedImage;
+
+    const ELF: &[u8] = include_bytes!("../test_elf/de");
+
+    fn summary(section_headers:&[SectionHeader],name:&str)->Option<(u32,u64,u64,u64)>{
+        section_headers.iter().find(|sh| sh.string_name==name).map(|sh| (sh.sh_type,sh.sh_addr,sh.sh_offset,sh.sh_size))
+    }
+
+    #[test]
+    fn rebuilds_from_segments(){
+        let rebuilt=RebuiltFile::rebuild(ELF.to_vec(),RebuildOptions::default()).unwrap();
+        let elf=ElfFile::parse(rebuilt.data.clone()).unwrap();
+        assert_eq!(elf.section_headers.len(),rebuilt.section_headers.len());
+        let headers=&elf.section_headers;
+        assert_eq!(summary(headers,".gnu.hash"),Some((abi::SHT_GNU_HASH,0x3a0,0x3a0,0x24)));
+        assert_eq!(summary(headers,".dynsym"),Some((abi::SHT_DYNSYM,0x3c8,0x3c8,0x198)));
+        assert_eq!(summary(headers,".dynstr"),Some((abi::SHT_STRTAB,0x560,0x560,0xe5)));
+        assert_eq!(summary(headers,".rela.dyn"),Some((abi::SHT_RELA,0x698,0x698,0xc0)));
+        assert_eq!(summary(headers,".rela.plt"),Some((abi::SHT_RELA,0x758,0x758,0x108)));
+        assert_eq!(summary(headers,".dynamic"),Some((abi::SHT_DYNAMIC,0x3dd0,0x2dd0,0x1f0)));
+        assert_eq!(summary(headers,".bss"),Some((abi::SHT_NOBITS,0x4068,0x3068,0x8)));
+        //旧的节头表与.comment被丢掉,文件头之后的段内容不变
+        assert_eq!(&rebuilt.data[0x40..0x3068],&ELF[0x40..0x3068]);
+        assert!(elf.dynamic_symbols().iter().any(|symbol| symbol.string_name=="puts"));
+    }
+
+    //把装载后的内存镜像当作dump,.dynamic里的DT_STRTAB像glibc那样加上基址
+    #[test]
+    fn rebuilds_memory_dump(){
+        const BASE: u64 = 0x7f00_0000_0000;
+        let elf=ElfFile::parse(ELF.to_vec()).unwrap();
+        let mut dump=LoadedImage::load(&elf,BASE,|_| Some(0)).unwrap().data;
+        for entry in dump[0x3dd0..0x3dd0+0x1f0].chunks_mut(16) {
+            if u64::from_le_bytes(entry[..8].try_into().unwrap())==abi::DT_STRTAB as u64 {
+                entry[8..].copy_from_slice(&(BASE+0x560).to_le_bytes());
+            }
+        }
+        let rebuilt=RebuiltFile::rebuild(dump,RebuildOptions{dumped:true,load_bias:BASE}).unwrap();
+        let elf=ElfFile::parse(rebuilt.data).unwrap();
+        //dump里每个段都在自己的虚拟地址处
+        assert_eq!(summary(&elf.section_headers,".dynstr"),Some((abi::SHT_STRTAB,0x560,0x560,0xe5)));
+        assert_eq!(summary(&elf.section_headers,".dynamic"),Some((abi::SHT_DYNAMIC,0x3dd0,0x3dd0,0x1f0)));
+        assert_eq!(Dyn::find(&elf.dynamic(),abi::DT_STRTAB),Some(0x560));
+        let data=elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_LOAD && ph.p_vaddr==0x3dc0).unwrap();
+        assert_eq!((data.p_offset,data.p_filesz),(0x3dc0,0x2b0));
+    }
+
+    #[test]
+    fn crafted_segments(){
+        let set=|data:&mut Vec<u8>,i:usize,field:usize,value:u64| {
+            data[0x40+i*56+field..0x40+i*56+field+8].copy_from_slice(&value.to_le_bytes());
+        };
+        let mut data=ELF.to_vec();
+        set(&mut data,5,32,u64::MAX);
+        assert_eq!(RebuiltFile::rebuild(data,RebuildOptions::default()).unwrap_err(),RebuildError::SegmentOverflow(5));
+        let mut data=ELF.to_vec();
+        set(&mut data,5,32,0x8000_0000);
+        assert_eq!(RebuiltFile::rebuild(data,RebuildOptions::default()).unwrap_err(),RebuildError::TooLarge(0x8000_2dc0));
+        let mut data=ELF.to_vec();
+        set(&mut data,5,40,0x8000_0000);
+        assert!(matches!(RebuiltFile::rebuild(data,RebuildOptions{dumped:true,load_bias:0}),Err(RebuildError::TooLarge(_))));
+        let mut data=ELF.to_vec();
+        set(&mut data,5,16,u64::MAX-0x100);
+        assert_eq!(RebuiltFile::rebuild(data,RebuildOptions::default()).unwrap_err(),RebuildError::SegmentOverflow(5));
+    }
+}
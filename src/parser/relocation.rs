@@ -1,6 +1,6 @@
 use crate::parser;
 use crate::parser::elf_header::FileHeader;
-use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
 use crate::parser::file;
 use crate::parser::file::Class;
 use crate::parser::section::SectionHeader;
@@ -32,7 +32,7 @@ impl Rel {
         let mut offset:usize=0;
         let (_,class)=ident;
         let size=Self::size_for(class);
-        while  offset< e_size as usize {
+        while  offset< e_size as usize && offset+size<=data.len() {
             let ele=Self::parse_rel(ident,data,offset);
             offset+=size;
             v.push(ele);
@@ -68,7 +68,29 @@ impl Rel {
             }
         }
     }
-    fn size_for(class: Class) -> usize {
+    /// Write this entry at `offset` using the layout `parse_rel` reads
+    pub fn write_rel(&self,ident: (AnyEndian, Class),data: &mut [u8],offset:usize){
+        let (endian,class)=ident;
+        match class {
+            Class::ELF32 => {
+                endian.write_u32_at(offset, data, self.r_offset as u32);
+                endian.write_u32_at(offset+4, data, (self.r_sym << 8) | (self.r_type & 0xFF));
+            }
+            Class::ELF64 => {
+                endian.write_u64_at(offset, data, self.r_offset);
+                endian.write_u64_at(offset+8, data, ((self.r_sym as u64) << 32) | self.r_type as u64);
+            }
+        }
+    }
+    pub fn to_bytes(ident: (AnyEndian, Class),rels:&[Rel])->Vec<u8>{
+        let size=Self::size_for(ident.1);
+        let mut data=vec![0u8;rels.len()*size];
+        for (i,rel) in rels.iter().enumerate() {
+            rel.write_rel(ident,&mut data,i*size);
+        }
+        data
+    }
+    pub(crate) fn size_for(class: Class) -> usize {
         match class {
             Class::ELF32 => 8,
             Class::ELF64 => 16,
@@ -100,22 +122,33 @@ pub struct Rela {
     pub r_addend: i64,
 }
 
+/// REL entries keep their addend in the relocated field, so it is left as 0 here.
+impl From<Rel> for Rela {
+    fn from(rel: Rel) -> Self {
+        Rela {
+            r_offset: rel.r_offset,
+            r_sym: rel.r_sym,
+            r_type: rel.r_type,
+            r_addend: 0,
+        }
+    }
+}
+
 
 impl Rela {
     pub fn read_rela(file_path:&str,name:String,section_header:Vec<SectionHeader>,binary_header:FileHeader)->Option<Vec<Rela>>{
         let idents=(binary_header.endianness,binary_header.class);
         let rela_table_idx=parser::section::SectionHeader::
         find_section_header_by_name(section_header.clone(),name);
-        let rela_table=&section_header[rela_table_idx as usize];
-
-        if rela_table_idx==0{
+        if rela_table_idx<=0{
             return None;
         }
+        let rela_table=&section_header[rela_table_idx as usize];
         let e_rela_offset=rela_table.sh_offset;
         let e_rela_size=rela_table.sh_size;
         let rela_bytes=file::file_utils::
-        read_file_range(file_path,e_rela_offset,e_rela_offset+e_rela_size);
-        let rela_tables=parser::relocation::Rela::parse(idents,&rela_bytes.unwrap(),e_rela_size);
+        read_file_range(file_path,e_rela_offset,e_rela_offset.checked_add(e_rela_size)?).ok()?;
+        let rela_tables=parser::relocation::Rela::parse(idents,&rela_bytes,e_rela_size);
         println!("[*]解析重定位表成功");
         println!("{:?}",rela_tables);
         return Some(rela_tables);
@@ -126,7 +159,7 @@ impl Rela {
         let mut offset:u64=0;
         let (_,class)=ident;
         let size=Self::size_for(class);
-        while  offset< e_size {
+        while  offset< e_size && offset as usize+size<=data.len() {
             let ele=Self::parse_rela(ident,data,offset as usize);
             offset+=(size as u64);
             v.push(ele);
@@ -168,7 +201,31 @@ impl Rela {
             }
         }
     }
-    fn size_for(class: Class) -> usize {
+    /// Write this entry at `offset` using the layout `parse_rela` reads
+    pub fn write_rela(&self,ident: (AnyEndian, Class),data: &mut [u8],offset:usize){
+        let (endian,class)=ident;
+        match class {
+            Class::ELF32 => {
+                endian.write_u32_at(offset, data, self.r_offset as u32);
+                endian.write_u32_at(offset+4, data, (self.r_sym << 8) | (self.r_type & 0xFF));
+                endian.write_u32_at(offset+8, data, self.r_addend as u32);
+            }
+            Class::ELF64 => {
+                endian.write_u64_at(offset, data, self.r_offset);
+                endian.write_u64_at(offset+8, data, ((self.r_sym as u64) << 32) | self.r_type as u64);
+                endian.write_u64_at(offset+16, data, self.r_addend as u64);
+            }
+        }
+    }
+    pub fn to_bytes(ident: (AnyEndian, Class),relas:&[Rela])->Vec<u8>{
+        let size=Self::size_for(ident.1);
+        let mut data=vec![0u8;relas.len()*size];
+        for (i,rela) in relas.iter().enumerate() {
+            rela.write_rela(ident,&mut data,i*size);
+        }
+        data
+    }
+    pub(crate) fn size_for(class: Class) -> usize {
         match class {
             Class::ELF32 => 12,
             Class::ELF64 => 24,
@@ -1,4 +1,4 @@
-use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
 use crate::parser::file::Class;
 use std::collections::HashMap;
 use crate::parser;
@@ -33,6 +33,24 @@ pub struct SectionHeader {
 
 impl  SectionHeader {
 
+    /// A header with everything but the name, type and flags zeroed, for building files
+    pub fn new(name:&str,sh_type:u32,sh_flags:u64)->SectionHeader{
+        SectionHeader{
+            sh_name:0,
+            sh_type,
+            sh_flags,
+            sh_addr:0,
+            sh_offset:0,
+            sh_size:0,
+            sh_link:0,
+            sh_info:0,
+            sh_addralign:1,
+            sh_entsize:0,
+            string_name:name.to_string(),
+            index:0,
+        }
+    }
+
     pub fn parser_string_section(file_path:&str,section_headers:Vec<SectionHeader>,binary_header:FileHeader)->Option<HashMap<u32,String>>{
         let e_shstrndx=binary_header.e_shstrndx;
         if e_shstrndx>= section_headers.len() as u16 {
@@ -43,8 +61,8 @@ impl  SectionHeader {
         let e_shstr_size=e_shstr.sh_size;
 
         let string_table_bytes=file::file_utils::read_file_range
-            (file_path,e_shstr_offset,e_shstr_offset+e_shstr_size);
-        let string_map=parser::section::SectionHeader::parser_string_table(string_table_bytes.unwrap());
+            (file_path,e_shstr_offset,e_shstr_offset.checked_add(e_shstr_size)?).ok()?;
+        let string_map=parser::section::SectionHeader::parser_string_table(string_table_bytes);
         println!("[*]字符表节区解析成功:");
         println!("{:?}",string_map);
         return Some(string_map);
@@ -54,20 +72,27 @@ impl  SectionHeader {
         let e_shsz=binary_header.e_shentsize;
         let e_shoff=binary_header.e_shoff;
         let idents=(binary_header.endianness,binary_header.class);
+        if e_shoff==0 || (e_shsz as usize)<SectionHeader::size_for(binary_header.class) {
+            return None;
+        }
         let section_bytes=file::file_utils::read_file_range
-            (file_path,e_shoff,(e_shoff+(e_shsz*e_shnum) as u64));
+            (file_path,e_shoff,e_shoff.checked_add(e_shsz as u64*e_shnum as u64)?).ok()?;
         //解析section
         let section_header=SectionHeader::parse_section
-            (idents, section_bytes.unwrap(), e_shnum,e_shsz);
+            (idents, section_bytes, e_shnum,e_shsz);
         println!("[*]程序节区解析成功:");
         println!("{:?}",section_header);
         return Some(section_header);
     }
     pub fn parse_section(ident: (AnyEndian, Class),section_bytes:Vec<u8>,e_shnum:u16,e_shsz:u16)->Vec<SectionHeader>{
         let mut v: Vec<SectionHeader> = Vec::new();
-        //let (endian, class)=ident;
+        let (_, class)=ident;
         for i in 0..e_shnum{
-            let e_shdr=Self::parse_at(ident, (i * e_shsz) as usize, section_bytes.as_slice(),i);
+            let offset=i as usize*e_shsz as usize;
+            if offset+Self::size_for(class)>section_bytes.len(){
+                break;
+            }
+            let e_shdr=Self::parse_at(ident, offset, section_bytes.as_slice(),i);
             v.push(e_shdr);
         }
 
@@ -78,20 +103,33 @@ impl  SectionHeader {
     pub fn fix_section_name(string_table_map:HashMap<u32, String>, mut section_headers:Vec<SectionHeader>)->Vec<SectionHeader>{
         for mut section_header in  section_headers.iter_mut() {
             let sh_name = section_header.sh_name;
-            if let Some(string) = string_table_map.get(&sh_name) {
-                section_header.string_name = string.to_string();
+            if let Some(string) = Self::lookup_string(&string_table_map,sh_name) {
+                section_header.string_name = string;
             }
         }
         //println!("{:?}",section_headers);
         return section_headers;
     }
 
+    /// Like `fix_section_name`, but resolves names from the raw string table so
+    /// non-UTF-8 bytes cannot shift suffix offsets
+    pub fn fix_section_name_from_bytes(string_table_bytes:&[u8], mut section_headers:Vec<SectionHeader>)->Vec<SectionHeader>{
+        for section_header in section_headers.iter_mut() {
+            if let Some(string) = Self::parse_string_at(string_table_bytes,section_header.sh_name as usize) {
+                section_header.string_name = string;
+            }
+        }
+        section_headers
+    }
+
     pub fn parser_string_table(string_table_bytes:Vec<u8>)->HashMap<u32, String>{
         let mut result = HashMap::new();
         let mut start = 0;
         while start < string_table_bytes.len() {
             // Find the end of the current string
-            let end = start + string_table_bytes[start..].iter().position(|&b| b == 0).unwrap();
+            // An unterminated last string runs to the end of the table
+            let end = start + string_table_bytes[start..].iter().position(|&b| b == 0)
+                .unwrap_or(string_table_bytes.len() - start);
 
             // Convert the bytes to a UTF-8 string
             let s = String::from_utf8_lossy(&string_table_bytes[start..end]).to_string();
@@ -105,6 +143,35 @@ impl  SectionHeader {
         result
 
     }
+    /// Resolve a string table offset. The linker merges names that are suffixes of other
+    /// names (".plt" inside ".rela.plt"), so an offset may point into the middle of an entry.
+    pub fn lookup_string(string_table_map:&HashMap<u32, String>,offset:u32)->Option<String>{
+        if let Some(string) = string_table_map.get(&offset) {
+            return Some(string.to_string());
+        }
+        //有损转换后的字符串长度可能与原字节不同,切到非字符边界时放弃
+        string_table_map.iter()
+            .find(|(&start,string)| start<offset && offset-start<string.len() as u32)
+            .and_then(|(&start,string)| string.get((offset-start) as usize..))
+            .map(|string| string.to_string())
+    }
+    /// Serialize a string table from raw entries, each string at its offset followed
+    /// by a NUL. Entries are bytes rather than `String`s so names that are not UTF-8
+    /// come out unchanged.
+    pub fn build_string_table(strings:&[(u32,&[u8])])->Vec<u8>{
+        let size=strings.iter().map(|&(offset,s)| offset as usize+s.len()+1).max().unwrap_or(0);
+        let mut bytes=vec![0u8;size];
+        for &(offset,s) in strings {
+            bytes[offset as usize..offset as usize+s.len()].copy_from_slice(s);
+        }
+        bytes
+    }
+    /// Read the NUL terminated string starting at `offset` of a raw string table
+    pub fn parse_string_at(string_table_bytes:&[u8],offset:usize)->Option<String>{
+        let bytes=string_table_bytes.get(offset..)?;
+        let end=bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+    }
     pub fn find_section_header_by_name(section_headers:Vec<SectionHeader>,name:String)
         ->i64{
         let mut count:i64=0;
@@ -209,8 +276,36 @@ impl  SectionHeader {
         });
     }
 
+    /// Write this header at `offset` using the layout `parse_at` reads
+    pub fn write_at(&self, ident: (AnyEndian, Class), offset: usize, data: &mut [u8]) {
+        let (endian, class)=ident;
+        if class == Class::ELF32 {
+            endian.write_u32_at(offset, data, self.sh_name);
+            endian.write_u32_at(offset+4, data, self.sh_type);
+            endian.write_u32_at(offset+8, data, self.sh_flags as u32);
+            endian.write_u32_at(offset+12, data, self.sh_addr as u32);
+            endian.write_u32_at(offset+16, data, self.sh_offset as u32);
+            endian.write_u32_at(offset+20, data, self.sh_size as u32);
+            endian.write_u32_at(offset+24, data, self.sh_link);
+            endian.write_u32_at(offset+28, data, self.sh_info);
+            endian.write_u32_at(offset+32, data, self.sh_addralign as u32);
+            endian.write_u32_at(offset+36, data, self.sh_entsize as u32);
+            return;
+        }
+        endian.write_u32_at(offset, data, self.sh_name);
+        endian.write_u32_at(offset+4, data, self.sh_type);
+        endian.write_u64_at(offset+8, data, self.sh_flags);
+        endian.write_u64_at(offset+16, data, self.sh_addr);
+        endian.write_u64_at(offset+24, data, self.sh_offset);
+        endian.write_u64_at(offset+32, data, self.sh_size);
+        endian.write_u32_at(offset+40, data, self.sh_link);
+        endian.write_u32_at(offset+44, data, self.sh_info);
+        endian.write_u64_at(offset+48, data, self.sh_addralign);
+        endian.write_u64_at(offset+56, data, self.sh_entsize);
+    }
+
     #[inline]
-    fn size_for(class: Class) -> usize {
+    pub(crate) fn size_for(class: Class) -> usize {
         match class {
             Class::ELF32 => 40,
             Class::ELF64 => 64,
@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::edit::{self, EditError, NewSegment, MAX_OUTPUT_SIZE};
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{EndianParse, EndianWrite};
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::section::SectionHeader;
+use crate::parser::symbol::Symbol;
+
+/// Add, replace, remove and rename sections of an existing file.
+///
+/// Allocated sections keep their addresses: a loaded section that is added, or one
+/// that grows, goes into a new PT_LOAD appended after the image. Everything else —
+/// the non-alloc sections, a rebuilt `.shstrtab` and the section header table — is
+/// laid out again after the image on every write, so edits never leave holes in the
+/// mapped part of the file.
+#[derive(Debug, Clone)]
+pub struct SectionEditor {
+    pub elf: ElfFile,
+    sections: Vec<EditedSection>,
+    shstrndx: usize,
+    //输入文件的权限,写出时沿用
+    permissions: Option<fs::Permissions>,
+}
+
+#[derive(Debug, Clone)]
+struct EditedSection {
+    header: SectionHeader,
+    //None表示沿用原文件内容
+    data: Option<Vec<u8>>,
+    //原文件中的大小,原地替换时清零用
+    original_size: u64,
+    //需要放进新的PT_LOAD
+    loaded: bool,
+}
+
+impl SectionEditor {
+    pub fn read_editor(file_path:&str)->Result<SectionEditor,EditError>{
+        let data=fs::read(file_path).map_err(|e| EditError::Io(e.to_string()))?;
+        let mut editor=Self::new(ElfFile::parse(data).ok_or(EditError::Parse)?)?;
+        editor.permissions=fs::metadata(file_path).map(|m| m.permissions()).ok();
+        Ok(editor)
+    }
+
+    pub fn new(elf:ElfFile)->Result<SectionEditor,EditError>{
+        let shstrndx=elf.header.e_shstrndx as usize;
+        if shstrndx==0 || shstrndx>=elf.section_headers.len() {
+            return Err(EditError::NoSection(".shstrtab".to_string()));
+        }
+        let sections=elf.section_headers.iter().map(|sh| EditedSection{
+            header:sh.clone(),
+            data:None,
+            original_size:sh.sh_size,
+            loaded:false,
+        }).collect();
+        Ok(SectionEditor{
+            elf,
+            sections,
+            shstrndx,
+            permissions:None,
+        })
+    }
+
+    /// Section headers as they stand after the edits so far; offsets and addresses of
+    /// moved sections are only assigned by `to_bytes`
+    pub fn section_headers(&self)->Vec<SectionHeader>{
+        self.sections.iter().map(|s| s.header.clone()).collect()
+    }
+
+    pub fn find(&self,name:&str)->Result<usize,EditError>{
+        self.sections.iter().position(|s| s.header.string_name==name)
+            .ok_or_else(|| EditError::NoSection(name.to_string()))
+    }
+
+    /// Append a section that is not mapped at run time and return its index.
+    /// `sh_name`, `sh_offset` and `sh_size` are filled in on write.
+    pub fn add_section(&mut self,mut header:SectionHeader,data:Vec<u8>)->u16{
+        header.sh_flags&=!abi::SHF_ALLOC;
+        header.sh_addr=0;
+        self.push(header,data,false)
+    }
+
+    /// Append an allocated section mapped by a new PT_LOAD and return its index
+    pub fn add_loaded_section(&mut self,mut header:SectionHeader,data:Vec<u8>)->Result<u16,EditError>{
+        if !self.has_loads() {
+            return Err(EditError::NotLoadable);
+        }
+        header.sh_flags|=abi::SHF_ALLOC;
+        Ok(self.push(header,data,true))
+    }
+
+    fn push(&mut self,mut header:SectionHeader,data:Vec<u8>,loaded:bool)->u16{
+        let index=self.sections.len();
+        header.index=index as u16;
+        if header.sh_type!=abi::SHT_NOBITS {
+            header.sh_size=data.len() as u64;
+        }
+        self.sections.push(EditedSection{header,data:Some(data),original_size:0,loaded});
+        index as u16
+    }
+
+    /// Replace the contents of a section. An allocated section that grows is moved
+    /// into a new PT_LOAD; `.dynamic` entries that point at it and segments such as
+    /// PT_INTERP that cover exactly it follow, but code that refers to its old address
+    /// must not depend on it.
+    /// Fails with `SegmentOverlap` when the section would move but a segment covers
+    /// only part of it, since that segment could not follow.
+    pub fn replace_section(&mut self,name:&str,data:Vec<u8>)->Result<(),EditError>{
+        let index=self.find(name)?;
+        let header=&self.sections[index].header;
+        let original_size=self.sections[index].original_size;
+        let moves=self.has_loads() && header.sh_flags&abi::SHF_ALLOC!=0 && data.len() as u64>original_size;
+        let end=header.sh_addr.saturating_add(original_size);
+        //与节部分重叠的非PT_LOAD段无法跟着移动;PT_GNU_RELRO只是少保护一个节,不算
+        let partial=self.elf.program_headers.iter()
+            .filter(|ph| !matches!(ph.p_type,abi::PT_LOAD|abi::PT_PHDR|abi::PT_GNU_RELRO))
+            .filter(|ph| ph.p_vaddr<end && header.sh_addr<ph.p_vaddr.saturating_add(ph.p_memsz))
+            .any(|ph| ph.p_vaddr!=header.sh_addr || ph.p_memsz!=original_size);
+        if moves && original_size>0 && partial {
+            return Err(EditError::SegmentOverlap(name.to_string()));
+        }
+        self.replace_section_at(index,data);
+        Ok(())
+    }
+
+    pub(crate) fn replace_section_at(&mut self,index:usize,data:Vec<u8>){
+        let has_loads=self.has_loads();
+        let section=&mut self.sections[index];
+        if has_loads && section.header.sh_flags&abi::SHF_ALLOC!=0 && data.len() as u64>section.original_size {
+            section.loaded=true;
+        }
+        section.header.sh_size=data.len() as u64;
+        section.data=Some(data);
+    }
+
+    /// Rename a section and the `.rel`/`.rela` sections that apply to it
+    pub fn rename_section(&mut self,name:&str,new_name:&str)->Result<(),EditError>{
+        let index=self.find(name)?;
+        self.sections[index].header.string_name=new_name.to_string();
+        for section in self.sections.iter_mut() {
+            let header=&mut section.header;
+            if !Self::is_relocation(header) || header.sh_info as usize!=index {
+                continue;
+            }
+            for prefix in [".rela",".rel"] {
+                if header.string_name.strip_prefix(prefix)==Some(name) {
+                    header.string_name=format!("{}{}",prefix,new_name);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a non-alloc section together with the relocation sections that apply
+    /// to it, and renumber every section index that refers past it
+    pub fn remove_section(&mut self,name:&str)->Result<(),EditError>{
+        let index=self.find(name)?;
+        self.remove_section_at(index)
+    }
+
+    pub fn remove_section_at(&mut self,index:usize)->Result<(),EditError>{
+        let name=self.sections.get(index).map(|s| s.header.string_name.clone()).unwrap_or_default();
+        if index==0 || index>=self.sections.len() || index==self.shstrndx || self.sections[index].header.sh_flags&abi::SHF_ALLOC!=0 {
+            return Err(EditError::CannotRemove(name));
+        }
+        let removed:Vec<usize>=(0..self.sections.len())
+            .filter(|&i| i==index || (Self::is_relocation(&self.sections[i].header) && self.sections[i].header.sh_info as usize==index))
+            .collect();
+        let mut map=Vec::with_capacity(self.sections.len());
+        let mut next=0;
+        for i in 0..self.sections.len() {
+            if removed.contains(&i) {
+                map.push(None);
+            } else {
+                map.push(Some(next));
+                next+=1;
+            }
+        }
+        for symbol_table in 0..self.sections.len() {
+            let sh_type=self.sections[symbol_table].header.sh_type;
+            if sh_type==abi::SHT_SYMTAB || sh_type==abi::SHT_DYNSYM {
+                self.remap_symbols(symbol_table,&map);
+            } else if sh_type==abi::SHT_GROUP {
+                self.remap_group(symbol_table,&map);
+            }
+        }
+        let remap=|i:u32| map.get(i as usize).copied().flatten().unwrap_or(0) as u32;
+        for section in self.sections.iter_mut() {
+            section.header.sh_link=remap(section.header.sh_link);
+            if Self::is_relocation(&section.header) || section.header.sh_flags&abi::SHF_INFO_LINK!=0 {
+                section.header.sh_info=remap(section.header.sh_info);
+            }
+        }
+        self.shstrndx=remap(self.shstrndx as u32) as usize;
+        let mut i=0;
+        self.sections.retain(|_| {
+            i+=1;
+            !removed.contains(&(i-1))
+        });
+        for (i,section) in self.sections.iter_mut().enumerate() {
+            section.header.index=i as u16;
+        }
+        Ok(())
+    }
+
+    fn is_relocation(header:&SectionHeader)->bool{
+        header.sh_type==abi::SHT_REL || header.sh_type==abi::SHT_RELA
+    }
+
+    fn has_loads(&self)->bool{
+        self.elf.program_headers.iter().any(|ph| ph.p_type==abi::PT_LOAD)
+    }
+
+    pub(crate) fn shstrndx(&self)->usize{
+        self.shstrndx
+    }
+
+    pub(crate) fn header_mut(&mut self,index:usize)->&mut SectionHeader{
+        &mut self.sections[index].header
+    }
+
+    /// Replace the contents of a non-alloc section by index
+    pub(crate) fn set_contents(&mut self,index:usize,data:Vec<u8>){
+        let section=&mut self.sections[index];
+        section.header.sh_size=data.len() as u64;
+        section.data=Some(data);
+    }
+
+    pub(crate) fn contents(&self,index:usize)->Vec<u8>{
+        let section=&self.sections[index];
+        match &section.data {
+            Some(data) => data.clone(),
+            None => self.elf.section_data(&section.header).map(|bytes| bytes.to_vec()).unwrap_or_default(),
+        }
+    }
+
+    /// Entries of a symbol table with `string_name` read from its linked string table
+    pub(crate) fn named_symbols(&self,symtab:usize)->Vec<Symbol>{
+        let ident=self.elf.ident();
+        let size=Symbol::size_for(ident.1);
+        let bytes=self.contents(symtab);
+        let link=self.sections[symtab].header.sh_link as usize;
+        let strtab=if link<self.sections.len() { self.contents(link) } else { Vec::new() };
+        let mut symbols=Symbol::parser_Symbol(ident,&bytes[..bytes.len()/size*size],0);
+        for symbol in symbols.iter_mut() {
+            symbol.string_name=SectionHeader::parse_string_at(&strtab,symbol.st_name as usize).unwrap_or_default();
+        }
+        symbols
+    }
+
+    /// Write a symbol table back from `(old index, symbol)` pairs. Locals are moved in
+    /// front of the globals, `sh_info` and the linked string table are rebuilt, and
+    /// relocations and group signatures are renumbered; dropped symbols become index 0.
+    /// A string table shared with `.shstrtab` keeps the original `st_name` offsets.
+    pub(crate) fn set_symbols(&mut self,symtab:usize,mut symbols:Vec<(usize,Symbol)>){
+        let ident=self.elf.ident();
+        symbols.sort_by_key(|(i,symbol)| (*i!=0,symbol.st_bind()!=abi::STB_LOCAL));
+        let strtab_index=self.sections[symtab].header.sh_link as usize;
+        let rebuild_strtab=strtab_index!=self.shstrndx && strtab_index<self.sections.len();
+        let mut strtab=vec![0u8];
+        let mut offsets:HashMap<String,u32>=HashMap::new();
+        let mut map=vec![0u32;symbols.iter().map(|(i,_)| i+1).max().unwrap_or(0)];
+        for (new,(old,symbol)) in symbols.iter_mut().enumerate() {
+            map[*old]=new as u32;
+            if !rebuild_strtab {
+                continue;
+            }
+            symbol.st_name=match symbol.string_name.is_empty() {
+                true => 0,
+                false => *offsets.entry(symbol.string_name.clone()).or_insert_with(|| {
+                    let offset=strtab.len() as u32;
+                    strtab.extend_from_slice(symbol.string_name.as_bytes());
+                    strtab.push(0);
+                    offset
+                }),
+            };
+        }
+        let symbols:Vec<Symbol>=symbols.into_iter().map(|(_,symbol)| symbol).collect();
+        let first_global=symbols.iter().skip(1).position(|s| s.st_bind()!=abi::STB_LOCAL).map_or(symbols.len(),|i| i+1);
+        self.set_contents(symtab,Symbol::to_bytes(ident,&symbols));
+        self.sections[symtab].header.sh_info=first_global as u32;
+        if rebuild_strtab {
+            self.set_contents(strtab_index,strtab);
+        }
+        self.remap_symbol_references(symtab,&map);
+    }
+
+    /// Renumber `r_sym` in the relocation sections and the signature (`sh_info`) of the
+    /// groups that use symbol table `symtab`, after its entries moved (`map[old]=new`)
+    pub(crate) fn remap_symbol_references(&mut self,symtab:usize,map:&[u32]){
+        let ident=self.elf.ident();
+        let remap=|r_sym:u32| map.get(r_sym as usize).copied().unwrap_or(0);
+        for index in 0..self.sections.len() {
+            let header=self.sections[index].header.clone();
+            if header.sh_link as usize!=symtab {
+                continue;
+            }
+            let bytes=self.contents(index);
+            let data=match header.sh_type {
+                abi::SHT_RELA => {
+                    let entries:Vec<Rela>=Rela::parse(ident,&bytes,header.sh_size).into_iter()
+                        .map(|r| Rela{r_sym:remap(r.r_sym),..r})
+                        .collect();
+                    Rela::to_bytes(ident,&entries)
+                }
+                abi::SHT_REL => {
+                    let entries:Vec<Rel>=Rel::parse(ident,&bytes,header.sh_size).into_iter()
+                        .map(|r| Rel{r_sym:remap(r.r_sym),..r})
+                        .collect();
+                    Rel::to_bytes(ident,&entries)
+                }
+                abi::SHT_GROUP => {
+                    self.sections[index].header.sh_info=remap(header.sh_info);
+                    continue;
+                }
+                _ => continue,
+            };
+            //.rela.dyn等可能带有填充,只覆盖解析出的部分
+            let mut contents=bytes;
+            contents[..data.len()].copy_from_slice(&data);
+            self.sections[index].data=Some(contents);
+        }
+    }
+
+    //被删除节上的符号保留原值,改为SHN_ABS
+    fn remap_symbols(&mut self,index:usize,map:&[Option<usize>]){
+        let ident=self.elf.ident();
+        let bytes=self.contents(index);
+        let size=Symbol::size_for(ident.1);
+        let mut symbols=Symbol::parser_Symbol(ident,&bytes[..bytes.len()/size*size],0);
+        let mut changed=false;
+        for symbol in symbols.iter_mut() {
+            let shndx=symbol.st_shndx;
+            if shndx==abi::SHN_UNDEF || shndx>=abi::SHN_LORESERVE {
+                continue;
+            }
+            let new=map.get(shndx as usize).copied().flatten().map(|i| i as u16).unwrap_or(abi::SHN_ABS);
+            if new!=shndx {
+                symbol.st_shndx=new;
+                changed=true;
+            }
+        }
+        if changed {
+            let mut data=Symbol::to_bytes(ident,&symbols);
+            data.extend_from_slice(&bytes[data.len().min(bytes.len())..]);
+            self.sections[index].data=Some(data);
+        }
+    }
+
+    //节组内容:一个标志字,后面是成员节下标
+    fn remap_group(&mut self,index:usize,map:&[Option<usize>]){
+        let endian=self.elf.header.endianness;
+        let bytes=self.contents(index);
+        let mut members=Vec::new();
+        for offset in (4..bytes.len().saturating_sub(3)).step_by(4) {
+            let member=endian.parse_u32_at(offset,&bytes) as usize;
+            if let Some(Some(new))=map.get(member) {
+                members.push(*new as u32);
+            }
+        }
+        let mut data=vec![0u8;4+members.len()*4];
+        data[..4].copy_from_slice(&bytes[..4.min(bytes.len())]);
+        for (i,member) in members.iter().enumerate() {
+            endian.write_u32_at(4+i*4,&mut data,*member);
+        }
+        self.sections[index].header.sh_size=data.len() as u64;
+        self.sections[index].data=Some(data);
+    }
+
+    //恰好覆盖被移走节的PT_INTERP、PT_DYNAMIC、PT_NOTE、PT_GNU_EH_FRAME等段指向新位置
+    fn retarget_segments(&self,elf:&mut ElfFile,headers:&[SectionHeader],loaded:&[usize]){
+        for &i in loaded {
+            let old=&self.sections[i];
+            if old.header.sh_addr==0 || old.original_size==0 {
+                continue;
+            }
+            let new=&headers[i];
+            let filesz=if new.sh_type==abi::SHT_NOBITS { 0 } else { new.sh_size };
+            for ph in elf.program_headers.iter_mut() {
+                if matches!(ph.p_type,abi::PT_LOAD|abi::PT_PHDR|abi::PT_GNU_RELRO) || ph.p_vaddr!=old.header.sh_addr
+                    || ph.p_offset!=old.header.sh_offset || ph.p_memsz!=old.original_size {
+                    continue;
+                }
+                ph.p_offset=new.sh_offset;
+                ph.p_vaddr=new.sh_addr;
+                ph.p_paddr=new.sh_addr;
+                ph.p_filesz=filesz;
+                ph.p_memsz=new.sh_size;
+            }
+        }
+    }
+
+    //移走的.dynstr/.dynsym等在.dynamic中的地址随之更新
+    fn relocate_dynamic(&self,elf:&mut ElfFile,headers:&[SectionHeader],loaded:&[usize]){
+        const ADDRESS_TAGS:&[i64]=&[
+            abi::DT_HASH,abi::DT_GNU_HASH,abi::DT_STRTAB,abi::DT_SYMTAB,abi::DT_RELA,abi::DT_REL,
+            abi::DT_JMPREL,abi::DT_VERSYM,abi::DT_VERDEF,abi::DT_VERNEED,abi::DT_INIT_ARRAY,abi::DT_FINI_ARRAY,
+        ];
+        let Some(dynamic)=headers.iter().find(|sh| sh.sh_type==abi::SHT_DYNAMIC && !self.sections[sh.index as usize].loaded) else {
+            return;
+        };
+        let ident=elf.ident();
+        let offset=dynamic.sh_offset as usize;
+        let Some(bytes)=elf.data.get(offset..offset+dynamic.sh_size as usize) else {
+            return;
+        };
+        let mut dyns=Dyn::parse(ident,bytes);
+        let mut strsz=None;
+        for &i in loaded {
+            let old=&self.sections[i];
+            if old.header.sh_addr==0 || old.original_size==0 {
+                continue;
+            }
+            for entry in dyns.iter_mut().filter(|d| ADDRESS_TAGS.contains(&d.d_tag) && d.d_val==old.header.sh_addr) {
+                entry.d_val=headers[i].sh_addr;
+                if entry.d_tag==abi::DT_STRTAB {
+                    strsz=Some(headers[i].sh_size);
+                }
+            }
+        }
+        for entry in dyns.iter_mut().filter(|d| d.d_tag==abi::DT_STRSZ) {
+            entry.d_val=strsz.unwrap_or(entry.d_val);
+        }
+        let size=Dyn::size_for(ident.1);
+        for (i,entry) in dyns.iter().enumerate() {
+            entry.write_at(ident,offset+i*size,&mut elf.data);
+        }
+    }
+
+    /// Write the edited file, with the input's permissions when it was read from disk
+    pub fn write(&self,file_path:&str)->Result<(),EditError>{
+        edit::write_output(file_path,&self.to_bytes()?,self.permissions.as_ref()).map_err(|e| EditError::Io(e.to_string()))
+    }
+
+    /// Apply the edits and serialize the file
+    pub fn to_bytes(&self)->Result<Vec<u8>,EditError>{
+        let mut elf=self.elf.clone();
+        let word=elf.header.class.word_size() as u64;
+        let has_loads=self.has_loads();
+        let fixed=|section:&EditedSection| has_loads && section.header.sh_flags&abi::SHF_ALLOC!=0 && !section.loaded;
+        let contents:Vec<Vec<u8>>=(0..self.sections.len()).map(|i| self.contents(i)).collect();
+        let mut headers:Vec<SectionHeader>=self.sections.iter().map(|s| s.header.clone()).collect();
+
+        //映像之后的内容全部重新排布
+        let phdrs_end=elf.header.e_phoff.checked_add(elf.program_headers.len() as u64*elf.header.e_phentsize as u64)
+            .ok_or(EditError::TooLarge(elf.header.e_phoff))?;
+        let mut image_end=phdrs_end.max(elf.header.e_ehsize as u64);
+        for (i,ph) in elf.program_headers.iter().enumerate().filter(|(_,ph)| ph.p_type==abi::PT_LOAD) {
+            image_end=image_end.max(ph.p_offset.checked_add(ph.p_filesz).ok_or(EditError::SegmentOverflow(i))?);
+        }
+        for section in self.sections.iter().filter(|s| fixed(s) && s.header.sh_type!=abi::SHT_NOBITS) {
+            let end=section.header.sh_offset.checked_add(section.original_size)
+                .ok_or_else(|| EditError::SectionOverflow(section.header.string_name.clone()))?;
+            image_end=image_end.max(end);
+        }
+        if image_end>MAX_OUTPUT_SIZE {
+            return Err(EditError::TooLarge(image_end));
+        }
+        elf.data.resize(image_end as usize,0);
+
+        let mut shstrtab=vec![0u8];
+        for header in headers.iter_mut().skip(1) {
+            header.sh_name=shstrtab.len() as u32;
+            shstrtab.extend_from_slice(header.string_name.as_bytes());
+            shstrtab.push(0);
+        }
+        let mut contents=contents;
+        headers[self.shstrndx].sh_size=shstrtab.len() as u64;
+        contents[self.shstrndx]=shstrtab;
+
+        for (i,section) in self.sections.iter().enumerate() {
+            if !fixed(section) || section.data.is_none() || section.header.sh_type==abi::SHT_NOBITS {
+                continue;
+            }
+            let offset=section.header.sh_offset as usize;
+            elf.data[offset..offset+section.original_size as usize].fill(0);
+            elf.data[offset..offset+contents[i].len()].copy_from_slice(&contents[i]);
+        }
+
+        let loaded:Vec<usize>=(0..self.sections.len()).filter(|&i| self.sections[i].loaded).collect();
+        if !loaded.is_empty() {
+            let mut segment=NewSegment::begin(&elf)?;
+            let mut flags=abi::PF_R;
+            for &i in &loaded {
+                let header=&mut headers[i];
+                let size=if header.sh_type==abi::SHT_NOBITS { 0 } else { header.sh_size };
+                header.sh_offset=segment.place(size,header.sh_addralign.max(1));
+                header.sh_addr=segment.addr(header.sh_offset);
+                if header.sh_flags&abi::SHF_WRITE!=0 {
+                    flags|=abi::PF_W;
+                }
+                if header.sh_flags&abi::SHF_EXECINSTR!=0 {
+                    flags|=abi::PF_X;
+                }
+            }
+            if segment.end()>MAX_OUTPUT_SIZE {
+                return Err(EditError::TooLarge(segment.end()));
+            }
+            elf.data.resize(segment.end() as usize,0);
+            for &i in &loaded {
+                let offset=headers[i].sh_offset as usize;
+                if headers[i].sh_type!=abi::SHT_NOBITS {
+                    elf.data[offset..offset+contents[i].len()].copy_from_slice(&contents[i]);
+                }
+            }
+            segment.finish(&mut elf,flags);
+            self.relocate_dynamic(&mut elf,&headers,&loaded);
+            self.retarget_segments(&mut elf,&headers,&loaded);
+        }
+
+        let mut cursor=elf.data.len() as u64;
+        for (i,section) in self.sections.iter().enumerate().skip(1) {
+            if fixed(section) || section.loaded {
+                continue;
+            }
+            let header=&mut headers[i];
+            cursor=cursor.next_multiple_of(header.sh_addralign.max(1));
+            header.sh_offset=cursor;
+            if header.sh_type==abi::SHT_NOBITS {
+                continue;
+            }
+            elf.data.resize(cursor as usize,0);
+            elf.data.extend_from_slice(&contents[i]);
+            cursor+=contents[i].len() as u64;
+        }
+        elf.header.e_shoff=cursor.next_multiple_of(word);
+        elf.header.e_shnum=headers.len() as u16;
+        elf.header.e_shstrndx=self.shstrndx as u16;
+        elf.data.resize(elf.header.e_shoff as usize,0);
+        elf.section_headers=headers;
+        Ok(elf.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ELF: &[u8] = include_bytes!("../test_elf/de");
+
+    #[test]
+    fn moved_section_takes_its_segment(){
+        let mut editor=SectionEditor::new(ElfFile::parse(TEST_ELF.to_vec()).unwrap()).unwrap();
+        let mut interp=vec![b'/';4999];
+        interp.push(0);
+        editor.replace_section(".interp",interp.clone()).unwrap();
+        let out=ElfFile::parse(editor.to_bytes().unwrap()).unwrap();
+        let section=out.find_section(".interp").unwrap();
+        let segment=out.program_headers.iter().find(|ph| ph.p_type==abi::PT_INTERP).unwrap();
+        assert_ne!(section.sh_addr,0x318);
+        assert_eq!((segment.p_vaddr,segment.p_offset,segment.p_filesz),(section.sh_addr,section.sh_offset,5000));
+        assert_eq!(out.segment_data(segment).unwrap(),interp);
+    }
+
+    #[test]
+    fn partially_covered_section_is_refused(){
+        //第二个PT_NOTE同时覆盖.note.gnu.build-id和.note.ABI-tag
+        let mut editor=SectionEditor::new(ElfFile::parse(TEST_ELF.to_vec()).unwrap()).unwrap();
+        let result=editor.replace_section(".note.gnu.build-id",vec![0;0x100]);
+        assert_eq!(result,Err(EditError::SegmentOverlap(".note.gnu.build-id".to_string())));
+    }
+
+    #[test]
+    fn crafted_sizes(){
+        let set=|data:&mut Vec<u8>,at:usize,value:u64| data[at..at+8].copy_from_slice(&value.to_le_bytes());
+        let rename=|data:Vec<u8>| {
+            let mut editor=SectionEditor::new(ElfFile::parse(data).unwrap()).unwrap();
+            editor.rename_section(".comment",".comment.old").unwrap();
+            editor.to_bytes()
+        };
+        //LOAD5的p_filesz
+        let mut data=TEST_ELF.to_vec();
+        set(&mut data,0x40+5*56+32,u64::MAX);
+        assert_eq!(rename(data),Err(EditError::SegmentOverflow(5)));
+        let mut data=TEST_ELF.to_vec();
+        set(&mut data,0x40+5*56+32,0x8000_0000);
+        assert_eq!(rename(data),Err(EditError::TooLarge(0x8000_2dc0)));
+        //.interp的sh_offset
+        let mut data=TEST_ELF.to_vec();
+        set(&mut data,0x3198+64+24,u64::MAX-4);
+        assert_eq!(rename(data),Err(EditError::SectionOverflow(".interp".to_string())));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::parser::elf_header::FileHeader;
-use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
 use crate::parser::file;
 use crate::parser::file::Class;
 #[derive(Debug)]
@@ -57,31 +57,19 @@ impl ProgramHeader {
     /// i.e. (p_offset, p_offset + p_filesz)
 
     pub fn read_program(file_path:&str,binary_header:FileHeader)->Option<Vec<ProgramHeader>>{
-        let header_size = match binary_header.class{
-            Class::ELF32 =>0x34,
-            Class::ELF64 => 0x40,
-        };
         let class=binary_header.class;
         let idents=(binary_header.endianness,binary_header.class);
-        let program_header_offset=header_size.clone();
-        let program_header_end=header_size.clone()+ProgramHeader::size_for(class) as u64;
-        let program_header=file::file_utils::read_file_range(file_path,program_header_offset,program_header_end);
-        //println!("{:?}",binary_header.expect("REASON").len());
-        let program_header=ProgramHeader::parse_at
-            (idents, 0, &program_header.unwrap());
-        println!("[*]程序头部表头解析成功\n");
-        println!("{:?}",program_header);
-
-
-        let program_bytes=file::file_utils::read_file_range
-            (file_path,program_header.p_offset+ProgramHeader::size_for(class) as u64,program_header.p_offset+program_header.p_filesz);
         let e_phnum=binary_header.e_phnum;
         let e_phsz=binary_header.e_phentsize;
-        if ProgramHeader::check_program_size(binary_header,program_header){
+        //程序头表从e_phoff开始,第一项不一定是PT_PHDR
+        if e_phnum==0 || (e_phsz as usize)<ProgramHeader::size_for(class) {
             return None;
         }
+        let program_header_offset=binary_header.e_phoff;
+        let program_header_end=program_header_offset.checked_add(e_phsz as u64*e_phnum as u64)?;
+        let program_bytes=file::file_utils::read_file_range(file_path,program_header_offset,program_header_end).ok()?;
         let vec_header=ProgramHeader::parse_program
-            (idents.clone(),program_bytes.unwrap(),e_phnum,e_phsz);
+            (idents,program_bytes,e_phnum,e_phsz);
         println!("[*]程序头部表解析成功:");
         println!("{:?}",vec_header);
         return Some(vec_header);
@@ -95,8 +83,13 @@ impl ProgramHeader {
     }
     pub fn parse_program(ident: (AnyEndian, Class),program_bytes:Vec<u8>,e_phnum:u16,e_phsz:u16)->Vec<ProgramHeader>{
         let mut v: Vec<ProgramHeader> = Vec::new();
-        for i in 0..e_phnum-1{
-            let e_phdr=Self::parse_at(ident, (i * e_phsz) as usize, program_bytes.as_slice());
+        let (_,class)=ident;
+        for i in 0..e_phnum as usize{
+            let offset=i*e_phsz as usize;
+            if offset+Self::size_for(class)>program_bytes.len(){
+                break;
+            }
+            let e_phdr=Self::parse_at(ident, offset, program_bytes.as_slice());
             v.push(e_phdr);
         }
         return  v;
@@ -169,6 +162,31 @@ impl ProgramHeader {
         });
     }
 
+    /// Write this header at `offset` using the layout `parse_at` reads
+    pub fn write_at(&self, ident: (AnyEndian, Class), offset: usize, data: &mut [u8]) {
+        let (endian, class)=ident;
+        if class == Class::ELF32 {
+            endian.write_u32_at(offset, data, self.p_type);
+            endian.write_u32_at(offset+4, data, self.p_offset as u32);
+            endian.write_u32_at(offset+8, data, self.p_vaddr as u32);
+            endian.write_u32_at(offset+12, data, self.p_paddr as u32);
+            endian.write_u32_at(offset+16, data, self.p_filesz as u32);
+            endian.write_u32_at(offset+20, data, self.p_memsz as u32);
+            endian.write_u32_at(offset+24, data, self.p_flags);
+            endian.write_u32_at(offset+28, data, self.p_align as u32);
+            return;
+        }
+        // Note: 64-bit fields are in a different order
+        endian.write_u32_at(offset, data, self.p_type);
+        endian.write_u32_at(offset+4, data, self.p_flags);
+        endian.write_u64_at(offset+8, data, self.p_offset);
+        endian.write_u64_at(offset+16, data, self.p_vaddr);
+        endian.write_u64_at(offset+24, data, self.p_paddr);
+        endian.write_u64_at(offset+32, data, self.p_filesz);
+        endian.write_u64_at(offset+40, data, self.p_memsz);
+        endian.write_u64_at(offset+48, data, self.p_align);
+    }
+
     pub(crate) fn size_for(class: Class) -> usize {
         match class {
             Class::ELF32 => 32,
@@ -176,6 +194,6 @@ impl ProgramHeader {
         }
     }
     pub fn check_program_size(binary_header:FileHeader,program_header:ProgramHeader)->bool{
-        return u64::from(binary_header.e_phentsize*binary_header.e_phnum)!= program_header.p_filesz;
+        binary_header.e_phentsize as u64*binary_header.e_phnum as u64!= program_header.p_filesz
     }
 }
\ No newline at end of file
@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use crate::parser::abi;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
+use crate::parser::relocation::Rela;
+use crate::parser::symbol::Symbol;
+
+/// Errors reported while applying the relocations of an ET_REL object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelocError {
+    /// The file could not be parsed as ELF
+    Parse,
+    /// The file is not a relocatable object (ET_REL)
+    NotRelocatable,
+    UnsupportedMachine(u16),
+    /// A SHT_REL table targets an assigned section; only RELA is handled
+    UnsupportedTable { section: String },
+    UnsupportedType { section: String, r_offset: u64, r_type: u32 },
+    /// The computed value does not fit the relocated field
+    Overflow { section: String, r_offset: u64, r_type: u32, value: i64 },
+    UndefinedSymbol(String),
+    /// A section index that is missing from the assignment or the file
+    UnassignedSection(u16),
+    /// The relocated field lies outside the section
+    OutOfRange { section: String, r_offset: u64, r_type: u32 },
+    /// r_sym is not a valid index into the symbol table
+    BadSymbol { section: String, r_offset: u64, r_sym: u32 },
+    /// A RISC-V PCREL_LO12 relocation without a PCREL_HI20 at the address it names
+    MissingPcrelHi { section: String, r_offset: u64 },
+}
+
+/// Contents of one section after its `.rela.<section>` entries have been applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelocatedSection {
+    pub index: u16,
+    pub name: String,
+    /// Address the section was assigned
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+impl RelocatedSection {
+    pub fn read_relocated(file_path:&str,section_addrs:&HashMap<u16,u64>,externals:&HashMap<String,u64>)
+        ->Result<Vec<RelocatedSection>,RelocError>{
+        let elf=ElfFile::read_elf(file_path).ok_or(RelocError::Parse)?;
+        Self::relocate(&elf,section_addrs,externals)
+    }
+
+    /// Apply the relocations of an ET_REL object.
+    ///
+    /// `section_addrs` assigns an address to every section index that should be
+    /// produced; relocations may only reference sections listed there. Undefined
+    /// symbols are looked up in `externals`, and unresolved weak symbols become 0.
+    /// No linker relaxation is performed, so RISC-V RELAX/ALIGN markers are ignored.
+    pub fn relocate(elf:&ElfFile,section_addrs:&HashMap<u16,u64>,externals:&HashMap<String,u64>)
+        ->Result<Vec<RelocatedSection>,RelocError>{
+        if elf.header.e_type!=abi::ET_REL {
+            return Err(RelocError::NotRelocatable);
+        }
+        let machine=elf.header.e_machine;
+        if !matches!(machine,abi::EM_X86_64 | abi::EM_AARCH64 | abi::EM_RISCV) {
+            return Err(RelocError::UnsupportedMachine(machine));
+        }
+
+        let mut indexes:Vec<u16>=section_addrs.keys().copied().collect();
+        indexes.sort();
+        let mut sections=Vec::new();
+        for index in indexes {
+            let sh=elf.section_headers.get(index as usize).ok_or(RelocError::UnassignedSection(index))?;
+            let data=match elf.section_data(sh) {
+                Some(bytes) => bytes.to_vec(),
+                None => vec![0;sh.sh_size as usize],
+            };
+            sections.push(RelocatedSection{
+                index,
+                name:sh.string_name.clone(),
+                addr:section_addrs[&index],
+                data,
+            });
+        }
+
+        for rel_sh in &elf.section_headers {
+            if rel_sh.sh_type!=abi::SHT_RELA && rel_sh.sh_type!=abi::SHT_REL {
+                continue;
+            }
+            let Some(target)=sections.iter_mut().find(|s| s.index as u32==rel_sh.sh_info) else {
+                continue;
+            };
+            if rel_sh.sh_type==abi::SHT_REL {
+                return Err(RelocError::UnsupportedTable{section:rel_sh.string_name.clone()});
+            }
+            let bytes=elf.section_data(rel_sh).unwrap_or(&[]);
+            let relas=Rela::parse(elf.ident(),bytes,bytes.len() as u64);
+            let symtab=elf.section_headers.get(rel_sh.sh_link as usize)
+                .ok_or(RelocError::UnassignedSection(rel_sh.sh_link as u16))?;
+            let symbols=elf.symbols(symtab);
+            let mut ctx=Context{
+                machine,
+                endian:elf.header.endianness,
+                symbols:&symbols,
+                section_addrs,
+                externals,
+                pcrel_hi:HashMap::new(),
+            };
+            //RISC-V的PCREL_LO12引用auipc处PCREL_HI20的结果,先算出所有HI20
+            for rela in relas.iter().filter(|r| machine==abi::EM_RISCV && r.r_type==abi::R_RISCV_PCREL_HI20) {
+                let value=ctx.symbol_value(target,rela)?
+                    .wrapping_add(rela.r_addend)
+                    .wrapping_sub((target.addr+rela.r_offset) as i64);
+                ctx.pcrel_hi.insert(target.addr+rela.r_offset,value);
+            }
+            for rela in &relas {
+                ctx.apply(target,rela)?;
+            }
+        }
+        Ok(sections)
+    }
+}
+
+struct Context<'a> {
+    machine: u16,
+    endian: AnyEndian,
+    symbols: &'a [Symbol],
+    section_addrs: &'a HashMap<u16,u64>,
+    externals: &'a HashMap<String,u64>,
+    /// PCREL_HI20 results keyed by the address of their auipc
+    pcrel_hi: HashMap<u64,i64>,
+}
+
+impl Context<'_> {
+    fn symbol_value(&self,target:&RelocatedSection,rela:&Rela)->Result<i64,RelocError>{
+        if rela.r_sym==0 {
+            return Ok(0);
+        }
+        let symbol=self.symbols.get(rela.r_sym as usize).ok_or_else(|| RelocError::BadSymbol{
+            section:target.name.clone(),
+            r_offset:rela.r_offset,
+            r_sym:rela.r_sym,
+        })?;
+        let value=match symbol.st_shndx {
+            abi::SHN_ABS => symbol.st_value,
+            abi::SHN_UNDEF | abi::SHN_COMMON => match self.externals.get(&symbol.string_name) {
+                Some(&value) => value,
+                None if symbol.st_bind()==abi::STB_WEAK => 0,
+                None => return Err(RelocError::UndefinedSymbol(symbol.string_name.clone())),
+            },
+            shndx => self.section_addrs.get(&shndx)
+                .ok_or(RelocError::UnassignedSection(shndx))?
+                .wrapping_add(symbol.st_value),
+        };
+        Ok(value as i64)
+    }
+
+    fn apply(&self,target:&mut RelocatedSection,rela:&Rela)->Result<(),RelocError>{
+        let unsupported=|| RelocError::UnsupportedType{
+            section:target.name.clone(),
+            r_offset:rela.r_offset,
+            r_type:rela.r_type,
+        };
+        let field=match (self.machine,rela.r_type) {
+            (abi::EM_X86_64,abi::R_X86_64_NONE) |
+            (abi::EM_AARCH64,abi::R_AARCH64_NONE) |
+            (abi::EM_RISCV,abi::R_RISCV_NONE) |
+            (abi::EM_RISCV,abi::R_RISCV_RELAX) |
+            (abi::EM_RISCV,abi::R_RISCV_ALIGN) => return Ok(()),
+            (abi::EM_X86_64,r_type) => Self::x86_64_field(r_type).ok_or_else(unsupported)?,
+            (abi::EM_AARCH64,r_type) => Self::aarch64_field(r_type).ok_or_else(unsupported)?,
+            (_,r_type) => Self::riscv_field(r_type).ok_or_else(unsupported)?,
+        };
+
+        let s=self.symbol_value(target,rela)?;
+        let a=rela.r_addend;
+        let p=target.addr.wrapping_add(rela.r_offset) as i64;
+        let offset=rela.r_offset as usize;
+        if rela.r_offset.checked_add(field.width()).is_none_or(|end| end>target.data.len() as u64) {
+            return Err(RelocError::OutOfRange{section:target.name.clone(),r_offset:rela.r_offset,r_type:rela.r_type});
+        }
+        let overflow=|value:i64| RelocError::Overflow{
+            section:target.name.clone(),
+            r_offset:rela.r_offset,
+            r_type:rela.r_type,
+            value,
+        };
+        let data=&mut target.data;
+        let endian=self.endian;
+
+        match field {
+            Field::Data64{pcrel} => {
+                let value=if pcrel { s.wrapping_add(a).wrapping_sub(p) } else { s.wrapping_add(a) };
+                endian.write_u64_at(offset,data,value as u64);
+            }
+            Field::Data32{pcrel,check} => {
+                let value=if pcrel { s.wrapping_add(a).wrapping_sub(p) } else { s.wrapping_add(a) };
+                let ok=match check {
+                    Check::None => true,
+                    Check::Signed => fits_signed(value,32),
+                    Check::Unsigned => (0..=u32::MAX as i64).contains(&value),
+                    Check::Either => (i32::MIN as i64..=u32::MAX as i64).contains(&value),
+                };
+                if !ok {
+                    return Err(overflow(value));
+                }
+                endian.write_u32_at(offset,data,value as u32);
+            }
+            Field::RiscvAddSub{width,add} => {
+                let sa=s.wrapping_add(a);
+                if width==8 {
+                    let old=endian.parse_u64_at(offset,data) as i64;
+                    let value=if add { old.wrapping_add(sa) } else { old.wrapping_sub(sa) };
+                    endian.write_u64_at(offset,data,value as u64);
+                } else {
+                    let old=endian.parse_u32_at(offset,data) as i64;
+                    let value=if add { old.wrapping_add(sa) } else { old.wrapping_sub(sa) };
+                    endian.write_u32_at(offset,data,value as u32);
+                }
+            }
+            Field::Insn(kind) => {
+                let insn=read_insn(data,offset);
+                let patched=match kind {
+                    Insn::Aarch64Branch{bits} => {
+                        let value=s.wrapping_add(a).wrapping_sub(p);
+                        if !fits_signed(value,bits+2) {
+                            return Err(overflow(value));
+                        }
+                        let mask=(1u32<<bits)-1;
+                        //B/BL的imm26在最低位,B.cond的imm19从第5位开始
+                        let shift=if bits==26 { 0 } else { 5 };
+                        (insn & !(mask<<shift)) | ((((value>>2) as u32) & mask)<<shift)
+                    }
+                    Insn::Aarch64Adr{page,check} => {
+                        let value=if page {
+                            (s.wrapping_add(a) & !0xfff).wrapping_sub(p & !0xfff)
+                        } else {
+                            s.wrapping_add(a).wrapping_sub(p)
+                        };
+                        let imm=if page { value>>12 } else { value };
+                        if check && !fits_signed(imm,21) {
+                            return Err(overflow(value));
+                        }
+                        let imm=imm as u32;
+                        (insn & !((3<<29) | (0x7ffff<<5))) | ((imm & 3)<<29) | (((imm>>2) & 0x7ffff)<<5)
+                    }
+                    Insn::Aarch64Lo12{shift} => {
+                        let imm=((s.wrapping_add(a) as u32) & 0xfff)>>shift;
+                        (insn & !(0xfff<<10)) | (imm<<10)
+                    }
+                    Insn::RiscvBranch => {
+                        let value=s.wrapping_add(a).wrapping_sub(p);
+                        if !fits_signed(value,13) {
+                            return Err(overflow(value));
+                        }
+                        encode_b(insn,value as u32)
+                    }
+                    Insn::RiscvJal => {
+                        let value=s.wrapping_add(a).wrapping_sub(p);
+                        if !fits_signed(value,21) {
+                            return Err(overflow(value));
+                        }
+                        encode_j(insn,value as u32)
+                    }
+                    Insn::RiscvHi20{pcrel} => {
+                        let value=if pcrel { s.wrapping_add(a).wrapping_sub(p) } else { s.wrapping_add(a) };
+                        if !fits_signed(value.wrapping_add(0x800),32) {
+                            return Err(overflow(value));
+                        }
+                        (insn & 0xfff) | (hi20(value)<<12)
+                    }
+                    Insn::RiscvLo12{pcrel,store} => {
+                        let value=if pcrel {
+                            //符号指向对应的auipc指令
+                            *self.pcrel_hi.get(&(s as u64)).ok_or_else(|| RelocError::MissingPcrelHi{
+                                section:target.name.clone(),
+                                r_offset:rela.r_offset,
+                            })?
+                        } else {
+                            s.wrapping_add(a)
+                        };
+                        let lo=value.wrapping_sub((hi20(value) as i64)<<12) as u32;
+                        if store { encode_s(insn,lo) } else { (insn & 0x000f_ffff) | ((lo & 0xfff)<<20) }
+                    }
+                    Insn::RiscvCall => {
+                        let value=s.wrapping_add(a).wrapping_sub(p);
+                        if !fits_signed(value.wrapping_add(0x800),32) {
+                            return Err(overflow(value));
+                        }
+                        let jalr=read_insn(data,offset+4);
+                        let lo=value.wrapping_sub((hi20(value) as i64)<<12) as u32;
+                        write_insn(data,offset+4,(jalr & 0x000f_ffff) | ((lo & 0xfff)<<20));
+                        (insn & 0xfff) | (hi20(value)<<12)
+                    }
+                    Insn::RiscvRvcBranch => {
+                        let value=s.wrapping_add(a).wrapping_sub(p);
+                        if !fits_signed(value,9) {
+                            return Err(overflow(value));
+                        }
+                        let old=u16::from_le_bytes([data[offset],data[offset+1]]);
+                        let new=encode_cb(old,value as u16);
+                        data[offset..offset+2].copy_from_slice(&new.to_le_bytes());
+                        return Ok(());
+                    }
+                    Insn::RiscvRvcJump => {
+                        let value=s.wrapping_add(a).wrapping_sub(p);
+                        if !fits_signed(value,12) {
+                            return Err(overflow(value));
+                        }
+                        let old=u16::from_le_bytes([data[offset],data[offset+1]]);
+                        let new=encode_cj(old,value as u16);
+                        data[offset..offset+2].copy_from_slice(&new.to_le_bytes());
+                        return Ok(());
+                    }
+                };
+                write_insn(data,offset,patched);
+            }
+        }
+        Ok(())
+    }
+
+    fn x86_64_field(r_type:u32)->Option<Field>{
+        Some(match r_type {
+            abi::R_X86_64_64 => Field::Data64{pcrel:false},
+            abi::R_X86_64_PC64 => Field::Data64{pcrel:true},
+            //没有PLT时PLT32直接指向符号本身
+            abi::R_X86_64_PC32 | abi::R_X86_64_PLT32 => Field::Data32{pcrel:true,check:Check::Signed},
+            abi::R_X86_64_32 => Field::Data32{pcrel:false,check:Check::Unsigned},
+            abi::R_X86_64_32S => Field::Data32{pcrel:false,check:Check::Signed},
+            _ => return None,
+        })
+    }
+
+    fn aarch64_field(r_type:u32)->Option<Field>{
+        Some(match r_type {
+            abi::R_AARCH64_ABS64 => Field::Data64{pcrel:false},
+            abi::R_AARCH64_PREL64 => Field::Data64{pcrel:true},
+            abi::R_AARCH64_ABS32 => Field::Data32{pcrel:false,check:Check::Either},
+            abi::R_AARCH64_PREL32 => Field::Data32{pcrel:true,check:Check::Either},
+            abi::R_AARCH64_CALL26 | abi::R_AARCH64_JUMP26 => Field::Insn(Insn::Aarch64Branch{bits:26}),
+            abi::R_AARCH64_CONDBR19 => Field::Insn(Insn::Aarch64Branch{bits:19}),
+            abi::R_AARCH64_ADR_PREL_LO21 => Field::Insn(Insn::Aarch64Adr{page:false,check:true}),
+            abi::R_AARCH64_ADR_PREL_PG_HI21 => Field::Insn(Insn::Aarch64Adr{page:true,check:true}),
+            abi::R_AARCH64_ADR_PREL_PG_HI21_NC => Field::Insn(Insn::Aarch64Adr{page:true,check:false}),
+            abi::R_AARCH64_ADD_ABS_LO12_NC |
+            abi::R_AARCH64_LDST8_ABS_LO12_NC => Field::Insn(Insn::Aarch64Lo12{shift:0}),
+            abi::R_AARCH64_LDST16_ABS_LO12_NC => Field::Insn(Insn::Aarch64Lo12{shift:1}),
+            abi::R_AARCH64_LDST32_ABS_LO12_NC => Field::Insn(Insn::Aarch64Lo12{shift:2}),
+            abi::R_AARCH64_LDST64_ABS_LO12_NC => Field::Insn(Insn::Aarch64Lo12{shift:3}),
+            abi::R_AARCH64_LDST128_ABS_LO12_NC => Field::Insn(Insn::Aarch64Lo12{shift:4}),
+            _ => return None,
+        })
+    }
+
+    fn riscv_field(r_type:u32)->Option<Field>{
+        Some(match r_type {
+            abi::R_RISCV_64 => Field::Data64{pcrel:false},
+            abi::R_RISCV_32 => Field::Data32{pcrel:false,check:Check::None},
+            abi::R_RISCV_ADD32 => Field::RiscvAddSub{width:4,add:true},
+            abi::R_RISCV_ADD64 => Field::RiscvAddSub{width:8,add:true},
+            abi::R_RISCV_SUB32 => Field::RiscvAddSub{width:4,add:false},
+            abi::R_RISCV_SUB64 => Field::RiscvAddSub{width:8,add:false},
+            abi::R_RISCV_BRANCH => Field::Insn(Insn::RiscvBranch),
+            abi::R_RISCV_JAL => Field::Insn(Insn::RiscvJal),
+            abi::R_RISCV_CALL | abi::R_RISCV_CALL_PLT => Field::Insn(Insn::RiscvCall),
+            abi::R_RISCV_PCREL_HI20 => Field::Insn(Insn::RiscvHi20{pcrel:true}),
+            abi::R_RISCV_HI20 => Field::Insn(Insn::RiscvHi20{pcrel:false}),
+            abi::R_RISCV_PCREL_LO12_I => Field::Insn(Insn::RiscvLo12{pcrel:true,store:false}),
+            abi::R_RISCV_PCREL_LO12_S => Field::Insn(Insn::RiscvLo12{pcrel:true,store:true}),
+            abi::R_RISCV_LO12_I => Field::Insn(Insn::RiscvLo12{pcrel:false,store:false}),
+            abi::R_RISCV_LO12_S => Field::Insn(Insn::RiscvLo12{pcrel:false,store:true}),
+            abi::R_RISCV_RVC_BRANCH => Field::Insn(Insn::RiscvRvcBranch),
+            abi::R_RISCV_RVC_JUMP => Field::Insn(Insn::RiscvRvcJump),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Check {
+    None,
+    Signed,
+    Unsigned,
+    /// Either a signed or an unsigned value of the field width
+    Either,
+}
+
+/// The field a relocation type writes
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Data64 { pcrel: bool },
+    Data32 { pcrel: bool, check: Check },
+    RiscvAddSub { width: u64, add: bool },
+    Insn(Insn),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Insn {
+    Aarch64Branch { bits: u32 },
+    Aarch64Adr { page: bool, check: bool },
+    Aarch64Lo12 { shift: u32 },
+    RiscvBranch,
+    RiscvJal,
+    RiscvCall,
+    RiscvHi20 { pcrel: bool },
+    RiscvLo12 { pcrel: bool, store: bool },
+    RiscvRvcBranch,
+    RiscvRvcJump,
+}
+
+impl Field {
+    fn width(&self)->u64{
+        match self {
+            Field::Data64{..} => 8,
+            Field::Data32{..} => 4,
+            Field::RiscvAddSub{width,..} => *width,
+            Field::Insn(Insn::RiscvCall) => 8,
+            Field::Insn(Insn::RiscvRvcBranch) | Field::Insn(Insn::RiscvRvcJump) => 2,
+            Field::Insn(_) => 4,
+        }
+    }
+}
+
+fn fits_signed(value:i64,bits:u32)->bool{
+    let limit=1i64<<(bits-1);
+    (-limit..limit).contains(&value)
+}
+
+//AArch64与RISC-V的指令总是小端
+fn read_insn(data:&[u8],offset:usize)->u32{
+    u32::from_le_bytes([data[offset],data[offset+1],data[offset+2],data[offset+3]])
+}
+
+fn write_insn(data:&mut [u8],offset:usize,insn:u32){
+    data[offset..offset+4].copy_from_slice(&insn.to_le_bytes());
+}
+
+/// High 20 bits, rounded so that the sign-extended low 12 bits add back to `value`
+fn hi20(value:i64)->u32{
+    ((value.wrapping_add(0x800)>>12) as u32) & 0xfffff
+}
+
+fn encode_s(insn:u32,imm:u32)->u32{
+    (insn & 0x01ff_f07f) | (((imm>>5) & 0x7f)<<25) | ((imm & 0x1f)<<7)
+}
+
+fn encode_b(insn:u32,imm:u32)->u32{
+    (insn & 0x01ff_f07f)
+        | (((imm>>12) & 1)<<31)
+        | (((imm>>5) & 0x3f)<<25)
+        | (((imm>>1) & 0xf)<<8)
+        | (((imm>>11) & 1)<<7)
+}
+
+fn encode_j(insn:u32,imm:u32)->u32{
+    (insn & 0xfff)
+        | (((imm>>20) & 1)<<31)
+        | (((imm>>1) & 0x3ff)<<21)
+        | (((imm>>11) & 1)<<20)
+        | (((imm>>12) & 0xff)<<12)
+}
+
+fn encode_cb(insn:u16,imm:u16)->u16{
+    (insn & 0xe383)
+        | (((imm>>8) & 1)<<12)
+        | (((imm>>3) & 3)<<10)
+        | (((imm>>6) & 3)<<5)
+        | (((imm>>1) & 3)<<3)
+        | (((imm>>5) & 1)<<2)
+}
+
+fn encode_cj(insn:u16,imm:u16)->u16{
+    (insn & 0xe003)
+        | (((imm>>11) & 1)<<12)
+        | (((imm>>4) & 1)<<11)
+        | (((imm>>8) & 3)<<9)
+        | (((imm>>10) & 1)<<8)
+        | (((imm>>6) & 1)<<7)
+        | (((imm>>7) & 1)<<6)
+        | (((imm>>1) & 7)<<3)
+        | (((imm>>5) & 1)<<2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn riscv_encoders(){
+        //jal ra,+8
+        assert_eq!(encode_j(0x000000ef,8),0x008000ef);
+        //jal x0,-4
+        assert_eq!(encode_j(0x0000006f,-4i32 as u32),0xffdff06f);
+        //beq x0,x0,+16
+        assert_eq!(encode_b(0x00000063,16),0x00000863);
+        //sw a0,12(sp)
+        assert_eq!(encode_s(0x00a12023,12),0x00a12623);
+        //c.j +4, c.beqz a0,+8
+        assert_eq!(encode_cj(0xa001,4),0xa011);
+        assert_eq!(encode_cb(0xc101,8),0xc501);
+    }
+
+    #[test]
+    fn riscv_hi20_rounds_for_signed_lo12(){
+        assert_eq!(hi20(0x7ff),0);
+        assert_eq!(hi20(0x800),1);
+        assert_eq!(hi20(-1),0);
+        assert_eq!(hi20(0x12345fff),0x12346);
+        //hi20<<12加上符号扩展的低12位还原原值
+        for value in [0x12345678i64,-0x12345678,0x7ff,0x800,-0x800] {
+            let lo=((value as u32)&0xfff) as i32;
+            let lo=(lo<<20)>>20;
+            assert_eq!((((hi20(value)<<12) as i32) as i64)+lo as i64,value);
+        }
+    }
+
+    #[test]
+    fn signed_range(){
+        assert!(fits_signed(127,8));
+        assert!(fits_signed(-128,8));
+        assert!(!fits_signed(128,8));
+        assert!(!fits_signed(-129,8));
+        assert!(fits_signed(i32::MIN as i64,32));
+        assert!(!fits_signed(u32::MAX as i64,32));
+    }
+
+    fn apply_all(machine:u16,relas:&[Rela],data:Vec<u8>)->Result<Vec<u8>,RelocError>{
+        let symbols=vec![
+            Symbol::new("",0,0,0,0,0),
+            Symbol::new("func",abi::STB_GLOBAL,abi::STT_FUNC,1,0x40,0),
+            Symbol::new("far",abi::STB_GLOBAL,abi::STT_FUNC,abi::SHN_ABS,0x3000,0),
+            Symbol::new("huge",abi::STB_GLOBAL,abi::STT_FUNC,abi::SHN_ABS,0x1_0000_0000,0),
+        ];
+        let section_addrs=HashMap::from([(1,0x1000)]);
+        let externals=HashMap::new();
+        let ctx=Context{
+            machine,
+            endian:AnyEndian::new(1),
+            symbols:&symbols,
+            section_addrs:&section_addrs,
+            externals:&externals,
+            pcrel_hi:HashMap::new(),
+        };
+        let mut target=RelocatedSection{index:1,name:".text".to_string(),addr:0x1000,data};
+        for rela in relas {
+            ctx.apply(&mut target,rela)?;
+        }
+        Ok(target.data)
+    }
+
+    fn rela(r_offset:u64,r_sym:u32,r_type:u32,r_addend:i64)->Rela{
+        Rela{r_offset,r_sym,r_type,r_addend}
+    }
+
+    #[test]
+    fn aarch64_branch_and_page(){
+        let mut data=Vec::new();
+        //bl 0; adrp x0,0; add x0,x0,0
+        for insn in [0x94000000u32,0x90000000,0x91000000] {
+            data.extend_from_slice(&insn.to_le_bytes());
+        }
+        let data=apply_all(abi::EM_AARCH64,&[
+            rela(0,1,abi::R_AARCH64_CALL26,0),
+            rela(4,2,abi::R_AARCH64_ADR_PREL_PG_HI21,0x123),
+            rela(8,2,abi::R_AARCH64_ADD_ABS_LO12_NC,0x123),
+        ],data).unwrap();
+        assert_eq!(read_insn(&data,0),0x94000010);
+        assert_eq!(read_insn(&data,4),0xd0000000);
+        assert_eq!(read_insn(&data,8),0x91048c00);
+    }
+
+    #[test]
+    fn x86_64_pc32_and_overflow(){
+        let data=apply_all(abi::EM_X86_64,&[
+            rela(4,1,abi::R_X86_64_PC32,-4),
+            rela(8,2,abi::R_X86_64_64,8),
+        ],vec![0;16]).unwrap();
+        assert_eq!(data[4..8],0x38u32.to_le_bytes());
+        assert_eq!(data[8..16],0x3008u64.to_le_bytes());
+        let error=apply_all(abi::EM_X86_64,&[rela(0,3,abi::R_X86_64_32S,0)],vec![0;4]).unwrap_err();
+        assert!(matches!(error,RelocError::Overflow{value:0x1_0000_0000,..}));
+        let error=apply_all(abi::EM_X86_64,&[rela(1,1,abi::R_X86_64_PC32,0)],vec![0;4]).unwrap_err();
+        assert!(matches!(error,RelocError::OutOfRange{..}));
+    }
+
+    #[test]
+    fn field_widths(){
+        assert_eq!(Context::x86_64_field(abi::R_X86_64_PC32).map(|f| f.width()),Some(4));
+        assert_eq!(Context::x86_64_field(abi::R_X86_64_64).map(|f| f.width()),Some(8));
+        assert_eq!(Context::aarch64_field(abi::R_AARCH64_CALL26).map(|f| f.width()),Some(4));
+        assert_eq!(Context::riscv_field(abi::R_RISCV_CALL).map(|f| f.width()),Some(8));
+        assert_eq!(Context::riscv_field(abi::R_RISCV_RVC_JUMP).map(|f| f.width()),Some(2));
+        assert!(Context::x86_64_field(abi::R_X86_64_GLOB_DAT).is_none());
+    }
+}
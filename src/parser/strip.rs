@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use crate::parser::abi;
+use crate::parser::edit::EditError;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
+use crate::parser::inflate;
+use crate::parser::relocation::{Rel, Rela};
+use crate::parser::section::SectionHeader;
+use crate::parser::section_edit::SectionEditor;
+use crate::parser::symbol::Symbol;
+
+/// What `strip` removes, after the binutils options of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StripMode {
+    /// `--strip-all`: `.symtab`, `.strtab` and the debug sections; relocatable
+    /// objects also lose the relocation sections that refer to `.symtab`
+    All,
+    /// `--strip-debug`: debug sections, their relocations, and the file and
+    /// section symbols that refer to them
+    Debug,
+    /// `--strip-unneeded`: debug sections and every symbol that relocation
+    /// processing does not need; executables and shared objects lose `.symtab`
+    Unneeded,
+}
+
+/// Contents of `.gnu_debuglink`: the name of the separate debug file and the CRC32
+/// of that file, which debuggers check before using it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugLink {
+    pub filename: String,
+    pub crc: u32,
+}
+
+impl DebugLink {
+    pub fn new(filename:&str,debug_file:&[u8])->DebugLink{
+        DebugLink{
+            filename:filename.to_string(),
+            crc:inflate::crc32(debug_file),
+        }
+    }
+
+    pub fn read_debuglink(file_path:&str)->Option<DebugLink>{
+        Self::parse_debuglink(&ElfFile::read_elf(file_path)?)
+    }
+
+    pub fn parse_debuglink(elf:&ElfFile)->Option<DebugLink>{
+        let bytes=elf.section_data(elf.find_section(".gnu_debuglink")?)?;
+        let filename=SectionHeader::parse_string_at(bytes,0)?;
+        //文件名后补齐到4字节,再跟CRC
+        let offset=(filename.len()+1).next_multiple_of(4);
+        if bytes.len()<offset+4 {
+            return None;
+        }
+        Some(DebugLink{
+            filename,
+            crc:elf.header.endianness.parse_u32_at(offset,bytes),
+        })
+    }
+
+    pub fn to_bytes(&self,endian:AnyEndian)->Vec<u8>{
+        let offset=(self.filename.len()+1).next_multiple_of(4);
+        let mut bytes=vec![0u8;offset+4];
+        bytes[..self.filename.len()].copy_from_slice(self.filename.as_bytes());
+        endian.write_u32_at(offset,&mut bytes,self.crc);
+        bytes
+    }
+}
+
+impl SectionEditor {
+    /// Remove symbols and sections according to `mode`. Allocated sections are never
+    /// touched, so the loadable image stays byte for byte the same.
+    pub fn strip(&mut self,mode:StripMode)->Result<(),EditError>{
+        let relocatable=self.elf.header.e_type==abi::ET_REL;
+        let headers=self.section_headers();
+        let debug:HashSet<u16>=headers.iter()
+            .filter(|sh| Self::is_debug_section(sh))
+            .map(|sh| sh.index)
+            .collect();
+        let drop_symtab=mode==StripMode::All || (!relocatable && mode==StripMode::Unneeded);
+        let mut names:Vec<String>=Vec::new();
+        for sh in headers.iter().filter(|sh| sh.sh_type==abi::SHT_SYMTAB) {
+            if drop_symtab {
+                names.push(sh.string_name.clone());
+                names.extend(headers.iter()
+                    .filter(|r| (r.sh_type==abi::SHT_REL || r.sh_type==abi::SHT_RELA) && r.sh_link==sh.index as u32)
+                    .map(|r| r.string_name.clone()));
+                if let Some(strtab)=headers.get(sh.sh_link as usize) {
+                    if strtab.index as usize!=self.shstrndx() {
+                        names.push(strtab.string_name.clone());
+                    }
+                }
+                continue;
+            }
+            let in_debug=|symbol:&Symbol| debug.contains(&symbol.st_shndx);
+            match mode {
+                StripMode::Debug => self.filter_symbols(sh.index as usize,|symbol,_| {
+                    symbol.st_type()!=abi::STT_FILE && !in_debug(symbol)
+                }),
+                StripMode::All | StripMode::Unneeded => self.filter_symbols(sh.index as usize,|symbol,referenced| {
+                    !in_debug(symbol) && (referenced || symbol.st_bind()!=abi::STB_LOCAL || symbol.st_type()==abi::STT_SECTION)
+                }),
+            }
+        }
+        //删除会改变下标,每次重新查找
+        while let Some(index)=self.section_headers().iter()
+            .position(|sh| Self::is_debug_section(sh) || (sh.sh_flags&abi::SHF_ALLOC==0 && names.contains(&sh.string_name))) {
+            self.remove_section_at(index)?;
+        }
+        Ok(())
+    }
+
+    fn is_debug_section(header:&SectionHeader)->bool{
+        let name=header.string_name.as_str();
+        header.sh_flags&abi::SHF_ALLOC==0
+            && [".debug",".zdebug",".stab",".gdb_index",".line"].iter().any(|prefix| name.starts_with(prefix))
+    }
+
+    /// Keep the symbols `keep` accepts (it is told whether a relocation refers to the
+    /// symbol), then rebuild the string table and renumber `r_sym` in the relocation
+    /// sections that use this symbol table
+    fn filter_symbols<F>(&mut self,symtab:usize,keep:F)
+        where F: Fn(&Symbol,bool)->bool{
+        let ident=self.elf.ident();
+        let mut referenced=HashSet::new();
+        for sh in self.section_headers().iter().filter(|sh| sh.sh_link as usize==symtab) {
+            let bytes=self.contents(sh.index as usize);
+            if sh.sh_type==abi::SHT_RELA {
+                referenced.extend(Rela::parse(ident,&bytes,sh.sh_size).iter().map(|r| r.r_sym));
+            } else if sh.sh_type==abi::SHT_REL {
+                referenced.extend(Rel::parse(ident,&bytes,sh.sh_size).iter().map(|r| r.r_sym));
+            }
+        }
+        let symbols=self.named_symbols(symtab).into_iter().enumerate()
+            .filter(|(i,symbol)| *i==0 || keep(symbol,referenced.contains(&(*i as u32))))
+            .collect();
+        self.set_symbols(symtab,symbols);
+    }
+
+    /// Point `.gnu_debuglink` at a separate debug file, replacing an existing link
+    pub fn add_gnu_debuglink(&mut self,link:&DebugLink){
+        let data=link.to_bytes(self.elf.header.endianness);
+        match self.find(".gnu_debuglink") {
+            Ok(index) => self.set_contents(index,data),
+            Err(_) => {
+                let mut header=SectionHeader::new(".gnu_debuglink",abi::SHT_PROGBITS,0);
+                header.sh_addralign=4;
+                self.add_section(header,data);
+            }
+        }
+    }
+
+    /// The `--only-keep-debug` companion of the original file: every section header
+    /// is kept, but allocated sections other than notes become SHT_NOBITS and lose
+    /// their contents. Headers and notes stay at their original offsets, so the
+    /// segments that cover them still do; every other segment keeps no file bytes.
+    pub fn only_keep_debug(&self)->Vec<u8>{
+        let elf=&self.elf;
+        let mut out=elf.clone();
+        let is_note=|sh:&SectionHeader| sh.sh_type==abi::SHT_NOTE && sh.sh_flags&abi::SHF_ALLOC!=0;
+        let phdrs_end=elf.header.e_phoff+elf.program_headers.len() as u64*elf.header.e_phentsize as u64;
+        //文件头、程序头表与note节保留在原位置
+        let prefix_end=elf.section_headers.iter()
+            .filter(|sh| is_note(sh))
+            .map(|sh| sh.sh_offset+sh.sh_size)
+            .fold(phdrs_end.max(elf.header.e_ehsize as u64),u64::max)
+            .min(elf.data.len() as u64) as usize;
+        out.data=vec![0u8;prefix_end];
+        out.data[..phdrs_end.max(elf.header.e_ehsize as u64) as usize].copy_from_slice(&elf.data[..phdrs_end.max(elf.header.e_ehsize as u64) as usize]);
+        for sh in out.section_headers.iter_mut().skip(1) {
+            if is_note(sh) {
+                if let Some(bytes)=elf.section_data(sh) {
+                    let offset=sh.sh_offset as usize;
+                    out.data[offset..offset+bytes.len()].copy_from_slice(bytes);
+                }
+                continue;
+            }
+            if sh.sh_flags&abi::SHF_ALLOC!=0 || sh.sh_type==abi::SHT_NOBITS {
+                sh.sh_type=abi::SHT_NOBITS;
+                sh.sh_offset=sh.sh_offset.min(prefix_end as u64);
+                continue;
+            }
+            let bytes=elf.section_data(sh).unwrap_or(&[]);
+            let offset=out.data.len().next_multiple_of(sh.sh_addralign.max(1) as usize);
+            out.data.resize(offset,0);
+            out.data.extend_from_slice(bytes);
+            sh.sh_offset=offset as u64;
+        }
+        let prefix_end=prefix_end as u64;
+        let data_end=out.data.len() as u64;
+        for ph in out.program_headers.iter_mut() {
+            match ph.p_type {
+                abi::PT_PHDR => {}
+                abi::PT_LOAD | abi::PT_NOTE | abi::PT_GNU_PROPERTY if ph.p_offset<prefix_end => {
+                    ph.p_filesz=ph.p_filesz.min(prefix_end-ph.p_offset);
+                }
+                _ => {
+                    //不再占用文件内容，偏移取与p_vaddr同余且不超出文件的值
+                    ph.p_filesz=0;
+                    ph.p_offset=match ph.p_vaddr%ph.p_align.max(1) {
+                        offset if ph.p_type==abi::PT_LOAD && offset<=data_end => offset,
+                        _ => ph.p_offset.min(data_end),
+                    };
+                }
+            }
+        }
+        let word=elf.header.class.word_size();
+        out.header.e_shoff=out.data.len().next_multiple_of(word) as u64;
+        out.data.resize(out.header.e_shoff as usize,0);
+        out.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //gcc -g -O0 编译的小程序,带.symtab与.debug_*
+    const DEBUG_ELF: &[u8] = include_bytes!("../test_elf/dbg");
+
+    fn strip(mode:StripMode)->ElfFile{
+        let mut editor=SectionEditor::new(ElfFile::parse(DEBUG_ELF.to_vec()).unwrap()).unwrap();
+        editor.strip(mode).unwrap();
+        ElfFile::parse(editor.to_bytes().unwrap()).unwrap()
+    }
+
+    //文件头里的e_shoff/e_shnum会变,跳过
+    fn loaded_image(elf:&ElfFile)->Vec<Vec<u8>>{
+        elf.program_headers.iter().filter(|ph| ph.p_type==abi::PT_LOAD).map(|ph| {
+            let skip=if ph.p_offset==0 { elf.header.e_ehsize as usize } else { 0 };
+            elf.segment_data(ph).unwrap()[skip..].to_vec()
+        }).collect()
+    }
+
+    fn has_debug(elf:&ElfFile)->bool{
+        elf.section_headers.iter().any(|sh| sh.string_name.starts_with(".debug"))
+    }
+
+    #[test]
+    fn strip_all(){
+        let original=ElfFile::parse(DEBUG_ELF.to_vec()).unwrap();
+        let out=strip(StripMode::All);
+        assert!(!has_debug(&out));
+        assert!(out.find_section(".symtab").is_none() && out.find_section(".strtab").is_none());
+        assert!(out.find_section(".dynsym").is_some());
+        assert_eq!(loaded_image(&out),loaded_image(&original));
+    }
+
+    #[test]
+    fn strip_debug(){
+        let original=ElfFile::parse(DEBUG_ELF.to_vec()).unwrap();
+        let out=strip(StripMode::Debug);
+        assert!(!has_debug(&out));
+        let symbols=out.symbols(out.find_section(".symtab").unwrap());
+        assert!(symbols.iter().all(|symbol| symbol.st_type()!=abi::STT_FILE));
+        let bump=symbols.iter().find(|symbol| symbol.string_name=="bump").unwrap();
+        assert_eq!((bump.st_value,bump.st_size),(0x1139,32));
+        assert!(symbols.iter().any(|symbol| symbol.string_name=="counter"));
+        assert_eq!(loaded_image(&out),loaded_image(&original));
+    }
+
+    #[test]
+    fn only_keep_debug(){
+        let editor=SectionEditor::new(ElfFile::parse(DEBUG_ELF.to_vec()).unwrap()).unwrap();
+        let original=&editor.elf;
+        let out=ElfFile::parse(editor.only_keep_debug()).unwrap();
+        assert_eq!(out.section_headers.len(),original.section_headers.len());
+        for (old,new) in original.section_headers.iter().zip(&out.section_headers).skip(1) {
+            assert_eq!((&new.string_name,new.sh_addr,new.sh_size),(&old.string_name,old.sh_addr,old.sh_size));
+            if old.sh_flags&abi::SHF_ALLOC!=0 && old.sh_type!=abi::SHT_NOTE {
+                assert_eq!(new.sh_type,abi::SHT_NOBITS,"{}",new.string_name);
+            } else if old.sh_type!=abi::SHT_NOBITS {
+                assert_eq!(out.section_data(new),original.section_data(old),"{}",new.string_name);
+            }
+        }
+        assert!(out.data.len()<original.data.len());
+    }
+
+    #[test]
+    fn add_debuglink(){
+        //CRC-32标准校验值
+        let link=DebugLink::new("dbg.debug",b"123456789");
+        assert_eq!(link.crc,0xcbf43926);
+        let mut editor=SectionEditor::new(ElfFile::parse(DEBUG_ELF.to_vec()).unwrap()).unwrap();
+        editor.add_gnu_debuglink(&link);
+        let out=ElfFile::parse(editor.to_bytes().unwrap()).unwrap();
+        let section=out.find_section(".gnu_debuglink").unwrap();
+        assert_eq!(out.section_data(section).unwrap(),b"dbg.debug\0\0\0\x26\x39\xf4\xcb");
+        assert_eq!(DebugLink::parse_debuglink(&out),Some(link));
+    }
+}
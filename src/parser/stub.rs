@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::parser::abi;
+use crate::parser::builder::{BuildError, ElfBuilder};
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::file::Class;
+use crate::parser::section::SectionHeader;
+use crate::parser::symbol::Symbol;
+use crate::parser::version::{VersionDefinition, VersionTables};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StubError {
+    Parse,
+    Io(String),
+    /// The input has no dynamic symbols to export
+    NotDynamic,
+    /// No stub bodies for this e_machine
+    UnsupportedMachine(u16),
+    Build(BuildError),
+}
+
+/// A symbol the library exports, with its `.gnu.version` entry
+#[derive(Debug, Clone)]
+pub struct StubSymbol {
+    pub symbol: Symbol,
+    pub versym: u16,
+}
+
+/// The exported ABI of a shared library: what a stub `.so` has to reproduce so
+/// programs link against it as they would against the real one.
+///
+/// Functions become a single return instruction and data objects are zero-filled
+/// `.bss` of the original size; symbols that shared an address keep sharing one.
+/// DT_NEEDED is left out so the stub links without the library's own dependencies.
+#[derive(Debug, Clone)]
+pub struct StubLibrary {
+    pub soname: Option<String>,
+    pub e_machine: u16,
+    /// EI_OSABI of the input; STT_GNU_IFUNC and STB_GNU_UNIQUE only mean something
+    /// under ELFOSABI_GNU
+    pub osabi: u8,
+    pub symbols: Vec<StubSymbol>,
+    pub versions: Vec<VersionDefinition>,
+}
+
+impl StubLibrary {
+    pub fn read_stub(file_path:&str)->Result<StubLibrary,StubError>{
+        let data=fs::read(file_path).map_err(|e| StubError::Io(e.to_string()))?;
+        Self::parse_stub(&ElfFile::parse(data).ok_or(StubError::Parse)?)
+    }
+
+    /// Collect SONAME, the defined global/weak dynamic symbols and the version
+    /// definitions of `elf`
+    pub fn parse_stub(elf:&ElfFile)->Result<StubLibrary,StubError>{
+        let dyns=elf.dynamic();
+        if dyns.is_empty() {
+            return Err(StubError::NotDynamic);
+        }
+        let strtab=elf.dynamic_strings().unwrap_or(&[]);
+        let soname=Dyn::find(&dyns,abi::DT_SONAME).and_then(|offset| SectionHeader::parse_string_at(strtab,offset as usize));
+        let tables=VersionTables::parse_versions(elf);
+        let symbols:Vec<StubSymbol>=elf.dynamic_symbols().into_iter().enumerate()
+            .filter(|(_,symbol)| symbol.st_shndx!=abi::SHN_UNDEF && !symbol.string_name.is_empty())
+            .filter(|(_,symbol)| matches!(symbol.st_bind(),abi::STB_GLOBAL|abi::STB_WEAK|abi::STB_GNU_UNIQUE))
+            .map(|(i,symbol)| {
+                let versym=tables.as_ref().and_then(|tables| tables.versym.get(i).copied()).unwrap_or(abi::VER_NDX_GLOBAL);
+                StubSymbol{symbol,versym}
+            })
+            .collect();
+        if symbols.is_empty() {
+            return Err(StubError::NotDynamic);
+        }
+        Ok(StubLibrary{
+            soname,
+            e_machine:elf.header.e_machine,
+            osabi:elf.header.osabi,
+            symbols,
+            versions:tables.map(|tables| tables.verdef).unwrap_or_default(),
+        })
+    }
+
+    /// Build the stub for `e_machine` (EM_X86_64 or EM_AARCH64), or for the input's
+    /// machine when `None`
+    pub fn build(&self,e_machine:Option<u16>)->Result<Vec<u8>,StubError>{
+        let e_machine=e_machine.unwrap_or(self.e_machine);
+        //函数体只有一条返回指令
+        let (ret,text_align):(&[u8],u64)=match e_machine {
+            abi::EM_X86_64 => (&[0xc3],16),
+            abi::EM_AARCH64 => (&[0xc0,0x03,0x5f,0xd6],4),
+            _ => return Err(StubError::UnsupportedMachine(e_machine)),
+        };
+        let mut builder=ElfBuilder::new(Class::ELF64,AnyEndian::new(abi::ELFDATA2LSB),abi::ET_DYN,e_machine);
+        builder.header.osabi=self.osabi;
+        if let Some(soname)=&self.soname {
+            builder.set_soname(soname);
+        }
+        for definition in &self.versions {
+            builder.add_version_definition(definition.clone());
+        }
+
+        //按原地址分配桩地址,别名共用同一个位置
+        let mut text=Vec::new();
+        let mut bss=Stub::new();
+        let mut tbss=Stub::new();
+        let mut functions:HashMap<u64,u64>=HashMap::new();
+        let mut placed=Vec::new();
+        for stub in &self.symbols {
+            let symbol=&stub.symbol;
+            let (section,value)=match symbol.st_type() {
+                _ if symbol.st_shndx==abi::SHN_ABS => (None,symbol.st_value),
+                abi::STT_FUNC|abi::STT_GNU_IFUNC => {
+                    let offset=*functions.entry(symbol.st_value).or_insert_with(|| {
+                        let offset=text.len().next_multiple_of(text_align as usize);
+                        text.resize(offset,0);
+                        text.extend_from_slice(ret);
+                        offset as u64
+                    });
+                    (Some(Placement::Text),offset)
+                }
+                abi::STT_TLS => (Some(Placement::Tbss),tbss.place(symbol)),
+                _ => (Some(Placement::Bss),bss.place(symbol)),
+            };
+            placed.push((section,value));
+        }
+
+        let text_index=match text.is_empty() {
+            true => 0,
+            false => {
+                let mut header=SectionHeader::new(".text",abi::SHT_PROGBITS,abi::SHF_ALLOC|abi::SHF_EXECINSTR);
+                header.sh_addralign=text_align;
+                builder.add_section(header,text)
+            }
+        };
+        let bss_index=bss.add_section(&mut builder,".bss",abi::SHF_ALLOC|abi::SHF_WRITE);
+        let tbss_index=tbss.add_section(&mut builder,".tbss",abi::SHF_ALLOC|abi::SHF_WRITE|abi::SHF_TLS);
+        for (stub,(section,value)) in self.symbols.iter().zip(placed) {
+            let mut symbol=stub.symbol.clone();
+            symbol.st_name=0;
+            symbol.st_value=value;
+            symbol.st_shndx=match section {
+                None => abi::SHN_ABS,
+                Some(Placement::Text) => text_index,
+                Some(Placement::Bss) => bss_index,
+                Some(Placement::Tbss) => tbss_index,
+            };
+            let id=builder.add_dynamic_symbol(symbol);
+            builder.set_symbol_version(id,stub.versym).map_err(StubError::Build)?;
+        }
+        Ok(builder.build())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Placement {
+    Text,
+    Bss,
+    Tbss,
+}
+
+//.bss/.tbss里的数据对象:原地址到桩内偏移,以及节大小与对齐
+struct Stub {
+    offsets: HashMap<u64, u64>,
+    size: u64,
+    align: u64,
+}
+
+impl Stub {
+    fn new()->Stub{
+        Stub{offsets:HashMap::new(),size:0,align:1}
+    }
+
+    //对齐取原地址的低位零个数,最多16字节
+    fn place(&mut self,symbol:&Symbol)->u64{
+        if let Some(&offset)=self.offsets.get(&symbol.st_value) {
+            //别名大小不同时按最大的保留空间
+            self.size=self.size.max(offset+symbol.st_size);
+            return offset;
+        }
+        let align=1u64<<symbol.st_value.trailing_zeros().min(4);
+        let offset=self.size.next_multiple_of(align);
+        self.size=offset+symbol.st_size.max(1);
+        self.align=self.align.max(align);
+        self.offsets.insert(symbol.st_value,offset);
+        offset
+    }
+
+    fn add_section(&self,builder:&mut ElfBuilder,name:&str,flags:u64)->u16{
+        if self.offsets.is_empty() {
+            return 0;
+        }
+        let mut header=SectionHeader::new(name,abi::SHT_NOBITS,flags);
+        header.sh_size=self.size;
+        header.sh_addralign=self.align;
+        builder.add_section(header,Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //libsym.so的EI_OSABI改成3(ELFOSABI_GNU)
+    fn gnu_library()->ElfFile{
+        let mut data=include_bytes!("../test_elf/libsym.so").to_vec();
+        data[abi::EI_OSABI]=3;
+        ElfFile::parse(data).unwrap()
+    }
+
+    fn exports(elf:&ElfFile)->Vec<(String,u8,u64,Option<String>)>{
+        let mut symbols=elf.dynamic_symbols();
+        if let Some(tables)=VersionTables::parse_versions(elf) {
+            symbols=tables.fix_symbol_version(symbols);
+        }
+        let mut v:Vec<_>=symbols.into_iter().filter(|symbol| symbol.st_shndx!=abi::SHN_UNDEF)
+            .map(|symbol| (symbol.string_name.clone(),symbol.st_type(),symbol.st_size,symbol.version.map(|version| version.name)))
+            .collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn keeps_osabi_and_exports(){
+        let input=gnu_library();
+        let stub=StubLibrary::parse_stub(&input).unwrap();
+        assert_eq!((stub.osabi,stub.e_machine,stub.soname.as_deref(),stub.symbols.len()),(3,abi::EM_X86_64,None,5));
+
+        let output=ElfFile::parse(stub.build(None).unwrap()).unwrap();
+        assert_eq!((output.header.osabi,output.header.e_type,output.header.e_machine),(3,abi::ET_DYN,abi::EM_X86_64));
+        assert_eq!(exports(&output),exports(&input));
+        assert!(output.needed_libraries().is_empty());
+        //函数体是ret,数据对象在.bss
+        for symbol in output.dynamic_symbols().iter().filter(|symbol| symbol.st_shndx!=abi::SHN_UNDEF) {
+            let section=&output.section_headers[symbol.st_shndx as usize];
+            match symbol.st_type() {
+                abi::STT_FUNC => assert_eq!(output.vaddr_data(symbol.st_value,1),Some(&[0xc3][..])),
+                _ => assert_eq!((section.string_name.as_str(),section.sh_type),(".bss",abi::SHT_NOBITS)),
+            }
+        }
+    }
+
+    #[test]
+    fn soname_and_versions(){
+        let mut stub=StubLibrary::parse_stub(&gnu_library()).unwrap();
+        stub.soname=Some("libsym.so.1".to_string());
+        stub.versions=vec![
+            VersionDefinition{index:1,flags:abi::VER_FLG_BASE,hash:0,names:vec!["libsym.so.1".to_string()]},
+            VersionDefinition{index:2,flags:0,hash:0,names:vec!["SYM_1".to_string()]},
+        ];
+        for symbol in stub.symbols.iter_mut() {
+            symbol.versym=2;
+        }
+        let output=ElfFile::parse(stub.build(Some(abi::EM_AARCH64)).unwrap()).unwrap();
+        assert_eq!((output.header.osabi,output.header.e_machine),(3,abi::EM_AARCH64));
+        let strtab=output.dynamic_strings().unwrap();
+        let soname=Dyn::find(&output.dynamic(),abi::DT_SONAME).and_then(|offset| SectionHeader::parse_string_at(strtab,offset as usize));
+        assert_eq!(soname.as_deref(),Some("libsym.so.1"));
+        let exports=exports(&output);
+        assert_eq!(exports.len(),5);
+        assert!(exports.iter().all(|(_,_,_,version)| version.as_deref()==Some("SYM_1")));
+        let alpha=output.dynamic_symbols().into_iter().find(|symbol| symbol.string_name=="alpha").unwrap();
+        assert_eq!(output.vaddr_data(alpha.st_value,4),Some(&[0xc0,0x03,0x5f,0xd6][..]));
+    }
+
+    #[test]
+    fn rejected_inputs(){
+        let stub=StubLibrary::parse_stub(&gnu_library()).unwrap();
+        assert_eq!(stub.build(Some(abi::EM_RISCV)),Err(StubError::UnsupportedMachine(abi::EM_RISCV)));
+        //可执行文件的.dynsym只有导入
+        let de=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        assert!(matches!(StubLibrary::parse_stub(&de),Err(StubError::NotDynamic)));
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use crate::parser;
 use crate::parser::elf_header::FileHeader;
-use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
 use crate::parser::file::Class;
 use crate::parser::section::SectionHeader;
+use crate::parser::version::SymbolVersion;
 use crate::parser::{file, symbol};
 
 /// C-style 32-bit ELF Symbol definition
@@ -47,7 +48,9 @@ pub struct Symbol {
 
     pub st_size: u64,
     pub string_name:String,
-    pub index:u16,
+    pub index:u32,
+    /// Symbol version from `.gnu.version`, filled in by `VersionTables::fix_symbol_version`
+    pub version:Option<SymbolVersion>,
 }
 
 impl Symbol {
@@ -57,13 +60,16 @@ impl Symbol {
         let idents=(binary_header.endianness,binary_header.class);
         let symbol_index=parser::section::SectionHeader::
         find_section_header_by_type(section_headers.clone(), 11);
+        if symbol_index==-1{
+            return None;
+        }
         let symbol_section_header=&section_headers[symbol_index as usize];
         //解析symbol
         let offset=symbol_section_header.sh_offset;
         let size=symbol_section_header.sh_size;
         let symbol_bytes=file::file_utils::read_file_range
-            (file_path,offset,offset+size);
-        let symbol_bytes_u8=symbol_bytes.unwrap();
+            (file_path,offset,offset.checked_add(size)?);
+        let symbol_bytes_u8=symbol_bytes.ok()?;
         //检查读写大小是否能够被长度整除
         if symbol_bytes_u8.len()%Symbol::size_for(binary_header.class)!=0{
             return None;
@@ -84,8 +90,8 @@ impl Symbol {
         let symbol_str_section_header=&section_header[symbol_str_header_idx as usize];
         let e_shstr_offset=symbol_str_section_header.sh_offset;
         let e_shstr_size=symbol_str_section_header.sh_size;
-        let symbol_str_byte=file::file_utils::read_file_range(file_path,e_shstr_offset,e_shstr_offset+e_shstr_size);
-        let symbol_map_string=parser::section::SectionHeader::parser_string_table(symbol_str_byte.unwrap());
+        let symbol_str_byte=file::file_utils::read_file_range(file_path,e_shstr_offset,e_shstr_offset.checked_add(e_shstr_size)?).ok()?;
+        let symbol_map_string=parser::section::SectionHeader::parser_string_table(symbol_str_byte);
         println!("[*]符号字符串表解析成功:");
         println!("{:?}",symbol_map_string);
         return Some(symbol_map_string);
@@ -95,8 +101,8 @@ impl Symbol {
     pub fn parser_Symbol(ident: (AnyEndian, Class),data:&[u8],mut offset: usize)->Vec<Symbol>{
         let (endian, class)=ident;
         let mut symbol_tables:Vec<Symbol>=Vec::new();
-        let mut count:u16=0;
-        while offset<data.len() {
+        let mut count:u32=0;
+        while offset+Self::size_for(class)<=data.len() {
             let symbol_table=Self::parse_at(ident,data,offset,count);
             symbol_tables.push(symbol_table);
             offset+=Self::size_for(class);
@@ -108,14 +114,23 @@ impl Symbol {
 
         for mut symbol_table in symbol_tables.iter_mut() {
                 let st_name=symbol_table.st_name;
-            if let Some(string) = string_table_map.get(&st_name) {
-                symbol_table.string_name = string.to_string();
+            if let Some(string) = SectionHeader::lookup_string(&string_table_map,st_name) {
+                symbol_table.string_name = string;
             }
         }
         return  symbol_tables;
 
     }
-    pub fn parse_at(ident: (AnyEndian, Class),data:&[u8],mut offset: usize,count:u16)->Symbol{
+    /// Like `fix_symbol_name`, but resolves names from the raw string table
+    pub fn fix_symbol_name_from_bytes(string_table_bytes:&[u8],mut symbol_tables:Vec<Symbol>)->Vec<Symbol>{
+        for symbol_table in symbol_tables.iter_mut() {
+            if let Some(string) = SectionHeader::parse_string_at(string_table_bytes,symbol_table.st_name as usize) {
+                symbol_table.string_name = string;
+            }
+        }
+        symbol_tables
+    }
+    pub fn parse_at(ident: (AnyEndian, Class),data:&[u8],mut offset: usize,count:u32)->Symbol{
         let st_name: u32;
         let st_value: u64;
         let st_size: u64;
@@ -162,7 +177,90 @@ impl Symbol {
             st_other,
             string_name:"".to_string(),
             index:count,
+            version:None,
+        };
+    }
+    /// Write this symbol at `offset` using the layout `parse_at` reads
+    pub fn write_at(&self,ident: (AnyEndian, Class),data:&mut [u8],offset: usize){
+        let (endian, class)=ident;
+        endian.write_u32_at(offset, data, self.st_name);
+        if class == Class::ELF32 {
+            endian.write_u32_at(offset+4, data, self.st_value as u32);
+            endian.write_u32_at(offset+8, data, self.st_size as u32);
+            endian.write_u8_at(offset+12, data, self.st_info);
+            endian.write_u8_at(offset+13, data, self.st_other);
+            endian.write_u16_at(offset+14, data, self.st_shndx);
+        } else {
+            endian.write_u8_at(offset+4, data, self.st_info);
+            endian.write_u8_at(offset+5, data, self.st_other);
+            endian.write_u16_at(offset+6, data, self.st_shndx);
+            endian.write_u64_at(offset+8, data, self.st_value);
+            endian.write_u64_at(offset+16, data, self.st_size);
+        }
+    }
+
+    /// Serialize a whole symbol table, the inverse of `parser_Symbol`
+    pub fn to_bytes(ident: (AnyEndian, Class),symbol_tables:&[Symbol])->Vec<u8>{
+        let size=Self::size_for(ident.1);
+        let mut data=vec![0u8;symbol_tables.len()*size];
+        for (i,symbol_table) in symbol_tables.iter().enumerate() {
+            symbol_table.write_at(ident,&mut data,i*size);
+        }
+        data
+    }
+
+    /// Name in the `name@VERSION` / `name@@VERSION` form used by binutils.
+    /// `@@` marks the default version of a defined symbol.
+    pub fn versioned_name(&self) -> String {
+        match &self.version {
+            Some(version) if self.st_shndx != 0 && !version.hidden => format!("{}@@{}", self.string_name, version.name),
+            Some(version) => format!("{}@{}", self.string_name, version.name),
+            None => self.string_name.clone(),
+        }
+    }
+    /// Whether the symbol answers to `version`; None asks for the default binding,
+    /// which an unversioned or non-hidden symbol provides
+    pub fn matches_version(&self, version: Option<&str>) -> bool {
+        match (version, &self.version) {
+            (Some(wanted), Some(have)) => have.name == wanted,
+            (Some(_), None) => false,
+            (None, Some(have)) => !have.hidden,
+            (None, None) => true,
+        }
+    }
+    /// Find a symbol by `name`, `name@VERSION` or `name@@VERSION`
+    pub fn find_versioned<'a>(symbol_tables: &'a [Symbol], spec: &str) -> Option<&'a Symbol> {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name, Some(version.trim_start_matches('@'))),
+            None => (spec, None),
         };
+        symbol_tables.iter().find(|sym| sym.string_name == name && sym.matches_version(version))
+    }
+    /// A symbol with default visibility, for building symbol tables
+    pub fn new(name: &str, bind: u8, sym_type: u8, st_shndx: u16, st_value: u64, st_size: u64) -> Symbol {
+        Symbol {
+            st_name: 0,
+            st_shndx,
+            st_info: (bind << 4) | (sym_type & 0xf),
+            st_other: 0,
+            st_value,
+            st_size,
+            string_name: name.to_string(),
+            index: 0,
+            version: None,
+        }
+    }
+    /// Symbol binding (STB_*), the high nibble of st_info
+    pub fn st_bind(&self) -> u8 {
+        self.st_info >> 4
+    }
+    /// Change the binding, keeping the symbol type
+    pub fn set_bind(&mut self, bind: u8) {
+        self.st_info = (bind << 4) | (self.st_info & 0xf);
+    }
+    /// Symbol type (STT_*), the low nibble of st_info
+    pub fn st_type(&self) -> u8 {
+        self.st_info & 0xf
     }
     pub fn size_for(class: Class) -> usize {
         match class {
@@ -0,0 +1,294 @@
+use crate::parser::abi;
+use crate::parser::edit::EditError;
+use crate::parser::endian::{AnyEndian, EndianParse, EndianWrite};
+use crate::parser::file::Class;
+use crate::parser::hash::hash;
+use crate::parser::section_edit::SectionEditor;
+use crate::parser::symbol::Symbol;
+
+/// objcopy-style symbol edits over `.symtab` and `.dynsym`.
+///
+/// `.symtab` gets a fresh string table. `.dynsym` keeps `.dynstr` as it is, because
+/// DT_NEEDED and the version tables point into it; new names are appended, and a
+/// `.dynstr` that grows moves into a new PT_LOAD. Renaming or localizing a dynamic
+/// symbol changes its hash bucket, so `.dynsym` is re-sorted for `.gnu.hash`, and
+/// `.hash`, `.gnu.hash`, `.gnu.version` and the dynamic relocations follow the new order.
+impl SectionEditor {
+    /// `--redefine-sym OLD=NEW`; returns how many symbols changed
+    pub fn redefine_symbol(&mut self,old:&str,new:&str)->Result<usize,EditError>{
+        self.edit_symbols(|symbol| {
+            if symbol.string_name==old {
+                symbol.string_name=new.to_string();
+            }
+        })
+    }
+
+    /// `--localize-symbol`: a defined global or weak symbol becomes local
+    pub fn localize_symbol(&mut self,name:&str)->Result<usize,EditError>{
+        self.edit_symbols(|symbol| {
+            if symbol.string_name==name && symbol.st_shndx!=abi::SHN_UNDEF {
+                symbol.set_bind(abi::STB_LOCAL);
+            }
+        })
+    }
+
+    /// `--weaken-symbol`: a global symbol, defined or not, becomes weak
+    pub fn weaken_symbol(&mut self,name:&str)->Result<usize,EditError>{
+        self.edit_symbols(|symbol| {
+            if symbol.string_name==name && symbol.st_bind()==abi::STB_GLOBAL {
+                symbol.set_bind(abi::STB_WEAK);
+            }
+        })
+    }
+
+    /// `--prefix-symbols`: prefix every named symbol except section symbols
+    pub fn prefix_symbols(&mut self,prefix:&str)->Result<usize,EditError>{
+        self.edit_symbols(|symbol| {
+            if !symbol.string_name.is_empty() && symbol.st_type()!=abi::STT_SECTION {
+                symbol.string_name=format!("{}{}",prefix,symbol.string_name);
+            }
+        })
+    }
+
+    fn edit_symbols<F>(&mut self,edit:F)->Result<usize,EditError>
+        where F: Fn(&mut Symbol){
+        let mut changed=0;
+        for sh in self.section_headers() {
+            changed+=match sh.sh_type {
+                abi::SHT_SYMTAB => self.edit_static_symbols(sh.index as usize,&edit),
+                abi::SHT_DYNSYM => self.edit_dynamic_symbols(sh.index as usize,&edit)?,
+                _ => 0,
+            };
+        }
+        Ok(changed)
+    }
+
+    //返回名字或绑定被改动的符号下标
+    fn apply<F>(symbols:&mut [Symbol],edit:&F)->Vec<usize>
+        where F: Fn(&mut Symbol){
+        let mut changed=Vec::new();
+        for (i,symbol) in symbols.iter_mut().enumerate().skip(1) {
+            let before=(symbol.string_name.clone(),symbol.st_info);
+            edit(symbol);
+            if before!=(symbol.string_name.clone(),symbol.st_info) {
+                changed.push(i);
+            }
+        }
+        changed
+    }
+
+    fn edit_static_symbols<F>(&mut self,symtab:usize,edit:&F)->usize
+        where F: Fn(&mut Symbol){
+        let mut symbols=self.named_symbols(symtab);
+        let changed=Self::apply(&mut symbols,edit).len();
+        if changed>0 {
+            self.set_symbols(symtab,symbols.into_iter().enumerate().collect());
+        }
+        changed
+    }
+
+    fn edit_dynamic_symbols<F>(&mut self,dynsym:usize,edit:&F)->Result<usize,EditError>
+        where F: Fn(&mut Symbol){
+        let ident=self.elf.ident();
+        let endian=ident.0;
+        let headers=self.section_headers();
+        let original=self.named_symbols(dynsym);
+        let mut symbols=original.clone();
+        let changed=Self::apply(&mut symbols,edit);
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        let dynstr=headers[dynsym].sh_link as usize;
+        if dynstr==0 || dynstr>=headers.len() {
+            return Err(EditError::NoSection(".dynstr".to_string()));
+        }
+
+        //新名字追加到.dynstr末尾,已有的(含后缀共用)直接复用
+        let mut strtab=self.contents(dynstr);
+        let strtab_len=strtab.len();
+        for &i in &changed {
+            if symbols[i].string_name==original[i].string_name {
+                continue;
+            }
+            let mut name=symbols[i].string_name.clone().into_bytes();
+            name.push(0);
+            symbols[i].st_name=match strtab.windows(name.len()).position(|w| w==name.as_slice()) {
+                Some(offset) => offset as u32,
+                None => {
+                    strtab.extend_from_slice(&name);
+                    (strtab.len()-name.len()) as u32
+                }
+            };
+        }
+
+        //.gnu.hash要求参与散列的符号(已定义的全局符号)按桶排在最后
+        let linked=|sh_type:u32| headers.iter().find(|sh| sh.sh_type==sh_type && sh.sh_link as usize==dynsym).map(|sh| sh.index as usize);
+        let gnu_hash=linked(abi::SHT_GNU_HASH).map(|index| (index,hash::parser_hash_tables(ident,&self.contents(index))));
+        let hashed=|symbol:&Symbol| symbol.st_shndx!=abi::SHN_UNDEF && symbol.st_bind()!=abi::STB_LOCAL;
+        let mut order:Vec<usize>=(0..symbols.len()).collect();
+        order.sort_by_key(|&i| {
+            let symbol=&symbols[i];
+            let class=match (i,symbol.st_bind()==abi::STB_LOCAL,hashed(symbol)) {
+                (0,_,_) => 0,
+                (_,true,_) => 1,
+                (_,_,false) => 2,
+                _ => 3,
+            };
+            let bucket=match &gnu_hash {
+                Some((_,table)) if class==3 && table.nbucket>0 => hash::gnu_hash(symbols[i].string_name.as_bytes())%table.nbucket,
+                _ => 0,
+            };
+            (class,bucket)
+        });
+        let mut map=vec![0u32;symbols.len()];
+        for (new,&old) in order.iter().enumerate() {
+            map[old]=new as u32;
+        }
+        let sorted:Vec<Symbol>=order.iter().map(|&i| symbols[i].clone()).collect();
+
+        let mut bytes=self.contents(dynsym);
+        let table=Symbol::to_bytes(ident,&sorted);
+        bytes[..table.len()].copy_from_slice(&table);
+        self.set_contents(dynsym,bytes);
+        self.header_mut(dynsym).sh_info=sorted.iter().skip(1).position(|s| s.st_bind()!=abi::STB_LOCAL).map_or(sorted.len(),|i| i+1) as u32;
+
+        //.gnu.version与.dynsym一一对应,被局部化的符号不再有版本
+        if let Some(versym)=linked(abi::SHT_GNU_VERSYM) {
+            let mut bytes=self.contents(versym);
+            let old=bytes.clone();
+            for (i,&new) in map.iter().enumerate() {
+                let (from,to)=(i*2,new as usize*2);
+                if from+2>old.len() || to+2>bytes.len() {
+                    continue;
+                }
+                let version=match symbols[i].st_bind()==abi::STB_LOCAL && original[i].st_bind()!=abi::STB_LOCAL {
+                    true => abi::VER_NDX_LOCAL,
+                    false => endian.parse_u16_at(from,&old),
+                };
+                endian.write_u16_at(to,&mut bytes,version);
+            }
+            self.set_contents(versym,bytes);
+        }
+
+        if let Some(sysv)=linked(abi::SHT_HASH) {
+            let mut bytes=self.contents(sysv);
+            if bytes.len()>=8 {
+                let nbucket=endian.parse_u32_at(0,&bytes) as usize;
+                let mut buckets=vec![0u32;nbucket];
+                let mut chains=vec![0u32;sorted.len()];
+                for (i,symbol) in sorted.iter().enumerate().skip(1) {
+                    if nbucket==0 {
+                        break;
+                    }
+                    let bucket=hash::elf_hash(symbol.string_name.as_bytes()) as usize%nbucket;
+                    chains[i]=buckets[bucket];
+                    buckets[bucket]=i as u32;
+                }
+                let words:Vec<u32>=[nbucket as u32,sorted.len() as u32].into_iter().chain(buckets).chain(chains).collect();
+                bytes.resize(bytes.len().max(words.len()*4),0);
+                for (i,word) in words.iter().enumerate() {
+                    endian.write_u32_at(i*4,&mut bytes,*word);
+                }
+                self.set_contents(sysv,bytes);
+            }
+        }
+
+        if let Some((index,table))=gnu_hash {
+            let symoffset=sorted.iter().position(hashed).unwrap_or(sorted.len());
+            self.set_contents(index,Self::gnu_hash_table(ident.1,endian,&table,&sorted,symoffset));
+        }
+
+        self.remap_symbol_references(dynsym,&map);
+        if strtab.len()!=strtab_len {
+            self.replace_section_at(dynstr,strtab);
+        }
+        Ok(changed.len())
+    }
+
+    //沿用原表的桶数、布隆过滤器大小与移位,只重新计算内容
+    fn gnu_hash_table(class:Class,endian:AnyEndian,table:&hash,symbols:&[Symbol],symoffset:usize)->Vec<u8>{
+        let word=class.word_size();
+        let bits=word as u32*8;
+        let nbucket=table.nbucket.max(1);
+        let bloom_size=table.bloom_size.max(1);
+        let mut bloom=vec![0u64;bloom_size as usize];
+        let mut buckets=vec![0u32;nbucket as usize];
+        let mut chains=vec![0u32;symbols.len()-symoffset];
+        for (i,symbol) in symbols.iter().enumerate().skip(symoffset) {
+            let h=hash::gnu_hash(symbol.string_name.as_bytes());
+            bloom[((h/bits)%bloom_size) as usize]|=(1u64<<(h%bits))|(1u64<<((h>>table.bloom_shift)%bits));
+            let bucket=(h%nbucket) as usize;
+            if buckets[bucket]==0 {
+                buckets[bucket]=i as u32;
+            }
+            //桶内最后一个符号的散列值最低位置1
+            let last=symbols.get(i+1).is_none_or(|next| hash::gnu_hash(next.string_name.as_bytes())%nbucket!=h%nbucket);
+            chains[i-symoffset]=if last { h|1 } else { h&!1 };
+        }
+        let mut bytes=vec![0u8;16+bloom.len()*word+(buckets.len()+chains.len())*4];
+        endian.write_u32_at(0,&mut bytes,nbucket);
+        endian.write_u32_at(4,&mut bytes,symoffset as u32);
+        endian.write_u32_at(8,&mut bytes,bloom_size);
+        endian.write_u32_at(12,&mut bytes,table.bloom_shift);
+        let mut offset=16;
+        for mask in bloom {
+            match class {
+                Class::ELF32 => endian.write_u32_at(offset,&mut bytes,mask as u32),
+                Class::ELF64 => endian.write_u64_at(offset,&mut bytes,mask),
+            }
+            offset+=word;
+        }
+        for value in buckets.into_iter().chain(chains) {
+            endian.write_u32_at(offset,&mut bytes,value);
+            offset+=4;
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::elf_file::ElfFile;
+
+    //gcc -shared -nostdlib -Wl,--hash-style=both: alpha/beta/gamma_/delta/counter
+    const LIBSYM: &[u8] = include_bytes!("../test_elf/libsym.so");
+
+    fn gnu_lookup(elf:&ElfFile,name:&str)->Option<Symbol>{
+        let bytes=elf.section_data(elf.find_section(".gnu.hash")?)?;
+        let table=hash::parser_hash_tables(elf.ident(),bytes);
+        table.find(elf.dynamic_symbols(),name.as_bytes(),elf.header.class).map(|(_,symbol)| symbol)
+    }
+
+    fn sysv_lookup(elf:&ElfFile,name:&str)->Option<Symbol>{
+        let bytes=elf.section_data(elf.find_section(".hash")?)?;
+        let endian=elf.header.endianness;
+        let word=|i:usize| endian.parse_u32_at(i*4,bytes) as usize;
+        let (nbucket,nchain)=(word(0),word(1));
+        let symbols=elf.dynamic_symbols();
+        let mut index=word(2+hash::elf_hash(name.as_bytes()) as usize%nbucket);
+        while index!=0 && index<nchain {
+            if symbols[index].string_name==name {
+                return Some(symbols[index].clone());
+            }
+            index=word(2+nbucket+index);
+        }
+        None
+    }
+
+    #[test]
+    fn renamed_symbol_is_found_through_hash_tables(){
+        let mut editor=SectionEditor::new(ElfFile::parse(LIBSYM.to_vec()).unwrap()).unwrap();
+        //.symtab与.dynsym各一个
+        assert_eq!(editor.redefine_symbol("alpha","alpha_renamed_for_test").unwrap(),2);
+        let out=ElfFile::parse(editor.to_bytes().unwrap()).unwrap();
+        for lookup in [gnu_lookup,sysv_lookup] {
+            let renamed=lookup(&out,"alpha_renamed_for_test").unwrap();
+            assert_eq!((renamed.st_value,renamed.st_size),(0x1000,6));
+            assert!(lookup(&out,"alpha").is_none());
+            for (name,value) in [("beta",0x1006),("gamma_",0x100c),("delta",0x1012),("counter",0x4000)] {
+                assert_eq!(lookup(&out,name).map(|symbol| symbol.st_value),Some(value),"{}",name);
+            }
+        }
+    }
+}
@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+use crate::parser::abi;
+use crate::parser::coredump::{CoreDump, CoreThread};
+use crate::parser::eh_frame::{CfaRule, EhFrame, EhFrameHdr, RegisterRule, UnwindRow};
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::EndianParse;
+use crate::parser::symbol::Symbol;
+
+//防止损坏的栈导致无限展开
+const MAX_FRAMES: usize = 256;
+//DW_OP_skip/bra可以向后跳,限制执行的操作数以免死循环
+const MAX_EXPRESSION_STEPS: usize = 10_000;
+
+/// How a frame's registers were recovered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameSource {
+    /// The thread's registers in the core
+    Registers,
+    /// CFI of the callee
+    Cfi,
+    /// The callee's saved frame pointer
+    FramePointer,
+}
+
+/// A stack frame, innermost first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pc: u64,
+    /// Stack pointer in this frame
+    pub sp: u64,
+    pub source: FrameSource,
+    /// Path of the mapped file containing `pc`
+    pub module: Option<String>,
+    /// Function containing `pc` and the offset into it
+    pub symbol: Option<(String, u64)>,
+}
+
+//展开一帧的结果:调用者的pc与寄存器;call_site表示pc是返回地址
+struct Step {
+    pc: u64,
+    registers: HashMap<u16, u64>,
+    call_site: bool,
+}
+
+/// A file mapped into the crashed process, loaded from disk
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub path: String,
+    pub start: u64,
+    pub end: u64,
+    /// Load bias: runtime address minus link-time address
+    pub bias: u64,
+    pub elf: ElfFile,
+    pub eh_frame: Option<EhFrame>,
+    pub eh_frame_hdr: Option<EhFrameHdr>,
+    /// Function symbols sorted by address
+    symbols: Vec<Symbol>,
+}
+
+/// DWARF register numbering for a machine: the names in `pr_reg` of DWARF
+/// registers 0.., the stack pointer, the frame pointer and the program counter
+struct Registers {
+    names: Vec<String>,
+    sp: u16,
+    fp: u16,
+    /// Register holding the return address at function entry; None when it is on
+    /// the stack
+    link: Option<u16>,
+    pc: &'static str,
+}
+
+impl Registers {
+    fn for_machine(machine:u16)->Option<Registers>{
+        match machine {
+            abi::EM_X86_64 => Some(Registers{
+                names:["rax","rdx","rcx","rbx","rsi","rdi","rbp","rsp","r8","r9","r10","r11","r12","r13","r14","r15"]
+                    .iter().map(|name| name.to_string()).collect(),
+                sp:7,
+                fp:6,
+                link:None,
+                pc:"rip",
+            }),
+            abi::EM_AARCH64 => Some(Registers{
+                names:(0..31).map(|i| format!("x{}",i)).chain(["sp".to_string()]).collect(),
+                sp:31,
+                fp:29,
+                link:Some(30),
+                pc:"pc",
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Stack unwinder for the threads of a core dump.
+///
+/// The executable and the libraries listed in NT_FILE are loaded from disk (under an
+/// optional sysroot) and placed at the addresses the core maps them at; the vDSO is
+/// read from the core itself. Each frame is unwound with the `.eh_frame` CFI of the
+/// module containing its pc, found through `.eh_frame_hdr` when there is one, and
+/// with the frame pointer chain when there is no CFI.
+#[derive(Debug, Clone)]
+pub struct Unwinder<'a> {
+    core: &'a ElfFile,
+    pub modules: Vec<Module>,
+}
+
+impl Module {
+    fn load(path:&str,elf:ElfFile,start:u64,end:u64,bias:u64)->Module{
+        let eh_frame=EhFrame::parse_eh_frame(&elf).ok();
+        let eh_frame_hdr=EhFrameHdr::parse_eh_frame_hdr(&elf).ok();
+        //优先用.symtab,被strip时退回.dynsym
+        let mut symbols=match elf.find_section_by_type(abi::SHT_SYMTAB) {
+            Some(symtab) => elf.symbols(symtab),
+            None => elf.dynamic_symbols(),
+        };
+        symbols.retain(|symbol| {
+            symbol.st_shndx!=abi::SHN_UNDEF && symbol.st_value!=0 && !symbol.string_name.is_empty()
+                && matches!(symbol.st_type(),abi::STT_FUNC|abi::STT_GNU_IFUNC|abi::STT_NOTYPE)
+        });
+        symbols.sort_by_key(|symbol| symbol.st_value);
+        Module{path:path.to_string(),start,end,bias,elf,eh_frame,eh_frame_hdr,symbols}
+    }
+
+    /// The function containing a link-time address. Symbols without a size cover up
+    /// to the next symbol.
+    pub fn symbolize(&self,address:u64)->Option<(String,u64)>{
+        let index=self.symbols.partition_point(|symbol| symbol.st_value<=address).checked_sub(1)?;
+        //同一地址有多个符号时取第一个有大小的
+        let value=self.symbols[index].st_value;
+        let first=self.symbols.partition_point(|symbol| symbol.st_value<value);
+        let symbol=self.symbols[first..=index].iter().find(|symbol| symbol.st_size>0).unwrap_or(&self.symbols[index]);
+        if symbol.st_size>0 && address-symbol.st_value>=symbol.st_size {
+            return None;
+        }
+        Some((symbol.string_name.clone(),address-symbol.st_value))
+    }
+
+    /// The unwind row for a link-time address
+    pub fn unwind_row(&self,address:u64)->Option<(UnwindRow,u16)>{
+        let eh_frame=self.eh_frame.as_ref()?;
+        let fde=match &self.eh_frame_hdr {
+            Some(hdr) if !hdr.table.is_empty() => hdr.lookup(address)
+                .and_then(|fde| fde.checked_sub(eh_frame.address))
+                .and_then(|offset| eh_frame.fde_at(offset)),
+            _ => eh_frame.find_fde(address),
+        }?;
+        if address<fde.pc_begin || address-fde.pc_begin>=fde.pc_range {
+            return None;
+        }
+        let cie=eh_frame.cie(fde)?;
+        let row=eh_frame.row_at(fde,address).ok()??;
+        Some((row,cie.return_register))
+    }
+}
+
+impl<'a> Unwinder<'a> {
+    /// Load the modules of a core. Files are looked up at their NT_FILE path below
+    /// `sysroot`; files that cannot be read are left out.
+    pub fn new(core:&'a ElfFile,dump:&CoreDump,sysroot:Option<&str>)->Unwinder<'a>{
+        let mut modules=Vec::new();
+        let mut paths:Vec<&str>=Vec::new();
+        for file in &dump.files {
+            if !paths.contains(&file.path.as_str()) {
+                paths.push(&file.path);
+            }
+        }
+        for path in paths {
+            let mappings:Vec<_>=dump.files.iter().filter(|file| file.path==path).collect();
+            let full_path=match sysroot {
+                Some(root) => format!("{}/{}",root.trim_end_matches('/'),path.trim_start_matches('/')),
+                None => path.to_string(),
+            };
+            let Some(elf)=ElfFile::read_elf(&full_path) else {
+                continue;
+            };
+            //用文件偏移最小的映射和覆盖它的PT_LOAD算出装载偏移
+            let Some(first)=mappings.iter().min_by_key(|file| file.offset) else {
+                continue;
+            };
+            let Some(ph)=elf.program_headers.iter()
+                .filter(|ph| ph.p_type==abi::PT_LOAD)
+                .find(|ph| first.offset>=ph.p_offset & !(dump.page_size.max(1)-1) && first.offset<ph.p_offset+ph.p_filesz.max(1)) else {
+                continue;
+            };
+            let link_address=ph.p_vaddr.wrapping_sub(ph.p_offset).wrapping_add(first.offset);
+            let bias=first.start.wrapping_sub(link_address);
+            let start=mappings.iter().map(|file| file.start).min().unwrap_or(0);
+            let end=mappings.iter().map(|file| file.end).max().unwrap_or(0);
+            modules.push(Module::load(path,elf,start,end,bias));
+        }
+        if let Some(vdso)=Self::load_vdso(core,dump) {
+            modules.push(vdso);
+        }
+        Unwinder{core,modules}
+    }
+
+    //vDSO不在NT_FILE里,镜像在core的内存中
+    fn load_vdso(core:&ElfFile,dump:&CoreDump)->Option<Module>{
+        let address=dump.auxv_value(abi::AT_SYSINFO_EHDR)?;
+        let ph=core.program_headers.iter()
+            .filter(|ph| ph.p_type==abi::PT_LOAD)
+            .find(|ph| address>=ph.p_vaddr && address-ph.p_vaddr<ph.p_filesz)?;
+        let size=ph.p_vaddr+ph.p_filesz-address;
+        let elf=ElfFile::parse(core.read_memory(address,size).ok()?)?;
+        let first=elf.program_headers.iter().find(|ph| ph.p_type==abi::PT_LOAD)?;
+        let bias=address.wrapping_sub(first.p_vaddr&!0xfff);
+        Some(Module::load("[vdso]",elf,address,address+size,bias))
+    }
+
+    pub fn module_at(&self,address:u64)->Option<&Module>{
+        self.modules.iter().find(|module| address>=module.start && address<module.end)
+    }
+
+    /// The function containing a runtime address
+    pub fn symbolize(&self,address:u64)->Option<(String,u64)>{
+        let module=self.module_at(address)?;
+        module.symbolize(address.wrapping_sub(module.bias))
+    }
+
+    /// Walk a thread's stack, starting from the registers in its NT_PRSTATUS
+    pub fn backtrace(&self,thread:&CoreThread)->Vec<Frame>{
+        let Some(numbering)=Registers::for_machine(self.core.header.e_machine) else {
+            return Vec::new();
+        };
+        let mut registers:HashMap<u16,u64>=HashMap::new();
+        for (number,name) in numbering.names.iter().enumerate() {
+            if let Some(value)=thread.status.register(name) {
+                registers.insert(number as u16,value);
+            }
+        }
+        let Some(mut pc)=thread.status.register(numbering.pc) else {
+            return Vec::new();
+        };
+        let mut frames=Vec::new();
+        let mut source=FrameSource::Registers;
+        //调用者帧里pc是返回地址,用pc-1查找以落在call指令所在的范围
+        let mut call_site=false;
+        while frames.len()<MAX_FRAMES && pc!=0 {
+            let lookup=if call_site { pc-1 } else { pc };
+            let sp=registers.get(&numbering.sp).copied().unwrap_or(0);
+            let symbol=self.symbolize(lookup).map(|(name,offset)| (name,offset+pc-lookup));
+            frames.push(Frame{pc,sp,source,module:self.module_at(lookup).map(|module| module.path.clone()),symbol});
+            let next=match self.step_cfi(&numbering,lookup,&registers) {
+                Some(step) => {
+                    source=FrameSource::Cfi;
+                    Some(step)
+                }
+                None => {
+                    source=FrameSource::FramePointer;
+                    let at_entry=frames.len()==1 && frames[0].symbol.as_ref().is_some_and(|(_,offset)| *offset==0);
+                    self.step_frame_pointer(&numbering,&registers,at_entry)
+                }
+            };
+            let Some(step)=next else {
+                break;
+            };
+            //栈向低地址增长,调用者的sp不会更小
+            let next_sp=step.registers.get(&numbering.sp).copied().unwrap_or(0);
+            if next_sp<sp || (next_sp==sp && step.pc==pc) {
+                break;
+            }
+            pc=step.pc;
+            registers=step.registers;
+            call_site=step.call_site;
+        }
+        frames
+    }
+
+    //按CFI恢复调用者的寄存器
+    fn step_cfi(&self,numbering:&Registers,address:u64,registers:&HashMap<u16,u64>)->Option<Step>{
+        let module=self.module_at(address)?;
+        let (row,return_register)=module.unwind_row(address.wrapping_sub(module.bias))?;
+        let signal_frame=Self::is_signal_frame(module,address.wrapping_sub(module.bias));
+        let cfa=match &row.cfa {
+            CfaRule::RegisterOffset(register,offset) => registers.get(register)?.wrapping_add_signed(*offset),
+            CfaRule::Expression(expression) => self.evaluate(expression,registers,None)?,
+        };
+        let mut caller=registers.clone();
+        for (register,rule) in &row.registers {
+            let value=match rule {
+                RegisterRule::Undefined => {
+                    caller.remove(register);
+                    continue;
+                }
+                RegisterRule::SameValue => continue,
+                RegisterRule::Offset(offset) => self.read_word(cfa.wrapping_add_signed(*offset))?,
+                RegisterRule::ValOffset(offset) => cfa.wrapping_add_signed(*offset),
+                RegisterRule::Register(other) => *registers.get(other)?,
+                RegisterRule::Expression(expression) => self.read_word(self.evaluate(expression,registers,Some(cfa))?)?,
+                RegisterRule::ValExpression(expression) => self.evaluate(expression,registers,Some(cfa))?,
+            };
+            caller.insert(*register,value);
+        }
+        caller.insert(numbering.sp,cfa);
+        //返回地址列未定义时已到最外层
+        let return_address=*caller.get(&return_register)?;
+        Some(Step{pc:return_address,registers:caller,call_site:!signal_frame})
+    }
+
+    //CIE带'S'的是信号跳板,它恢复出的pc是被中断的指令而不是返回地址
+    fn is_signal_frame(module:&Module,address:u64)->bool{
+        let Some(eh_frame)=&module.eh_frame else {
+            return false;
+        };
+        eh_frame.find_fde(address).and_then(|fde| eh_frame.cie(fde)).is_some_and(|cie| cie.signal_frame)
+    }
+
+    //x86-64:[fp]是上一帧的fp,[fp+8]是返回地址;AArch64的x29/x30帧记录布局相同
+    fn step_frame_pointer(&self,numbering:&Registers,registers:&HashMap<u16,u64>,at_entry:bool)->Option<Step>{
+        let word=self.core.header.class.word_size() as u64;
+        //停在函数第一条指令时还没有建立帧,返回地址在链接寄存器或栈顶
+        if at_entry {
+            let mut caller=registers.clone();
+            return match numbering.link {
+                Some(link) => Some(Step{pc:*registers.get(&link)?,registers:caller,call_site:true}),
+                None => {
+                    let sp=*registers.get(&numbering.sp)?;
+                    caller.insert(numbering.sp,sp.checked_add(word)?);
+                    Some(Step{pc:self.read_word(sp)?,registers:caller,call_site:true})
+                }
+            };
+        }
+        let fp=*registers.get(&numbering.fp)?;
+        if fp==0 {
+            return None;
+        }
+        let return_address=fp.checked_add(word)?;
+        let mut caller=registers.clone();
+        caller.insert(numbering.fp,self.read_word(fp)?);
+        caller.insert(numbering.sp,return_address.checked_add(word)?);
+        Some(Step{pc:self.read_word(return_address)?,registers:caller,call_site:true})
+    }
+
+    /// A pointer-sized value from the core, or from the module file for memory the
+    /// core does not contain
+    pub fn read_word(&self,address:u64)->Option<u64>{
+        let word=self.core.header.class.word_size() as u64;
+        let data=match self.core.read_memory(address,word) {
+            Ok(data) => data,
+            Err(_) => {
+                let module=self.module_at(address)?;
+                module.elf.vaddr_data(address.wrapping_sub(module.bias),word)?.to_vec()
+            }
+        };
+        let endian=self.core.header.endianness;
+        Some(match word {
+            4 => endian.parse_u32_at(0,&data) as u64,
+            _ => endian.parse_u64_at(0,&data),
+        })
+    }
+
+    //CFI里常见的DWARF表达式子集;有CFA时先压栈(DW_CFA_expression的约定)
+    fn evaluate(&self,expression:&[u8],registers:&HashMap<u16,u64>,cfa:Option<u64>)->Option<u64>{
+        let mut stack:Vec<u64>=cfa.into_iter().collect();
+        let mut offset=0;
+        let uleb=|offset:&mut usize|->Option<u64> {
+            let (mut value,mut shift)=(0u64,0u32);
+            loop {
+                let byte=*expression.get(*offset)?;
+                *offset+=1;
+                if shift<64 {
+                    value|=((byte&0x7f) as u64)<<shift;
+                }
+                shift+=7;
+                if byte&0x80==0 {
+                    return Some(value);
+                }
+            }
+        };
+        let sleb=|offset:&mut usize|->Option<i64> {
+            let (mut value,mut shift)=(0i64,0u32);
+            loop {
+                let byte=*expression.get(*offset)?;
+                *offset+=1;
+                if shift<64 {
+                    value|=((byte&0x7f) as i64)<<shift;
+                }
+                shift+=7;
+                if byte&0x80==0 {
+                    if shift<64 && byte&0x40!=0 {
+                        value|=-1i64<<shift;
+                    }
+                    return Some(value);
+                }
+            }
+        };
+        let fixed=|offset:&mut usize,size:usize|->Option<u64> {
+            let bytes=expression.get(*offset..*offset+size)?;
+            *offset+=size;
+            let endian=self.core.header.endianness;
+            Some(match size {
+                1 => bytes[0] as u64,
+                2 => endian.parse_u16_at(0,bytes) as u64,
+                4 => endian.parse_u32_at(0,bytes) as u64,
+                _ => endian.parse_u64_at(0,bytes),
+            })
+        };
+        let mut steps=0;
+        while offset<expression.len() {
+            steps+=1;
+            if steps>MAX_EXPRESSION_STEPS {
+                return None;
+            }
+            let opcode=expression[offset];
+            offset+=1;
+            match opcode {
+                //DW_OP_addr
+                0x03 => stack.push(fixed(&mut offset,self.core.header.class.word_size())?),
+                //DW_OP_deref
+                0x06 => {
+                    let address=stack.pop()?;
+                    stack.push(self.read_word(address)?);
+                }
+                //DW_OP_const1u/1s/2u/2s/4u/4s/8u/8s
+                0x08 => stack.push(fixed(&mut offset,1)?),
+                0x09 => stack.push(fixed(&mut offset,1)? as i8 as u64),
+                0x0a => stack.push(fixed(&mut offset,2)?),
+                0x0b => stack.push(fixed(&mut offset,2)? as i16 as u64),
+                0x0c => stack.push(fixed(&mut offset,4)?),
+                0x0d => stack.push(fixed(&mut offset,4)? as i32 as u64),
+                0x0e|0x0f => stack.push(fixed(&mut offset,8)?),
+                //DW_OP_constu/consts
+                0x10 => stack.push(uleb(&mut offset)?),
+                0x11 => stack.push(sleb(&mut offset)? as u64),
+                //DW_OP_dup/drop/over/pick/swap
+                0x12 => stack.push(*stack.last()?),
+                0x13 => {
+                    stack.pop()?;
+                }
+                0x14 => stack.push(*stack.get(stack.len().checked_sub(2)?)?),
+                0x15 => {
+                    let index=fixed(&mut offset,1)? as usize;
+                    stack.push(*stack.get(stack.len().checked_sub(index+1)?)?);
+                }
+                0x16 => {
+                    let length=stack.len();
+                    if length<2 {
+                        return None;
+                    }
+                    stack.swap(length-1,length-2);
+                }
+                //DW_OP_and..DW_OP_xor以及比较运算
+                0x1a|0x1b|0x1c|0x1e|0x21|0x22|0x24|0x25|0x26|0x27|0x29..=0x2e => {
+                    let b=stack.pop()?;
+                    let a=stack.pop()?;
+                    stack.push(match opcode {
+                        0x1a => a&b,
+                        0x1b => (a as i64).checked_div(b as i64)? as u64,
+                        0x1c => a.wrapping_sub(b),
+                        0x1e => a.wrapping_mul(b),
+                        0x21 => a|b,
+                        0x22 => a.wrapping_add(b),
+                        0x24 => a.wrapping_shl(b as u32),
+                        0x25 => a.wrapping_shr(b as u32),
+                        0x26 => (a as i64).wrapping_shr(b as u32) as u64,
+                        0x27 => a^b,
+                        0x29 => (a==b) as u64,
+                        0x2a => (a as i64>=b as i64) as u64,
+                        0x2b => (a as i64>b as i64) as u64,
+                        0x2c => ((a as i64)<=b as i64) as u64,
+                        0x2d => ((a as i64)<(b as i64)) as u64,
+                        _ => (a!=b) as u64,
+                    });
+                }
+                //DW_OP_neg/not
+                0x1f => {
+                    let a=stack.pop()?;
+                    stack.push((a as i64).wrapping_neg() as u64);
+                }
+                0x20 => {
+                    let a=stack.pop()?;
+                    stack.push(!a);
+                }
+                //DW_OP_plus_uconst
+                0x23 => {
+                    let a=stack.pop()?;
+                    stack.push(a.wrapping_add(uleb(&mut offset)?));
+                }
+                //DW_OP_skip/bra
+                0x2f|0x28 => {
+                    let delta=fixed(&mut offset,2)? as i16 as isize;
+                    if opcode==0x2f || stack.pop()?!=0 {
+                        offset=offset.checked_add_signed(delta)?;
+                    }
+                }
+                //DW_OP_lit0..31
+                0x30..=0x4f => stack.push((opcode-0x30) as u64),
+                //DW_OP_reg0..31:值就在寄存器里
+                0x50..=0x6f => stack.push(*registers.get(&((opcode-0x50) as u16))?),
+                //DW_OP_breg0..31
+                0x70..=0x8f => {
+                    let base=*registers.get(&((opcode-0x70) as u16))?;
+                    stack.push(base.wrapping_add_signed(sleb(&mut offset)?));
+                }
+                //DW_OP_bregx
+                0x92 => {
+                    let register=uleb(&mut offset)? as u16;
+                    let base=*registers.get(&register)?;
+                    stack.push(base.wrapping_add_signed(sleb(&mut offset)?));
+                }
+                //DW_OP_deref_size
+                //大小只能是1到字长
+                0x94 => {
+                    let size=fixed(&mut offset,1)?;
+                    let word_size=self.core.header.class.word_size() as u64;
+                    if size==0 || size>word_size {
+                        return None;
+                    }
+                    let address=stack.pop()?;
+                    let word=self.read_word(address)?;
+                    let little=self.core.header.endianness.is_little();
+                    stack.push(match (size==word_size,little) {
+                        (true,_) => word,
+                        (false,true) => word&((1u64<<(size*8))-1),
+                        (false,false) => word>>((word_size-size)*8),
+                    });
+                }
+                //DW_OP_nop
+                0x96 => {}
+                _ => return None,
+            }
+        }
+        stack.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::eh_frame::PointerBases;
+    use crate::parser::segment::ProgramHeader;
+
+    const STACK: u64 = 0x7fff_0000;
+    //映射到地址空间顶端的16字节
+    const TOP: u64 = u64::MAX-15;
+
+    fn load(vaddr:u64,offset:u64,size:u64)->ProgramHeader{
+        ProgramHeader{p_type:abi::PT_LOAD,p_offset:offset,p_vaddr:vaddr,p_paddr:0,p_filesz:size,p_memsz:size,p_flags:abi::PF_R|abi::PF_W,p_align:8}
+    }
+
+    //借用de的x86-64头,内存只有STACK处的栈和TOP处的两个字
+    fn core(stack:&[u64])->ElfFile{
+        let mut core=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        core.data=stack.iter().chain(&[TOP,0x1234]).flat_map(|word| word.to_le_bytes()).collect();
+        let size=stack.len() as u64*8;
+        core.program_headers=vec![load(STACK,0,size),load(TOP,size,16)];
+        core.section_headers.clear();
+        core
+    }
+
+    //CIE:def_cfa r7+8, offset r16 cfa-8;FDE覆盖[0x1100,0x1120):advance_loc 1, def_cfa_offset 16, offset r6 cfa-16
+    fn module()->Module{
+        let mut data=vec![0x14,0,0,0,0,0,0,0,1,b'z',b'R',0,1,0x78,16,1,abi::DW_EH_PE_UDATA4,0x0c,7,8,0x90,1,0,0];
+        data.extend_from_slice(&[0x18,0,0,0,0x1c,0,0,0,0x00,0x11,0,0,0x20,0,0,0,0,0x41,0x0e,16,0x86,2,0,0,0,0,0,0]);
+        let elf=ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap();
+        let ident=(elf.header.endianness,elf.header.class);
+        let eh_frame=EhFrame::parse(ident,&data,0x2000,PointerBases{text:None,data:None}).unwrap();
+        Module{path:"a".to_string(),start:0x1000,end:0x3000,bias:0,elf,eh_frame:Some(eh_frame),eh_frame_hdr:None,symbols:Vec::new()}
+    }
+
+    fn registers(values:&[(u16,u64)])->HashMap<u16,u64>{
+        values.iter().copied().collect()
+    }
+
+    #[test]
+    fn cfa_rules(){
+        let core=core(&[0xdead,0x1180]);
+        let unwinder=Unwinder{core:&core,modules:vec![module()]};
+        let numbering=Registers::for_machine(abi::EM_X86_64).unwrap();
+        //已经push rbp:CFA=rsp+16,rbp在CFA-16,返回地址在CFA-8
+        let step=unwinder.step_cfi(&numbering,0x1105,&registers(&[(7,STACK),(6,0x5000)])).unwrap();
+        assert_eq!((step.pc,step.call_site),(0x1180,true));
+        assert_eq!(step.registers,registers(&[(7,STACK+16),(6,0xdead),(16,0x1180)]));
+        //第一条指令:CFA=rsp+8,rbp还没保存
+        let step=unwinder.step_cfi(&numbering,0x1100,&registers(&[(7,STACK+8),(6,0x5000)])).unwrap();
+        assert_eq!(step.registers,registers(&[(7,STACK+16),(6,0x5000),(16,0x1180)]));
+        assert!(unwinder.step_cfi(&numbering,0x1120,&registers(&[(7,STACK)])).is_none());
+    }
+
+    #[test]
+    fn frame_pointer_fallback(){
+        let core=core(&[STACK+0x10,0x1180,0,0x1190]);
+        let unwinder=Unwinder{core:&core,modules:Vec::new()};
+        let numbering=Registers::for_machine(abi::EM_X86_64).unwrap();
+        let step=unwinder.step_frame_pointer(&numbering,&registers(&[(6,STACK),(7,STACK-0x20)]),false).unwrap();
+        assert_eq!((step.pc,step.call_site),(0x1180,true));
+        assert_eq!(step.registers,registers(&[(6,STACK+0x10),(7,STACK+0x10)]));
+        //函数入口:返回地址在栈顶
+        let step=unwinder.step_frame_pointer(&numbering,&registers(&[(6,0),(7,STACK+8)]),true).unwrap();
+        assert_eq!(step.pc,0x1180);
+        assert_eq!(step.registers[&7],STACK+0x10);
+        assert!(unwinder.step_frame_pointer(&numbering,&registers(&[(6,0),(7,STACK)]),false).is_none());
+        //fp或sp在地址空间顶端时加字长会溢出
+        assert!(unwinder.step_frame_pointer(&numbering,&registers(&[(6,TOP),(7,STACK)]),false).is_none());
+        assert!(unwinder.step_frame_pointer(&numbering,&registers(&[(6,0),(7,u64::MAX-7)]),true).is_none());
+    }
+
+    #[test]
+    fn expressions(){
+        let core=core(&[0x1122_3344_5566_7788]);
+        let unwinder=Unwinder{core:&core,modules:Vec::new()};
+        let registers=registers(&[(7,STACK)]);
+        let evaluate=|expression:&[u8],cfa:Option<u64>| unwinder.evaluate(expression,&registers,cfa);
+        //DW_OP_breg7 16; DW_OP_plus_uconst 8; 有CFA时先压栈
+        assert_eq!(evaluate(&[0x77,16],None),Some(STACK+16));
+        assert_eq!(evaluate(&[0x23,8],Some(0x100)),Some(0x108));
+        //向前的skip跳过lit1;lit0时bra不跳,lit1时跳过lit3
+        assert_eq!(evaluate(&[0x32,0x2f,1,0,0x31],None),Some(2));
+        assert_eq!(evaluate(&[0x30,0x28,1,0,0x33,0x32],None),Some(2));
+        assert_eq!(evaluate(&[0x31,0x28,1,0,0x33,0x32],None),Some(2));
+        assert_eq!(evaluate(&[0x31,0x28,1,0,0x33],None),None);
+        //向后跳回自身的skip和条件恒真的bra
+        assert_eq!(evaluate(&[0x2f,0xfd,0xff],None),None);
+        assert_eq!(evaluate(&[0x31,0x28,0xfc,0xff],None),None);
+        //跳到表达式之前
+        assert_eq!(evaluate(&[0x2f,0xf0,0xff],None),None);
+
+        for (size,value) in [(1,Some(0x88)),(2,Some(0x7788)),(4,Some(0x5566_7788)),(8,Some(0x1122_3344_5566_7788)),(0,None),(9,None),(255,None)] {
+            assert_eq!(evaluate(&[0x77,0,0x94,size],None),value,"{}",size);
+        }
+    }
+}
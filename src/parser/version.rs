@@ -0,0 +1,306 @@
+use crate::parser::abi;
+use crate::parser::dynamic::Dyn;
+use crate::parser::elf_file::ElfFile;
+use crate::parser::endian::{AnyEndian, EndianParse};
+use crate::parser::file::Class;
+use crate::parser::section::SectionHeader;
+use crate::parser::symbol::Symbol;
+
+/// Version attached to a dynamic symbol through `.gnu.version`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolVersion {
+    /// Version name, e.g. `GLIBC_2.2.5`
+    pub name: String,
+    /// Versym index (without the hidden bit)
+    pub index: u16,
+    /// Hidden versions are only reachable as `name@VERSION`, never as the default
+    pub hidden: bool,
+    /// Library that provides the version, for versions required through `.gnu.version_r`
+    pub library: Option<String>,
+}
+
+/// One `Elf_Verdef` entry of `.gnu.version_d`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionDefinition {
+    pub index: u16,
+    pub flags: u16,
+    pub hash: u32,
+    /// Version name followed by the names of the versions it inherits from
+    pub names: Vec<String>,
+}
+
+/// One `Elf_Vernaux` entry: a version required from a library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionNeedAux {
+    pub name: String,
+    pub hash: u32,
+    pub flags: u16,
+    /// Versym index assigned to the version (vna_other)
+    pub index: u16,
+}
+
+/// One `Elf_Verneed` entry of `.gnu.version_r`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionNeed {
+    /// Library the versions are required from (vn_file)
+    pub file: String,
+    pub entries: Vec<VersionNeedAux>,
+}
+
+/// Parsed `.gnu.version`, `.gnu.version_d` and `.gnu.version_r`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionTables {
+    /// One versym per `.dynsym` entry
+    pub versym: Vec<u16>,
+    pub verdef: Vec<VersionDefinition>,
+    pub verneed: Vec<VersionNeed>,
+}
+
+impl VersionTables {
+    pub fn read_versions(file_path:&str)->Option<VersionTables>{
+        let elf=ElfFile::read_elf(file_path)?;
+        Self::parse_versions(&elf)
+    }
+
+    /// Locate the version tables through their sections, falling back to
+    /// DT_VERSYM/DT_VERDEF/DT_VERNEED when the section headers are missing.
+    /// Returns None for files without symbol versioning.
+    pub fn parse_versions(elf:&ElfFile)->Option<VersionTables>{
+        let ident=elf.ident();
+        let dyns=elf.dynamic();
+        let strtab=elf.dynamic_strings().unwrap_or(&[]);
+        let symbol_count=elf.dynamic_symbols().len();
+
+        let versym_bytes=match elf.find_section_by_type(abi::SHT_GNU_VERSYM) {
+            Some(sh) => elf.section_data(sh),
+            None => Dyn::find(&dyns,abi::DT_VERSYM)
+                .and_then(|addr| elf.vaddr_data(addr,symbol_count as u64*2)),
+        };
+        let (verdef_bytes,verdef_num)=Self::locate(elf,&dyns,abi::SHT_GNU_VERDEF,abi::DT_VERDEF,abi::DT_VERDEFNUM);
+        let (verneed_bytes,verneed_num)=Self::locate(elf,&dyns,abi::SHT_GNU_VERNEED,abi::DT_VERNEED,abi::DT_VERNEEDNUM);
+        if versym_bytes.is_none() && verdef_bytes.is_none() && verneed_bytes.is_none() {
+            return None;
+        }
+        Some(VersionTables{
+            versym:versym_bytes.map(|bytes| Self::parse_versym(ident,bytes)).unwrap_or_default(),
+            verdef:verdef_bytes.map(|bytes| Self::parse_verdef(ident,bytes,verdef_num,strtab)).unwrap_or_default(),
+            verneed:verneed_bytes.map(|bytes| Self::parse_verneed(ident,bytes,verneed_num,strtab)).unwrap_or_default(),
+        })
+    }
+
+    //返回表的字节(从表开始到文件尾)和表项个数
+    fn locate<'a>(elf:&'a ElfFile,dyns:&[Dyn],sh_type:u32,addr_tag:i64,num_tag:i64)->(Option<&'a [u8]>,usize){
+        if let Some(sh)=elf.find_section_by_type(sh_type) {
+            let bytes=elf.data.get(sh.sh_offset as usize..);
+            return (bytes,sh.sh_info as usize);
+        }
+        let num=Dyn::find(dyns,num_tag).unwrap_or(0) as usize;
+        let bytes=Dyn::find(dyns,addr_tag)
+            .and_then(|addr| elf.vaddr_to_offset(addr))
+            .and_then(|offset| elf.data.get(offset as usize..));
+        (bytes,num)
+    }
+
+    pub fn parse_versym(ident:(AnyEndian, Class),data:&[u8])->Vec<u16>{
+        let (endian,_)=ident;
+        (0..data.len()/2).map(|i| endian.parse_u16_at(i*2,data)).collect()
+    }
+
+    pub fn parse_verdef(ident:(AnyEndian, Class),data:&[u8],count:usize,strtab:&[u8])->Vec<VersionDefinition>{
+        let (endian,_)=ident;
+        let mut v=Vec::new();
+        let mut offset=0usize;
+        for _ in 0..count {
+            if offset+20>data.len() {
+                break;
+            }
+            let flags=endian.parse_u16_at(offset+2,data);
+            let index=endian.parse_u16_at(offset+4,data);
+            let cnt=endian.parse_u16_at(offset+6,data);
+            let hash=endian.parse_u32_at(offset+8,data);
+            let aux=endian.parse_u32_at(offset+12,data) as usize;
+            let next=endian.parse_u32_at(offset+16,data) as usize;
+
+            let mut names=Vec::new();
+            let mut aux_offset=offset+aux;
+            for _ in 0..cnt {
+                if aux_offset+8>data.len() {
+                    break;
+                }
+                let vda_name=endian.parse_u32_at(aux_offset,data) as usize;
+                names.push(SectionHeader::parse_string_at(strtab,vda_name).unwrap_or_default());
+                let vda_next=endian.parse_u32_at(aux_offset+4,data) as usize;
+                if vda_next==0 {
+                    break;
+                }
+                aux_offset+=vda_next;
+            }
+            v.push(VersionDefinition{index,flags,hash,names});
+            if next==0 {
+                break;
+            }
+            offset+=next;
+        }
+        v
+    }
+
+    pub fn parse_verneed(ident:(AnyEndian, Class),data:&[u8],count:usize,strtab:&[u8])->Vec<VersionNeed>{
+        let (endian,_)=ident;
+        let mut v=Vec::new();
+        let mut offset=0usize;
+        for _ in 0..count {
+            if offset+16>data.len() {
+                break;
+            }
+            let cnt=endian.parse_u16_at(offset+2,data);
+            let file=endian.parse_u32_at(offset+4,data) as usize;
+            let aux=endian.parse_u32_at(offset+8,data) as usize;
+            let next=endian.parse_u32_at(offset+12,data) as usize;
+
+            let mut entries=Vec::new();
+            let mut aux_offset=offset+aux;
+            for _ in 0..cnt {
+                if aux_offset+16>data.len() {
+                    break;
+                }
+                let vna_name=endian.parse_u32_at(aux_offset+8,data) as usize;
+                entries.push(VersionNeedAux{
+                    name:SectionHeader::parse_string_at(strtab,vna_name).unwrap_or_default(),
+                    hash:endian.parse_u32_at(aux_offset,data),
+                    flags:endian.parse_u16_at(aux_offset+4,data),
+                    index:endian.parse_u16_at(aux_offset+6,data),
+                });
+                let vna_next=endian.parse_u32_at(aux_offset+12,data) as usize;
+                if vna_next==0 {
+                    break;
+                }
+                aux_offset+=vna_next;
+            }
+            v.push(VersionNeed{
+                file:SectionHeader::parse_string_at(strtab,file).unwrap_or_default(),
+                entries,
+            });
+            if next==0 {
+                break;
+            }
+            offset+=next;
+        }
+        v
+    }
+
+    /// Version of the dynamic symbol at `sym_index`; None for local and unversioned symbols
+    pub fn symbol_version(&self,sym_index:usize)->Option<SymbolVersion>{
+        let versym=*self.versym.get(sym_index)?;
+        let index=versym & !abi::VERSYM_HIDDEN;
+        if index==abi::VER_NDX_LOCAL || index==abi::VER_NDX_GLOBAL {
+            return None;
+        }
+        let hidden=versym & abi::VERSYM_HIDDEN!=0;
+        if let Some(def)=self.verdef.iter().find(|def| def.index==index) {
+            return Some(SymbolVersion{
+                name:def.names.first().cloned().unwrap_or_default(),
+                index,
+                hidden,
+                library:None,
+            });
+        }
+        self.verneed.iter()
+            .flat_map(|need| need.entries.iter().map(move |aux| (need,aux)))
+            .find(|(_,aux)| aux.index==index)
+            .map(|(need,aux)| SymbolVersion{
+                name:aux.name.clone(),
+                index,
+                hidden,
+                library:Some(need.file.clone()),
+            })
+    }
+
+    /// Attach versions to `.dynsym` entries, like `fix_symbol_name` does for names
+    pub fn fix_symbol_version(&self,mut symbol_tables:Vec<Symbol>)->Vec<Symbol>{
+        for symbol_table in symbol_tables.iter_mut() {
+            symbol_table.version=self.symbol_version(symbol_table.index as usize);
+        }
+        symbol_tables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn de()->ElfFile{
+        ElfFile::parse(include_bytes!("../test_elf/de").to_vec()).unwrap()
+    }
+
+    #[test]
+    fn required_versions(){
+        let elf=de();
+        let tables=VersionTables::parse_versions(&elf).unwrap();
+        assert_eq!(tables.versym.len(),17);
+        assert!(tables.verdef.is_empty());
+        assert_eq!(tables.verneed.len(),1);
+        assert_eq!(tables.verneed[0].file,"libc.so.6");
+        let names:Vec<(&str,u16)>=tables.verneed[0].entries.iter().map(|aux| (aux.name.as_str(),aux.index)).collect();
+        assert_eq!(names,[("GLIBC_2.2.5",3),("GLIBC_2.34",2)]);
+
+        let symbols=tables.fix_symbol_version(elf.dynamic_symbols());
+        assert_eq!(symbols[1].versioned_name(),"__libc_start_main@GLIBC_2.34");
+        assert_eq!(symbols[4].version,Some(SymbolVersion{name:"GLIBC_2.2.5".to_string(),index:3,hidden:false,library:Some("libc.so.6".to_string())}));
+        //libz的符号没有版本
+        assert_eq!(symbols[3].versioned_name(),"inflate");
+        assert_eq!(Symbol::find_versioned(&symbols,"puts@GLIBC_2.2.5").map(|symbol| symbol.index),Some(4));
+        assert!(Symbol::find_versioned(&symbols,"puts@GLIBC_2.34").is_none());
+        assert_eq!(Symbol::find_versioned(&symbols,"puts").map(|symbol| symbol.index),Some(4));
+    }
+
+    //没有节头时通过DT_VERSYM/DT_VERNEED找到同样的表
+    #[test]
+    fn located_through_dynamic(){
+        let mut elf=de();
+        let expected=VersionTables::parse_versions(&elf).unwrap();
+        elf.section_headers.clear();
+        let tables=VersionTables::parse_versions(&elf).unwrap();
+        assert_eq!((tables.versym,tables.verneed),(expected.versym,expected.verneed));
+    }
+
+    //lib.so(基础版本)、V_2继承V_1;符号3是隐藏的V_2
+    #[test]
+    fn definitions(){
+        let ident=(AnyEndian::new(abi::ELFDATA2LSB),Class::ELF64);
+        let strtab=b"\0lib.so\0V_1\0V_2\0";
+        let verdef=|flags:u16,index:u16,count:u16,next:u32| -> Vec<u8> {
+            [1,flags,index,count].iter().flat_map(|half:&u16| half.to_le_bytes())
+                .chain([0u32,20,next].iter().flat_map(|word| word.to_le_bytes())).collect()
+        };
+        let aux=|name:u32,next:u32| -> Vec<u8> { [name,next].iter().flat_map(|word| word.to_le_bytes()).collect() };
+        let mut data=verdef(abi::VER_FLG_BASE,1,1,28);
+        data.extend(aux(1,0));
+        data.extend(verdef(0,2,2,0));
+        data.extend(aux(12,8));
+        data.extend(aux(8,0));
+        let verdef=VersionTables::parse_verdef(ident,&data,2,strtab);
+        assert_eq!(verdef,[
+            VersionDefinition{index:1,flags:abi::VER_FLG_BASE,hash:0,names:vec!["lib.so".to_string()]},
+            VersionDefinition{index:2,flags:0,hash:0,names:vec!["V_2".to_string(),"V_1".to_string()]},
+        ]);
+
+        let tables=VersionTables{versym:vec![0,1,2,2|abi::VERSYM_HIDDEN],verdef,verneed:Vec::new()};
+        assert_eq!(tables.symbol_version(1),None);
+        assert_eq!(tables.symbol_version(2),Some(SymbolVersion{name:"V_2".to_string(),index:2,hidden:false,library:None}));
+        assert!(tables.symbol_version(3).unwrap().hidden);
+        assert_eq!(tables.symbol_version(4),None);
+
+        //定义的符号:默认版本是name@@V_2,隐藏版本只能用name@V_2找到
+        let mut symbols=de().dynamic_symbols()[..4].to_vec();
+        for symbol in symbols.iter_mut() {
+            symbol.string_name="f".to_string();
+            symbol.st_shndx=14;
+        }
+        let symbols=tables.fix_symbol_version(symbols);
+        assert_eq!(symbols[2].versioned_name(),"f@@V_2");
+        assert_eq!(symbols[3].versioned_name(),"f@V_2");
+        assert_eq!(Symbol::find_versioned(&symbols,"f@V_2").map(|symbol| symbol.index),Some(2));
+        assert_eq!(Symbol::find_versioned(&symbols[3..],"f@@V_2").map(|symbol| symbol.index),Some(3));
+        assert!(Symbol::find_versioned(&symbols[3..],"f").is_none());
+    }
+}